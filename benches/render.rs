@@ -0,0 +1,83 @@
+// render-time costs on a 50MP canvas, each against the per-pixel way it used to be done
+use {
+    mazesolve_rk::{math::*, render},
+    image::{self as im, ConvertBuffer},
+    std::time::Instant,
+};
+
+const SIZE: u32 = 7072;
+const CELL: Coord = 16;
+const RUNS: usize = 3;
+
+fn best_of<T>(runs: usize, mut f: impl FnMut() -> T) -> f64 {
+    (0 .. runs)
+        .map(|_| {
+            let begin = Instant::now();
+            std::hint::black_box(f());
+            begin.elapsed().as_secs_f64()
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn report(name: &str, before: f64, after: f64) {
+    println!("{:<12} before {:>8.3} s   after {:>8.3} s   {:>6.1}x", name, before, after, before / after);
+}
+
+// the fill as it was, through put_pixel
+fn fill_rect_per_pixel(image: &mut im::RgbImage, rect: Rect, with: im::Rgb<u8>) {
+    let bounds = Rect::new_unchecked(V2::new(0, 0), V2::new(coord_clamped(image.width()), coord_clamped(image.height())));
+    let rect = rect.intersect(bounds);
+    for y in rect.mins.y .. rect.maxs.y {
+        for x in rect.mins.x .. rect.maxs.x {
+            image.put_pixel(x as u32, y as u32, with);
+        }
+    }
+}
+
+fn main() {
+    // a lattice of walls, and one rect per cell like a decomposition would give
+    let maze = im::GrayImage::from_fn(SIZE, SIZE, |x, y| {
+        let wall = x as Coord % CELL == 0 || y as Coord % CELL == 0;
+        im::Luma([if wall { 0x00 } else { 0xff }])
+    });
+    let cells = SIZE as Coord / CELL;
+    let rects: Vec<Rect> = (0 .. cells * cells)
+        .map(|i| {
+            let mins = V2::new(i % cells * CELL + 1, i / cells * CELL + 1);
+            Rect::new_unchecked(mins, mins + V2::new(CELL - 1, CELL - 1))
+        })
+        .collect();
+    println!("{}x{} px, {} rects", SIZE, SIZE, rects.len());
+
+    let before = best_of(RUNS, || -> im::RgbImage { maze.convert() });
+    let after  = best_of(RUNS, || render::to_rgb(&maze));
+    report("convert", before, after);
+
+    let after_blank = best_of(RUNS, || render::blank(SIZE, SIZE));
+    report("path-only", before, after_blank);
+
+    let mut canvas = render::to_rgb(&maze);
+    let before = best_of(RUNS, || for rect in rects.iter() {
+        fill_rect_per_pixel(&mut canvas, *rect, render::GREEN);
+    });
+    let after = best_of(RUNS, || for rect in rects.iter() {
+        render::fill_rect(&mut canvas, *rect, render::GREEN);
+    });
+    report("fill", before, after);
+
+    let after = best_of(RUNS, || {
+        let mut canvas = render::to_rgb(&maze);
+        for rect in rects.iter() {
+            render::fill_rect(&mut canvas, *rect, render::GREEN);
+        }
+        canvas
+    });
+    let before = best_of(RUNS, || {
+        let mut canvas: im::RgbImage = maze.convert();
+        for rect in rects.iter() {
+            fill_rect_per_pixel(&mut canvas, *rect, render::GREEN);
+        }
+        canvas
+    });
+    report("total", before, after);
+}
@@ -0,0 +1,100 @@
+// dijkstra over a million-node lattice, against the way it used to queue every node up front,
+// dial's buckets against the heap, and delta-stepping across threads against both
+use {
+    mazesolve_rk::graph::{AdjacencyGraph, DijkstraSearch, Edge, EdgeSetGraph, Graph, HashMap, HashSet, NodeID, RawNodeID},
+    priority_queue::PriorityQueue,
+    std::{cmp::Reverse, time::Instant},
+};
+
+const SIDE: u32 = 1000;
+const RUNS: usize = 3;
+
+fn best_of<T>(runs: usize, mut f: impl FnMut() -> T) -> f64 {
+    (0 .. runs)
+        .map(|_| {
+            let begin = Instant::now();
+            std::hint::black_box(f());
+            begin.elapsed().as_secs_f64()
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn report(name: &str, before: f64, after: f64) {
+    println!("{:<12} before {:>8.3} s   after {:>8.3} s   {:>6.1}x", name, before, after, before / after);
+}
+
+fn id(x: u32, y: u32) -> NodeID {
+    NodeID::new((y * SIDE + x + 1) as RawNodeID).unwrap()
+}
+
+// every node joined to the ones beside it, with the start in one corner
+fn lattice(goal: NodeID) -> AdjacencyGraph<()> {
+    let mut nodes = HashMap::default();
+    let mut edges = HashSet::default();
+    for y in 0 .. SIDE {
+        for x in 0 .. SIDE {
+            nodes.insert(id(x, y), ());
+            if x > 0 { edges.insert(Edge::new(id(x - 1, y), id(x, y))); }
+            if y > 0 { edges.insert(Edge::new(id(x, y - 1), id(x, y))); }
+        }
+    }
+    EdgeSetGraph::new(nodes, id(0, 0), goal, edges).into_adjacency_graph()
+}
+
+// the search as it was: every node queued at i32::MAX before the first expansion
+fn eager_dijkstra(graph: &AdjacencyGraph<()>) -> HashMap<NodeID, i32> {
+    let mut dists: HashMap<NodeID, i32> = graph.nodes().keys().map(|id| (*id, i32::MAX)).collect();
+    dists.insert(graph.start(), 0);
+    let mut queue: PriorityQueue<NodeID, Reverse<i32>> = PriorityQueue::with_capacity(dists.len());
+    for (id, dist) in dists.iter() {
+        queue.push(*id, Reverse(*dist));
+    }
+
+    while let Some((u, Reverse(u_dist))) = queue.pop() {
+        if u_dist == i32::MAX {
+            break;
+        }
+        for v in graph.neighbors(u) {
+            let new_dist = u_dist + graph.weight(u, v);
+            if new_dist < dists[&v] {
+                dists.insert(v, new_dist);
+                queue.change_priority(&v, Reverse(new_dist));
+            }
+        }
+    }
+    dists
+}
+
+// the best of RUNS searches, each of a freshly built lattice so that building isn't timed
+fn best_search<T>(goal: NodeID, search: impl Fn(AdjacencyGraph<()>) -> T) -> f64 {
+    let mut graphs: Vec<_> = (0 .. RUNS).map(|_| lattice(goal)).collect();
+    best_of(RUNS, || search(graphs.pop().unwrap()))
+}
+
+fn main() {
+    let corner = id(SIDE - 1, SIDE - 1);
+    let graph = lattice(corner);
+    println!("{} nodes", graph.nodes().len());
+
+    let before = best_of(RUNS, || eager_dijkstra(&graph));
+    let after = best_search(corner, |graph| graph.into_full_dijkstra());
+    report("full", before, after);
+
+    // the eager search settles every node whatever the goal, so it's the same before
+    let after = best_search(id(SIDE / 4, SIDE / 4), |graph| graph.into_dijkstra());
+    report("near goal", before, after);
+
+    // every weight on the lattice is one, so the ring is two buckets long
+    let before = best_search(corner, |graph| graph.into_full_dijkstra());
+    let after = best_search(corner, |graph| {
+        let start = graph.start();
+        DijkstraSearch::new(graph, &[start]).bucketed().finish()
+    });
+    report("buckets", before, after);
+
+    let after = best_search(corner, |graph| {
+        let start = graph.start();
+        graph.into_delta_stepping(&[start], 1)
+    });
+    report("delta", before, after);
+}
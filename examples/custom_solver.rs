@@ -0,0 +1,117 @@
+// a search of your own plugged in beside the built-in ones through the PathFinder trait:
+//
+//     cargo run --example custom_solver
+//
+// any search that hands back a Solution can be run, compared and rendered like the others. this
+// one is a depth-first search, which finds some route, not necessarily a short one.
+
+use {
+    mazesolve_rk::{
+        math::*,
+        graph::{AdjacencyGraph, Graph, NodeID, SearchStats},
+        image_graph::{extract_graph, ExtractOptions, NodeData},
+        pathfind::{self, PathFinder, Solution},
+        preprocess::{CLEAR, WALL},
+    },
+    image as im,
+    pcg_rand::Pcg32Basic,
+    rand::{seq::SliceRandom, SeedableRng},
+    std::collections::HashMap,
+};
+
+struct DepthFirst;
+
+impl PathFinder for DepthFirst {
+    fn name(&self) -> &'static str { "depth-first" }
+
+    fn solve(&self, graph: &AdjacencyGraph<NodeData>, start: NodeID, goal: NodeID) -> Option<Solution> {
+        let mut preds: HashMap<NodeID, NodeID> = HashMap::new();
+        let mut stack = vec![start];
+        preds.insert(start, start);
+        let mut stats = SearchStats { max_frontier: 1, ..SearchStats::default() };
+        let mut expanded = Vec::new();
+
+        while let Some(u) = stack.pop() {
+            stats.expansions += 1;
+            expanded.push(u);
+            if u == goal {
+                break;
+            }
+            for v in graph.neighbors(u) {
+                if graph.nodes().contains_key(&v) && !preds.contains_key(&v) {
+                    preds.insert(v, u);
+                    stack.push(v);
+                    stats.relaxations += 1;
+                }
+            }
+            stats.saw_frontier(stack.len());
+        }
+        if !preds.contains_key(&goal) {
+            return None;
+        }
+
+        // run over an uncontracted graph, every adjacency is a step into the next space, so
+        // there are no corridors to open back up and the cost is the count of steps
+        let mut nodes = vec![goal];
+        while *nodes.last()? != start {
+            nodes.push(preds[nodes.last()?]);
+        }
+        nodes.reverse();
+        let rects = nodes.iter().map(|id| graph.get_node(*id).rect).collect();
+        let cost = nodes.len() as i32 - 1;
+        Some(Solution { nodes, rects, cost, stats, expanded })
+    }
+}
+
+// a perfect maze of `cells` by `cells`, carved by a seeded random walk that backs up when stuck,
+// with each cell and passage a pixel wide
+fn carve(cells: Coord, seed: u64) -> im::GrayImage {
+    let side = (cells * 2 + 1) as u32;
+    let mut image = im::GrayImage::from_pixel(side, side, WALL);
+    let mut rng = Pcg32Basic::seed_from_u64(seed);
+    let open = |image: &mut im::GrayImage, p: V2| image.put_pixel(p.x as u32, p.y as u32, CLEAR);
+
+    let mut seen = vec![false; (cells * cells) as usize];
+    let mut stack = vec![V2::new(0, 0)];
+    seen[0] = true;
+    open(&mut image, V2::new(1, 1));
+    while let Some(&cell) = stack.last() {
+        let unseen: Vec<V2> = [V2::new(1, 0), V2::new(-1, 0), V2::new(0, 1), V2::new(0, -1)].iter()
+            .map(|step| cell + *step)
+            .filter(|next| next.x >= 0 && next.y >= 0 && next.x < cells && next.y < cells)
+            .filter(|next| !seen[(next.y * cells + next.x) as usize])
+            .collect();
+        let next = match unseen.choose(&mut rng) {
+            Some(next) => *next,
+            None       => { stack.pop(); continue; }
+        };
+        seen[(next.y * cells + next.x) as usize] = true;
+        open(&mut image, cell + next + V2::new(1, 1));
+        open(&mut image, next + next + V2::new(1, 1));
+        stack.push(next);
+    }
+    image
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // a maze with a loop knocked into it, so that there's more than one way through
+    let mut image = carve(24, 7);
+    for x in 10 .. 39 {
+        image.put_pixel(x, 24, CLEAR);
+    }
+    let (start, goal) = (V2::new(1, 1), V2::new(47, 47));
+    let graph = extract_graph(&image, start, goal, &ExtractOptions::default())?;
+    let graph = graph.prune().into_adjacency_graph();
+    let (start, goal) = (graph.start(), graph.goal());
+
+    let finders: [&dyn PathFinder; 4] = [&DepthFirst, &pathfind::Dijkstra, &pathfind::AStar, &pathfind::BreadthFirst];
+    for finder in finders.iter() {
+        match finder.solve(&graph, start, goal) {
+            Some(solution) => println!("{:<12} length {:>3}, {:>3} expanded, frontier at most {}",
+                finder.name(), solution.cost, solution.stats.expansions, solution.stats.max_frontier),
+            None           => println!("{:<12} no route", finder.name()),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+// solving over HTTP, with no window and no files:
+//
+//     cargo run --release --example headless_server [127.0.0.1:8080]
+//     curl --data-binary @maze.png 'localhost:8080/?start=2,0&goal=638,479' -o solved.png
+//
+// the start and goal default as on the command line, and only white is clear. each image is
+// solved by a SolveSession stepped a little at a time, so that a maze too big to finish inside
+// the time limit is given up on rather than holding up everyone behind it. a sketch of the
+// library in a service, so one connection at a time and just enough HTTP.
+
+use {
+    mazesolve_rk::{
+        math::*,
+        formats,
+        image_graph::ExtractOptions,
+        render::{self, RenderStyle},
+        session::{SolveSession, Status},
+        sink::encode_png,
+    },
+    std::{
+        env,
+        io::{BufRead, BufReader, Read, Write},
+        net::{TcpListener, TcpStream},
+        time::{Duration, Instant},
+    },
+};
+
+const LIMIT:  Duration = Duration::from_secs(5);
+const BUDGET: usize = 10_000;
+const MAX_BODY: usize = 64 << 20;
+
+struct Request {
+    start: Option<V2>,
+    goal:  Option<V2>,
+    body:  Vec<u8>,
+}
+
+fn parse_point(text: &str) -> Result<V2, String> {
+    let mut parts = text.splitn(2, ',').map(str::parse::<Coord>);
+    match (parts.next(), parts.next()) {
+        (Some(Ok(x)), Some(Ok(y))) => Ok(V2::new(x, y)),
+        _                          => Err(format!("expected X,Y, got '{}'", text)),
+    }
+}
+
+fn read_request(stream: &mut TcpStream) -> Result<Request, String> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|err| err.to_string())?;
+    let target = line.split_whitespace().nth(1).ok_or("no request line")?.to_string();
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).map_err(|err| err.to_string())?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().map_err(|_| "bad content-length")?;
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err(format!("a maze of {} bytes is more than {}", length, MAX_BODY));
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|err| err.to_string())?;
+
+    let mut request = Request { start: None, goal: None, body };
+    let query = target.split_once('?').map_or("", |(_, query)| query);
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        match pair.split_once('=') {
+            Some(("start", value)) => request.start = Some(parse_point(value)?),
+            Some(("goal", value))  => request.goal = Some(parse_point(value)?),
+            _                      => return Err(format!("unknown parameter '{}'", pair)),
+        }
+    }
+    Ok(request)
+}
+
+// the solved maze as PNG bytes
+fn solve(request: &Request) -> Result<Vec<u8>, String> {
+    let image = formats::decode(&request.body).map_err(|err| err.to_string())?.to_luma();
+    let size = |n| coord(n).map_err(|err| err.to_string());
+    let (width, height) = (size(image.width())?, size(image.height())?);
+    let start = request.start.unwrap_or(V2::new(2, 0));
+    let goal = request.goal.unwrap_or(V2::new(width - 1, height - 3));
+
+    let began = Instant::now();
+    let mut session = SolveSession::new(&image, start, goal, &ExtractOptions::default());
+    loop {
+        match session.step(BUDGET) {
+            Status::Solved => break,
+            Status::Failed => return Err(session.error().map_or("failed".into(), |err| err.to_string())),
+            _ if began.elapsed() > LIMIT => return Err(format!("not solved within {:?}", LIMIT)),
+            _ => { }
+        }
+    }
+
+    let solution = session.solution().and_then(|graph| graph.solution()).ok_or("solved without a route")?;
+    let mut out = render::to_rgb(&image);
+    render::render_path(&mut out, &solution, &RenderStyle::default());
+    encode_png(&out).map_err(|err| err.to_string())
+}
+
+fn respond(stream: &mut TcpStream, status: &str, kind: &str, body: &[u8]) -> std::io::Result<()> {
+    write!(stream, "HTTP/1.0 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n", status, kind, body.len())?;
+    stream.write_all(body)
+}
+
+fn main() -> std::io::Result<()> {
+    let addr = env::args().nth(1).unwrap_or_else(|| "127.0.0.1:8080".into());
+    let listener = TcpListener::bind(&addr)?;
+    eprintln!("listening on {}", addr);
+
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let result = read_request(&mut stream).and_then(|request| solve(&request));
+        let sent = match result {
+            Ok(png)  => respond(&mut stream, "200 OK", "image/png", &png),
+            Err(err) => {
+                eprintln!("rejected: {}", err);
+                respond(&mut stream, "422 Unprocessable Entity", "text/plain", format!("{}\n", err).as_bytes())
+            }
+        };
+        if let Err(err) = sent {
+            eprintln!("replying: {}", err);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+// a maze edited under a solve, re-extracting only around each edit:
+//
+//     cargo run --example incremental_editing
+//
+// as an editor or a game would, with doors opening and closing. the rects clear of each change
+// are kept, ids and all, so a big maze costs little more to re-solve than a small one.
+
+use {
+    mazesolve_rk::{
+        math::*,
+        graph::{EdgeSetGraph, Graph},
+        image_graph::{changed_region, extract_graph, update_graph, ExtractOptions, NodeData},
+        preprocess::{CLEAR, WALL},
+    },
+    image as im,
+};
+
+// the shortest route, in steps from one space into the next
+fn length(graph: &EdgeSetGraph<NodeData>) -> Option<i32> {
+    let solved = graph.clone().prune().into_adjacency_graph().contract().into_dijkstra();
+    solved.solution().map(|solution| solution.cost)
+}
+
+// paints the pixels of `rect`
+fn paint(image: &mut im::GrayImage, rect: Rect, with: im::Luma<u8>) {
+    for y in rect.mins.y .. rect.maxs.y {
+        for x in rect.mins.x .. rect.maxs.x {
+            image.put_pixel(x as u32, y as u32, with);
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // two halls split by a wall, with a near door and a far one
+    let mut image = im::GrayImage::from_pixel(60, 40, CLEAR);
+    paint(&mut image, Rect::new_unchecked(V2::new(29, 0), V2::new(31, 40)), WALL);
+    let near = Rect::new_unchecked(V2::new(29, 4), V2::new(31, 8));
+    let far  = Rect::new_unchecked(V2::new(29, 32), V2::new(31, 36));
+    paint(&mut image, near, CLEAR);
+    paint(&mut image, far, CLEAR);
+
+    let (start, goal) = (V2::new(2, 2), V2::new(57, 2));
+    let opts = ExtractOptions::default();
+    let mut graph = extract_graph(&image, start, goal, &opts)?;
+    let steps = length(&graph).ok_or("no way through to begin with")?;
+    println!("both doors open: {} spaces, {} steps across them", graph.nodes().len(), steps);
+
+    let edits = [("near door shut", near, WALL), ("far door shut too", far, WALL), ("near door open again", near, CLEAR)];
+    for (what, door, with) in edits.iter() {
+        let before = image.clone();
+        paint(&mut image, *door, *with);
+        let changed = changed_region(&before, &image).ok_or("the edit changed nothing")?;
+
+        let kept = graph.nodes().values().filter(|data| !data.rect.overlaps(changed)).count();
+        graph = update_graph(&graph, &image, changed, start, goal, &opts)?;
+        match length(&graph) {
+            Some(length) => println!("{}: kept {} of the spaces, {} steps across them", what, kept, length),
+            None         => println!("{}: kept {} of the spaces, no way through", what, kept),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+// solves a maze held in memory as raw luma bytes, with no files in or out unless asked:
+//
+//     cargo run --example solve_from_buffer [solved.png]
+//
+// the whole pipeline as the command line runs it, less the preprocessing: extract the rects,
+// prune the dead ends, contract the corridors, search, refine to pixels, render.
+
+use {
+    mazesolve_rk::{
+        math::*,
+        graph::Graph,
+        image_graph::{extract_graph, ExtractOptions},
+        refine::refine_path,
+        render::{self, RenderStyle},
+        sink::encode_png,
+    },
+    image as im,
+    std::{env, fs},
+};
+
+// '#' is wall and anything else clear, as a scanner or another program might hand over pixels
+const MAZE: &[&str] = &[
+    "#.#########",
+    "#.#.....#.#",
+    "#.#.###.#.#",
+    "#...#...#.#",
+    "#####.###.#",
+    "#...#.....#",
+    "#.#.#####.#",
+    "#.#...#...#",
+    "#.###.#.###",
+    "#...#.....#",
+    "#########.#",
+];
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (width, height) = (MAZE[0].len() as u32, MAZE.len() as u32);
+    let buffer: Vec<u8> = MAZE.iter()
+        .flat_map(|row| row.bytes())
+        .map(|cell| if cell == b'#' { 0 } else { 255 })
+        .collect();
+    let image = im::GrayImage::from_raw(width, height, buffer).ok_or("buffer doesn't fit the size")?;
+
+    let (start, goal) = (V2::new(1, 0), V2::new(9, 10));
+    let graph = extract_graph(&image, start, goal, &ExtractOptions::default())?;
+    println!("{} spaces extracted", graph.nodes().len());
+
+    let graph = graph.prune().into_adjacency_graph().contract();
+    let solved = graph.into_dijkstra();
+    let solution = solved.solution().ok_or("goal unreachable")?;
+    println!("length {} through {} spaces, {} expanded", solution.cost, solution.nodes.len(), solution.stats.expansions);
+
+    let pixels = refine_path(&solution.rects, start, goal);
+    let route: Vec<String> = pixels.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+    println!("{}", route.join(" "));
+
+    if let Some(path) = env::args().nth(1) {
+        let mut out = render::to_rgb(&image);
+        render::render_pixel_path(&mut out, &solution, &pixels, &RenderStyle::default());
+        fs::write(&path, encode_png(&out)?)?;
+        println!("wrote {}", path);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+#![no_main]
+
+// runs arbitrary small images and points through everything the library does to them. any
+// panic is a bug: bad input should come back as an Err.
+
+use {
+    libfuzzer_sys::fuzz_target,
+    mazesolve_rk::{
+        math::*,
+        image_graph::{self, Decomposition, ExtractOptions},
+        preprocess::{self, CLEAR, WALL},
+        refine, render,
+    },
+    image as im,
+};
+
+fn point(x: u8, y: u8) -> V2 {
+    // signed, so that points off the top and left get tried too
+    V2::new(x as i8 as Coord, y as i8 as Coord)
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 7 {
+        return;
+    }
+
+    let (w, h) = (data[0] as u32 % 48, data[1] as u32 % 48);
+    let start = point(data[2], data[3]);
+    let goal  = point(data[4], data[5]);
+    let flags = data[6];
+    let bits  = &data[7 ..];
+
+    let mut image = im::GrayImage::from_pixel(w, h, CLEAR);
+    for (i, pixel) in image.pixels_mut().enumerate() {
+        let set = bits.get(i / 8).is_some_and(|byte| byte & (1 << (i % 8)) != 0);
+        if set {
+            *pixel = WALL;
+        }
+    }
+
+    if flags & 0x01 != 0 { preprocess::mask_text(&mut image, (flags >> 4) as u32); }
+    if flags & 0x02 != 0 { image = preprocess::join_walls(&image, (flags >> 4) as u32); }
+    if flags & 0x04 != 0 { image_graph::seal_border(&mut image, &[start, goal]); }
+    if flags & 0x08 != 0 {
+        let skew = preprocess::estimate_skew(&image);
+        image = preprocess::rotate(&image, -skew);
+    }
+
+    let decomposition = if flags & 0x80 != 0 { Decomposition::Strips } else { Decomposition::Greedy };
+    let opts = ExtractOptions { decomposition, min_passage: 1 + (flags >> 5 & 3) as Coord };
+    let style = render::RenderStyle { alpha: 0.5, thickness: 3, ..Default::default() };
+    let mut canvas = im::RgbImage::new(w, h);
+
+    if let Ok(graph) = image_graph::extract_graph(&image, start, goal, &opts) {
+        let graph = graph.prune().into_adjacency_graph().contract().into_dijkstra();
+        render::render_nodes(&mut canvas, &graph, &style);
+        if let Some(solution) = graph.solution() {
+            let pixels = refine::refine_path(&solution.rects, start, goal);
+            render::render_path(&mut canvas, &solution, &style);
+            render::render_pixel_path(&mut canvas, &solution, &pixels, &style);
+        }
+    }
+
+    let exits = image_graph::border_openings(&image);
+    if let Ok((graph, sources)) = image_graph::extract_seeded_graph(&image, &exits, &opts) {
+        let graph = graph.into_adjacency_graph().contract().into_multi_source_dijkstra(&sources);
+        render::render_distance_field(&mut canvas, &graph, &style);
+        render::render_partition(&mut canvas, &graph, &sources, &style);
+    }
+});
@@ -0,0 +1,98 @@
+// batch mode: every image in a directory, each optionally with a sidecar beside it, maze.png
+// with maze.toml, overriding the global options for just that maze. sidecars are read as config
+// files are, though only these keys are taken from them:
+//
+//     start     = [2, 0]
+//     goal      = [401, 398]
+//     threshold = 200
+//     invert    = true
+
+use {
+    crate::{
+        cli::{parse_number, parse_point, Options},
+        config::{self, Setting},
+    },
+    mazesolve_rk::math::*,
+    std::{fs, io, path::{Path, PathBuf}},
+};
+
+// which files in a directory are mazes; what they hold is then told by their contents
+const IMAGE_EXTENSIONS: [&str; 11] = ["png", "gif", "bmp", "tga", "tif", "tiff", "pbm", "pgm", "ppm", "pnm", "qoi"];
+// videos, which are neither read nor written: batches and sequences take and give directories
+// of frames, split from and joined into video by e.g. ffmpeg
+const VIDEO_EXTENSIONS: [&str; 7] = ["mp4", "mkv", "webm", "avi", "mov", "m4v", "mpg"];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Overrides {
+    pub start:     Option<V2>,
+    pub goal:      Option<V2>,
+    pub threshold: Option<u8>,
+    pub invert:    Option<bool>,
+}
+
+impl Overrides {
+    pub fn apply(&self, opts: &mut Options) {
+        if let Some(start) = self.start         { opts.start = Some(start); }
+        if let Some(goal) = self.goal           { opts.goal = Some(goal); }
+        if let Some(threshold) = self.threshold { opts.threshold = Some(threshold); }
+        if let Some(invert) = self.invert       { opts.invert = invert; }
+    }
+}
+
+// the images directly inside `dir`, by name
+pub fn inputs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_image = path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+        if path.is_file() && is_image {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+    Ok(inputs)
+}
+
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+pub fn sidecar_path(input: &Path) -> PathBuf {
+    input.with_extension("toml")
+}
+
+// the input's overrides, or none if it has no sidecar
+pub fn read_overrides(input: &Path) -> Result<Overrides, String> {
+    let path = sidecar_path(input);
+    match fs::read_to_string(&path) {
+        Ok(text) => parse_overrides(&text).map_err(|err| format!("{}:{}", path.display(), err)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Overrides::default()),
+        Err(err) => Err(format!("reading {}: {}", path.display(), err)),
+    }
+}
+
+// the overrides among a sidecar's settings. errors start with the line number, as config's do
+pub fn parse_overrides(text: &str) -> Result<Overrides, String> {
+    let mut overrides = Overrides::default();
+    let settings: Vec<Setting> = config::parse(text)?;
+    for (key, values) in settings {
+        let value = || match values.last() {
+            Some(Some(value)) => Ok(value.as_str()),
+            _                 => Err(format!("{} needs a value", key)),
+        };
+        let bad = |err: String| format!("{}: {}", key, err);
+        match key.as_str() {
+            "start"     => overrides.start = Some(parse_point(value()?).map_err(bad)?),
+            "goal"      => overrides.goal = Some(parse_point(value()?).map_err(bad)?),
+            "threshold" => overrides.threshold = Some(parse_number(value()?).map_err(bad)?),
+            // true is a switch with no value, and false leaves it out
+            "invert"    => overrides.invert = Some(!values.is_empty()),
+            _           => return Err(format!("{}: can't be set per maze, only start, goal, threshold and invert", key)),
+        }
+    }
+    Ok(overrides)
+}
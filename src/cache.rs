@@ -0,0 +1,437 @@
+// extracted graphs saved to disk, compactly and in a layout that's read in place: a cache file
+// is mapped into memory and its spaces and openings decoded only as they're asked for.
+//
+// all little-endian. a 56-byte header:
+//     "MZGC", version: u32, key: u64, nodes: u64, edges: u64, start id: u64, goal id: u64,
+//     block: u32, 0: u32
+// then two tables of a u64 offset per block of `block` nodes, one into the node records and one
+// into the edge records, and the records. nodes are in id order. a node record is the id less
+// the last one's, then the rect's top-left less the last one's, zigzagged, then its width and
+// height, all varints; the first in a block is taken from 0 and the origin, so that any block
+// can be decoded alone. an edge record, one per node, is how many neighbours the node has with
+// higher ids and then those ids in order, each less the one before, starting from the node's.
+
+use {
+    crate::{
+        math::*,
+        graph::{Edge, EdgeSetGraph, HashMap, HashSet, NodeID, RawNodeID},
+        image_graph::{Decomposition, ExtractOptions, NodeData},
+    },
+    image as im,
+    std::{
+        convert::{TryFrom, TryInto},
+        fs, io,
+        path::Path,
+    },
+};
+
+const MAGIC:   &[u8; 4] = b"MZGC";
+const VERSION: u32 = 1;
+const HEADER:  usize = 56;
+// nodes per independently decodable block
+const BLOCK:   u32 = 256;
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("bad graph cache: {}", what))
+}
+
+// what the graph was extracted from, so that a cache of some other image or options is no use.
+// fnv-1a over the pixels and everything else extraction is given.
+pub fn cache_key(image: &im::GrayImage, start: V2, goal: V2, opts: &ExtractOptions) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash = (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    };
+    let decomposition: u8 = match opts.decomposition {
+        Decomposition::Greedy => 0,
+        Decomposition::Strips => 1,
+    };
+    feed(&image.width().to_le_bytes());
+    feed(&image.height().to_le_bytes());
+    for n in [start.x, start.y, goal.x, goal.y, opts.min_passage].iter() {
+        feed(&n.to_le_bytes());
+    }
+    feed(&[decomposition]);
+    feed(image.as_ref());
+    hash
+}
+
+// a no-op with wide-ids
+#[allow(clippy::useless_conversion)]
+fn raw(id: NodeID) -> u64 {
+    id.get().into()
+}
+
+fn put_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+// taken through 64 bits whatever the width of a coordinate, so 32 and 64-bit builds agree on
+// every coordinate both can hold
+#[allow(clippy::unnecessary_cast)]
+fn zigzag(n: Coord) -> u64 {
+    let n = n as i64;
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> Coord {
+    (((n >> 1) as i64) ^ -((n & 1) as i64)) as Coord
+}
+
+// the graph in the cache layout, tagged with `key`
+pub fn encode_graph(graph: &EdgeSetGraph<NodeData>, key: u64) -> Vec<u8> {
+    let mut nodes: Vec<(NodeID, Rect)> = graph.com.nodes.iter().map(|(id, data)| (*id, data.rect)).collect();
+    nodes.sort_unstable_by_key(|(id, _)| *id);
+
+    let mut higher: HashMap<NodeID, Vec<NodeID>> = HashMap::default();
+    for Edge { min, max } in graph.edges.iter().copied() {
+        higher.entry(min).or_default().push(max);
+    }
+
+    let blocks = nodes.chunks(BLOCK as usize);
+    let (mut node_data, mut edge_data) = (Vec::new(), Vec::new());
+    let (mut node_offsets, mut edge_offsets) = (Vec::new(), Vec::new());
+    for block in blocks {
+        node_offsets.push(node_data.len());
+        edge_offsets.push(edge_data.len());
+        let (mut last_id, mut last_mins) = (0, V2::new(0, 0));
+        for (id, rect) in block.iter() {
+            put_varint(&mut node_data, raw(*id) - last_id);
+            put_varint(&mut node_data, zigzag(rect.mins.x - last_mins.x));
+            put_varint(&mut node_data, zigzag(rect.mins.y - last_mins.y));
+            put_varint(&mut node_data, rect.width() as u64);
+            put_varint(&mut node_data, rect.height() as u64);
+            last_id = raw(*id);
+            last_mins = rect.mins;
+
+            let mut above = higher.remove(id).unwrap_or_default();
+            above.sort_unstable();
+            put_varint(&mut edge_data, above.len() as u64);
+            let mut last = raw(*id);
+            for neighbor in above {
+                put_varint(&mut edge_data, raw(neighbor) - last);
+                last = raw(neighbor);
+            }
+        }
+    }
+
+    let tables = HEADER + 16 * node_offsets.len();
+    let mut out = Vec::with_capacity(tables + node_data.len() + edge_data.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    for n in [key, nodes.len() as u64, graph.edges.len() as u64,
+              raw(graph.com.start), raw(graph.com.goal)].iter() {
+        out.extend_from_slice(&n.to_le_bytes());
+    }
+    out.extend_from_slice(&BLOCK.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    for offset in node_offsets.iter() {
+        out.extend_from_slice(&((tables + offset) as u64).to_le_bytes());
+    }
+    for offset in edge_offsets.iter() {
+        out.extend_from_slice(&((tables + node_data.len() + offset) as u64).to_le_bytes());
+    }
+    out.extend_from_slice(&node_data);
+    out.extend_from_slice(&edge_data);
+    out
+}
+
+pub fn save_graph(graph: &EdgeSetGraph<NodeData>, key: u64, path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, encode_graph(graph, key))
+}
+
+// a cache file's bytes: mapped where the platform allows, read whole otherwise
+pub enum CacheBytes {
+    #[cfg(unix)]
+    Mapped(Mapping),
+    Owned(Vec<u8>),
+}
+
+impl std::ops::Deref for CacheBytes {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(unix)]
+            CacheBytes::Mapped(mapping) => mapping.bytes(),
+            CacheBytes::Owned(bytes)    => bytes,
+        }
+    }
+}
+
+#[cfg(unix)]
+pub struct Mapping {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+#[cfg(unix)]
+impl Mapping {
+    fn bytes(&self) -> &[u8] {
+        // the mapping is readable for `len` bytes until dropped. a file truncated or rewritten
+        // under it while mapped is the one thing that can break this, as with any mapped file
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr, self.len); }
+    }
+}
+
+pub fn open_cache(path: impl AsRef<Path>) -> io::Result<CacheBytes> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        let file = fs::File::open(&path)?;
+        let len = usize::try_from(file.metadata()?.len()).map_err(|_| invalid("too big to map"))?;
+        if len == 0 {
+            return Ok(CacheBytes::Owned(Vec::new()));
+        }
+        let ptr = unsafe {
+            libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0)
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(CacheBytes::Mapped(Mapping { ptr, len }))
+    }
+    #[cfg(not(unix))]
+    {
+        fs::read(path).map(CacheBytes::Owned)
+    }
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    at:    usize,
+}
+
+impl Cursor<'_> {
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut n = 0u64;
+        for shift in (0 .. 64).step_by(7) {
+            let byte = *self.bytes.get(self.at).ok_or_else(|| invalid("record runs past the end"))?;
+            self.at += 1;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(invalid("varint too long"))
+    }
+
+    fn int(&mut self) -> io::Result<Coord> {
+        Coord::try_from(self.varint()?).map_err(|_| invalid("coordinate out of range"))
+    }
+}
+
+fn to_id(n: u64) -> io::Result<NodeID> {
+    RawNodeID::try_from(n).ok()
+        .and_then(NodeID::new)
+        .ok_or_else(|| invalid("node id out of range"))
+}
+
+// a cache file read in place. only the header is checked up front; each block is checked as
+// it's decoded.
+pub struct GraphView<'a> {
+    bytes:  &'a [u8],
+    key:    u64,
+    nodes:  u64,
+    edges:  u64,
+    start:  NodeID,
+    goal:   NodeID,
+    block:  u64,
+    blocks: usize,
+}
+
+impl<'a> GraphView<'a> {
+    pub fn new(bytes: &'a [u8]) -> io::Result<GraphView<'a>> {
+        if bytes.len() < HEADER || &bytes[0 .. 4] != MAGIC {
+            return Err(invalid("not a graph cache"));
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at .. at + 4].try_into().expect("4 bytes"));
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at .. at + 8].try_into().expect("8 bytes"));
+        if u32_at(4) != VERSION {
+            return Err(invalid("unknown version"));
+        }
+
+        // every node and edge takes at least a byte, so counts past the length are lies
+        let (nodes, edges, block) = (u64_at(16), u64_at(24), u32_at(48) as u64);
+        if block == 0 || nodes > bytes.len() as u64 || edges > bytes.len() as u64 {
+            return Err(invalid("impossible counts"));
+        }
+        let blocks = usize::try_from(nodes.div_ceil(block)).map_err(|_| invalid("too many blocks"))?;
+        let tables = blocks.checked_mul(16).and_then(|n| n.checked_add(HEADER));
+        if tables.is_none_or(|tables| tables > bytes.len()) {
+            return Err(invalid("offset tables run past the end"));
+        }
+
+        Ok(GraphView {
+            bytes,
+            key:   u64_at(8),
+            nodes,
+            edges,
+            start: to_id(u64_at(32))?,
+            goal:  to_id(u64_at(40))?,
+            block,
+            blocks,
+        })
+    }
+
+    pub fn key(&self) -> u64 { self.key }
+    pub fn len(&self) -> u64 { self.nodes }
+    pub fn is_empty(&self) -> bool { self.nodes == 0 }
+    pub fn edge_count(&self) -> u64 { self.edges }
+    pub fn start(&self) -> NodeID { self.start }
+    pub fn goal(&self) -> NodeID { self.goal }
+
+    fn offset(&self, table: usize, block: usize) -> io::Result<usize> {
+        let at = HEADER + 8 * (table * self.blocks + block);
+        let offset = u64::from_le_bytes(self.bytes[at .. at + 8].try_into().expect("8 bytes"));
+        usize::try_from(offset).ok()
+            .filter(|offset| *offset <= self.bytes.len())
+            .ok_or_else(|| invalid("offset past the end"))
+    }
+
+    fn block_len(&self, block: usize) -> usize {
+        (self.nodes - block as u64 * self.block).min(self.block) as usize
+    }
+
+    // the nodes of one block, in id order, each with the ids of its neighbours above it
+    pub fn decode_block(&self, block: usize) -> io::Result<Vec<(NodeID, Rect, Vec<NodeID>)>> {
+        if block >= self.blocks {
+            return Err(invalid("no such block"));
+        }
+        let mut nodes = Cursor { bytes: self.bytes, at: self.offset(0, block)? };
+        let mut edges = Cursor { bytes: self.bytes, at: self.offset(1, block)? };
+
+        let (mut id, mut mins) = (0u64, V2::new(0, 0));
+        let mut decoded = Vec::new();
+        for _ in 0 .. self.block_len(block) {
+            id = id.checked_add(nodes.varint()?).ok_or_else(|| invalid("node id out of range"))?;
+            mins.x = mins.x.wrapping_add(unzigzag(nodes.varint()?));
+            mins.y = mins.y.wrapping_add(unzigzag(nodes.varint()?));
+            let size = V2::new(nodes.int()?, nodes.int()?);
+            let maxs = mins.x.checked_add(size.x).zip(mins.y.checked_add(size.y))
+                .ok_or_else(|| invalid("rect out of range"))?;
+            let rect = Rect::new_unchecked(mins, V2::new(maxs.0, maxs.1));
+
+            let count = edges.varint()?;
+            let mut neighbor = id;
+            let mut above = Vec::new();
+            for _ in 0 .. count {
+                // neighbours are only ever above, so a step of nothing would join a node to itself
+                let step = match edges.varint()? {
+                    0    => return Err(invalid("node joined to itself")),
+                    step => step,
+                };
+                neighbor = neighbor.checked_add(step).ok_or_else(|| invalid("node id out of range"))?;
+                above.push(to_id(neighbor)?);
+            }
+            decoded.push((to_id(id)?, rect, above));
+        }
+        Ok(decoded)
+    }
+
+    // the node at `index` in id order, decoding only its block
+    pub fn node(&self, index: u64) -> io::Result<(NodeID, Rect)> {
+        if index >= self.nodes {
+            return Err(invalid("no such node"));
+        }
+        let block = self.decode_block((index / self.block) as usize)?;
+        let (id, rect, _) = block[(index % self.block) as usize];
+        Ok((id, rect))
+    }
+
+    // the whole graph, for searching
+    pub fn to_graph(&self) -> io::Result<EdgeSetGraph<NodeData>> {
+        let mut nodes: HashMap<NodeID, NodeData> = HashMap::with_capacity_and_hasher(self.nodes as usize, Default::default());
+        let mut edges: HashSet<Edge> = HashSet::with_capacity_and_hasher(self.edges as usize, Default::default());
+        for block in 0 .. self.blocks {
+            for (id, rect, above) in self.decode_block(block)? {
+                nodes.insert(id, NodeData::from_rect(rect));
+                edges.extend(above.into_iter().map(|neighbor| Edge::new(id, neighbor)));
+            }
+        }
+        if !nodes.contains_key(&self.start) || !nodes.contains_key(&self.goal)
+            || edges.iter().any(|edge| !nodes.contains_key(&edge.max))
+        {
+            return Err(invalid("edges or endpoints name missing nodes"));
+        }
+        Ok(EdgeSetGraph::new(nodes, self.start, self.goal, edges))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{graph::{tests::id, Graph}, image_graph::extract_graph, preprocess::{CLEAR, WALL}},
+    };
+
+    #[test]
+    fn cached_graph_reads_back_whole_and_in_part() {
+        // a grid of pillars, which splits into enough rects to fill a few blocks
+        let mut image = im::GrayImage::from_pixel(60, 60, CLEAR);
+        for y in (1 .. 60).step_by(2) {
+            for x in (1 .. 60).step_by(2) {
+                image.put_pixel(x, y, WALL);
+            }
+        }
+        let graph = extract_graph(&image, V2::new(0, 0), V2::new(58, 58), &ExtractOptions::default()).unwrap();
+        assert!(graph.nodes().len() > 512);
+        let bytes = encode_graph(&graph, 42);
+        let view = GraphView::new(&bytes).unwrap();
+        assert_eq!((view.key(), view.len()), (42, graph.nodes().len() as u64));
+
+        let read = view.to_graph().unwrap();
+        assert_eq!((read.start(), read.goal()), (graph.start(), graph.goal()));
+        assert_eq!(read.edges, graph.edges);
+        let mut ids: Vec<NodeID> = graph.nodes().keys().copied().collect();
+        ids.sort_unstable();
+        for (index, id) in ids.iter().enumerate() {
+            assert_eq!(read.get_node(*id).rect, graph.get_node(*id).rect);
+            assert_eq!(view.node(index as u64).unwrap(), (*id, graph.get_node(*id).rect));
+        }
+
+        assert!(GraphView::new(&bytes[.. 20]).is_err());
+        assert!(GraphView::new(&bytes[.. bytes.len() - 1]).and_then(|view| view.to_graph()).is_err());
+    }
+
+    #[test]
+    fn corrupt_blocks_are_refused() {
+        // two spaces joined, the first at the far right of what a coordinate can hold
+        let far = Rect::new_unchecked(V2::new(Coord::MAX - 1, 0), V2::new(Coord::MAX, 1));
+        let near = Rect::new_unchecked(V2::new(0, 0), V2::new(1, 1));
+        let nodes: HashMap<NodeID, NodeData> = [(id(1), far), (id(2), near)].iter()
+            .map(|(id, rect)| (*id, NodeData::from_rect(*rect)))
+            .collect();
+        let edges: HashSet<Edge> = [Edge::new(id(1), id(2))].iter().copied().collect();
+        let bytes = encode_graph(&EdgeSetGraph::new(nodes, id(1), id(2), edges), 0);
+        assert!(GraphView::new(&bytes).unwrap().to_graph().is_ok());
+
+        // the first node's width, after its id and x and y, widened past the coordinate's range
+        let mut wide = bytes.clone();
+        let mut at = GraphView::new(&bytes).unwrap().offset(0, 0).unwrap() + 1;
+        while wide[at] & 0x80 != 0 { at += 1; }
+        assert_eq!(wide[at + 2], 1);
+        wide[at + 2] = 5;
+        assert!(GraphView::new(&wide).unwrap().decode_block(0).is_err());
+
+        // the edges come last: the first node's one neighbour a step of one on, then the second's
+        // none. a step of nothing joins the first to itself
+        let mut looped = bytes.clone();
+        let len = looped.len();
+        assert_eq!(looped[len - 3 ..], [1, 1, 0]);
+        looped[len - 2] = 0;
+        assert!(GraphView::new(&looped).unwrap().decode_block(0).is_err());
+    }
+}
@@ -0,0 +1,1103 @@
+use {
+    crate::config,
+    mazesolve_rk::{
+        math::*,
+        floorplan::FloorplanOptions,
+        grid_search::PathCosts,
+        image_graph::{Decomposition, ExtractOptions},
+        levels::StairColors,
+        preprocess::{Adaptive, ColorClasses, MorphOp, MorphStep},
+        render::{RenderStyle, Theme},
+    },
+    std::str::FromStr,
+    image as im,
+};
+
+#[cfg(feature = "json")]
+use mazesolve_rk::schema::{self, Schema};
+
+pub const DEFAULT_OUTPUT: &str = "solved.png";
+pub const DIFF_OUTPUT: &str = "diff.png";
+pub const DEFAULT_WEIGHTS: PathCosts = PathCosts { step: 1000, turn: 10_000, crowding: 10_000 };
+
+pub const USAGE: &str = "\
+usage: mazesolve-rk [options] [input.png] [output.png]
+       mazesolve-rk [options] mazes/ [outdir/]
+       mazesolve-rk [options] --level floor0.png --level floor1.png ... [output.png]
+       mazesolve-rk [options] compare input.png
+       mazesolve-rk [options] check input.png
+       mazesolve-rk [options] diff first second [overlay.png]
+       mazesolve-rk self-test
+
+an input of - reads the image from stdin, and an output of - writes it to stdout as PNG,
+moving the results to stderr: curl -s URL | mazesolve-rk - - | display. inputs may be PNG,
+GIF, BMP, TIFF, TGA, PBM, PGM, PPM or QOI, told apart by their contents, not their names, or
+a text maze: mazelib's '#' grids, CSV of 0s and 1s or box drawing, whose S and E marks are
+its start and goal unless --start or --goal say otherwise.
+given a directory, solves every image in it into outdir (default: mazes/solved), summed up
+with thumbnails in outdir/report.html. a sidecar beside an image, maze.toml for maze.png, can
+set start, goal, threshold and invert for it. only directories of images are read and written,
+never video: split one into frames and join the solved frames back with e.g. ffmpeg.
+defaults for any option can be kept in mazesolve.toml in the working directory, or the file
+--config names, one `option = value` per line: algo = \"astar\", refine = true, start = [2, 0].
+options given on the command line override the file's: a mode or subcommand there replaces
+one the file picks, and --level, --seed or --exit there replace the file's whole list.
+compare runs every --algo and --solver from the start to the goal and tables the length each
+found, how much each searched and how long it took, without writing an image. explored is
+the share of the searchable area each looked at; --explore-maps DIR draws it as DIR/algo.png.
+with --frames DIR it also films two of the rect searches racing, side by side, a frame every
+--frame-every expansions; --race picks which.
+check reports whether the input is a well-formed maze: walled all round but for --openings
+gaps, with its clear space all one region they lead into. it exits 1 if not.
+diff sets two solutions of one maze against each other, each a solved image or a --path-json
+export, giving their lengths and whether the paths are the same, and draws them over the maze
+in the overlay (default: diff.png): the first's own pixels red, the second's blue, shared ones
+black. it exits 1 if they differ by more than --tolerance.
+
+options:
+    -o, --output F  the output, as an alternative to giving it after the input
+    --config FILE   read defaults for the options from FILE rather than mazesolve.toml
+    -v, --verbose   say more about each stage: spaces grown, openings found, prune passes;
+                    twice for every step. RUST_LOG, if set, overrides it, e.g.
+                    RUST_LOG=mazesolve_rk::graph=debug
+    -q, --quiet     only warnings and errors; twice for errors alone
+    --start X,Y     start pixel (default: 2,0)
+    --goal X,Y      goal pixel (default: bottom-right opening)
+    --snap R        move a start or goal on a wall to the nearest clear pixel within R px,
+                    warning of the move (default: 0, off)
+    --seed X,Y      partition the maze between seeds instead of solving;
+                    may be given several times
+    --escape        colour every corridor by its distance to the nearest exit
+    --sequence      treat the input directory as the frames of one changing maze, in name
+                    order, re-extracting only what changes between frames. split a video
+                    into frames first, e.g. ffmpeg -i maze.mp4 frames/%05d.png, and join
+                    the solved ones after: ffmpeg -i frames/solved/%05d.png out.mp4. spaces
+                    kept from earlier frames split the maze differently, so lengths may
+                    differ slightly from solving the frame alone
+    --exit X,Y      exit pixel for --escape (default: every border opening);
+                    may be given several times
+    --delta D       find --seed and --escape distances by delta-stepping across threads,
+                    settling every distance in a band D wide at once; try 1 on open images,
+                    larger where corridors are long
+    --wall-color C  hex RGB colour of the walls, e.g. 2040c0 for blue ink
+    --corridor-color C
+                    hex RGB colour of the corridors
+    --color-tolerance N
+                    how far from those colours a pixel may be (default: 64)
+    --clusters K    group the input's colours into K by k-means, for walls drawn in several
+                    colours, and list them darkest first. which are walls is asked on the
+                    terminal unless given by --wall-clusters; otherwise it's the darkest
+    --wall-clusters LIST
+                    comma-separated indices of the --clusters that are walls, e.g. 0,2
+    --level FILE    one floor of a multi-level maze, bottom first; may be given several
+                    times. writes OUTPUT.levelN.png for each floor
+    --up-color C    hex RGB colour of stairs going up a level (default: ff00ff)
+    --down-color C  hex RGB colour of stairs going down a level (default: 00ffff)
+    --start-level N level holding the start (default: 0)
+    --goal-level N  level holding the goal (default: the top one)
+    --teleporters   join places marked in the same strong colour, exactly two a colour, so
+                    that stepping onto either mark takes you to the other. the marks are
+                    found in the input as given, so not with --rotate or --deskew
+    --teleport-cost N
+                    what a jump costs, in steps from one space to the next; at least 1,
+                    as every step is (default: 1). implies --teleporters
+    --keys          solve a maze of keys and doors, marked in strong colours: a mark across a
+                    passage, wall to wall, is a door, and one standing clear of the walls a
+                    key. a door opens once a key of its colour has been stepped on, and one
+                    whose colour has no key stays shut
+    --one-way FILE  a map the size of the maze marking one-way passages, red where the way
+                    is right, green down, blue left and yellow up. a space with a mark in it
+                    can't be crossed into or out of against the arrow
+    --invert        swap light and dark, for light corridors on a dark ground
+    --threshold N   count every pixel at least N bright as clear (default: only white)
+    --flatten R     even out shadows and vignetting first, dividing by the paper's
+                    brightness estimated R px round, R more than half the walls' thickness.
+                    unless thresholded otherwise, the result is split at otsu's level
+    --adaptive M    threshold each pixel against its own neighbourhood instead, for photos
+                    of pages lit unevenly or faded toward the spine. M is sauvola, niblack or
+                    mean, optionally :WINDOW in px (default: 31) and :K, as sauvola:41:0.3.
+                    sauvola keeps blank paper clean; niblack finds fainter walls but specks;
+                    mean counts a pixel as wall a fraction K (default: 0.15) under the window's
+                    mean, quickest and fine for clean line drawings in shadow
+    --rotate DEG    rotate the input clockwise before anything else
+    --deskew        straighten the input by its dominant wall direction (after --rotate)
+    --floorplan     treat the input as a building plan: binarize, despeckle, strip door
+                    swings, and classify rooms and corridors
+    --room-width N  narrowest space counted as a room in --floorplan (default: 32)
+    --morph STEPS   wall morphology before extraction, as comma-separated OP:RADIUS[xITERS]
+                    with OP one of dilate, erode, open, close; e.g. close:1x2,open:1
+    --join-walls N  bridge gaps of up to N px in dashed and double-line walls
+    --mask-text N   clear detached wall blobs under N px across (labels, page numbers)
+    --crop          cut the input down to the maze, the box round its biggest piece of
+                    wall, before solving, so white margins and lettering round a scan don't
+                    become one huge space or a way round the outside. points given are still
+                    in the input, and the output is drawn over the whole of it
+    --crop-margin N px kept round the maze with --crop, for endpoints outside its walls
+                    (default: 0). implies --crop
+    --seal-border   wall up every border opening except those holding the start and goal
+                    (or the seeds/exits), after any --crop
+    --decompose D   rect decomposition: greedy (default), strips, or best, which tries
+                    both and keeps whichever gives fewer nodes
+    --min-passage N openings between spaces narrower than N px don't join them, so that
+                    pinholes in anti-aliased walls don't make false shortcuts (default: 1).
+                    strips span thin horizontal gaps whole, so pair it with greedy. not
+                    with --tile, whose seams cut openings short
+    --tile N        extract in N-px square tiles, stitched at the seams. a binary PGM input
+                    is then read a tile at a time and never held whole, for mazes too big
+                    for memory; only the results and --path-json are written for it
+    --graph-cache FILE
+                    keep the extracted graph in FILE and reuse it while the image, start,
+                    goal and extraction options are unchanged. it's read in place, mapped
+                    rather than parsed, so even huge graphs load quickly
+    --solver S      rects (default), searching the rect graph; jps, a jump point search
+                    straight over the pixels with no rects at all, for comparing the two,
+                    its length in px with diagonal steps counting 1.41; pixel-bfs, the
+                    exact shortest 4-connected pixel path however slow; left-hand or
+                    right-hand, walking with a hand on the wall, which needs no graph but
+                    only finds goals on the walls it sets out along, its route drawn from
+                    green at the start to red at the goal as it doubles back; or
+                    dead-end-fill, filling in every dead end pixel by pixel until only the
+                    way through is left, drawn at its full width, which --dead-ends dims
+                    the filled pixels around. in a maze with loops, they're left too
+    --algo A        how to search the rect graph: dijkstra (default); astar, as short but
+                    expanding fewer spaces; bfs, fewest hops between junctions, ignoring
+                    corridor lengths; greedy, quick but not always shortest; or tremaux,
+                    walking the maze as someone in it with chalk could, marking passages
+                    as it goes, each step logged at -vv and --frames filming the walk. jps,
+                    pixel-bfs, left-hand, right-hand and dead-end-fill pick those solvers.
+                    --frames films dijkstra and tremaux only
+    --objective O   what the path is shortest by: distance (default); turns, the fewest
+                    changes of direction and then the shortest, for plotters; or weighted,
+                    a balance of length, turns and clearance set by --weights. turns and
+                    weighted search 4-connected over the pixels, whatever the --solver
+    --weights L,T,C with --objective weighted, which giving them implies, what a step, a turn
+                    and running close to a wall each cost, in px: a step into a pixel
+                    N px from the nearest wall costs L + C/N, and each turn T
+                    (default: 1,10,10)
+    --full-search   have dijkstra reach every space rather than stop at the goal, so the
+                    stats and --frames cover the whole maze
+    --buckets       queue dijkstra's spaces in a bucket per distance (dial's) rather than a
+                    heap; quicker where corridors are short, as on open or noisy images
+    --refine        draw the exact pixel path through the solution rects
+    --hints FILE    a mask the size of the maze marking open fields light and tight corridors
+                    dark, for mixed maps like game levels. the refined path is then redone
+                    by any-angle (theta*) search across each open field it crosses
+    --dead-ends     dim every space pruned as a dead end
+    --show-rects    fill every space in a colour of its own, the same each run, rather than
+                    the explored ones in --explore-color, to see how the maze was cut up
+    --components    count the regions of clear space walls cut off from each other, warning
+                    if the start and goal, seeds or exits aren't all in one
+    --component-map FILE
+                    also write the input with each region in its own colour
+    --skeleton FILE also write the graph the search runs over, a dot for each space and a
+                    line for each way between, drawn over the maze faded
+    --openings N    gaps in the outer wall check expects (default: 2)
+    --tolerance R   how far apart in px diff lets two paths run and still count them the
+                    same, for solvers that take one corridor by different lines (default: 0)
+    --path-only     draw just the solution on white, without the maze or explored spaces
+    --indexed       write PNGs with a palette rather than RGB, which for a big maze's handful
+                    of colours comes out many times smaller
+    --no-render     draw nothing and write no image, for runs after only the results, stats
+                    or exports
+    --longest       find and draw the longest route without loops instead of the shortest;
+                    exact unless the search runs out of budget
+    --longest-budget N
+                    search steps before --longest settles for the best so far, then
+                    lengthens it by splicing in detours
+                    (default: 1000000)
+    --random-seed N seed the choices made at random, for runs that differ from the default
+                    but can still be repeated: the order --longest tries ways in, which
+                    matters once it runs out of budget, and the passage tremaux takes where
+                    several are marked alike. without it both go in a fixed order
+    --k-paths K     find and draw the K shortest distinct routes, each in its own colour
+    --theme NAME    the colours to render in: classic, red on green over black and white;
+                    dark, light walls on dark floors; colorblind, blues and oranges that
+                    read the same to most colour-blind eyes; or print, greys only
+                    (default: classic)
+    --path-color C  hex RGB colour of the solution, over the theme's (default: ff0000)
+    --explore-color C
+                    hex RGB colour of the explored spaces, over the theme's
+                    (default: 00ff00)
+    --thickness N   width of the --refine path line in px (default: 1)
+    --alpha A       opacity of the overlay fills, 0 to 1 (default: 1)
+    --downscale N   render at 1/N of the image's size, each output pixel the mean of N by N,
+                    though the maze is still solved at full size (default: 1)
+    --upscale N     render at N times the image's size, each pixel an N by N square and the
+                    path line N times as wide, for mazes of a pixel a cell (default: 1)
+    --frames DIR    write the search as numbered PNGs into DIR, ending on the solution
+    --explore-maps DIR
+                    with compare, write each rect search's explored spaces into DIR as
+                    algo.png, in the explore colour over the unexplored ones in blue
+    --debug-dir DIR write each stage into DIR as its own image, for seeing where extraction
+                    went wrong: binarized.png, the image as the walls were read;
+                    decomposition.png, each space in its own colour; pruned.png, the
+                    spaces left after pruning with the openings joining them and the dead
+                    ends dimmed; and distances.png, how far the search got from the start
+    --frame-every N expansions between frames (default: 100)
+    --race A,B      the two rect searches compare --frames races: dijkstra, astar, bfs,
+                    greedy or tremaux (default: dijkstra,astar)
+    --path-json F   write the solution rects and pixel path to F as JSON
+    --graph-json F  write the extracted spaces and the openings between them to F as JSON
+    --trace-json F  write the spaces the search expanded, in order, to F as JSON
+    --origin O      corner exported coordinates count from: top-left (default), as images
+                    do, or bottom-left with y up, as ROS maps and other robotics frames do
+    --jobs N        threads for batch runs and --tile (default: one per core). a batch
+                    solves mazes side by side, and splits any it tiles across threads too
+    --stats         print solver statistics and stage timings
+    --stats-json    as --stats, formatted as JSON
+    --manifest      write OUTPUT.manifest.json beside the output, recording the input hash,
+                    effective options, version and timings
+    --schema NAME   print the JSON schema of an export, one of path, stats, graph or trace,
+                    and exit. each export names its schema and version in a \"schema\" field;
+                    fields are only ever added within a version
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Solve,
+    Partition,
+    Escape,
+    Levels,
+    Sequence,
+    Compare,
+    Check,
+    Diff,
+    SelfTest,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Solver {
+    Rects,
+    JumpPoints,
+    PixelBfs,
+    LeftHand,
+    RightHand,
+    DeadEndFill,
+}
+
+impl Solver {
+    pub fn name(self) -> &'static str {
+        match self {
+            Solver::Rects      => "rects",
+            Solver::JumpPoints => "jps",
+            Solver::PixelBfs   => "pixel-bfs",
+            Solver::LeftHand   => "left-hand",
+            Solver::RightHand  => "right-hand",
+            Solver::DeadEndFill => "dead-end-fill",
+        }
+    }
+}
+
+// what the solution is shortest by
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Objective {
+    Distance,
+    Turns,
+    // length, turns and clearance weighed up by --weights
+    Weighted,
+}
+
+impl Objective {
+    pub fn name(self) -> &'static str {
+        match self {
+            Objective::Distance => "distance",
+            Objective::Turns    => "turns",
+            Objective::Weighted => "weighted",
+        }
+    }
+}
+
+// how the rect graph is searched
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Search {
+    Dijkstra,
+    AStar,
+    Bfs,
+    Greedy,
+    Tremaux,
+}
+
+impl Search {
+    pub fn name(self) -> &'static str {
+        match self {
+            Search::Dijkstra => "dijkstra",
+            Search::AStar    => "astar",
+            Search::Bfs      => "bfs",
+            Search::Greedy   => "greedy",
+            Search::Tremaux  => "tremaux",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsFormat {
+    Text,
+    #[cfg(feature = "json")]
+    Json,
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub mode:   Mode,
+    // the config file read, if any
+    pub config: Option<String>,
+    pub input:  String,
+    pub output: String,
+    pub start:  Option<V2>,
+    pub goal:   Option<V2>,
+    pub snap:   Coord,
+    pub seeds:  Vec<V2>,
+    pub exits:  Vec<V2>,
+    pub delta:  Option<i32>,
+    pub stats:  Option<StatsFormat>,
+    pub colors: ColorClasses,
+    pub clusters: Option<usize>,
+    pub wall_clusters: Option<Vec<usize>>,
+    pub levels: Vec<String>,
+    pub stairs: StairColors,
+    pub start_level: usize,
+    pub goal_level: Option<usize>,
+    pub teleporters: bool,
+    pub teleport_cost: i32,
+    pub one_way: Option<String>,
+    pub keys: bool,
+    pub invert: bool,
+    pub threshold: Option<u8>,
+    pub adaptive: Option<Adaptive>,
+    pub flatten: Option<u32>,
+    pub rotate: Option<f32>,
+    pub deskew: bool,
+    pub floorplan: Option<FloorplanOptions>,
+    pub morph: Vec<MorphStep>,
+    pub join_walls: Option<u32>,
+    pub mask_text: Option<u32>,
+    // the margin round the maze, if cropping
+    pub crop: Option<Coord>,
+    // set once --crop has cut the input down: the box kept, and the input's size
+    pub cropped: Option<(Rect, (u32, u32))>,
+    pub seal_border: bool,
+    pub extract: ExtractOptions,
+    pub best_decomposition: bool,
+    pub tile: Option<u32>,
+    pub graph_cache: Option<String>,
+    pub solver: Solver,
+    pub search: Search,
+    pub objective: Objective,
+    pub weights:   PathCosts,
+    pub full_search: bool,
+    pub buckets:     bool,
+    pub hints: Option<String>,
+    pub refine: bool,
+    pub k_paths: Option<usize>,
+    pub dead_ends: bool,
+    pub show_rects: bool,
+    pub components: bool,
+    pub component_map: Option<String>,
+    pub skeleton:   Option<String>,
+    pub openings: usize,
+    // diff's second solution, the input being the first
+    pub diff_with: Option<String>,
+    pub tolerance: Coord,
+    pub path_only: bool,
+    pub indexed: bool,
+    pub no_render: bool,
+    pub longest: Option<usize>,
+    pub random_seed: Option<u64>,
+    pub style: RenderStyle,
+    pub theme: Theme,
+    // the colours given outright, which win over the theme's whichever came first
+    pub path_color: Option<im::Rgb<u8>>,
+    pub explore_color: Option<im::Rgb<u8>>,
+    pub frames: Option<String>,
+    pub explore_maps: Option<String>,
+    pub race: Option<[Search; 2]>,
+    pub frame_every: usize,
+    pub debug_dir: Option<String>,
+    pub path_json: Option<String>,
+    pub graph_json: Option<String>,
+    pub trace_json: Option<String>,
+    pub origin: Origin,
+    pub jobs: Option<usize>,
+    // up one per -v, down one per -q
+    pub verbosity: i32,
+    #[cfg(feature = "json")]
+    pub manifest: bool,
+    #[cfg(feature = "json")]
+    pub schema: Option<Schema>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            mode:   Mode::Solve,
+            config: None,
+            input:  "maze2.png".into(),
+            output: DEFAULT_OUTPUT.into(),
+            start:  None,
+            goal:   None,
+            snap:   0,
+            seeds:  Vec::new(),
+            exits:  Vec::new(),
+            delta:  None,
+            stats:  None,
+            colors: ColorClasses::default(),
+            clusters: None,
+            wall_clusters: None,
+            levels: Vec::new(),
+            stairs: StairColors::default(),
+            start_level: 0,
+            goal_level: None,
+            teleporters: false,
+            teleport_cost: 1,
+            one_way: None,
+            keys: false,
+            invert: false,
+            threshold: None,
+            adaptive: None,
+            flatten: None,
+            rotate: None,
+            deskew: false,
+            floorplan: None,
+            morph: Vec::new(),
+            join_walls: None,
+            mask_text: None,
+            crop: None,
+            cropped: None,
+            seal_border: false,
+            extract: ExtractOptions::default(),
+            best_decomposition: false,
+            tile: None,
+            graph_cache: None,
+            solver: Solver::Rects,
+            search: Search::Dijkstra,
+            objective: Objective::Distance,
+            weights:   DEFAULT_WEIGHTS,
+            full_search: false,
+            buckets:     false,
+            hints: None,
+            refine: false,
+            k_paths: None,
+            dead_ends: false,
+            show_rects: false,
+            components: false,
+            component_map: None,
+            skeleton:   None,
+            openings: 2,
+            diff_with: None,
+            tolerance: 0,
+            path_only: false,
+            indexed: false,
+            no_render: false,
+            longest: None,
+            random_seed: None,
+            style: RenderStyle::default(),
+            theme: Theme::Classic,
+            path_color: None,
+            explore_color: None,
+            frames: None,
+            explore_maps: None,
+            race: None,
+            frame_every: 100,
+            debug_dir: None,
+            path_json: None,
+            graph_json: None,
+            trace_json: None,
+            origin: Origin::default(),
+            jobs: None,
+            verbosity: 0,
+            #[cfg(feature = "json")]
+            manifest: false,
+            #[cfg(feature = "json")]
+            schema: None,
+        }
+    }
+}
+
+pub fn parse_point(text: &str) -> Result<V2, String> {
+    let mut parts = text.splitn(2, ',');
+    let mut coord = || -> Result<Coord, String> {
+        let part = parts.next().ok_or_else(|| format!("expected X,Y, got '{}'", text))?;
+        Coord::from_str(part.trim()).map_err(|_| format!("bad coordinate in '{}'", text))
+    };
+    let x = coord()?;
+    let y = coord()?;
+    Ok(V2::new(x, y))
+}
+
+pub fn parse_number<T: FromStr>(text: &str) -> Result<T, String> {
+    T::from_str(text.trim()).map_err(|_| format!("bad number '{}'", text))
+}
+
+fn parse_color(text: &str) -> Result<im::Rgb<u8>, String> {
+    let hex = text.trim_start_matches('#');
+    let bad = || format!("expected an RRGGBB colour, got '{}'", text);
+    if hex.len() != 6 {
+        return Err(bad());
+    }
+
+    let rgb = u32::from_str_radix(hex, 16).map_err(|_| bad())?;
+    Ok(im::Rgb([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]))
+}
+
+pub fn parse_indices(text: &str) -> Result<Vec<usize>, String> {
+    text.split(',').map(parse_number).collect()
+}
+
+fn parse_race(text: &str) -> Result<[Search; 2], String> {
+    let searches = text.split(',')
+        .map(|name| match name.trim() {
+            "dijkstra" => Ok(Search::Dijkstra),
+            "astar"    => Ok(Search::AStar),
+            "bfs"      => Ok(Search::Bfs),
+            "greedy"   => Ok(Search::Greedy),
+            "tremaux"  => Ok(Search::Tremaux),
+            other      => Err(format!("unknown rect search '{}'", other)),
+        })
+        .collect::<Result<Vec<Search>, String>>()?;
+    match searches[..] {
+        [a, b] => Ok([a, b]),
+        _      => Err(format!("expected two searches to race, got '{}'", text)),
+    }
+}
+
+fn parse_morph(text: &str) -> Result<Vec<MorphStep>, String> {
+    text.split(',')
+        .map(|step| {
+            let bad = || format!("expected OP:RADIUS[xITERS], got '{}'", step);
+            let mut parts = step.splitn(2, ':');
+            let op = match parts.next().map(str::trim) {
+                Some("dilate") => MorphOp::Dilate,
+                Some("erode")  => MorphOp::Erode,
+                Some("open")   => MorphOp::Open,
+                Some("close")  => MorphOp::Close,
+                _              => return Err(bad()),
+            };
+
+            let size = parts.next().ok_or_else(bad)?;
+            let mut size = size.splitn(2, 'x');
+            let radius = parse_number(size.next().ok_or_else(bad)?)?;
+            let iterations = match size.next() {
+                Some(iters) => parse_number(iters)?,
+                None        => 1,
+            };
+
+            Ok(MorphStep { op, radius, iterations })
+        })
+        .collect()
+}
+
+// LENGTH,TURNS,CLEARANCE in px, as the search's costs in thousandths of one
+fn parse_weights(text: &str) -> Result<PathCosts, String> {
+    let weights = text.split(',')
+        .map(|part| match parse_number::<f64>(part)? {
+            w if w >= 0.0 && w.is_finite() => Ok((w * 1000.0).round() as u64),
+            _                             => Err(format!("weights can't be negative, got '{}'", part)),
+        })
+        .collect::<Result<Vec<u64>, String>>()?;
+    match weights[..] {
+        [step, turn, crowding] => Ok(PathCosts { step, turn, crowding }),
+        _ => Err(format!("expected LENGTH,TURNS,CLEARANCE weights, got '{}'", text)),
+    }
+}
+
+fn parse_adaptive(text: &str) -> Result<Adaptive, String> {
+    let bad = || format!("expected sauvola, niblack or mean[:WINDOW[:K]], got '{}'", text);
+    let mut parts = text.split(':');
+    let window = match parts.clone().nth(1) {
+        Some(window) => parse_number(window)?,
+        None         => 31,
+    };
+    if window == 0 {
+        return Err("--adaptive window must be at least 1".into());
+    }
+
+    let mut method = match parts.next().map(str::trim) {
+        Some("sauvola") => Adaptive::sauvola(window),
+        Some("niblack") => Adaptive::niblack(window),
+        Some("mean")    => Adaptive::mean(window),
+        _               => return Err(bad()),
+    };
+    if let Some(text) = parts.nth(1) {
+        let given: f32 = parse_number(text)?;
+        match &mut method {
+            Adaptive::Mean { k, .. } | Adaptive::Sauvola { k, .. } | Adaptive::Niblack { k, .. } => *k = given,
+        }
+    }
+    if parts.next().is_some() {
+        return Err(bad());
+    }
+    Ok(method)
+}
+
+// what one layer of options, the config file's or the command line's, has given so far
+#[derive(Default)]
+struct Layer {
+    // the output, by -o rather than as a positional
+    output_given: bool,
+    // the mode its options chose
+    mode:         Option<Mode>,
+    // the options it has given that can be given more than once
+    repeated:     Vec<&'static str>,
+    // whether it has given --upscale or --downscale
+    scaled:       bool,
+}
+
+impl Layer {
+    fn set_mode(&mut self, mode: Mode) -> Result<(), String> {
+        match self.mode {
+            Some(chosen) if chosen != mode => Err(format!("{:?} and {:?} modes are mutually exclusive", chosen, mode)),
+            _ => { self.mode = Some(mode); Ok(()) }
+        }
+    }
+
+    // the values of a repeated option, emptied the first time this layer gives it, so that the
+    // command line's replace the config file's rather than adding to them
+    fn repeat<'a, T>(&mut self, name: &'static str, values: &'a mut Vec<T>) -> &'a mut Vec<T> {
+        if !self.repeated.contains(&name) {
+            self.repeated.push(name);
+            values.clear();
+        }
+        values
+    }
+
+    // the render style, with both scales reset the first time this layer gives one, so that
+    // the command line's --downscale replaces the config file's upscale rather than clashing
+    fn rescale<'a>(&mut self, style: &'a mut RenderStyle) -> &'a mut RenderStyle {
+        if !self.scaled {
+            self.scaled = true;
+            style.upscale = 1;
+            style.downscale = 1;
+        }
+        style
+    }
+}
+
+// every option read so far, the command line's over the config file's
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let args: Vec<String> = args.into_iter().collect();
+    let named = match args.iter().position(|arg| arg == "--config") {
+        Some(at) => Some(args.get(at + 1).ok_or("--config needs a value")?.clone()),
+        None     => None,
+    };
+    let path = named.clone().unwrap_or_else(|| config::DEFAULT_PATH.to_string());
+    let settings = config::read(&path, named.is_some())?;
+    parse_layers(settings.map(|settings| (path, settings)), args)
+}
+
+// the config file's settings, if there is one, as the defaults the command line's options are
+// read over
+fn parse_layers(config: Option<(String, Vec<config::Setting>)>, args: Vec<String>) -> Result<Options, String> {
+    let mut opts = Options::default();
+    let mut defaults = Layer::default();
+    let mut given = Layer::default();
+    let mut positional = Vec::new();
+
+    if let Some((path, settings)) = config {
+        for (key, values) in settings {
+            if key == "config" {
+                return Err(format!("{}: a config file can't name another", path));
+            }
+            let flag = format!("--{}", key);
+            for value in values {
+                let mut value = value;
+                let mut take = || value.take().ok_or_else(|| format!("{} needs a value", key));
+                if !parse_option(&mut opts, &flag, &mut take, &mut defaults).map_err(|err| format!("{}: {}", path, err))? {
+                    return Err(format!("{}: unknown option {}", path, key));
+                }
+                if let Some(value) = value {
+                    return Err(format!("{}: {} takes no value, only {} = true, not '{}'", path, key, key, value));
+                }
+            }
+        }
+        opts.config = Some(path);
+    }
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        if !parse_option(&mut opts, &arg, &mut value, &mut given)? {
+            positional.push(arg);
+        }
+    }
+
+    let subcommand = match positional.first().map(String::as_str) {
+        Some("self-test") => Some(Mode::SelfTest),
+        Some("compare")   => Some(Mode::Compare),
+        Some("check")     => Some(Mode::Check),
+        Some("diff")      => Some(Mode::Diff),
+        _                 => None,
+    };
+    if let Some(mode) = subcommand {
+        positional.remove(0);
+        given.set_mode(mode)?;
+    }
+    // a mode the command line chooses replaces the config file's
+    opts.mode = given.mode.or(defaults.mode).unwrap_or(Mode::Solve);
+    if opts.mode == Mode::Diff {
+        if positional.len() < 2 {
+            return Err("diff needs two solutions to compare".into());
+        }
+        if !given.output_given && !defaults.output_given {
+            opts.output = DIFF_OUTPUT.into();
+        }
+    }
+
+    // the levels are the inputs, so then the only positional is the output
+    let mut positional = positional.into_iter();
+    if opts.mode != Mode::Levels {
+        if let Some(input) = positional.next() { opts.input = input; }
+    }
+    if opts.mode == Mode::Diff {
+        opts.diff_with = positional.next();
+    }
+    if !given.output_given {
+        if let Some(output) = positional.next() { opts.output = output; }
+    }
+    if let Some(extra)  = positional.next() {
+        return Err(format!("unexpected argument '{}'", extra));
+    }
+    if opts.style.upscale > 1 && opts.style.downscale > 1 {
+        return Err("--upscale and --downscale pull opposite ways; give one".into());
+    }
+    if opts.tile.is_some() && opts.extract.min_passage > 1 {
+        return Err("--tile cuts openings short at its seams, so can't be used with --min-passage".into());
+    }
+    opts.style = opts.theme.apply(opts.style);
+    if let Some(color) = opts.path_color { opts.style.path_color = color; }
+    if let Some(color) = opts.explore_color { opts.style.explore_color = color; }
+
+    Ok(opts)
+}
+
+// one option and its value, if it takes one, into `opts`. false if `arg` isn't an option at all
+fn parse_option(
+    opts:         &mut Options,
+    arg:          &str,
+    value:        &mut dyn FnMut() -> Result<String, String>,
+    layer:        &mut Layer)
+    -> Result<bool, String>
+{
+    match arg {
+        // read before everything else
+        "--config" => { value()?; }
+        "--start" => opts.start = Some(parse_point(&value()?)?),
+        "--goal"  => opts.goal  = Some(parse_point(&value()?)?),
+        "--snap"  => {
+            let radius: Coord = parse_number(&value()?)?;
+            if radius < 0 {
+                return Err("--snap can't be negative".into());
+            }
+            opts.snap = radius;
+        }
+        "--seed"  => {
+            let seed = parse_point(&value()?)?;
+            layer.repeat("seed", &mut opts.seeds).push(seed);
+            layer.set_mode(Mode::Partition)?;
+        }
+        "--escape" => layer.set_mode(Mode::Escape)?,
+        "--sequence" => layer.set_mode(Mode::Sequence)?,
+        "--exit"   => {
+            let exit = parse_point(&value()?)?;
+            layer.repeat("exit", &mut opts.exits).push(exit);
+        }
+        "--delta"  => {
+            let delta: i32 = parse_number(&value()?)?;
+            if delta < 1 {
+                return Err("--delta must be at least 1".into());
+            }
+            opts.delta = Some(delta);
+        }
+        "--wall-color"     => opts.colors.wall = Some(parse_color(&value()?)?),
+        "--corridor-color" => opts.colors.corridor = Some(parse_color(&value()?)?),
+        "--clusters"   => {
+            let k: usize = parse_number(&value()?)?;
+            if k < 2 {
+                return Err("--clusters must be at least 2".into());
+            }
+            opts.clusters = Some(k);
+        }
+        "--wall-clusters" => opts.wall_clusters = Some(parse_indices(&value()?)?),
+        "--color-tolerance" => {
+            opts.colors.tolerance = parse_number(&value()?)?;
+            opts.stairs.tolerance = opts.colors.tolerance;
+        }
+        "--level"      => {
+            let level = value()?;
+            layer.repeat("level", &mut opts.levels).push(level);
+            layer.set_mode(Mode::Levels)?;
+        }
+        "--up-color"   => opts.stairs.up = parse_color(&value()?)?,
+        "--down-color" => opts.stairs.down = parse_color(&value()?)?,
+        "--start-level" => opts.start_level = parse_number(&value()?)?,
+        "--goal-level" => opts.goal_level = Some(parse_number(&value()?)?),
+        "--teleporters" => opts.teleporters = true,
+        "--teleport-cost" => {
+            let cost: i32 = parse_number(&value()?)?;
+            if cost < 1 {
+                return Err("--teleport-cost must be at least 1".into());
+            }
+            opts.teleport_cost = cost;
+            opts.teleporters = true;
+        }
+        "--one-way"    => opts.one_way = Some(value()?),
+        "--keys"       => opts.keys = true,
+        "--invert"     => opts.invert = true,
+        "--threshold"  => opts.threshold = Some(parse_number(&value()?)?),
+        "--rotate"     => opts.rotate = Some(parse_number(&value()?)?),
+        "--deskew"     => opts.deskew = true,
+        "--floorplan"  => { opts.floorplan.get_or_insert_with(Default::default); }
+        "--room-width" => {
+            opts.floorplan.get_or_insert_with(Default::default).room_width = parse_number(&value()?)?;
+        }
+        "--flatten"    => {
+            let radius: u32 = parse_number(&value()?)?;
+            if radius == 0 {
+                return Err("--flatten must be at least 1".into());
+            }
+            opts.flatten = Some(radius);
+        }
+        "--adaptive"   => opts.adaptive = Some(parse_adaptive(&value()?)?),
+        "--morph"      => opts.morph = parse_morph(&value()?)?,
+        "--join-walls" => opts.join_walls = Some(parse_number(&value()?)?),
+        "--mask-text"  => opts.mask_text = Some(parse_number(&value()?)?),
+        "--crop"       => { opts.crop.get_or_insert(0); }
+        "--crop-margin" => {
+            let margin: Coord = parse_number(&value()?)?;
+            if margin < 0 {
+                return Err("--crop-margin can't be negative".into());
+            }
+            opts.crop = Some(margin);
+        }
+        "--seal-border" => opts.seal_border = true,
+        "--decompose"  => match value()?.as_str() {
+            "greedy" => opts.extract.decomposition = Decomposition::Greedy,
+            "strips" => opts.extract.decomposition = Decomposition::Strips,
+            "best"   => opts.best_decomposition = true,
+            other    => return Err(format!("unknown decomposition '{}'", other)),
+        },
+        "--min-passage" => {
+            let width: Coord = parse_number(&value()?)?;
+            if width < 1 {
+                return Err("--min-passage must be at least 1".into());
+            }
+            opts.extract.min_passage = width;
+        }
+        "--tile"       => {
+            let size: u32 = parse_number(&value()?)?;
+            if size == 0 {
+                return Err("--tile must be at least 1".into());
+            }
+            opts.tile = Some(size);
+        }
+        "--graph-cache" => opts.graph_cache = Some(value()?),
+        "-o" | "--output" => {
+            opts.output = value()?;
+            layer.output_given = true;
+        }
+        "--solver"     => opts.solver = match value()?.as_str() {
+            "rects"     => Solver::Rects,
+            "jps"       => Solver::JumpPoints,
+            "pixel-bfs" => Solver::PixelBfs,
+            "left-hand"  => Solver::LeftHand,
+            "right-hand" => Solver::RightHand,
+            "dead-end-fill" => Solver::DeadEndFill,
+            other       => return Err(format!("unknown solver '{}'", other)),
+        },
+        // the rect graph's searches, or the pixel solvers by name
+        "--algo"       => {
+            let (solver, search) = match value()?.as_str() {
+                "dijkstra"  => (Solver::Rects, Search::Dijkstra),
+                "astar"     => (Solver::Rects, Search::AStar),
+                "bfs"       => (Solver::Rects, Search::Bfs),
+                "greedy"    => (Solver::Rects, Search::Greedy),
+                "tremaux"   => (Solver::Rects, Search::Tremaux),
+                "jps"       => (Solver::JumpPoints, opts.search),
+                "pixel-bfs" => (Solver::PixelBfs, opts.search),
+                "left-hand"  => (Solver::LeftHand, opts.search),
+                "right-hand" => (Solver::RightHand, opts.search),
+                "dead-end-fill" => (Solver::DeadEndFill, opts.search),
+                other       => return Err(format!("unknown algorithm '{}'", other)),
+            };
+            opts.solver = solver;
+            opts.search = search;
+        }
+        "--objective"  => opts.objective = match value()?.as_str() {
+            "distance" => Objective::Distance,
+            "turns"    => Objective::Turns,
+            "weighted" => Objective::Weighted,
+            other      => return Err(format!("unknown objective '{}'", other)),
+        },
+        "--weights"    => {
+            opts.weights = parse_weights(&value()?)?;
+            opts.objective = Objective::Weighted;
+        }
+        "--full-search" => opts.full_search = true,
+        "--buckets" => opts.buckets = true,
+        "--refine"     => opts.refine = true,
+        "--hints"      => opts.hints = Some(value()?),
+        "--dead-ends"  => opts.dead_ends = true,
+        "--show-rects" => opts.show_rects = true,
+        "--components" => opts.components = true,
+        "--component-map" => {
+            opts.components = true;
+            opts.component_map = Some(value()?);
+        }
+        "--skeleton"   => opts.skeleton = Some(value()?),
+        "--openings"   => opts.openings = parse_number(&value()?)?,
+        "--tolerance"  => opts.tolerance = coord_clamped(parse_number(&value()?)?),
+        "--path-only"  => opts.path_only = true,
+        "--indexed"    => opts.indexed = true,
+        "--no-render"  => opts.no_render = true,
+        "--longest"    => { opts.longest.get_or_insert(1_000_000); }
+        "--longest-budget" => opts.longest = Some(parse_number(&value()?)?),
+        "--random-seed" => opts.random_seed = Some(parse_number(&value()?)?),
+        "--k-paths"    => {
+            let k: usize = parse_number(&value()?)?;
+            if k == 0 {
+                return Err("--k-paths must be at least 1".into());
+            }
+            opts.k_paths = Some(k);
+        }
+        "--frames"      => opts.frames = Some(value()?),
+        "--explore-maps" => opts.explore_maps = Some(value()?),
+        "--race"        => opts.race = Some(parse_race(&value()?)?),
+        "--frame-every" => opts.frame_every = parse_number(&value()?)?,
+        "--debug-dir"   => opts.debug_dir = Some(value()?),
+        "--theme"      => {
+            let name = value()?;
+            opts.theme = Theme::from_name(&name).ok_or_else(|| format!("unknown theme '{}'", name))?;
+        }
+        "--path-color"    => opts.path_color = Some(parse_color(&value()?)?),
+        "--explore-color" => opts.explore_color = Some(parse_color(&value()?)?),
+        "--thickness"  => {
+            let thickness = parse_number(&value()?)?;
+            if thickness == 0 {
+                return Err("--thickness must be at least 1".into());
+            }
+            opts.style.thickness = thickness;
+        }
+        "--downscale"  => {
+            let scale: u32 = parse_number(&value()?)?;
+            if scale == 0 {
+                return Err("--downscale must be at least 1".into());
+            }
+            layer.rescale(&mut opts.style).downscale = scale;
+        }
+        "--upscale"    => {
+            let scale: u32 = parse_number(&value()?)?;
+            if scale == 0 {
+                return Err("--upscale must be at least 1".into());
+            }
+            layer.rescale(&mut opts.style).upscale = scale;
+        }
+        "--alpha"      => {
+            let alpha: f32 = parse_number(&value()?)?;
+            if !(0.0 ..= 1.0).contains(&alpha) {
+                return Err(format!("--alpha must be between 0 and 1, got {}", alpha));
+            }
+            opts.style.alpha = alpha;
+        }
+        #[cfg(feature = "json")]
+        "--path-json"  => opts.path_json = Some(value()?),
+        #[cfg(feature = "json")]
+        "--graph-json" => opts.graph_json = Some(value()?),
+        #[cfg(feature = "json")]
+        "--trace-json" => opts.trace_json = Some(value()?),
+        "--origin"     => opts.origin = match value()?.as_str() {
+            "top-left"    => Origin::TopLeft,
+            "bottom-left" => Origin::BottomLeft,
+            other         => return Err(format!("unknown origin '{}'", other)),
+        },
+        "--jobs"       => {
+            let jobs: usize = parse_number(&value()?)?;
+            if jobs == 0 {
+                return Err("--jobs must be at least 1".into());
+            }
+            opts.jobs = Some(jobs);
+        }
+        "-v" | "--verbose" => opts.verbosity += 1,
+        "-vv"          => opts.verbosity += 2,
+        "-q" | "--quiet" => opts.verbosity -= 1,
+        "-qq"          => opts.verbosity -= 2,
+        "--stats"      => opts.stats = Some(StatsFormat::Text),
+        #[cfg(feature = "json")]
+        "--stats-json" => opts.stats = Some(StatsFormat::Json),
+        #[cfg(feature = "json")]
+        "--manifest"   => opts.manifest = true,
+        #[cfg(feature = "json")]
+        "--schema"     => {
+            let name = value()?;
+            let names: Vec<&str> = schema::ALL.iter().map(|schema| schema.name).collect();
+            opts.schema = Some(Schema::by_name(&name)
+                .ok_or_else(|| format!("unknown schema '{}', expected one of {}", name, names.join(", ")))?);
+        }
+        #[cfg(not(feature = "json"))]
+        "--path-json" | "--graph-json" | "--trace-json" | "--stats-json" | "--manifest" | "--schema" =>
+            return Err(format!("{} needs the json feature", arg)),
+        flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(config: &str, args: &[&str]) -> Result<Options, String> {
+        let settings = config::parse(config)?;
+        parse_layers(Some(("test.toml".into(), settings)), args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    #[test]
+    fn config_output_gives_way_to_a_positional() {
+        let opts = parse("output = \"x.png\"", &["in.png", "out.png"]).unwrap();
+        assert_eq!((opts.input.as_str(), opts.output.as_str()), ("in.png", "out.png"));
+        assert_eq!(parse("output = \"x.png\"", &["in.png"]).unwrap().output, "x.png");
+        assert!(parse("", &["-o", "x.png", "in.png", "out.png"]).is_err());
+    }
+
+    #[test]
+    fn command_line_modes_replace_the_configs() {
+        let config = "seed = [[1, 2], [3, 4]]";
+        assert_eq!(parse(config, &["in.png"]).unwrap().mode, Mode::Partition);
+        for (args, mode) in [
+            (&["check", "in.png"][..], Mode::Check),
+            (&["compare", "in.png"], Mode::Compare),
+            (&["self-test"], Mode::SelfTest),
+            (&["diff", "a.png", "b.png"], Mode::Diff),
+            (&["--escape", "--exit", "0,0", "in.png"], Mode::Escape),
+        ] {
+            assert_eq!(parse(config, args).unwrap().mode, mode, "{:?}", args);
+        }
+        assert!(parse("escape = true", &["--level", "a.png", "--level", "b.png"]).is_ok());
+        assert!(parse("", &["--escape", "check"]).is_err());
+    }
+
+    #[test]
+    fn command_line_repeats_replace_the_configs() {
+        let opts = parse("level = [\"a.png\", \"b.png\"]\nexit = [[0, 0]]", &["--level", "c.png"]).unwrap();
+        assert_eq!(opts.levels, vec!["c.png".to_string()]);
+        assert_eq!(opts.exits, vec![V2::new(0, 0)]);
+
+        let opts = parse("seed = [[1, 2], [3, 4]]", &["--seed", "5,6", "--seed", "7,8"]).unwrap();
+        assert_eq!(opts.seeds, vec![V2::new(5, 6), V2::new(7, 8)]);
+    }
+
+    #[test]
+    fn tiles_refuse_a_min_passage() {
+        assert!(parse("", &["--tile", "64", "--min-passage", "1"]).is_ok());
+        assert!(parse("", &["--tile", "64", "--min-passage", "2"]).is_err());
+        assert!(parse("min-passage = 2", &["--tile", "64"]).is_err());
+    }
+
+    #[test]
+    fn command_line_scales_replace_the_configs() {
+        let opts = parse("upscale = 2", &["--downscale", "3"]).unwrap();
+        assert_eq!((opts.style.upscale, opts.style.downscale), (1, 3));
+        let opts = parse("downscale = 2", &["--downscale", "3"]).unwrap();
+        assert_eq!((opts.style.upscale, opts.style.downscale), (1, 3));
+        assert!(parse("upscale = 2\ndownscale = 2", &[]).is_err());
+        assert!(parse("", &["--upscale", "2", "--downscale", "2"]).is_err());
+    }
+}
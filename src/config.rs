@@ -0,0 +1,119 @@
+// a config file of defaults for any option, mazesolve.toml in the working directory or the file
+// --config names, and the sidecars of batch mode, which read the same small subset of TOML: one
+// `key = value` per line, each key an option's long name without its dashes:
+//
+//     algo        = "astar"
+//     threshold   = 200
+//     refine      = true
+//     path-color  = "#0000ff"
+//     start       = [2, 0]
+//     level       = ["floor0.png", "floor1.png"]
+//
+// true gives an option that takes no value, false leaves it out; an array is joined with commas,
+// as X,Y and the like are given, but for options that can be given more than once, which take
+// one of its items each. the file's options are only defaults: the command line's replace them,
+// a repeated option's values all together, and a mode it picks replaces the file's.
+
+use std::{fs, io};
+
+pub const DEFAULT_PATH: &str = "mazesolve.toml";
+
+// an option's long name, and each value it's given: None for one that takes no value
+pub type Setting = (String, Vec<Option<String>>);
+
+// options that may be given more than once, each item of an array being one of them
+const REPEATED: [&str; 3] = ["level", "seed", "exit"];
+
+// each option in the file, or None if it's the default file and there isn't one
+pub fn read(path: &str, named: bool) -> Result<Option<Vec<Setting>>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse(&text).map(Some).map_err(|err| format!("{}:{}", path, err)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound && !named => Ok(None),
+        Err(err) => Err(format!("reading {}: {}", path, err)),
+    }
+}
+
+// the text before any comment, which a # within quotes doesn't start
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (at, c) in line.char_indices() {
+        match c {
+            '"'            => quoted = !quoted,
+            '#' if !quoted => return &line[.. at],
+            _              => { }
+        }
+    }
+    line
+}
+
+// the items of an array's insides, split at the commas outside any inner array or string
+fn split_items(text: &str) -> Vec<&str> {
+    let (mut items, mut depth, mut quoted, mut from) = (Vec::new(), 0, false, 0);
+    for (at, c) in text.char_indices() {
+        match c {
+            '"'                          => quoted = !quoted,
+            '[' if !quoted               => depth += 1,
+            ']' if !quoted               => depth -= 1,
+            ',' if !quoted && depth == 0 => { items.push(text[from .. at].trim()); from = at + 1; }
+            _                            => { }
+        }
+    }
+    items.push(text[from ..].trim());
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+// a string, number or array as the command line would give it
+fn parse_value(text: &str) -> Result<String, String> {
+    if let Some(inner) = text.strip_prefix('"') {
+        let inner = inner.strip_suffix('"').ok_or_else(|| format!("unterminated string {}", text))?;
+        if inner.contains('"') {
+            return Err(format!("expected one string, got {}", text));
+        }
+        return Ok(inner.to_string());
+    }
+    if let Some(inner) = text.strip_prefix('[') {
+        let inner = inner.strip_suffix(']').ok_or_else(|| format!("unterminated array {}", text))?;
+        let items = split_items(inner).into_iter().map(parse_value).collect::<Result<Vec<_>, _>>()?;
+        return Ok(items.join(","));
+    }
+    if text.parse::<f64>().is_ok() {
+        return Ok(text.to_string());
+    }
+    Err(format!("expected a number, \"string\", true, false or [array], got '{}'", text))
+}
+
+// errors start with the line number, for prefixing with the file name
+pub fn parse(text: &str) -> Result<Vec<Setting>, String> {
+    let mut options = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let at = |msg: String| format!("{}: {}", index + 1, msg);
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(at(format!("tables such as {} aren't read; give every option at the top", line)));
+        }
+
+        let mut parts = line.splitn(2, '=').map(str::trim);
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) if !key.is_empty() => (key, value),
+            _ => return Err(at(format!("expected KEY = VALUE, got '{}'", line))),
+        };
+
+        let values = match value {
+            "true"  => vec![None],
+            "false" => Vec::new(),
+            _ if REPEATED.contains(&key) && value.starts_with('[') && value.ends_with(']') => {
+                let items = split_items(&value[1 .. value.len() - 1]);
+                items.into_iter().map(|item| parse_value(item).map(Some)).collect::<Result<_, _>>().map_err(at)?
+            }
+            _ => vec![Some(parse_value(value).map_err(at)?)],
+        };
+        options.push((key.to_string(), values));
+    }
+
+    Ok(options)
+}
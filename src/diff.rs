@@ -0,0 +1,275 @@
+// diff mode: two solutions of one maze, each a solved image or a --path-json export, set against
+// each other. an image's path is its pixels in the path colour, so one drawn with another
+// --path-color needs that given again. the paths are identical if they cover the same pixels,
+// equivalent if each runs within --tolerance px of the other, and otherwise differ
+
+use {
+    crate::cli::Options,
+    mazesolve_rk::{graph::HashSet, math::*, sink::OutputSink, render},
+    image as im,
+    std::path::Path,
+};
+
+#[cfg(feature = "json")]
+use {
+    mazesolve_rk::{refine, schema},
+    serde::Deserialize,
+    std::fs,
+};
+
+const FIRST_ONLY:  im::Rgb<u8> = im::Rgb([0xe6, 0x19, 0x4b]);
+const SECOND_ONLY: im::Rgb<u8> = im::Rgb([0x43, 0x63, 0xd8]);
+const SHARED:      im::Rgb<u8> = im::Rgb([0x22, 0x22, 0x22]);
+
+// a solution as read back, before the two are brought into one frame
+enum Loaded {
+    Image(im::RgbImage),
+    // in the export's own frame, which bottom-left ones count up from the bottom in
+    #[cfg_attr(not(feature = "json"), allow(dead_code))]
+    Export { length: i32, bottom_left: bool, pixels: Vec<V2> },
+}
+
+#[cfg(feature = "json")]
+#[derive(Deserialize)]
+struct PathFile {
+    schema: String,
+    length: i32,
+    origin: String,
+    rects:  Vec<[Coord; 4]>,
+    pixels: Vec<[Coord; 2]>,
+}
+
+struct Route {
+    pixels: HashSet<V2>,
+    // in graph steps; an image doesn't record it
+    length: Option<i32>,
+}
+
+fn load(path: &str) -> Result<Loaded, String> {
+    let json = Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if json { load_export(path) }
+    else    { crate::open_image(path).map(|image| Loaded::Image(image.to_rgb())) }
+}
+
+#[cfg(feature = "json")]
+fn load_export(path: &str) -> Result<Loaded, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("reading {}: {}", path, err))?;
+    let export: PathFile = serde_json::from_str(&text).map_err(|err| format!("parsing {}: {}", path, err))?;
+    if export.schema != schema::PATH.id() {
+        return Err(format!("{} is {}, not a path export", path, export.schema));
+    }
+
+    // only refined solutions carry their pixels; otherwise they're walked through the rects
+    let mut pixels: Vec<V2> = export.pixels.iter().map(|p| V2::new(p[0], p[1])).collect();
+    if pixels.is_empty() && !export.rects.is_empty() {
+        let rects: Vec<Rect> = export.rects.iter()
+            .map(|r| Rect::new(V2::new(r[0], r[1]), V2::new(r[2], r[3])))
+            .collect();
+        pixels = refine::refine_path(&rects, rects[0].center(), rects[rects.len() - 1].center());
+    }
+    Ok(Loaded::Export { length: export.length, bottom_left: export.origin == "bottom-left", pixels })
+}
+
+#[cfg(not(feature = "json"))]
+fn load_export(path: &str) -> Result<Loaded, String> {
+    Err(format!("reading {} needs the json feature", path))
+}
+
+// the images' size, or for two exports just enough to hold both paths
+fn canvas_size(paths: [&str; 2], loaded: [&Loaded; 2]) -> Result<(u32, u32), String> {
+    let mut size: Option<(u32, u32)> = None;
+    let mut bounds = (0, 0);
+    for (path, loaded) in paths.iter().zip(loaded.iter()) {
+        match loaded {
+            Loaded::Image(image) => match size {
+                Some(dims) if dims != image.dimensions() =>
+                    return Err(format!("{} is {}x{}, but the other is {}x{}",
+                                       path, image.width(), image.height(), dims.0, dims.1)),
+                _ => size = Some(image.dimensions()),
+            },
+            Loaded::Export { pixels, .. } => for p in pixels {
+                bounds = (bounds.0.max(p.x + 1), bounds.1.max(p.y + 1));
+            },
+        }
+    }
+    Ok(size.unwrap_or((bounds.0.max(1) as u32, bounds.1.max(1) as u32)))
+}
+
+fn route(loaded: &Loaded, opts: &Options, height: Coord) -> Route {
+    match loaded {
+        Loaded::Image(image) => Route {
+            pixels: image.enumerate_pixels()
+                .filter(|(_, _, pixel)| **pixel == opts.style.path_color)
+                .map(|(x, y, _)| V2::new(x as Coord, y as Coord))
+                .collect(),
+            length: None,
+        },
+        Loaded::Export { length, bottom_left, pixels } => {
+            let origin = if *bottom_left { Origin::BottomLeft } else { Origin::TopLeft };
+            Route {
+                pixels: pixels.iter().map(|p| origin.point(*p, height)).collect(),
+                length: Some(*length),
+            }
+        }
+    }
+}
+
+// whether every pixel of `a` has one of `b` within `radius`
+fn covered(a: &HashSet<V2>, b: &HashSet<V2>, radius: Coord) -> bool {
+    a.iter().all(|p| {
+        let near = Rect::new(*p - V2::new(radius, radius), *p + V2::new(radius + 1, radius + 1));
+        near.points().any(|q| b.contains(&q))
+    })
+}
+
+// the maze faded under the paths, from the first image given, or blank for two exports
+fn overlay(loaded: [&Loaded; 2], (width, height): (u32, u32), routes: [&Route; 2]) -> im::RgbImage {
+    let maze = loaded.iter().find_map(|loaded| match loaded {
+        Loaded::Image(image) => Some(image),
+        Loaded::Export { .. } => None,
+    });
+    let mut canvas = match maze {
+        Some(maze) => { let mut canvas = maze.clone(); render::fade(&mut canvas); canvas }
+        None       => render::blank(width, height),
+    };
+
+    let [first, second] = routes;
+    let mut paint = |p: &V2, color| {
+        if p.x >= 0 && p.y >= 0 && (p.x as u32) < width && (p.y as u32) < height {
+            canvas.put_pixel(p.x as u32, p.y as u32, color);
+        }
+    };
+    for p in first.pixels.difference(&second.pixels)  { paint(p, FIRST_ONLY); }
+    for p in second.pixels.difference(&first.pixels)  { paint(p, SECOND_ONLY); }
+    for p in first.pixels.intersection(&second.pixels) { paint(p, SHARED); }
+    canvas
+}
+
+// reports how the two solutions compare and draws them overlaid, returning whether they're
+// equivalent
+pub fn run(opts: &Options, sink: &mut dyn OutputSink) -> Result<bool, String> {
+    let other = opts.diff_with.as_deref().expect("diff is given two solutions");
+    let paths = [opts.input.as_str(), other];
+    let (first, second) = (load(paths[0])?, load(paths[1])?);
+    let size = canvas_size(paths, [&first, &second])?;
+
+    let height = coord(size.1).map_err(|err| err.to_string())?;
+    let routes = [route(&first, opts, height), route(&second, opts, height)];
+    for (path, route) in paths.iter().zip(routes.iter()) {
+        if route.pixels.is_empty() {
+            return Err(format!("found no path in {}", path));
+        }
+        match route.length {
+            Some(length) => crate::report(opts, format_args!("{}: length {}, {} px", path, length, route.pixels.len())),
+            None         => crate::report(opts, format_args!("{}: {} px", path, route.pixels.len())),
+        }
+    }
+
+    let [a, b] = &routes;
+    if let (Some(first), Some(second)) = (a.length, b.length) {
+        crate::report(opts, format_args!("length difference: {:+}", second - first));
+    }
+    let identical = a.pixels == b.pixels;
+    let equivalent = identical
+        || (covered(&a.pixels, &b.pixels, opts.tolerance) && covered(&b.pixels, &a.pixels, opts.tolerance));
+    let shared = a.pixels.intersection(&b.pixels).count();
+    if identical {
+        crate::report(opts, "paths identical");
+    }
+    else {
+        let verdict = if equivalent { format!("equivalent within {} px", opts.tolerance) } else { "differ".to_string() };
+        crate::report(opts, format_args!("paths {}: {} px shared, {} only in the first, {} only in the second",
+                                         verdict, shared, a.pixels.len() - shared, b.pixels.len() - shared));
+    }
+
+    if !opts.no_render {
+        let image = overlay([&first, &second], size, [a, b]);
+        crate::save(sink, &opts.output, &image)?;
+    }
+    Ok(equivalent)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, mazesolve_rk::sink::MemorySink};
+
+    // a solved corridor, walled above and below, with the path along its middle row but for
+    // a wall in it at `block`, which the path steps down and round
+    fn solved(width: u32, block: Option<u32>, path_color: im::Rgb<u8>) -> im::RgbImage {
+        let mut image = im::RgbImage::from_fn(width, 5, |_, y| {
+            if y == 0 || y == 4 { im::Rgb([0, 0, 0]) } else { im::Rgb([255, 255, 255]) }
+        });
+        for x in 0 .. width {
+            match block {
+                Some(b) if x == b => {
+                    image.put_pixel(x, 2, im::Rgb([0, 0, 0]));
+                    image.put_pixel(x, 3, path_color);
+                }
+                Some(b) if x + 1 == b || x == b + 1 => {
+                    image.put_pixel(x, 2, path_color);
+                    image.put_pixel(x, 3, path_color);
+                }
+                _ => image.put_pixel(x, 2, path_color),
+            }
+        }
+        image
+    }
+
+    // diffs the two images as files, returning the verdict and the overlay
+    fn diff(name: &str, first: &im::RgbImage, second: &im::RgbImage, tolerance: Coord)
+        -> Result<(bool, im::RgbImage), String>
+    {
+        let dir = std::env::temp_dir();
+        let path = |n| dir.join(format!("mazesolve-diff-{}-{}-{}.png", std::process::id(), name, n));
+        let (a, b) = (path(1), path(2));
+        first.save(&a).unwrap();
+        second.save(&b).unwrap();
+
+        let opts = Options {
+            input:     a.to_string_lossy().into_owned(),
+            diff_with: Some(b.to_string_lossy().into_owned()),
+            tolerance,
+            ..Options::default()
+        };
+        let mut sink = MemorySink::default();
+        let result = run(&opts, &mut sink);
+        std::fs::remove_file(&a).unwrap();
+        std::fs::remove_file(&b).unwrap();
+        result.map(|same| (same, im::load_from_memory(&sink.images[0].1).unwrap().to_rgb()))
+    }
+
+    #[test]
+    fn identical_solutions_share_every_pixel() {
+        let color = Options::default().style.path_color;
+        let image = solved(12, None, color);
+        let (same, overlay) = diff("identical", &image, &image, 0).unwrap();
+        assert!(same);
+        assert!((0 .. 12).all(|x| *overlay.get_pixel(x, 2) == SHARED));
+        assert!(overlay.pixels().all(|p| *p != FIRST_ONLY && *p != SECOND_ONLY));
+    }
+
+    #[test]
+    fn a_wall_added_or_removed_splits_the_paths_round_it() {
+        let color = Options::default().style.path_color;
+        let (open, walled) = (solved(12, None, color), solved(12, Some(6), color));
+
+        // the wall added, then taken away again: the same pixels differ, the other way round
+        let (same, added) = diff("added", &open, &walled, 0).unwrap();
+        assert!(!same);
+        assert_eq!(*added.get_pixel(6, 2), FIRST_ONLY);
+        assert_eq!((*added.get_pixel(6, 3), *added.get_pixel(5, 2)), (SECOND_ONLY, SHARED));
+        let (same, removed) = diff("removed", &walled, &open, 0).unwrap();
+        assert!(!same);
+        assert_eq!((*removed.get_pixel(6, 2), *removed.get_pixel(6, 3)), (SECOND_ONLY, FIRST_ONLY));
+
+        // the detour runs a pixel from the straight way, so within a pixel they're as good
+        assert!(diff("tolerated", &open, &walled, 1).unwrap().0);
+    }
+
+    #[test]
+    fn images_of_different_sizes_are_refused() {
+        let color = Options::default().style.path_color;
+        let err = diff("sizes", &solved(12, None, color), &solved(13, None, color), 0).unwrap_err();
+        assert!(err.contains("is 13x5, but the other is 12x5"), "{}", err);
+    }
+}
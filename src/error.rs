@@ -0,0 +1,62 @@
+use {
+    crate::math::*,
+    std::fmt,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    // the point lies outside the image
+    OutOfBounds(V2),
+    // the point is on a wall
+    Blocked(V2),
+    // the point is clear, but not connected to the start
+    Unreachable(V2),
+    // the start and goal are each clear, but no passage joins them
+    Disconnected(V2, V2),
+    // no seeds were given to grow the graph from
+    NoSeeds,
+    // a multi-level point names a level past the last
+    NoLevel(usize),
+    // the image has more spaces than there are node ids
+    TooManyNodes,
+    // a pixel buffer's length isn't its width times its height
+    BufferSize { len: usize, expected: usize },
+    // the solve was cancelled by its token
+    Cancelled,
+    // the solve ran past its time limit
+    TimedOut,
+    // more colours of key and door than a key set has bits for
+    TooManyLocks(usize),
+    // following the wall came back round without passing the goal
+    WallLoop(V2),
+    // an image this many px across or down has positions past what a coordinate holds
+    TooLarge(u32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::OutOfBounds(p) => write!(f, "{},{} is outside the image", p.x, p.y),
+            Error::Blocked(p)     => write!(f, "{},{} is on a wall", p.x, p.y),
+            Error::Unreachable(p) => write!(f, "{},{} can't be reached from the start", p.x, p.y),
+            Error::Disconnected(a, b) => write!(f, "{},{} and {},{} are in parts of the maze no passage joins",
+                                          a.x, a.y, b.x, b.y),
+            Error::NoSeeds        => write!(f, "no seed points given"),
+            Error::NoLevel(level) => write!(f, "there is no level {}", level),
+            Error::TooManyNodes   => write!(f, "the image has more spaces than node ids; \
+                                                build with the wide-ids feature for more"),
+            Error::BufferSize { len, expected } => write!(f, "the buffer holds {} pixels, not {}",
+                                                          len, expected),
+            Error::Cancelled      => write!(f, "the solve was cancelled"),
+            Error::TimedOut       => write!(f, "the solve ran out of time"),
+            Error::TooManyLocks(n) => write!(f, "{} colours of key and door, but at most {} can be told apart",
+                                              n, crate::keys::MAX_LOCKS),
+            Error::WallLoop(p)    => write!(f, "following the wall came back round without passing {},{}",
+                                                p.x, p.y),
+            Error::TooLarge(n)    => write!(f, "{} px is too large for 32-bit coordinates; \
+                                                build with the wide-coords feature for more", n),
+        }
+    }
+}
+
+impl std::error::Error for Error { }
@@ -0,0 +1,181 @@
+use {
+    crate::{
+        math::*,
+        graph::{DijkstraGraph, EdgeSetGraph, Graph, RawNodeID},
+        image_graph::NodeData,
+        schema::{self, Schema},
+    },
+    serde::Serialize,
+    std::{fs, io},
+};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PathExport {
+    // in graph steps
+    pub length: i32,
+    // "top-left" or "bottom-left", the corner the coordinates count from
+    pub origin: &'static str,
+    // [x0, y0, x1, y1], half-open, from start to goal
+    pub rects:  Vec<[Coord; 4]>,
+    // [x, y], from start to goal
+    pub pixels: Vec<[Coord; 2]>,
+}
+
+impl PathExport {
+    // from image coordinates into `origin`'s frame, for an image `height` high
+    pub fn new(length: i32, rects: &[Rect], pixels: &[V2], origin: Origin, height: Coord) -> PathExport {
+        PathExport {
+            length,
+            origin: origin.name(),
+            rects:  rects.iter().map(|r| rect_array(*r, origin, height)).collect(),
+            pixels: pixels.iter()
+                .map(|p| origin.point(*p, height))
+                .map(|p| [p.x, p.y])
+                .collect(),
+        }
+    }
+
+    // moved by `by` in its own frame, as for a path through part of a bigger image
+    pub fn offset(mut self, by: V2) -> PathExport {
+        for rect in self.rects.iter_mut() {
+            *rect = offset_rect(*rect, by);
+        }
+        for p in self.pixels.iter_mut() {
+            *p = [p[0] + by.x, p[1] + by.y];
+        }
+        self
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        save(schema::PATH, self, path)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GraphNode {
+    pub id:   RawNodeID,
+    pub rect: [Coord; 4],
+    pub area: i64,
+}
+
+// the spaces as extracted, before pruning or contraction
+#[derive(Clone, Debug, Serialize)]
+pub struct GraphExport {
+    pub origin: &'static str,
+    pub start:  RawNodeID,
+    pub goal:   RawNodeID,
+    // sorted by id
+    pub nodes:  Vec<GraphNode>,
+    // sorted, each pair lower id first
+    pub edges:  Vec<[RawNodeID; 2]>,
+}
+
+impl GraphExport {
+    pub fn new(graph: &EdgeSetGraph<NodeData>, origin: Origin, height: Coord) -> GraphExport {
+        let mut nodes: Vec<GraphNode> = graph.nodes().iter()
+            .map(|(id, data)| GraphNode { id: id.get(), rect: rect_array(data.rect, origin, height), area: data.area })
+            .collect();
+        nodes.sort_by_key(|node| node.id);
+        let mut edges: Vec<[RawNodeID; 2]> = graph.edges.iter()
+            .map(|edge| [edge.min.get(), edge.max.get()])
+            .collect();
+        edges.sort_unstable();
+
+        GraphExport {
+            origin: origin.name(),
+            start:  graph.start().get(),
+            goal:   graph.goal().get(),
+            nodes, edges,
+        }
+    }
+
+    // as PathExport::offset()
+    pub fn offset(mut self, by: V2) -> GraphExport {
+        for node in self.nodes.iter_mut() {
+            node.rect = offset_rect(node.rect, by);
+        }
+        self
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        save(schema::GRAPH, self, path)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TraceStep {
+    pub node: RawNodeID,
+    pub dist: i32,
+    pub rect: [Coord; 4],
+}
+
+// the spaces a search expanded, in order. the searches other than dijkstra's only hand back
+// their route, so for them it's just that.
+#[derive(Clone, Debug, Serialize)]
+pub struct TraceExport {
+    pub origin: &'static str,
+    pub steps:  Vec<TraceStep>,
+}
+
+impl TraceExport {
+    pub fn new(graph: &DijkstraGraph<NodeData>, origin: Origin, height: Coord) -> TraceExport {
+        let steps = graph.order.iter()
+            .map(|id| TraceStep {
+                node: id.get(),
+                dist: graph.dists.get(id).copied().unwrap_or(i32::MAX),
+                rect: rect_array(graph.get_node(*id).rect, origin, height),
+            })
+            .collect();
+        TraceExport { origin: origin.name(), steps }
+    }
+
+    // as PathExport::offset()
+    pub fn offset(mut self, by: V2) -> TraceExport {
+        for step in self.steps.iter_mut() {
+            step.rect = offset_rect(step.rect, by);
+        }
+        self
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        save(schema::TRACE, self, path)
+    }
+}
+
+fn rect_array(r: Rect, origin: Origin, height: Coord) -> [Coord; 4] {
+    let r = origin.rect(r, height);
+    [r.mins.x, r.mins.y, r.maxs.x, r.maxs.y]
+}
+
+fn offset_rect([x0, y0, x1, y1]: [Coord; 4], by: V2) -> [Coord; 4] {
+    [x0 + by.x, y0 + by.y, x1 + by.x, y1 + by.y]
+}
+
+fn save<T: Serialize>(schema: Schema, export: &T, path: &str) -> io::Result<()> {
+    let json = serde_json::to_string(&schema.tag(export)).map_err(io::Error::other)?;
+    fs::write(path, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{graph::tests::single_room, image_graph::{extract_graph, ExtractOptions}},
+    };
+
+    #[test]
+    fn exports_carry_what_their_schemas_require() {
+        let graph = extract_graph(&single_room(), V2::new(1, 1), V2::new(6, 4), &ExtractOptions::default()).unwrap();
+        let exports = [
+            (schema::GRAPH, serde_json::to_value(schema::GRAPH.tag(&GraphExport::new(&graph, Origin::TopLeft, 6)))),
+            (schema::PATH, serde_json::to_value(schema::PATH.tag(&PathExport::new(0, &[], &[], Origin::TopLeft, 6)))),
+        ];
+        for (schema, export) in exports.iter() {
+            let export = export.as_ref().unwrap();
+            assert_eq!(export["schema"], schema.id());
+            for field in schema.document()["required"].as_array().unwrap() {
+                assert!(export.get(field.as_str().unwrap()).is_some(), "{} has no {}", schema.id(), field);
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use {
+    crate::{
+        graph::EdgeSetGraph,
+        image_graph::{NodeData, NodeKind},
+        math::Coord,
+        preprocess,
+    },
+    image as im,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct FloorplanOptions {
+    pub threshold:  u8,
+    pub despeckle:  usize,
+    pub thin_walls: u32,
+    pub room_width: Coord,
+}
+
+impl Default for FloorplanOptions {
+    fn default() -> FloorplanOptions {
+        FloorplanOptions {
+            threshold:  128,
+            despeckle:  64,
+            thin_walls: 1,
+            room_width: 32,
+        }
+    }
+}
+
+// binarizes a scanned plan, drops labels and furniture specks, and strips thin strokes such as
+// door swings so that doorways read as gaps in the thick walls
+pub fn preprocess(image: &im::GrayImage, opts: &FloorplanOptions) -> im::GrayImage {
+    let mut image = preprocess::threshold(image, opts.threshold);
+    preprocess::despeckle(&mut image, opts.despeckle);
+    if opts.thin_walls > 0 {
+        image = preprocess::open_walls(&image, opts.thin_walls);
+    }
+    image
+}
+
+// a rect wide enough in both directions to stand in is a room, anything narrower a corridor
+pub fn classify(graph: &mut EdgeSetGraph<NodeData>, opts: &FloorplanOptions) {
+    for data in graph.com.nodes.values_mut() {
+        let rect = data.rect;
+        data.kind = if rect.width().min(rect.height()) >= opts.room_width { NodeKind::Room }
+                    else                                                   { NodeKind::Corridor };
+    }
+}
@@ -0,0 +1,251 @@
+// inputs are told apart by their leading bytes, never their names, so a PGM saved as maze.png
+// or a PNG piped in on stdin loads all the same. beyond PNG, the formats feature reads GIF, BMP,
+// TIFF, the netpbm family (PBM, PGM and PPM, as generators tend to write) and QOI, decoded here;
+// mazes written out as text (see text_maze) come next, and TGA, with no magic of its own, is
+// tried last on whatever nothing else claims.
+
+use {
+    crate::math::*,
+    std::{fs, path::Path},
+    image as im,
+};
+
+// an input, and the start and goal it marks itself, as text mazes can
+pub struct Input {
+    pub image: im::DynamicImage,
+    pub start: Option<V2>,
+    pub goal:  Option<V2>,
+}
+
+impl From<im::DynamicImage> for Input {
+    fn from(image: im::DynamicImage) -> Input {
+        Input { image, start: None, goal: None }
+    }
+}
+
+pub fn open(path: impl AsRef<Path>) -> im::ImageResult<im::DynamicImage> {
+    decode(&fs::read(path)?)
+}
+
+pub fn decode(bytes: &[u8]) -> im::ImageResult<im::DynamicImage> {
+    read(bytes).map(|input| input.image)
+}
+
+pub fn open_input(path: impl AsRef<Path>) -> im::ImageResult<Input> {
+    read(&fs::read(path)?)
+}
+
+pub fn read(bytes: &[u8]) -> im::ImageResult<Input> {
+    #[cfg(feature = "formats")]
+    if bytes.starts_with(qoi::MAGIC) {
+        return qoi::decode(bytes).map(Input::from);
+    }
+    #[cfg(feature = "formats")]
+    if bytes.starts_with(b"P1") {
+        return plain_pbm(bytes).map(Input::from);
+    }
+
+    let format = match im::guess_format(bytes) {
+        Ok(format) => format,
+        #[cfg(feature = "formats")]
+        Err(_)     => return read_text_or_tga(bytes),
+        #[cfg(not(feature = "formats"))]
+        Err(err)   => return Err(err),
+    };
+    im::load_from_memory_with_format(bytes, format).map(Input::from)
+}
+
+#[cfg(feature = "formats")]
+fn read_text_or_tga(bytes: &[u8]) -> im::ImageResult<Input> {
+    use crate::text_maze::{self, TextFormat};
+
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) if TextFormat::sniff(text).is_some() => text,
+        _ => return im::load_from_memory_with_format(bytes, im::ImageFormat::TGA).map(Input::from),
+    };
+    let maze = text_maze::parse(text).map_err(im::ImageError::FormatError)?;
+    Ok(Input { image: im::DynamicImage::ImageLuma8(maze.image), start: maze.start, goal: maze.goal })
+}
+
+// a PBM written as text, '1' for black and '0' for white. the image crate's own reader hands
+// these back as bits unpacked but labelled packed, which comes out as noise
+#[cfg(feature = "formats")]
+fn plain_pbm(bytes: &[u8]) -> im::ImageResult<im::DynamicImage> {
+    let bad = |msg: &str| im::ImageError::FormatError(format!("bad PBM: {}", msg));
+
+    // whitespace-separated, with # comments to the end of the line, and the pixels needn't be
+    // separated at all
+    let mut tokens = Vec::new();
+    let mut at = 2;
+    while tokens.len() < 2 {
+        match bytes.get(at) {
+            None                          => return Err(bad("ends in the header")),
+            Some(b'#')                    => while bytes.get(at).is_some_and(|b| *b != b'\n') { at += 1; },
+            Some(b) if b.is_ascii_digit() => {
+                let start = at;
+                while bytes.get(at).is_some_and(u8::is_ascii_digit) { at += 1; }
+                tokens.push(std::str::from_utf8(&bytes[start .. at]).unwrap().parse::<u32>().map_err(|_| bad("size"))?);
+            }
+            Some(b) if b.is_ascii_whitespace() => at += 1,
+            Some(_)                       => return Err(bad("unexpected byte in the header")),
+        }
+    }
+    let (width, height) = (tokens[0], tokens[1]);
+
+    let mut pixels = Vec::with_capacity((width as usize * height as usize).min(bytes.len()));
+    for b in &bytes[at ..] {
+        match b {
+            b'0' => pixels.push(255),
+            b'1' => pixels.push(0),
+            b'#' => return Err(bad("comment among the pixels")),
+            b if b.is_ascii_whitespace() => { }
+            _    => return Err(bad("unexpected byte among the pixels")),
+        }
+        if pixels.len() as u64 == width as u64 * height as u64 {
+            break;
+        }
+    }
+    im::GrayImage::from_raw(width, height, pixels)
+        .map(im::DynamicImage::ImageLuma8)
+        .ok_or_else(|| bad("too few pixels"))
+}
+
+// the quite ok image format, https://qoiformat.org/qoi-specification.pdf
+#[cfg(feature = "formats")]
+pub mod qoi {
+    use image as im;
+
+    pub const MAGIC: &[u8] = b"qoif";
+
+    const HEADER_LEN: usize = 14;
+    const END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+    const OP_RGB:   u8 = 0xfe;
+    const OP_RGBA:  u8 = 0xff;
+    const OP_INDEX: u8 = 0b00;
+    const OP_DIFF:  u8 = 0b01;
+    const OP_LUMA:  u8 = 0b10;
+
+    fn bad(msg: &str) -> im::ImageError {
+        im::ImageError::FormatError(format!("bad QOI: {}", msg))
+    }
+
+    fn hash([r, g, b, a]: [u8; 4]) -> usize {
+        (r as usize * 3 + g as usize * 5 + b as usize * 7 + a as usize * 11) % 64
+    }
+
+    pub fn decode(bytes: &[u8]) -> im::ImageResult<im::DynamicImage> {
+        if bytes.len() < HEADER_LEN + END_MARKER.len() || !bytes.starts_with(MAGIC) {
+            return Err(bad("too short for a header"));
+        }
+        let be = |at: usize| u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+        let (width, height) = (be(4), be(8));
+        if width == 0 || height == 0 {
+            return Err(bad("no pixels"));
+        }
+        // every chunk makes at least one pixel, and a run at most 62, which bounds the size a
+        // file can honestly claim before anything's allocated for it
+        let chunks = bytes.len() - HEADER_LEN - END_MARKER.len();
+        let total = width as u64 * height as u64;
+        if total > chunks as u64 * 62 {
+            return Err(bad("fewer chunks than the size needs"));
+        }
+
+        let mut out = Vec::with_capacity(total as usize * 4);
+        let mut index = [[0u8; 4]; 64];
+        let mut px = [0, 0, 0, 255];
+        let mut at = HEADER_LEN;
+        let end = bytes.len() - END_MARKER.len();
+        let mut next = || {
+            let byte = bytes.get(at).copied().filter(|_| at < end).ok_or_else(|| bad("ends early"));
+            at += 1;
+            byte
+        };
+
+        while (out.len() as u64) < total * 4 {
+            let op = next()?;
+            let mut run = 1;
+            match op {
+                OP_RGB  => { px[0] = next()?; px[1] = next()?; px[2] = next()?; }
+                OP_RGBA => { px = [next()?, next()?, next()?, next()?]; }
+                _ => match op >> 6 {
+                    OP_INDEX => px = index[(op & 0x3f) as usize],
+                    OP_DIFF  => {
+                        px[0] = px[0].wrapping_add((op >> 4 & 3).wrapping_sub(2));
+                        px[1] = px[1].wrapping_add((op >> 2 & 3).wrapping_sub(2));
+                        px[2] = px[2].wrapping_add((op & 3).wrapping_sub(2));
+                    }
+                    OP_LUMA  => {
+                        let dg = (op & 0x3f).wrapping_sub(32);
+                        let rest = next()?;
+                        px[0] = px[0].wrapping_add(dg.wrapping_add(rest >> 4).wrapping_sub(8));
+                        px[1] = px[1].wrapping_add(dg);
+                        px[2] = px[2].wrapping_add(dg.wrapping_add(rest & 0xf).wrapping_sub(8));
+                    }
+                    _        => run = (op & 0x3f) as usize + 1,
+                },
+            }
+            index[hash(px)] = px;
+            for _ in 0 .. run {
+                out.extend_from_slice(&px);
+            }
+        }
+        out.truncate(total as usize * 4);
+
+        let image = im::RgbaImage::from_raw(width, height, out).ok_or_else(|| bad("size"))?;
+        Ok(im::DynamicImage::ImageRgba8(image))
+    }
+}
+
+#[cfg(all(test, feature = "formats"))]
+mod tests {
+    use {
+        super::*,
+        crate::{image_graph::{extract_graph, ExtractOptions}, preprocess::{CLEAR, WALL}},
+    };
+
+    #[test]
+    fn inputs_decode_by_their_contents() {
+        // a QOI with one chunk of each kind: rgb, run, rgb, index, diff and luma
+        let mut qoi = b"qoif\0\0\0\x06\0\0\0\x01\x03\0".to_vec();
+        qoi.extend_from_slice(&[0xfe, 255, 255, 255, 0xc0, 0xfe, 0, 0, 0, 0x26, 0x53, 0x96, 0xa5]);
+        qoi.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        let read = decode(&qoi).unwrap().to_rgb();
+        assert_eq!(read.into_raw(), [
+            255, 255, 255,  255, 255, 255,  0, 0, 0,  255, 255, 255,  254, 253, 0,  246, 243, 243,
+        ]);
+
+        // plain and packed PBMs of the same two rows, black being 1
+        let plain = decode(b"P1\n# a comment\n3 2\n1 0 1\n010\n").unwrap().to_luma();
+        let packed = decode(b"P4 3 2\n\xa0\x40").unwrap().to_luma();
+        assert_eq!(plain.into_raw(), [0, 255, 0, 255, 0, 255]);
+        assert_eq!(packed.into_raw(), [0, 255, 0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn text_mazes_read_with_their_marks() {
+        // the same corridor three ways, S to E round a wall
+        let mazelib = read(b"#S###\n#   #\n### #\n#   #\n#E###\n").unwrap();
+        let csv = read(b"1,S,1,1,1\n1,0,0,0,1\n1,1,1,0,1\n1,0,0,0,1\n1,E,1,1,1\n").unwrap();
+        for input in &[&mazelib, &csv] {
+            assert_eq!((input.start, input.goal), (Some(V2::new(1, 0)), Some(V2::new(1, 4))));
+            assert_eq!(input.image.to_luma().into_raw(), mazelib.image.to_luma().into_raw());
+        }
+        let graph = extract_graph(&mazelib.image.to_luma(), V2::new(1, 0), V2::new(1, 4), &ExtractOptions::default());
+        assert!(graph.unwrap().into_adjacency_graph().contract().into_dijkstra().solution().is_some());
+
+        // box drawing, three px a character with the outer rim cut off, and ASCII's likewise
+        let boxed = "┌S──┐\n│ ╶─┤\n└──E┘\n";
+        let ascii = "+S--+\n|  -+\n+--E+\n";
+        for text in &[boxed, ascii] {
+            let input = read(text.as_bytes()).unwrap();
+            let image = input.image.to_luma();
+            assert_eq!(image.dimensions(), (13, 7));
+            assert_eq!((input.start, input.goal), (Some(V2::new(3, 0)), Some(V2::new(9, 6))));
+            assert_eq!(*image.get_pixel(3, 0), CLEAR);
+            assert_eq!(*image.get_pixel(12, 3), WALL);
+            let graph = extract_graph(&image, V2::new(3, 0), V2::new(9, 6), &ExtractOptions::default());
+            assert!(graph.unwrap().into_adjacency_graph().contract().into_dijkstra().solution().is_some());
+        }
+    }
+}
@@ -1,21 +1,77 @@
 
 use {
+    crate::{
+        error::Error,
+        math::Rect,
+        spatial::{RectIndex, Spatial},
+    },
     std::{
         cmp::Reverse,
-        collections::{HashMap, HashSet},
-        mem::swap,
+        collections::{hash_map::DefaultHasher, BinaryHeap, VecDeque},
+        hash::BuildHasherDefault,
+        sync::OnceLock,
     },
     priority_queue::PriorityQueue,
+    log::debug,
+    pcg_rand::Pcg32Basic,
+    rand::{seq::SliceRandom, SeedableRng},
 };
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// std's maps seed their hashers afresh for every map, so two runs over the same image would walk
+// nodes, and break ties between them, in different orders. graphs hash with fixed keys instead,
+// so the same input always comes out byte for byte the same
+pub type FixedState = BuildHasherDefault<DefaultHasher>;
+pub type HashMap<K, V> = std::collections::HashMap<K, V, FixedState>;
+pub type HashSet<T> = std::collections::HashSet<T, FixedState>;
+
 pub trait Graph<Data> {
     fn get_node(&self, id: NodeID) -> &Data;
     fn start(&self) -> NodeID;
     fn goal(&self) -> NodeID;
     fn nodes(&self) -> &HashMap<NodeID, Data>;
+    fn common(&self) -> &GraphCommon<Data>;
+
+    // start and goal in the same space, so the route is just that space
+    fn is_trivial(&self) -> bool {
+        self.start() == self.goal()
+    }
+
+    // every node whose rect shares a pixel with `rect`, in no particular order. the index behind
+    // it is built by the first call and kept for the graph's life.
+    fn nodes_in<'a>(&'a self, rect: Rect) -> impl Iterator<Item = NodeID> + 'a where Data: Spatial + 'a {
+        let com = self.common();
+        com.index
+            .get_or_init(|| RectIndex::new(com.nodes.iter().map(|(id, data)| (*id, data.rect()))))
+            .nodes_in(rect)
+    }
 }
 
+// 32 bits unless the wide-ids feature asks for 64, for images of more than four billion spaces
+#[cfg(not(feature = "wide-ids"))]
 pub type NodeID = std::num::NonZeroU32;
+#[cfg(feature = "wide-ids")]
+pub type NodeID = std::num::NonZeroU64;
+
+// the integer under a NodeID
+#[cfg(not(feature = "wide-ids"))]
+pub type RawNodeID = u32;
+#[cfg(feature = "wide-ids")]
+pub type RawNodeID = u64;
+
+pub const FIRST_ID: NodeID = NodeID::MIN;
+
+// `id` moved up by `offset`, or an error if that's past the last id
+pub fn offset_id(id: NodeID, offset: RawNodeID) -> Result<NodeID, Error> {
+    id.checked_add(offset).ok_or(Error::TooManyNodes)
+}
+
+// the id after `id`
+pub fn next_id(id: NodeID) -> Result<NodeID, Error> {
+    offset_id(id, 1)
+}
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Edge {
@@ -29,25 +85,82 @@ impl Edge {
     }
 }
 
-struct GraphCommon<Data> {
+#[derive(Clone)]
+pub struct GraphCommon<Data> {
     pub nodes: HashMap<NodeID, Data>,
     pub start: NodeID,
-    pub goal:  NodeID
+    pub goal:  NodeID,
+    // for nodes_in(), once asked. graphs are rebuilt rather than edited, and an edit in place
+    // that moves a node's rect leaves this behind
+    index:     OnceLock<RectIndex>,
 }
 
+#[derive(Clone)]
 pub struct EdgeSetGraph<Data> {
     pub com:   GraphCommon<Data>,
     pub edges: HashSet<Edge>,
+    // edges that can only be crossed one way, each with the node it leads to
+    pub one_way: HashMap<Edge, NodeID>,
+}
+
+// a corridor of degree-2 nodes collapsed by contract(); `via` runs from `from` to `to`
+#[derive(Clone, Debug)]
+pub struct Chain {
+    pub from:    NodeID,
+    pub to:      NodeID,
+    pub via:     Vec<NodeID>,
+    // the weight from `from` to each of `via`, then on to `to`
+    pub offsets: Vec<i32>,
+}
+
+impl Chain {
+    pub fn weight(&self) -> i32 {
+        self.offsets.last().copied().unwrap_or(1)
+    }
 }
 
 pub struct AdjacencyGraph<Data> {
-    pub com:  GraphCommon<Data>,
-    pub adjs: HashMap<NodeID, HashSet<NodeID>>,
+    pub com:    GraphCommon<Data>,
+    pub adjs:   HashMap<NodeID, HashSet<NodeID>>,
+    pub chains: Vec<Chain>,
+    // the shortest chain standing in for each contracted adjacency
+    pub links:  HashMap<Edge, usize>,
+    // what each adjacency costs, where it's not the 1 of stepping into the next space
+    pub weights: HashMap<Edge, i32>,
+}
+
+pub struct LongestPath {
+    pub length: i32,
+    pub path:   Vec<NodeID>,
+    // whether every path was tried, rather than the search running out of budget
+    pub exact:  bool,
+}
+
+// what a search did to find its answer, for comparing one against another
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    // nodes taken off the open list
+    pub expansions:   usize,
+    // times a node was reached more cheaply than before
+    pub relaxations:  usize,
+    // the most entries on the open list at once
+    pub max_frontier: usize,
+}
+
+impl SearchStats {
+    pub fn saw_frontier(&mut self, len: usize) {
+        self.max_frontier = self.max_frontier.max(len);
+    }
 }
 
 pub struct DijkstraGraph<Data> {
-    pub inner: AdjacencyGraph<(Data, i32)>,
-    pub paths: HashMap<NodeID, NodeID>,
+    pub inner:   AdjacencyGraph<Data>,
+    pub dists:   HashMap<NodeID, i32>,
+    pub paths:   HashMap<NodeID, NodeID>,
+    pub origins: HashMap<NodeID, NodeID>,
+    // search nodes in the order they were expanded
+    pub order:   Vec<NodeID>,
+    pub stats:   SearchStats,
 }
 
 impl<Data> Graph<Data> for EdgeSetGraph<Data> {
@@ -59,6 +172,7 @@ impl<Data> Graph<Data> for EdgeSetGraph<Data> {
     fn nodes(&self) -> &HashMap<NodeID, Data> {
         &self.com.nodes
     }
+    fn common(&self) -> &GraphCommon<Data> { &self.com }
 }
 
 impl<Data> Graph<Data> for AdjacencyGraph<Data> {
@@ -70,145 +184,879 @@ impl<Data> Graph<Data> for AdjacencyGraph<Data> {
     fn nodes(&self) -> &HashMap<NodeID, Data> {
         &self.com.nodes
     }
+    fn common(&self) -> &GraphCommon<Data> { &self.com }
 }
 
 impl<Data> Graph<Data> for DijkstraGraph<Data> {
     fn get_node(&self, id: NodeID) -> &Data {
-        &self.inner.get_node(id).0
+        self.inner.get_node(id)
     }
     fn start(&self) -> NodeID { self.inner.start() }
     fn goal(&self) -> NodeID { self.inner.goal() }
     fn nodes(&self) -> &HashMap<NodeID, Data> {
         self.inner.nodes()
     }
+    fn common(&self) -> &GraphCommon<Data> { self.inner.common() }
 }
 
-impl<Data> EdgeSetGraph<Data> {
+impl<Data: Copy> EdgeSetGraph<Data> {
     pub fn new(nodes: HashMap<NodeID, Data>, start: NodeID, goal: NodeID, edges: HashSet<Edge>)
         -> EdgeSetGraph<Data>
     {
-        let com = GraphCommon { nodes, start, goal };
-        EdgeSetGraph { com, edges }
+        let com = GraphCommon { nodes, start, goal, index: OnceLock::new() };
+        EdgeSetGraph { com, edges, one_way: HashMap::default() }
     }
 
-    pub fn to_adjacency_graph(self) -> AdjacencyGraph<Data> {
-        let mut adjs = HashMap::new();
+    // every node gets an entry, even with no edges, such as a lone room holding start and goal.
+    // a one-way edge is a neighbour of the node it leaves only
+    pub fn into_adjacency_graph(self) -> AdjacencyGraph<Data> {
+        let mut adjs: HashMap<NodeID, HashSet<NodeID>> = self.com.nodes.keys()
+            .map(|id| (*id, HashSet::default()))
+            .collect();
 
-        for Edge { min, max } in self.edges {
-            adjs.entry(min).or_insert(HashSet::new()).insert(max);
-            adjs.entry(max).or_insert(HashSet::new()).insert(min);
+        for edge in self.edges {
+            let Edge { min, max } = edge;
+            match self.one_way.get(&edge) {
+                Some(to) if *to == max => { adjs.entry(min).or_default().insert(max); }
+                Some(_)                => { adjs.entry(max).or_default().insert(min); }
+                None                   => {
+                    adjs.entry(min).or_default().insert(max);
+                    adjs.entry(max).or_default().insert(min);
+                }
+            }
         }
 
-        AdjacencyGraph { com: self.com, adjs }
+        AdjacencyGraph { com: self.com, adjs, chains: Vec::new(), links: HashMap::default(), weights: HashMap::default() }
     }
 
     pub fn prune(self) -> EdgeSetGraph<Data> {
+        self.prune_with_dead_ends().0
+    }
+
+    // repeatedly strips dead ends: nodes of degree < 2 other than the start and goal.
+    // degrees are kept up to date as leaves are peeled, so each edge is only visited a couple
+    // of times overall. the stripped nodes are handed back too.
+    pub fn prune_with_dead_ends(self) -> (EdgeSetGraph<Data>, HashMap<NodeID, Data>) {
+        let (start, goal) = (self.start(), self.goal());
+
+        let mut adjs: HashMap<NodeID, Vec<NodeID>> = self.com.nodes.keys()
+            .map(|id| (*id, Vec::new()))
+            .collect();
+        // edges to nodes the graph doesn't have are left out of the degrees, and so dropped
+        for Edge { min, max } in self.edges.iter().copied() {
+            if adjs.contains_key(&min) && adjs.contains_key(&max) {
+                adjs.entry(min).or_default().push(max);
+                adjs.entry(max).or_default().push(min);
+            }
+        }
+
+        let mut degrees: HashMap<NodeID, usize> = adjs.iter()
+            .map(|(id, neighbors)| (*id, neighbors.len()))
+            .collect();
+
+        let is_dead_end = |id: NodeID, degree: usize| degree < 2 && id != start && id != goal;
+
+        // each with the pass that peeled it: the original leaves first, then what they left
+        let mut worklist: VecDeque<(NodeID, usize)> = degrees.iter()
+            .filter(|(id, degree)| is_dead_end(**id, **degree))
+            .map(|(id, _)| (*id, 1))
+            .collect();
+
+        let mut removed: HashSet<NodeID> = HashSet::default();
+        let mut passes = 0;
+
+        while let Some((id, pass)) = worklist.pop_front() {
+            if !removed.insert(id) {
+                continue;
+            }
+            passes = pass;
+
+            for neighbor in adjs[&id].iter() {
+                if removed.contains(neighbor) {
+                    continue;
+                }
+
+                let degree = degrees.get_mut(neighbor).unwrap();
+                *degree -= 1;
+                if is_dead_end(*neighbor, *degree) {
+                    worklist.push_back((*neighbor, pass + 1));
+                }
+            }
+        }
+
         let mut edges = self.edges;
-        let mut edges_temp: HashSet<Edge> = HashSet::with_capacity(edges.len());
+        edges.retain(|edge| {
+            adjs.contains_key(&edge.min) && adjs.contains_key(&edge.max)
+                && !removed.contains(&edge.min) && !removed.contains(&edge.max)
+        });
+        let mut one_way = self.one_way;
+        one_way.retain(|edge, _| edges.contains(edge));
 
-        let mut nodes = self.com.nodes;
-        let mut nodes_temp: HashMap<NodeID, Data> = HashMap::with_capacity(nodes.len());
+        let (dead_ends, nodes): (HashMap<_, _>, HashMap<_, _>) = self.com.nodes.into_iter()
+            .partition(|(id, _)| removed.contains(id));
+        debug!("pruned {} dead-end spaces in {} passes, leaving {}", dead_ends.len(), passes, nodes.len());
 
-        let mut degrees: HashMap<NodeID, usize> = HashMap::with_capacity(nodes.len());
-        let mut dead_ends: HashSet<NodeID> = HashSet::with_capacity(nodes.len());
+        let com = GraphCommon { nodes, start: self.com.start, goal: self.com.goal, index: OnceLock::new() };
+        (EdgeSetGraph { com, edges, one_way }, dead_ends)
+    }
+}
 
-        loop {
-            degrees.clear();
-            degrees.extend(nodes
-                .keys()
-                .map(|id| (*id, 0))
-            );
-
-            for edge in edges.iter() {
-                *degrees.get_mut(&edge.min).unwrap() += 1;
-                *degrees.get_mut(&edge.max).unwrap() += 1;
+impl<Data: Copy> AdjacencyGraph<Data> {
+    pub fn neighbors(&self, id: NodeID) -> impl Iterator<Item = NodeID> + '_ {
+        self.adjs.get(&id).into_iter().flatten().copied()
+    }
+
+    pub fn weight(&self, u: NodeID, v: NodeID) -> i32 {
+        let edge = Edge::new(u, v);
+        match self.links.get(&edge) {
+            Some(index) => self.chains[*index].weight(),
+            None        => self.weights.get(&edge).copied().unwrap_or(1),
+        }
+    }
+
+    // the heaviest adjacency: 1 unless weighted or contracted
+    pub fn max_weight(&self) -> i32 {
+        let chains = self.chains.iter().map(Chain::weight);
+        chains.chain(self.weights.values().copied()).max().unwrap_or(1).max(1)
+    }
+
+    fn is_junction(&self, id: NodeID) -> bool {
+        id == self.start() || id == self.goal() || self.adjs.get(&id).map_or(0, |n| n.len()) != 2
+    }
+
+    // collapses every run of degree-2 nodes between two junctions into a single weighted
+    // adjacency. the collapsed nodes keep their data, and get their distances and predecessors
+    // back when the search results are expanded.
+    pub fn contract(self) -> AdjacencyGraph<Data> {
+        let mut chains: Vec<Chain> = Vec::new();
+        let mut interior: HashSet<NodeID> = HashSet::default();
+
+        // the ends of one-way edges are junctions too, so that every chain can be walked either way
+        let one_way: HashSet<NodeID> = self.adjs.iter()
+            .flat_map(|(u, neighbors)| neighbors.iter().map(move |v| (*u, *v)))
+            .filter(|(u, v)| !self.adjs.get(v).is_some_and(|n| n.contains(u)))
+            .flat_map(|(u, v)| [u, v])
+            .collect();
+        let is_junction = |id: NodeID| self.is_junction(id) || one_way.contains(&id);
+
+        for (u, neighbors) in self.adjs.iter() {
+            if !is_junction(*u) {
+                continue;
             }
 
-            dead_ends.clear();
-            dead_ends.extend(degrees
-                .iter()
-                .filter(|(id, degree)| **degree < 2 && **id != self.start() && **id != self.goal())
-                .map(|(id, _)| *id)
-            );
+            for first in neighbors.iter() {
+                if is_junction(*first) || interior.contains(first) {
+                    continue;
+                }
+
+                let mut via = vec![*first];
+                let mut offsets = vec![self.weight(*u, *first)];
+                let mut prev = *u;
+                let mut cur = *first;
+                while !is_junction(cur) {
+                    let next = match self.neighbors(cur).find(|n| *n != prev) {
+                        Some(next) => next,
+                        // a dead end, with nothing past it to step to
+                        None       => { offsets.push(offsets[offsets.len() - 1].saturating_add(1)); break; }
+                    };
+                    offsets.push(offsets[offsets.len() - 1].saturating_add(self.weight(cur, next)));
+                    prev = cur;
+                    cur = next;
+                    if !is_junction(cur) {
+                        via.push(cur);
+                    }
+                }
+
+                interior.extend(via.iter().copied());
+                chains.push(Chain { from: *u, to: cur, via, offsets });
+            }
+        }
+
+        let mut adjs: HashMap<NodeID, HashSet<NodeID>> = self.adjs.iter()
+            .filter(|(id, _)| !interior.contains(id))
+            .map(|(id, neighbors)| {
+                let kept = neighbors.iter()
+                    .copied()
+                    .filter(|n| !interior.contains(n))
+                    .collect();
+                (*id, kept)
+            })
+            .collect();
 
-            if dead_ends.is_empty() {
-                break;
+        let mut links: HashMap<Edge, usize> = HashMap::default();
+        for (index, chain) in chains.iter().enumerate() {
+            // loops back to the same junction never shorten anything
+            if chain.from == chain.to || adjs[&chain.from].contains(&chain.to) {
+                continue;
             }
 
-            edges_temp.clear();
-            edges_temp.extend(edges.iter()
-                .copied()
-                .filter(|edge| !dead_ends.contains(&edge.min) && !dead_ends.contains(&edge.max))
-            );
-            swap(&mut edges, &mut edges_temp);
+            let edge = Edge::new(chain.from, chain.to);
+            let shorter = links.get(&edge).is_none_or(|other| chains[*other].weight() > chain.weight());
+            if shorter {
+                links.insert(edge, index);
+            }
+        }
 
-            nodes_temp.clear();
-            nodes_temp.extend(nodes.iter()
-                .filter(|(id, _)| !dead_ends.contains(&id))
-                .map(|(id, data)| (*id, *data))
-            );
-            swap(&mut nodes, &mut nodes_temp);
+        for edge in links.keys() {
+            adjs.get_mut(&edge.min).unwrap().insert(edge.max);
+            adjs.get_mut(&edge.max).unwrap().insert(edge.min);
         }
 
-        let com = GraphCommon { nodes, ..self.com };
-        EdgeSetGraph { com, edges }
+        debug!("contracted {} corridors of {} spaces into {} links", chains.len(), interior.len(), links.len());
+        AdjacencyGraph { com: self.com, adjs, chains, links, weights: self.weights }
     }
-}
 
-impl<Data> AdjacencyGraph<Data> {
-    pub fn new(
-        nodes: HashMap<NodeID, Data>, start: NodeID, goal: NodeID,
-        adjs: HashMap<NodeID, HashSet<NodeID>>)
-        -> AdjacencyGraph<Data>
+    fn path_cost(&self, path: &[NodeID]) -> i32 {
+        path.windows(2).fold(0, |cost, pair| cost.saturating_add(self.weight(pair[0], pair[1])))
+    }
+
+    // dijkstra from `source` to `target` that keeps off the banned nodes and edges
+    fn restricted_path(
+        &self,
+        source:       NodeID,
+        target:       NodeID,
+        banned_nodes: &HashSet<NodeID>,
+        banned_edges: &HashSet<Edge>)
+        -> Option<(i32, Vec<NodeID>)>
     {
-        let com = GraphCommon { nodes, start, goal };
-        AdjacencyGraph { com, adjs }
+        let mut dists: HashMap<NodeID, i32> = HashMap::default();
+        let mut preds: HashMap<NodeID, NodeID> = HashMap::default();
+        let mut queue: PriorityQueue<NodeID, Reverse<i32>> = PriorityQueue::new();
+        dists.insert(source, 0);
+        queue.push(source, Reverse(0));
+
+        while let Some((u, Reverse(u_dist))) = queue.pop() {
+            if u == target {
+                let mut path = vec![u];
+                while let Some(pred) = preds.get(path.last()?) {
+                    path.push(*pred);
+                }
+                path.reverse();
+                return Some((u_dist, path));
+            }
+
+            for v in self.neighbors(u) {
+                if banned_nodes.contains(&v) || banned_edges.contains(&Edge::new(u, v))
+                    || !self.com.nodes.contains_key(&v)
+                {
+                    continue;
+                }
+
+                let new_dist = u_dist.saturating_add(self.weight(u, v));
+                if dists.get(&v).is_none_or(|dist| new_dist < *dist) {
+                    dists.insert(v, new_dist);
+                    preds.insert(v, u);
+                    queue.push(v, Reverse(new_dist));
+                }
+            }
+        }
+
+        None
     }
 
-    pub fn neighbors(&self, id: NodeID) -> &HashSet<NodeID> {
-        self.adjs.get(&id).unwrap()
+    // up to `k` loopless start-to-goal paths in order of cost, by yen's algorithm. contraction
+    // keeps only the shortest of several corridors between the same two junctions, so for every
+    // alternative route this wants the uncontracted graph.
+    pub fn k_shortest_paths(&self, k: usize) -> Vec<(i32, Vec<NodeID>)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let (start, goal) = (self.start(), self.goal());
+        let no_nodes = HashSet::default();
+        let no_edges = HashSet::default();
+
+        let mut found: Vec<(i32, Vec<NodeID>)> = Vec::new();
+        found.extend(self.restricted_path(start, goal, &no_nodes, &no_edges));
+        let mut candidates: Vec<(i32, Vec<NodeID>)> = Vec::new();
+
+        while found.len() < k {
+            let last = match found.last() {
+                Some((_, path)) => path.clone(),
+                None            => break,
+            };
+
+            // branch off the last path at each of its nodes in turn, keeping to it up to there
+            // but not leaving the way any found path sharing that root already did
+            for i in 0 .. last.len().saturating_sub(1) {
+                let (spur, root) = (last[i], &last[..= i]);
+
+                let banned_edges: HashSet<Edge> = found.iter()
+                    .filter(|(_, path)| path.len() > i + 1 && path[..= i] == *root)
+                    .map(|(_, path)| Edge::new(path[i], path[i + 1]))
+                    .collect();
+                let banned_nodes: HashSet<NodeID> = root[.. i].iter().copied().collect();
+
+                if let Some((cost, tail)) = self.restricted_path(spur, goal, &banned_nodes, &banned_edges) {
+                    let mut path = root[.. i].to_vec();
+                    path.extend(tail);
+                    let candidate = (self.path_cost(root).saturating_add(cost), path);
+                    if !candidates.contains(&candidate) && !found.contains(&candidate) {
+                        candidates.push(candidate);
+                    }
+                }
+            }
+
+            let best = candidates.iter()
+                .enumerate()
+                .min_by_key(|(_, (cost, path))| (*cost, path.len()))
+                .map(|(index, _)| index);
+            match best {
+                Some(index) => found.push(candidates.swap_remove(index)),
+                None        => break,
+            }
+        }
+
+        found
     }
 
-    pub fn into_dijkstra(self) -> DijkstraGraph<Data> {
-        let mut dists: HashMap<NodeID, Option<i32>> = self.com.nodes.keys()
+    // the longest loopless start-to-goal path, by depth-first search over all of them. that's
+    // exponential in the number of junctions, so after `budget` steps the longest found so far
+    // is returned instead. for the same reason as k_shortest_paths, wants the uncontracted graph.
+    pub fn longest_path(&self, budget: usize, seed: Option<u64>) -> Option<LongestPath> {
+        let (start, goal) = (self.start(), self.goal());
+
+        // fixed neighbour order, so that a search cut short is at least repeatable. a seed
+        // shuffles it, so a search cut short can try its luck elsewhere and still be repeated
+        let mut rng = seed.map(Pcg32Basic::seed_from_u64);
+        let mut ids: Vec<NodeID> = self.adjs.keys().copied().collect();
+        ids.sort();
+        let adjs: HashMap<NodeID, Vec<NodeID>> = ids.into_iter()
             .map(|id| {
-                let dist = if *id == self.start() { Some(0) } else { None };
-                (*id, dist)
+                let mut neighbors: Vec<NodeID> = self.neighbors(id)
+                    .filter(|n| self.com.nodes.contains_key(n))
+                    .collect();
+                neighbors.sort();
+                if let Some(rng) = &mut rng {
+                    neighbors.shuffle(rng);
+                }
+                (id, neighbors)
             })
             .collect();
 
-        let mut paths: HashMap<NodeID, NodeID> = HashMap::new();
+        let mut best: Option<(i32, Vec<NodeID>)> = None;
+        let mut path = vec![start];
+        let mut on_path: HashSet<NodeID> = path.iter().copied().collect();
+        let mut cursors = vec![0];
+        let mut length = 0;
+        let mut steps = 0;
+        let mut exact = true;
 
-        let mut queue: PriorityQueue<NodeID, Reverse<i32>> = dists.iter()
-            .map(|(id, dist)| (*id, Reverse(dist.unwrap_or(std::i32::MAX))))
-            .collect();
+        while let (Some(cursor), Some(u)) = (cursors.last_mut(), path.last().copied()) {
+            let neighbors = adjs.get(&u).map_or(&[][..], Vec::as_slice);
+            let next = if u == goal { None } else { neighbors.get(*cursor).copied() };
+            *cursor += 1;
+
+            match next {
+                Some(v) => {
+                    if on_path.contains(&v) {
+                        continue;
+                    }
+
+                    steps += 1;
+                    if steps > budget {
+                        exact = false;
+                        break;
+                    }
+
+                    length += self.weight(u, v);
+                    path.push(v);
+                    on_path.insert(v);
+                    cursors.push(0);
+                }
+                None => {
+                    if u == goal && best.as_ref().is_none_or(|(best_len, _)| length > *best_len) {
+                        best = Some((length, path.clone()));
+                    }
 
-        while let Some((u, _)) = queue.pop() {
-            let neighbors = self.neighbors(u);
+                    path.pop();
+                    on_path.remove(&u);
+                    cursors.pop();
+                    if let Some(prev) = path.last() {
+                        length -= self.weight(*prev, u);
+                    }
+                }
+            }
+        }
+
+        // nothing found in time: start from the shortest route instead
+        let mut path = match best {
+            Some((_, path)) => path,
+            None => self.restricted_path(start, goal, &HashSet::default(), &HashSet::default())?.1,
+        };
+        if !exact {
+            self.stretch_path(&mut path, budget);
+        }
+        Some(LongestPath { length: self.path_cost(&path), path, exact })
+    }
+
+    // lengthens a path by local search: wherever some stretch of unused nodes links two
+    // neighbours on the path the long way round, splices it in. stops when nothing improves,
+    // or after `budget` steps.
+    fn stretch_path(&self, path: &mut Vec<NodeID>, budget: usize) {
+        let mut on_path: HashSet<NodeID> = path.iter().copied().collect();
+        let mut steps = 0;
+
+        'improve: loop {
+            for i in 0 .. path.len().saturating_sub(1) {
+                let (a, b) = (path[i], path[i + 1]);
+
+                // breadth-first from a to b through nodes off the path, not by the direct link
+                let mut preds: HashMap<NodeID, NodeID> = HashMap::default();
+                let mut queue: VecDeque<NodeID> = VecDeque::new();
+                queue.push_back(a);
+                while let Some(u) = queue.pop_front() {
+                    for v in self.neighbors(u) {
+                        steps += 1;
+                        if steps > budget {
+                            return;
+                        }
 
-            for v in neighbors.iter() {
-                let new_dist = dists[&u].unwrap_or(0) + 1;
-                if new_dist < dists[&v].unwrap_or(std::i32::MAX) {
-                    dists.insert(*v, Some(new_dist));
-                    paths.insert(*v, u);
-                    queue.change_priority(&v, Reverse(new_dist));
+                        let direct = u == a && v == b;
+                        let free = !on_path.contains(&v) && !preds.contains_key(&v);
+                        if direct || !(free || v == b) || !self.com.nodes.contains_key(&v) {
+                            continue;
+                        }
+
+                        preds.insert(v, u);
+                        if v != b {
+                            queue.push_back(v);
+                            continue;
+                        }
+
+                        let mut detour = Vec::new();
+                        let mut cur = u;
+                        while cur != a {
+                            detour.push(cur);
+                            cur = preds[&cur];
+                        }
+                        detour.reverse();
+
+                        let old = self.weight(a, b);
+                        let new = self.path_cost(&[&[a][..], &detour, &[b]].concat());
+                        if new > old {
+                            on_path.extend(detour.iter().copied());
+                            path.splice(i + 1 .. i + 1, detour);
+                            continue 'improve;
+                        }
+                        break;
+                    }
                 }
             }
+            return;
         }
+    }
+
+    // stops once the goal is settled, so only the nodes nearer the start than it are sure to
+    // have their true distance; the rest are unreached or only as near as found so far
+    pub fn into_dijkstra(self) -> DijkstraGraph<Data> {
+        let (start, goal) = (self.start(), self.goal());
+        DijkstraSearch::new(self, &[start]).stopping_at(goal).finish()
+    }
+
+    // the distance to every node reachable from the start, goal or no goal
+    pub fn into_full_dijkstra(self) -> DijkstraGraph<Data> {
+        let start = self.start();
+        self.into_multi_source_dijkstra(&[start])
+    }
 
-        let nodes: HashMap<NodeID, (Data, i32)> = self.com.nodes.iter()
-            .map(|(id, data)| (*id, (*data, dists[id].unwrap_or(std::i32::MAX))))
+    // every node ends up labelled with the source it was reached from
+    pub fn into_multi_source_dijkstra(self, sources: &[NodeID]) -> DijkstraGraph<Data> {
+        DijkstraSearch::new(self, sources).finish()
+    }
+
+    fn expand_chains(
+        &self,
+        dists:   &mut HashMap<NodeID, i32>,
+        paths:   &mut HashMap<NodeID, NodeID>,
+        origins: &mut HashMap<NodeID, NodeID>)
+    {
+        // junctions reached over a chain should point at the chain's last node, not across it
+        let relinked: Vec<(NodeID, NodeID)> = paths.iter()
+            .filter_map(|(v, u)| {
+                let chain = &self.chains[*self.links.get(&Edge::new(*u, *v))?];
+                let adjacent = if chain.from == *u { *chain.via.last()? } else { chain.via[0] };
+                Some((*v, adjacent))
+            })
             .collect();
+        paths.extend(relinked);
+
+        for chain in self.chains.iter() {
+            let from_dist = dists.get(&chain.from).copied().unwrap_or(i32::MAX);
+            let to_dist   = dists.get(&chain.to).copied().unwrap_or(i32::MAX);
+            let len = chain.via.len();
+
+            for (i, id) in chain.via.iter().enumerate() {
+                let forward  = from_dist.saturating_add(chain.offsets[i]);
+                let backward = to_dist.saturating_add(chain.weight() - chain.offsets[i]);
+
+                let (dist, pred, end) =
+                    if forward <= backward {
+                        (forward, if i == 0 { chain.from } else { chain.via[i - 1] }, chain.from)
+                    }
+                    else {
+                        (backward, if i + 1 == len { chain.to } else { chain.via[i + 1] }, chain.to)
+                    };
+
+                if dist == i32::MAX {
+                    continue;
+                }
 
-        let com = GraphCommon { nodes, start: self.com.start, goal: self.com.goal };
-        let inner = AdjacencyGraph { com, adjs: self.adjs };
-        DijkstraGraph { inner, paths }
+                dists.insert(*id, dist);
+                paths.insert(*id, pred);
+                if let Some(origin) = origins.get(&end).copied() {
+                    origins.insert(*id, origin);
+                }
+            }
+        }
+    }
+}
+
+// the nodes reached but not yet expanded, nearest first
+enum Frontier {
+    Heap(BinaryHeap<Reverse<(i32, NodeID)>>),
+    Buckets(Buckets),
+}
+
+// dial's queue: a ring of one bucket per distance. dijkstra's never pushes anything nearer than
+// what it last popped, nor further past it than the heaviest adjacency, so a ring one longer
+// than that covers every distance queued at once, and finding the nearest is a walk forward
+// rather than a heap's log n.
+struct Buckets {
+    ring: Vec<Vec<NodeID>>,
+    // the nearest distance that might still have entries
+    at:   i32,
+    len:  usize,
+}
+
+impl Frontier {
+    fn push(&mut self, dist: i32, id: NodeID) {
+        match self {
+            Frontier::Heap(heap) => heap.push(Reverse((dist, id))),
+            Frontier::Buckets(buckets) => {
+                let slot = dist as usize % buckets.ring.len();
+                buckets.ring[slot].push(id);
+                buckets.len += 1;
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Option<(i32, NodeID)> {
+        match self {
+            Frontier::Heap(heap) => heap.pop().map(|Reverse(entry)| entry),
+            Frontier::Buckets(buckets) => {
+                if buckets.len == 0 {
+                    return None;
+                }
+                let ring = buckets.ring.len();
+                loop {
+                    if let Some(id) = buckets.ring[buckets.at as usize % ring].pop() {
+                        buckets.len -= 1;
+                        return Some((buckets.at, id));
+                    }
+                    buckets.at += 1;
+                }
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Frontier::Heap(heap)       => heap.len(),
+            Frontier::Buckets(buckets) => buckets.len,
+        }
+    }
+}
+
+// what one step() of a search did, for hosts that run it a little at a time and show it
+// growing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchEvent {
+    // `node` came off the frontier, settled at `dist`
+    Expanded { node: NodeID, dist: i32 },
+    // `node` joined the frontier at `dist` by way of `from`, or moved up it
+    Reached { node: NodeID, dist: i32, from: NodeID },
+    // the target was expanded, or there's nothing left to; every step from here is this
+    Done,
+}
+
+// a dijkstra search run an event or a bounded number of expansions at a time, for callers that
+// can't block until it's done. into_multi_source_dijkstra() is this run to the end in one go,
+// and as an iterator it yields the events up to Done.
+//
+// nodes go on the heap only once reached, and again each time they're reached more cheaply; the
+// entries that leaves behind are skipped as they come off. a search that ends early never
+// touches most of the graph at all.
+//
+// the queue is a binary heap unless bucketed() swaps in dial's, which is quicker on graphs whose
+// weights are all small, as they are before contraction.
+pub struct DijkstraSearch<Data> {
+    graph:   AdjacencyGraph<Data>,
+    dists:   HashMap<NodeID, i32>,
+    paths:   HashMap<NodeID, NodeID>,
+    origins: HashMap<NodeID, NodeID>,
+    queue:   Frontier,
+    order:   Vec<NodeID>,
+    stats:   SearchStats,
+    target:  Option<NodeID>,
+    done:    bool,
+    // the node being expanded, with its neighbours and how many of them are relaxed
+    current: Option<(NodeID, i32)>,
+    pending: Vec<NodeID>,
+    relaxed: usize,
+}
+
+impl<Data: Copy> DijkstraSearch<Data> {
+    pub fn new(graph: AdjacencyGraph<Data>, sources: &[NodeID]) -> DijkstraSearch<Data> {
+        let mut dists = HashMap::default();
+        let mut origins = HashMap::default();
+        let mut queue = Frontier::Heap(BinaryHeap::new());
+        for source in sources.iter().filter(|id| graph.com.nodes.contains_key(id)) {
+            dists.insert(*source, 0);
+            origins.insert(*source, *source);
+            queue.push(0, *source);
+        }
+
+        let mut stats = SearchStats::default();
+        stats.saw_frontier(queue.len());
+        DijkstraSearch {
+            graph, dists, paths: HashMap::default(), origins, queue,
+            order: Vec::new(), stats, target: None, done: false,
+            current: None, pending: Vec::new(), relaxed: 0,
+        }
+    }
+
+    // queues by dial's buckets instead of the heap, one per distance up to the heaviest
+    // adjacency. best where that's small; after contraction a long corridor makes a long ring,
+    // walked a slot at a time.
+    pub fn bucketed(mut self) -> DijkstraSearch<Data> {
+        // whatever's queued already comes off nearest first, so the first is where the ring starts
+        let mut queued = Vec::with_capacity(self.queue.len());
+        while let Some(entry) = self.queue.pop() {
+            queued.push(entry);
+        }
+
+        let ring = vec![Vec::new(); self.graph.max_weight() as usize + 1];
+        let at = queued.first().map_or(0, |(dist, _)| *dist);
+        self.queue = Frontier::Buckets(Buckets { ring, at, len: 0 });
+        for (dist, id) in queued {
+            self.queue.push(dist, id);
+        }
+        self
+    }
+
+    // ends the search as soon as `target` is expanded, rather than once every node is
+    pub fn stopping_at(mut self, target: NodeID) -> DijkstraSearch<Data> {
+        self.target = Some(target);
+        self
+    }
+
+    // relaxes the next neighbour of the node being expanded, or else expands the next node
+    pub fn step(&mut self) -> SearchEvent {
+        self.next_event(true).unwrap_or(SearchEvent::Done)
+    }
+
+    // expands up to `budget` nodes, returning whether the search is done. the last one expanded
+    // has all its neighbours relaxed before it returns.
+    pub fn run(&mut self, budget: usize) -> bool {
+        let mut expanded = 0;
+        while !self.done {
+            match self.next_event(expanded < budget) {
+                Some(SearchEvent::Expanded { .. }) => expanded += 1,
+                Some(_) => (),
+                None    => break,
+            }
+        }
+        self.done
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    // the next event, or None where that would mean expanding a node and `expand` is false
+    fn next_event(&mut self, expand: bool) -> Option<SearchEvent> {
+        loop {
+            if let Some((u, u_dist)) = self.current {
+                if let Some(v) = self.pending.get(self.relaxed).copied() {
+                    self.relaxed += 1;
+                    let new_dist = u_dist.saturating_add(self.graph.weight(u, v));
+                    if self.dists.get(&v).is_none_or(|dist| new_dist < *dist) {
+                        self.dists.insert(v, new_dist);
+                        self.paths.insert(v, u);
+                        if let Some(origin) = self.origins.get(&u).copied() {
+                            self.origins.insert(v, origin);
+                        }
+                        self.queue.push(new_dist, v);
+                        self.stats.relaxations += 1;
+                        return Some(SearchEvent::Reached { node: v, dist: new_dist, from: u });
+                    }
+                    continue;
+                }
+                self.current = None;
+                self.stats.saw_frontier(self.queue.len());
+            }
+
+            if self.done {
+                return Some(SearchEvent::Done);
+            }
+            if !expand {
+                return None;
+            }
+            let (u_dist, u) = match self.queue.pop() {
+                Some(entry) => entry,
+                None        => { self.done = true; return Some(SearchEvent::Done); }
+            };
+            // reached more cheaply since this was pushed, and expanded then
+            if u_dist > self.dists[&u] {
+                continue;
+            }
+            self.stats.expansions += 1;
+            self.order.push(u);
+            if self.target == Some(u) {
+                self.done = true;
+                return Some(SearchEvent::Expanded { node: u, dist: u_dist });
+            }
+
+            // only edges into the graph's own nodes are followed
+            let nodes = &self.graph.com.nodes;
+            self.pending.clear();
+            self.pending.extend(self.graph.neighbors(u).filter(|v| nodes.contains_key(v)));
+            self.relaxed = 0;
+            self.current = Some((u, u_dist));
+            return Some(SearchEvent::Expanded { node: u, dist: u_dist });
+        }
+    }
+
+    pub fn stats(&self) -> SearchStats {
+        self.stats
+    }
+
+    // the search run to the end, with the contracted corridors filled back in
+    pub fn finish(mut self) -> DijkstraGraph<Data> {
+        while !self.run(usize::MAX) { }
+
+        let (mut dists, mut paths, mut origins) = (self.dists, self.paths, self.origins);
+        self.graph.expand_chains(&mut dists, &mut paths, &mut origins);
+
+        DijkstraGraph { inner: self.graph, dists, paths, origins, order: self.order, stats: self.stats }
+    }
+}
+
+impl<Data: Copy> Iterator for DijkstraSearch<Data> {
+    type Item = SearchEvent;
+
+    fn next(&mut self) -> Option<SearchEvent> {
+        Some(self.step()).filter(|event| *event != SearchEvent::Done)
+    }
+}
+
+// delta-stepping: the distances from the sources to everything reachable, like
+// into_multi_source_dijkstra(), but settling a whole band `delta` wide at once, with the
+// adjacencies out of the band's nodes looked over across threads. within a band the light
+// adjacencies, no heavier than delta, are followed again for every node they bring nearer; the
+// heavy ones only once the band is done, since they can't lead back into it.
+//
+// a delta of 1 settles one distance at a time; max_weight() makes every adjacency light. either
+// way the distances are dijkstra's, though ties between equally short paths may go another way.
+impl<Data: Copy + Send + Sync> AdjacencyGraph<Data> {
+    pub fn into_delta_stepping(self, sources: &[NodeID], delta: i32) -> DijkstraGraph<Data> {
+        let delta = delta.max(1);
+        let mut dists: HashMap<NodeID, i32> = HashMap::default();
+        let mut paths = HashMap::default();
+        let mut origins = HashMap::default();
+        let mut bands: Vec<Vec<NodeID>> = vec![Vec::new()];
+        let mut order = Vec::new();
+        let mut stats = SearchStats::default();
+
+        for source in sources.iter().filter(|id| self.com.nodes.contains_key(id)) {
+            dists.insert(*source, 0);
+            origins.insert(*source, *source);
+            bands[0].push(*source);
+        }
+
+        // a better distance, or nothing; band entries left stale by one are skipped later
+        let mut relax = |requests: Vec<(NodeID, i32, NodeID)>,
+                         dists: &mut HashMap<NodeID, i32>,
+                         bands: &mut Vec<Vec<NodeID>>,
+                         stats: &mut SearchStats|
+        {
+            for (v, dist, u) in requests {
+                if dists.get(&v).is_some_and(|old| *old <= dist) {
+                    continue;
+                }
+                dists.insert(v, dist);
+                paths.insert(v, u);
+                if let Some(origin) = origins.get(&u).copied() {
+                    origins.insert(v, origin);
+                }
+                let band = (dist / delta) as usize;
+                if bands.len() <= band {
+                    bands.resize_with(band + 1, Vec::new);
+                }
+                bands[band].push(v);
+                stats.relaxations += 1;
+            }
+        };
+
+        let mut band = 0;
+        while band < bands.len() {
+            let mut settled = Vec::new();
+            loop {
+                let mut frontier = std::mem::take(&mut bands[band]);
+                frontier.retain(|id| (dists[id] / delta) as usize == band);
+                frontier.sort_unstable();
+                frontier.dedup();
+                if frontier.is_empty() {
+                    break;
+                }
+
+                stats.expansions += frontier.len();
+                stats.saw_frontier(frontier.len());
+                let light = self.requests(&frontier, &dists, |weight| weight <= delta);
+                relax(light, &mut dists, &mut bands, &mut stats);
+                settled.extend(frontier);
+            }
+
+            settled.sort_unstable();
+            settled.dedup();
+            let heavy = self.requests(&settled, &dists, |weight| weight > delta);
+            relax(heavy, &mut dists, &mut bands, &mut stats);
+
+            settled.sort_by_key(|id| dists[id]);
+            order.extend(settled);
+            band += 1;
+        }
+
+        self.expand_chains(&mut dists, &mut paths, &mut origins);
+        DijkstraGraph { inner: self, dists, paths, origins, order, stats }
+    }
+
+    // (node, distance through, from) for each adjacency out of `from` whose weight `follow`s
+    fn requests(&self, from: &[NodeID], dists: &HashMap<NodeID, i32>, follow: impl Fn(i32) -> bool + Sync)
+        -> Vec<(NodeID, i32, NodeID)>
+    {
+        let out = |u: &NodeID| {
+            let u_dist = dists[u];
+            self.neighbors(*u)
+                .filter(|v| self.com.nodes.contains_key(v))
+                .filter_map(|v| {
+                    let weight = self.weight(*u, v);
+                    follow(weight).then(|| (v, u_dist.saturating_add(weight), *u))
+                })
+                .collect::<Vec<_>>()
+        };
+        #[cfg(feature = "parallel")]
+        let requests = from.par_iter().flat_map_iter(out).collect();
+        #[cfg(not(feature = "parallel"))]
+        let requests = from.iter().flat_map(out).collect();
+        requests
     }
 }
 
 impl<Data> DijkstraGraph<Data> {
+    // i32::MAX where unreached
     pub fn distance(&self, id: NodeID) -> i32 {
-        self.inner.get_node(id).1
+        self.dists.get(&id).copied().unwrap_or(i32::MAX)
     }
 
     pub fn goal_distance(&self) -> i32 {
@@ -216,7 +1064,23 @@ impl<Data> DijkstraGraph<Data> {
     }
 
     pub fn predecessor(&self, id: NodeID) -> Option<NodeID> {
-        self.paths.get(&id).map(|pred| *pred)
+        self.paths.get(&id).copied()
+    }
+
+    // the nodes along the found path from its source to `id`, inclusive
+    pub fn path_to(&self, id: NodeID) -> Vec<NodeID> {
+        let mut path = vec![id];
+        let mut next_id = self.predecessor(id);
+        while let Some(id) = next_id {
+            path.push(id);
+            next_id = self.predecessor(id);
+        }
+        path.reverse();
+        path
+    }
+
+    pub fn origin(&self, id: NodeID) -> Option<NodeID> {
+        self.origins.get(&id).copied()
     }
 }
 
@@ -245,8 +1109,8 @@ impl<Data> DijkstraGraph<Data> {
 //        }
 //
 //        for neighbor in adjs[&current].iter() {
-//            let new_score = metrics.get(&current) .map_or(std::i32::MAX, |(_, g)| *g) + 1;
-//            let old_score = metrics.get(&neighbor).map_or(std::i32::MAX, |(_, g)| *g);
+//            let new_score = metrics.get(&current) .map_or(i32::MAX, |(_, g)| *g) + 1;
+//            let old_score = metrics.get(&neighbor).map_or(i32::MAX, |(_, g)| *g);
 //            if new_score < old_score {
 //                let new_metrics = (current, new_score);
 //                metrics.insert(*neighbor, new_metrics);
@@ -258,3 +1122,215 @@ impl<Data> DijkstraGraph<Data> {
 //    None
 //}
 
+#[cfg(test)]
+pub(crate) mod tests {
+    use {
+        super::*,
+        crate::{
+            math::*,
+            image_graph::{extract_graph, Decomposition, ExtractOptions},
+            preprocess::{CLEAR, WALL},
+        },
+        image as im,
+    };
+
+    pub(crate) fn id(n: RawNodeID) -> NodeID {
+        NodeID::new(n).unwrap()
+    }
+
+    // a walled room with nothing else in it
+    pub(crate) fn single_room() -> im::GrayImage {
+        let mut image = im::GrayImage::from_pixel(8, 6, WALL);
+        for y in 1 .. 5 {
+            for x in 1 .. 7 {
+                image.put_pixel(x, y, CLEAR);
+            }
+        }
+        image
+    }
+
+    // a 17x13 hall with pillars on a lattice, so that there are many ways from (1, 1) to
+    // (16, 11) and many of them as short
+    pub(crate) fn pillared_hall() -> im::GrayImage {
+        let mut image = im::GrayImage::from_pixel(17, 13, CLEAR);
+        for y in (0 .. 13).step_by(3) {
+            for x in (0 .. 17).step_by(3) {
+                image.put_pixel(x, y, WALL);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn isolated_nodes_get_empty_adjacency() {
+        let nodes: HashMap<NodeID, ()> = (1 ..= 3).map(|n| (id(n), ())).collect();
+        let edges: HashSet<Edge> = [Edge::new(id(1), id(2))].iter().copied().collect();
+        let graph = EdgeSetGraph::new(nodes, id(1), id(2), edges).into_adjacency_graph();
+
+        assert_eq!(graph.adjs.len(), 3);
+        assert_eq!(graph.neighbors(id(3)).count(), 0);
+        assert_eq!(graph.neighbors(id(1)).collect::<Vec<_>>(), vec![id(2)]);
+    }
+
+    #[test]
+    fn single_room_solves_in_place() {
+        for decomposition in [Decomposition::Greedy, Decomposition::Strips].iter().copied() {
+            let opts = ExtractOptions { decomposition, ..ExtractOptions::default() };
+            let (start, goal) = (V2::new(1, 1), V2::new(6, 4));
+            let graph = extract_graph(&single_room(), start, goal, &opts).unwrap();
+            assert_eq!(graph.start(), graph.goal());
+            assert!(graph.edges.is_empty());
+
+            let graph = graph.prune().into_adjacency_graph();
+            assert_eq!(graph.neighbors(graph.start()).count(), 0);
+
+            let graph = graph.contract().into_dijkstra();
+            assert_eq!(graph.goal_distance(), 0);
+            assert_eq!(graph.path_to(graph.goal()), vec![graph.start()]);
+        }
+    }
+
+    #[test]
+    fn k_shortest_paths_come_cheapest_first_and_no_more_than_asked() {
+        let (start, goal) = (V2::new(1, 1), V2::new(16, 11));
+        let graph = extract_graph(&pillared_hall(), start, goal, &ExtractOptions::default()).unwrap()
+            .prune()
+            .into_adjacency_graph();
+        assert!(graph.k_shortest_paths(0).is_empty());
+        let paths = graph.k_shortest_paths(3);
+        assert_eq!(paths.len(), 3);
+        assert!(paths.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+
+    #[test]
+    fn unreached_isolated_node() {
+        let nodes: HashMap<NodeID, ()> = (1 ..= 2).map(|n| (id(n), ())).collect();
+        let graph = EdgeSetGraph::new(nodes, id(1), id(2), HashSet::default())
+            .into_adjacency_graph()
+            .contract()
+            .into_dijkstra();
+
+        assert_eq!(graph.goal_distance(), i32::MAX);
+        assert_eq!(graph.path_to(graph.goal()), vec![id(2)]);
+    }
+
+    #[test]
+    fn stepped_search_matches_whole_search() {
+        // a ring of six with a spur, so that the search has ties and a contracted corridor
+        let nodes: HashMap<NodeID, ()> = (1 ..= 7).map(|n| (id(n), ())).collect();
+        let edges: HashSet<Edge> = [(1, 2), (2, 3), (3, 4), (4, 5), (5, 6), (6, 1), (3, 7)].iter()
+            .map(|(a, b)| Edge::new(id(*a), id(*b)))
+            .collect();
+        let graph = || EdgeSetGraph::new(nodes.clone(), id(1), id(4), edges.clone()).into_adjacency_graph();
+        let whole = graph().into_full_dijkstra();
+
+        let mut search = DijkstraSearch::new(graph(), &[id(1)]);
+        let mut steps = 0;
+        while !search.run(1) {
+            steps += 1;
+        }
+        let stepped = search.finish();
+
+        assert!(steps > 1);
+        for n in 1 ..= 7 {
+            assert_eq!(stepped.distance(id(n)), whole.distance(id(n)));
+        }
+        assert_eq!(stepped.path_to(id(4)).len(), whole.path_to(id(4)).len());
+
+        // stopping at the goal settles it all the same, with no more expanded after it
+        let early = graph().into_dijkstra();
+        assert_eq!(early.goal_distance(), whole.goal_distance());
+        assert_eq!(early.path_to(id(4)).len(), whole.path_to(id(4)).len());
+        assert_eq!(early.order.last(), Some(&id(4)));
+    }
+
+    #[test]
+    fn search_events_retrace_the_whole_search() {
+        let nodes: HashMap<NodeID, ()> = (1 ..= 5).map(|n| (id(n), ())).collect();
+        let edges: HashSet<Edge> = [(1, 2), (2, 3), (3, 4), (1, 5), (5, 4)].iter()
+            .map(|(a, b)| Edge::new(id(*a), id(*b)))
+            .collect();
+        let graph = || EdgeSetGraph::new(nodes.clone(), id(1), id(4), edges.clone()).into_adjacency_graph();
+        let whole = graph().into_dijkstra();
+
+        let mut search = DijkstraSearch::new(graph(), &[id(1)]).stopping_at(id(4));
+        let events: Vec<SearchEvent> = search.by_ref().collect();
+        assert!(search.is_done());
+        assert_eq!(search.step(), SearchEvent::Done);
+
+        let expanded: Vec<NodeID> = events.iter()
+            .filter_map(|event| match event { SearchEvent::Expanded { node, .. } => Some(*node), _ => None })
+            .collect();
+        assert_eq!(expanded, whole.order);
+        for event in events.iter() {
+            if let SearchEvent::Reached { node, dist, from } = *event {
+                assert!(dist >= whole.distance(node));
+                assert!(expanded.contains(&from));
+            }
+        }
+        assert_eq!(search.finish().goal_distance(), whole.goal_distance());
+    }
+
+    #[test]
+    fn bucketed_search_matches_heap() {
+        // contracted pillars, so that the weights run past one and the ring wraps
+        let image = pillared_hall();
+        let extracted = extract_graph(&image, V2::new(1, 1), V2::new(16, 11), &ExtractOptions::default()).unwrap();
+        let graph = || extracted.clone().prune().into_adjacency_graph().contract();
+        assert!(graph().max_weight() > 1);
+        let start = graph().start();
+        let heap = graph().into_full_dijkstra();
+
+        let bucketed = DijkstraSearch::new(graph(), &[start]).bucketed().finish();
+        // and swapped to buckets partway, with entries already queued
+        let mut search = DijkstraSearch::new(graph(), &[start]);
+        search.run(3);
+        let switched = search.bucketed().finish();
+
+        for id in extracted.nodes().keys() {
+            assert_eq!(bucketed.distance(*id), heap.distance(*id));
+            assert_eq!(switched.distance(*id), heap.distance(*id));
+        }
+        assert_eq!(bucketed.stats.expansions, heap.stats.expansions);
+    }
+
+    #[test]
+    fn delta_stepping_matches_dijkstra() {
+        let image = pillared_hall();
+        let extracted = extract_graph(&image, V2::new(1, 1), V2::new(16, 11), &ExtractOptions::default()).unwrap();
+        let graph = || extracted.clone().prune().into_adjacency_graph().contract();
+        let sources = [graph().start(), graph().goal()];
+        let whole = graph().into_multi_source_dijkstra(&sources);
+
+        for delta in [1, 3, graph().max_weight()].iter().copied() {
+            let stepped = graph().into_delta_stepping(&sources, delta);
+            for id in extracted.nodes().keys() {
+                assert_eq!(stepped.distance(*id), whole.distance(*id), "delta {}", delta);
+                if whole.distance(*id) != i32::MAX {
+                    assert!(stepped.origin(*id).is_some_and(|origin| sources.contains(&origin)), "delta {}", delta);
+                }
+            }
+            assert!(stepped.order.windows(2).all(|pair| stepped.distance(pair[0]) <= stepped.distance(pair[1])));
+        }
+    }
+
+    #[test]
+    fn same_image_walks_in_the_same_order() {
+        let image = pillared_hall();
+        let walk = || {
+            let graph = extract_graph(&image, V2::new(1, 1), V2::new(16, 11), &ExtractOptions::default()).unwrap();
+            let nodes: Vec<NodeID> = graph.nodes().keys().copied().collect();
+            let dijkstra = graph.into_adjacency_graph().into_full_dijkstra();
+            let path = dijkstra.path_to(dijkstra.goal());
+            (nodes, dijkstra.order, path)
+        };
+        assert_eq!(walk(), walk());
+    }
+
+    #[test]
+    fn ids_run_out_with_an_error() {
+        assert_eq!(next_id(id(1)), Ok(id(2)));
+        assert_eq!(next_id(NodeID::MAX), Err(Error::TooManyNodes));
+        assert_eq!(offset_id(id(2), RawNodeID::MAX - 1), Err(Error::TooManyNodes));
+    }
+}
@@ -0,0 +1,943 @@
+// solving on the pixels themselves, without decomposing into rects, for checking the rect graph
+// against and for mazes it decomposes poorly. jump point search moves 8-connected but never cuts
+// a corner; the breadth-first search moves 4-connected, as the rects join, as do the wall
+// follower and dead-end filling.
+
+use {
+    crate::{math::*, error::Error, graph::SearchStats},
+    std::{cmp::Reverse, collections::{BinaryHeap, HashMap, VecDeque}},
+    priority_queue::PriorityQueue,
+    image as im,
+};
+
+const WHITE: im::Luma<u8> = im::Luma([255; 1]);
+
+// costs in thousandths of a pixel, so that diagonals are near enough sqrt 2
+const STRAIGHT: i64 = 1000;
+const DIAGONAL: i64 = 1414;
+
+// a bit a pixel
+#[derive(Clone)]
+struct Bitmap(Vec<u64>);
+
+impl Bitmap {
+    fn new(len: usize) -> Bitmap {
+        Bitmap(vec![0; len.div_ceil(64)])
+    }
+
+    fn get(&self, index: usize) -> bool {
+        self.0[index / 64] >> (index % 64) & 1 != 0
+    }
+
+    fn set(&mut self, index: usize) {
+        self.0[index / 64] |= 1 << (index % 64);
+    }
+
+    fn unset(&mut self, index: usize) {
+        self.0[index / 64] &= !(1 << (index % 64));
+    }
+}
+
+pub struct PixelGrid {
+    clear:  Bitmap,
+    width:  Coord,
+    height: Coord,
+}
+
+impl PixelGrid {
+    pub fn new(image: &im::GrayImage) -> Result<PixelGrid, Error> {
+        let mut clear = Bitmap::new(image.pixels().len());
+        for (index, pixel) in image.pixels().enumerate() {
+            if *pixel == WHITE {
+                clear.set(index);
+            }
+        }
+
+        Ok(PixelGrid {
+            clear,
+            width:  coord(image.width())?,
+            height: coord(image.height())?,
+        })
+    }
+
+    fn index(&self, p: V2) -> usize {
+        (p.y as usize) * (self.width as usize) + p.x as usize
+    }
+
+    fn pos(&self, index: usize) -> V2 {
+        V2::new((index % self.width as usize) as Coord, (index / self.width as usize) as Coord)
+    }
+
+    pub fn in_bounds(&self, p: V2) -> bool {
+        p.x >= 0 && p.y >= 0 && p.x < self.width && p.y < self.height
+    }
+
+    // off the grid counts as wall
+    pub fn is_clear(&self, p: V2) -> bool {
+        self.in_bounds(p) && self.clear.get(self.index(p))
+    }
+
+    fn check(&self, p: V2) -> Result<(), Error> {
+        if !self.in_bounds(p) { Err(Error::OutOfBounds(p)) }
+        else if !self.is_clear(p) { Err(Error::Blocked(p)) }
+        else { Ok(()) }
+    }
+
+    // a diagonal step needs both of the squares beside it clear
+    fn can_step(&self, p: V2, d: V2) -> bool {
+        self.is_clear(p + d) && (d.x == 0 || d.y == 0 ||
+            (self.is_clear(V2::new(p.x + d.x, p.y)) && self.is_clear(V2::new(p.x, p.y + d.y))))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GridPath {
+    // every pixel from start to goal
+    pub pixels:     Vec<V2>,
+    // in pixels, diagonal steps counting sqrt 2
+    pub length:     f64,
+    pub stats:      SearchStats,
+}
+
+// a no-op cast with wide-coords
+#[allow(clippy::unnecessary_cast)]
+fn octile(a: V2, b: V2) -> i64 {
+    let dx = (a.x - b.x).abs() as i64;
+    let dy = (a.y - b.y).abs() as i64;
+    DIAGONAL * dx.min(dy) + STRAIGHT * (dx - dy).abs()
+}
+
+fn signum(d: V2) -> V2 {
+    V2::new(d.x.signum(), d.y.signum())
+}
+
+// the directions worth searching from `p`, having arrived along `d`. everything else is reached
+// at least as cheaply some other way.
+fn pruned_neighbors(grid: &PixelGrid, p: V2, d: Option<V2>) -> Vec<V2> {
+    let all = [
+        V2::new(1, 0), V2::new(-1, 0), V2::new(0, 1), V2::new(0, -1),
+        V2::new(1, 1), V2::new(1, -1), V2::new(-1, 1), V2::new(-1, -1),
+    ];
+    let d = match d {
+        Some(d) => d,
+        None    => return all.iter().copied().filter(|d| grid.can_step(p, *d)).collect(),
+    };
+
+    let mut dirs = Vec::with_capacity(3);
+    if d.x != 0 && d.y != 0 {
+        dirs.extend([V2::new(d.x, 0), V2::new(0, d.y), d].iter().copied());
+    }
+    else {
+        // sideways, and diagonally forward past whatever wall made this a jump point
+        let side = V2::new(d.y.abs(), d.x.abs());
+        let back = -side;
+        dirs.extend([d, side, back, d + side, d + back].iter().copied());
+    }
+    dirs.retain(|dir| grid.can_step(p, *dir));
+    dirs
+}
+
+// where a straight run from `p` along `d` turns interesting: the goal, or a square with a
+// wall ended just behind it to one side, opening a way round
+fn jump_straight(grid: &PixelGrid, mut p: V2, d: V2, goal: V2) -> Option<V2> {
+    let side = V2::new(d.y.abs(), d.x.abs());
+    loop {
+        if !grid.can_step(p, d) {
+            return None;
+        }
+        p = p + d;
+        if p == goal {
+            return Some(p);
+        }
+
+        let behind = p - d;
+        let forced = |s: V2| grid.is_clear(p + s) && !grid.is_clear(behind + s);
+        if forced(side) || forced(-side) {
+            return Some(p);
+        }
+    }
+}
+
+// where a diagonal run from `p` along `d` turns interesting: the goal, or a square a straight
+// run from would find one
+fn jump(grid: &PixelGrid, p: V2, d: V2, goal: V2) -> Option<V2> {
+    if d.x == 0 || d.y == 0 {
+        return jump_straight(grid, p, d, goal);
+    }
+
+    let mut p = p;
+    loop {
+        if !grid.can_step(p, d) {
+            return None;
+        }
+        p = p + d;
+        if p == goal {
+            return Some(p);
+        }
+        if jump_straight(grid, p, V2::new(d.x, 0), goal).is_some() ||
+           jump_straight(grid, p, V2::new(0, d.y), goal).is_some()
+        {
+            return Some(p);
+        }
+    }
+}
+
+// the pixels from `a` to `b`, which lie on a straight or diagonal line, excluding `a`
+fn push_run(pixels: &mut Vec<V2>, a: V2, b: V2) {
+    let d = signum(b - a);
+    let mut p = a;
+    while p != b {
+        p = p + d;
+        pixels.push(p);
+    }
+}
+
+// A* over jump points only, skipping the squares between them a run passes straight over
+pub fn jump_point_search(grid: &PixelGrid, start: V2, goal: V2) -> Result<GridPath, Error> {
+    grid.check(start)?;
+    grid.check(goal)?;
+
+    let mut costs: HashMap<V2, i64> = HashMap::new();
+    let mut parents: HashMap<V2, V2> = HashMap::new();
+    let mut open: PriorityQueue<V2, Reverse<i64>> = PriorityQueue::new();
+    costs.insert(start, 0);
+    open.push(start, Reverse(octile(start, goal)));
+
+    let mut stats = SearchStats { max_frontier: 1, ..SearchStats::default() };
+    while let Some((p, _)) = open.pop() {
+        stats.expansions += 1;
+        if p == goal {
+            let mut points = vec![goal];
+            while let Some(parent) = parents.get(points.last().unwrap()) {
+                points.push(*parent);
+            }
+            points.reverse();
+
+            let mut pixels = vec![start];
+            for pair in points.windows(2) {
+                push_run(&mut pixels, pair[0], pair[1]);
+            }
+            let length = costs[&goal] as f64 / STRAIGHT as f64;
+            return Ok(GridPath { pixels, length, stats });
+        }
+
+        let cost = costs[&p];
+        let from = parents.get(&p).map(|parent| signum(p - *parent));
+        for d in pruned_neighbors(grid, p, from) {
+            let next = match jump(grid, p, d, goal) {
+                Some(next) => next,
+                None       => continue,
+            };
+
+            let next_cost = cost + octile(p, next);
+            if costs.get(&next).is_none_or(|known| next_cost < *known) {
+                costs.insert(next, next_cost);
+                parents.insert(next, p);
+                open.push(next, Reverse(next_cost + octile(next, goal)));
+                stats.relaxations += 1;
+            }
+        }
+        stats.saw_frontier(open.len());
+    }
+
+    Err(Error::Unreachable(goal))
+}
+
+// as the parents are packed, two bits each
+const NEIGHBORS: [V2; 4] = [V2 { x: 1, y: 0 }, V2 { x: 0, y: 1 }, V2 { x: -1, y: 0 }, V2 { x: 0, y: -1 }];
+
+// breadth-first over every clear pixel, for the exact shortest 4-connected path whatever it
+// costs. each pixel keeps only a seen bit and the two-bit direction to its parent, so even a
+// huge maze costs three bits a pixel beyond the queue.
+pub fn pixel_bfs(grid: &PixelGrid, start: V2, goal: V2) -> Result<GridPath, Error> {
+    grid.check(start)?;
+    grid.check(goal)?;
+
+    let len = (grid.width as usize) * (grid.height as usize);
+    let mut seen = Bitmap::new(len);
+    let mut parents = vec![0u8; len.div_ceil(4)];
+    let mut queue = VecDeque::new();
+    seen.set(grid.index(start));
+    queue.push_back(grid.index(start));
+
+    let mut stats = SearchStats { max_frontier: 1, ..SearchStats::default() };
+    while let Some(index) = queue.pop_front() {
+        stats.expansions += 1;
+        let p = grid.pos(index);
+        if p == goal {
+            break;
+        }
+
+        for (dir, d) in NEIGHBORS.iter().enumerate() {
+            let next = p + *d;
+            if !grid.is_clear(next) || seen.get(grid.index(next)) {
+                continue;
+            }
+
+            // the direction back, so the path is walked from the goal
+            let at = grid.index(next);
+            let back = (dir as u8 + 2) % 4;
+            parents[at / 4] |= back << (at % 4 * 2);
+            seen.set(at);
+            queue.push_back(at);
+            stats.relaxations += 1;
+        }
+        stats.saw_frontier(queue.len());
+    }
+
+    if !seen.get(grid.index(goal)) {
+        return Err(Error::Unreachable(goal));
+    }
+
+    let mut pixels = vec![goal];
+    let mut p = goal;
+    while p != start {
+        let at = grid.index(p);
+        let back = parents[at / 4] >> (at % 4 * 2) & 3;
+        p = p + NEIGHBORS[back as usize];
+        pixels.push(p);
+    }
+    pixels.reverse();
+
+    let length = (pixels.len() - 1) as f64;
+    Ok(GridPath { pixels, length, stats })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+// the classic hand-on-the-wall walk: 4-connected, keeping the wall always on one side, it
+// needs no graph and no memory of the way, only of the way it's facing. from the start it heads
+// right until it meets a wall. it only finds goals on a wall joined to the one it set out
+// along, coming back round otherwise, and walks every dead end it passes, so the route it
+// gives is often far longer than the path, going over itself.
+pub fn wall_follower(grid: &PixelGrid, start: V2, goal: V2, hand: Hand) -> Result<GridPath, Error> {
+    grid.check(start)?;
+    grid.check(goal)?;
+
+    // the directions as indices into NEIGHBORS, which go clockwise
+    let (towards_hand, away) = match hand {
+        Hand::Right => (1, 3),
+        Hand::Left  => (3, 1),
+    };
+    let turn = |heading: usize, by: usize| (heading + by) % 4;
+
+    let len = (grid.width as usize) * (grid.height as usize);
+    // each pixel and heading stood at, to tell when the walk has come back round
+    let mut been = Bitmap::new(len * 4);
+    let mut pixels = vec![start];
+    let mut stats = SearchStats { max_frontier: 1, ..SearchStats::default() };
+    let mut p = start;
+    let mut heading = 0;
+
+    // out to a wall, then turned to have it at hand
+    while p != goal && grid.is_clear(p + NEIGHBORS[heading]) {
+        p = p + NEIGHBORS[heading];
+        pixels.push(p);
+        stats.expansions += 1;
+    }
+    if p != goal {
+        heading = turn(heading, away);
+    }
+
+    while p != goal {
+        let state = grid.index(p) * 4 + heading;
+        if been.get(state) {
+            return Err(Error::WallLoop(goal));
+        }
+        been.set(state);
+        stats.expansions += 1;
+
+        let ahead = p + NEIGHBORS[heading];
+        if !grid.is_clear(ahead) {
+            heading = turn(heading, away);
+            continue;
+        }
+        p = ahead;
+        pixels.push(p);
+
+        // the wall at hand ends here, so round its corner
+        let round = turn(heading, towards_hand);
+        if p != goal && grid.is_clear(p + NEIGHBORS[round]) {
+            heading = round;
+            p = p + NEIGHBORS[heading];
+            pixels.push(p);
+        }
+    }
+
+    let length = (pixels.len() - 1) as f64;
+    Ok(GridPath { pixels, length, stats })
+}
+
+// the eight pixels round about, anticlockwise from the one to the right
+const RING: [V2; 8] = [
+    V2 { x: 1, y: 0 }, V2 { x: 1, y: -1 }, V2 { x: 0, y: -1 }, V2 { x: -1, y: -1 },
+    V2 { x: -1, y: 0 }, V2 { x: -1, y: 1 }, V2 { x: 0, y: 1 }, V2 { x: 1, y: 1 },
+];
+
+// whether clearing `p` away leaves the clear pixels about it joined as they were: yokoi's
+// connectivity number for 4-connected clear space is one. a pixel at the tip of a dead end is
+// simple, as is one along the side of a corridor; one the way through depends on isn't, nor is
+// one with clear all round, which would leave a hole.
+fn is_simple(grid: &PixelGrid, p: V2) -> bool {
+    let x = |k: usize| grid.is_clear(p + RING[k % 8]) as u8;
+    (0 .. 8).step_by(2).map(|k| x(k) - x(k) * x(k + 1) * x(k + 2)).sum::<u8>() == 1
+}
+
+pub struct FilledMaze {
+    // the clear pixels left once every dead end is filled, the way from start to goal at its
+    // full width, and any loops in it
+    pub corridor:  Vec<V2>,
+    // the clear pixels filled in
+    pub dead_ends: Vec<V2>,
+    // the shortest way along the middle of the corridor
+    pub path:      GridPath,
+}
+
+// dead-end filling over the pixels, with no graph: every clear pixel that can go without
+// parting the start from the goal is filled in, beside the walls first and working inwards, so
+// the dead ends wear away to nothing and the way through to a line down its middle. the
+// corridor is then that line grown back out as wide as the walls allowed. in a maze with loops,
+// they're kept whole, so it's for simply connected mazes that the corridor is just the way.
+pub fn fill_dead_ends(grid: &PixelGrid, start: V2, goal: V2) -> Result<FilledMaze, Error> {
+    grid.check(start)?;
+    grid.check(goal)?;
+
+    let clearance = clearance(grid);
+    let len = (grid.width as usize) * (grid.height as usize);
+    let mut thin = PixelGrid { clear: grid.clear.clone(), width: grid.width, height: grid.height };
+    let mut stats = SearchStats::default();
+
+    // fewest clear pixels out first, so the walls close in evenly from both sides
+    let mut queue: BinaryHeap<Reverse<(u16, usize)>> = (0 .. len)
+        .filter(|index| grid.clear.get(*index))
+        .map(|index| Reverse((clearance[index], index)))
+        .collect();
+    while let Some(Reverse((_, index))) = queue.pop() {
+        stats.expansions += 1;
+        let p = grid.pos(index);
+        if !thin.clear.get(index) || p == start || p == goal || !is_simple(&thin, p) {
+            continue;
+        }
+        thin.clear.unset(index);
+        stats.relaxations += 1;
+        for d in RING.iter() {
+            if thin.is_clear(p + *d) {
+                let at = thin.index(p + *d);
+                queue.push(Reverse((clearance[at], at)));
+            }
+        }
+        stats.saw_frontier(queue.len());
+    }
+
+    // what's left of the parts of the maze apart from the start, each worn to a point or a loop,
+    // goes too
+    let path = pixel_bfs(&thin, start, goal)?;
+    let mut kept = Bitmap::new(len);
+    let mut reach = vec![thin.index(start)];
+    kept.set(thin.index(start));
+    while let Some(index) = reach.pop() {
+        for d in NEIGHBORS.iter() {
+            let next = thin.pos(index) + *d;
+            if thin.is_clear(next) && !kept.get(thin.index(next)) {
+                kept.set(thin.index(next));
+                reach.push(thin.index(next));
+            }
+        }
+    }
+
+    // each pixel of the line covers what was clear within its clearance, less one, as the
+    // pixels 1 from a wall cover only themselves
+    let mut reaches = vec![0u16; len];
+    let mut grow: BinaryHeap<(u16, usize)> = BinaryHeap::new();
+    for index in (0 .. len).filter(|index| kept.get(*index)) {
+        reaches[index] = clearance[index];
+        grow.push((clearance[index], index));
+    }
+    while let Some((reach, index)) = grow.pop() {
+        if reach < reaches[index] || reach <= 1 {
+            continue;
+        }
+        for d in RING.iter() {
+            let next = grid.pos(index) + *d;
+            if grid.is_clear(next) && reaches[grid.index(next)] < reach - 1 {
+                reaches[grid.index(next)] = reach - 1;
+                grow.push((reach - 1, grid.index(next)));
+            }
+        }
+    }
+
+    let (corridor, dead_ends) = (0 .. len)
+        .filter(|index| grid.clear.get(*index))
+        .partition::<Vec<usize>, _>(|index| reaches[*index] > 0);
+    Ok(FilledMaze {
+        corridor:  corridor.into_iter().map(|index| grid.pos(index)).collect(),
+        dead_ends: dead_ends.into_iter().map(|index| grid.pos(index)).collect(),
+        path:      GridPath { stats, ..path },
+    })
+}
+
+// what a 4-connected pixel path costs: `step` for each step, `turn` for each change of
+// direction, and `crowding` over the clearance of each pixel stepped into, so a path pays more
+// the closer it runs to the walls
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathCosts {
+    pub step:     u64,
+    pub turn:     u64,
+    pub crowding: u64,
+}
+
+impl PathCosts {
+    // turns costing more than any length could, so the fewest turns and then the shortest
+    pub const FEWEST_TURNS: PathCosts = PathCosts { step: 1, turn: 1 << 32, crowding: 0 };
+}
+
+// each clear pixel's distance in px to the nearest wall or the edge of the grid, counting
+// diagonal steps as one, so 1 beside a wall; 0 on walls. two chamfer passes, forward and back
+pub fn clearance(grid: &PixelGrid) -> Vec<u16> {
+    let (w, h) = (grid.width, grid.height);
+    let mut dist = vec![0u16; (w as usize) * (h as usize)];
+    for (index, d) in dist.iter_mut().enumerate() {
+        let p = grid.pos(index);
+        if grid.clear.get(index) {
+            let edge = (p.x + 1).min(p.y + 1).min(w - p.x).min(h - p.y);
+            *d = edge.min(u16::MAX as Coord) as u16;
+        }
+    }
+
+    let len = dist.len();
+    let mut pass = |order: &mut dyn Iterator<Item = usize>, behind: [V2; 4]| {
+        for at in order {
+            let p = grid.pos(at);
+            for n in behind.iter().map(|d| p + *d).filter(|n| grid.in_bounds(*n)) {
+                dist[at] = dist[at].min(dist[grid.index(n)].saturating_add(1));
+            }
+        }
+    };
+    let forward = [V2::new(-1, -1), V2::new(0, -1), V2::new(1, -1), V2::new(-1, 0)];
+    pass(&mut (0 .. len), forward);
+    pass(&mut (0 .. len).rev(), forward.map(|d| -d));
+    dist
+}
+
+// the mean clearance along a path, in px
+pub fn mean_clearance(grid: &PixelGrid, clearance: &[u16], pixels: &[V2]) -> f64 {
+    let sum: u64 = pixels.iter().map(|p| u64::from(clearance[grid.index(*p)])).sum();
+    sum as f64 / pixels.len().max(1) as f64
+}
+
+// the fewest 4-connected straight runs from start to goal, and of those the shortest, for
+// plotters and the like where every turn costs more than any distance
+pub fn fewest_turns(grid: &PixelGrid, start: V2, goal: V2) -> Result<GridPath, Error> {
+    cheapest_path(grid, start, goal, PathCosts::FEWEST_TURNS)
+}
+
+// the 4-connected path costing least by `costs`. the search is over a pixel and the direction it
+// was entered in, so it keeps a cost and a parent for four states a pixel: 36 bytes a pixel
+// beyond the queue, and two more for the clearances where crowding costs anything.
+pub fn cheapest_path(grid: &PixelGrid, start: V2, goal: V2, costs: PathCosts) -> Result<GridPath, Error> {
+    grid.check(start)?;
+    grid.check(goal)?;
+    if start == goal {
+        return Ok(GridPath { pixels: vec![start], length: 0.0, stats: SearchStats::default() });
+    }
+
+    let clearance = if costs.crowding > 0 { clearance(grid) } else { Vec::new() };
+    let step = |next: V2| match clearance.get(grid.index(next)) {
+        Some(c) => costs.step + costs.crowding / u64::from((*c).max(1)),
+        None    => costs.step,
+    };
+
+    let len = (grid.width as usize) * (grid.height as usize);
+    let mut totals = vec![u64::MAX; len * 4];
+    let mut parents = vec![0u8; len * 4];
+    let mut open = BinaryHeap::new();
+    for (dir, d) in NEIGHBORS.iter().enumerate() {
+        if grid.is_clear(start + *d) {
+            let state = grid.index(start + *d) * 4 + dir;
+            totals[state] = step(start + *d);
+            parents[state] = dir as u8;
+            open.push(Reverse((totals[state], state)));
+        }
+    }
+
+    let mut stats = SearchStats { max_frontier: open.len(), ..SearchStats::default() };
+    let mut found = None;
+    while let Some(Reverse((total, state))) = open.pop() {
+        if total > totals[state] {
+            continue;
+        }
+        stats.expansions += 1;
+        let (p, dir) = (grid.pos(state / 4), state % 4);
+        if p == goal {
+            found = Some(state);
+            break;
+        }
+
+        for (next_dir, d) in NEIGHBORS.iter().enumerate() {
+            // never straight back the way it came
+            let next = p + *d;
+            if next_dir == (dir + 2) % 4 || !grid.is_clear(next) {
+                continue;
+            }
+            let turn = if next_dir == dir { 0 } else { costs.turn };
+            let next_total = total.saturating_add(turn).saturating_add(step(next));
+            let next_state = grid.index(next) * 4 + next_dir;
+            if next_total < totals[next_state] {
+                totals[next_state] = next_total;
+                parents[next_state] = dir as u8;
+                open.push(Reverse((next_total, next_state)));
+                stats.relaxations += 1;
+            }
+        }
+        stats.saw_frontier(open.len());
+    }
+
+    // each state's pixel is a step on from its parent's along the direction it was entered in
+    let mut state = found.ok_or(Error::Unreachable(goal))?;
+    let mut pixels = vec![goal];
+    loop {
+        let (p, dir) = (grid.pos(state / 4), state % 4);
+        let back = p - NEIGHBORS[dir];
+        pixels.push(back);
+        if back == start {
+            break;
+        }
+        state = grid.index(back) * 4 + parents[state] as usize;
+    }
+    pixels.reverse();
+
+    let length = (pixels.len() - 1) as f64;
+    Ok(GridPath { pixels, length, stats })
+}
+
+// the times a pixel path changes direction
+pub fn turns(pixels: &[V2]) -> usize {
+    let steps: Vec<V2> = pixels.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    steps.windows(2).filter(|pair| pair[0] != pair[1]).count()
+}
+
+fn euclid(a: V2, b: V2) -> i64 {
+    let d = b - a;
+    ((d.length2() as f64).sqrt() * STRAIGHT as f64).round() as i64
+}
+
+// the pixels of the line from `a` to `b`, each a step on from the last, excluding `a`
+fn line_pixels(a: V2, b: V2) -> Vec<V2> {
+    let (dx, dy) = ((b.x - a.x).abs(), -(b.y - a.y).abs());
+    let step = signum(b - a);
+    let mut err = dx + dy;
+    let mut p = a;
+    let mut pixels = Vec::new();
+    while p != b {
+        let twice = 2 * err;
+        let mut next = p;
+        if twice >= dy { err += dy; next.x += step.x; }
+        if twice <= dx { err += dx; next.y += step.y; }
+        pixels.push(next);
+        p = next;
+    }
+    pixels
+}
+
+// whether the straight line from `a` to `b` keeps to clear, allowed squares without cutting a
+// corner on the way
+fn line_of_sight(grid: &PixelGrid, a: V2, b: V2, allowed: &impl Fn(V2) -> bool) -> bool {
+    let mut p = a;
+    for next in line_pixels(a, b) {
+        if !allowed(next) || !grid.can_step(p, next - p) {
+            return false;
+        }
+        p = next;
+    }
+    true
+}
+
+// any-angle A*, theta*: each square reached may take the parent of the square it was reached
+// from as its own wherever there's a straight line between them, so paths run at any angle
+// rather than in steps of 45 degrees. only squares `allowed` says are searched. the path comes
+// back as its straight lines drawn pixel by pixel.
+pub fn theta_star(grid: &PixelGrid, start: V2, goal: V2, allowed: impl Fn(V2) -> bool)
+    -> Result<GridPath, Error>
+{
+    grid.check(start)?;
+    grid.check(goal)?;
+    let allowed = |p: V2| p == start || p == goal || allowed(p);
+
+    let mut costs: HashMap<V2, i64> = HashMap::new();
+    let mut parents: HashMap<V2, V2> = HashMap::new();
+    let mut open: PriorityQueue<V2, Reverse<i64>> = PriorityQueue::new();
+    costs.insert(start, 0);
+    parents.insert(start, start);
+    open.push(start, Reverse(euclid(start, goal)));
+
+    let mut stats = SearchStats { max_frontier: 1, ..SearchStats::default() };
+    while let Some((p, _)) = open.pop() {
+        stats.expansions += 1;
+        if p == goal {
+            let mut corners = vec![goal];
+            while corners.last() != Some(&start) {
+                corners.push(parents[corners.last().unwrap()]);
+            }
+            corners.reverse();
+
+            let mut pixels = vec![start];
+            for pair in corners.windows(2) {
+                pixels.extend(line_pixels(pair[0], pair[1]));
+            }
+            let length = costs[&goal] as f64 / STRAIGHT as f64;
+            return Ok(GridPath { pixels, length, stats });
+        }
+
+        let parent = parents[&p];
+        for d in [V2::new(1, 0), V2::new(-1, 0), V2::new(0, 1), V2::new(0, -1),
+                  V2::new(1, 1), V2::new(1, -1), V2::new(-1, 1), V2::new(-1, -1)].iter()
+        {
+            let next = p + *d;
+            if !allowed(next) || !grid.can_step(p, *d) {
+                continue;
+            }
+
+            let (from, cost) = if line_of_sight(grid, parent, next, &allowed) {
+                (parent, costs[&parent] + euclid(parent, next))
+            }
+            else {
+                (p, costs[&p] + euclid(p, next))
+            };
+            if costs.get(&next).is_none_or(|known| cost < *known) {
+                costs.insert(next, cost);
+                parents.insert(next, from);
+                open.push(next, Reverse(cost + euclid(next, goal)));
+                stats.relaxations += 1;
+            }
+        }
+        stats.saw_frontier(open.len());
+    }
+
+    Err(Error::Unreachable(goal))
+}
+
+// `pixels` with every stretch through the open squares redone by theta*, for mixed maps whose
+// corridors suit the rect graph and whose open fields suit lines at any angle. a stretch theta*
+// can't better is kept as it was.
+pub fn straighten_open_runs(grid: &PixelGrid, pixels: &[V2], is_open: impl Fn(V2) -> bool) -> Vec<V2> {
+    let mut out = Vec::with_capacity(pixels.len());
+    let mut i = 0;
+    while i < pixels.len() {
+        if !is_open(pixels[i]) {
+            out.push(pixels[i]);
+            i += 1;
+            continue;
+        }
+
+        let mut end = i;
+        while end + 1 < pixels.len() && is_open(pixels[end + 1]) {
+            end += 1;
+        }
+        let run = &pixels[i ..= end];
+        let run_length: i64 = run.windows(2).map(|pair| euclid(pair[0], pair[1])).sum();
+        match theta_star(grid, run[0], run[run.len() - 1], &is_open) {
+            Ok(path) if (path.length * STRAIGHT as f64) < run_length as f64 => out.extend(path.pixels),
+            _ => out.extend_from_slice(run),
+        }
+        i = end + 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{graph::tests::pillared_hall, preprocess::{CLEAR, WALL}},
+        pcg_rand::Pcg32Basic,
+        rand::{Rng, SeedableRng},
+    };
+
+    // the cost of the cheapest 8-connected path, in thousandths, by plain dijkstra over every
+    // pixel, with no jumps and no pruning to get wrong
+    fn octile_dijkstra(grid: &PixelGrid, start: V2, goal: V2) -> Option<i64> {
+        let mut costs: HashMap<V2, i64> = HashMap::new();
+        let mut open = BinaryHeap::new();
+        costs.insert(start, 0);
+        open.push(Reverse((0, start.x, start.y)));
+        while let Some(Reverse((cost, x, y))) = open.pop() {
+            let p = V2::new(x, y);
+            if p == goal {
+                return Some(cost);
+            }
+            if cost > costs[&p] {
+                continue;
+            }
+            for dx in -1 ..= 1 {
+                for dy in -1 ..= 1 {
+                    let d = V2::new(dx, dy);
+                    if d == V2::new(0, 0) || !grid.can_step(p, d) {
+                        continue;
+                    }
+                    let next = cost + if dx != 0 && dy != 0 { DIAGONAL } else { STRAIGHT };
+                    if costs.get(&(p + d)).is_none_or(|known| next < *known) {
+                        costs.insert(p + d, next);
+                        open.push(Reverse((next, p.x + dx, p.y + dy)));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // jump point search finds as short a way as dijkstra over every pixel, by steps it could
+    // take, and one wherever the 4-connected search does, since it never cuts a corner
+    fn check_jps(image: &im::GrayImage, start: V2, goal: V2) {
+        let grid = PixelGrid::new(image).unwrap();
+        let jps = jump_point_search(&grid, start, goal);
+        let bfs = pixel_bfs(&grid, start, goal);
+        let expected = octile_dijkstra(&grid, start, goal);
+        let path = match (jps, expected) {
+            (Ok(path), Some(cost)) => { assert_eq!((path.length * 1000.0).round() as i64, cost); path }
+            (Err(err), None)       => {
+                assert_eq!(err, Error::Unreachable(goal));
+                assert_eq!(bfs.err(), Some(Error::Unreachable(goal)));
+                return;
+            }
+            (jps, expected) => panic!("{:?} to {:?}: jps {:?}, dijkstra {:?}", start, goal, jps.map(|p| p.length), expected),
+        };
+
+        assert_eq!((path.pixels[0], path.pixels[path.pixels.len() - 1]), (start, goal));
+        assert!(path.pixels.windows(2).all(|pair| grid.can_step(pair[0], pair[1] - pair[0])));
+        let steps: i64 = path.pixels.windows(2).map(|pair| octile(pair[0], pair[1])).sum();
+        assert_eq!(steps as f64 / 1000.0, path.length);
+        assert!(path.length <= bfs.unwrap().length);
+    }
+
+    #[test]
+    fn jump_points_cost_what_every_pixel_does() {
+        // an open room, corner to corner and across
+        let room = im::GrayImage::from_pixel(11, 7, CLEAR);
+        check_jps(&room, V2::new(0, 0), V2::new(10, 6));
+        check_jps(&room, V2::new(0, 3), V2::new(10, 3));
+        check_jps(&room, V2::new(5, 6), V2::new(5, 6));
+
+        // a corridor a pixel wide winding back and forth
+        let winding = im::GrayImage::from_fn(11, 9, |x, y| match (x, y % 4) {
+            (_, 0) | (_, 2)  => CLEAR,
+            (10, 1) | (0, 3) => CLEAR,
+            _                => WALL,
+        });
+        check_jps(&winding, V2::new(0, 0), V2::new(0, 8));
+
+        // two walls meeting only at a corner, which no diagonal squeezes through: apart, and
+        // then with a way round the far end of one
+        let mut squeeze = im::GrayImage::from_pixel(9, 9, CLEAR);
+        for y in 0 .. 4 {
+            squeeze.put_pixel(4, y, WALL);
+        }
+        for y in 4 .. 9 {
+            squeeze.put_pixel(5, y, WALL);
+        }
+        check_jps(&squeeze, V2::new(4, 4), V2::new(5, 3));
+        check_jps(&squeeze, V2::new(0, 0), V2::new(8, 0));
+        squeeze.put_pixel(5, 8, CLEAR);
+        check_jps(&squeeze, V2::new(4, 4), V2::new(5, 3));
+        check_jps(&squeeze, V2::new(0, 0), V2::new(8, 0));
+
+        // and a third of the pillared hall's pixels walled at random, between a few pairs
+        let mut rng = Pcg32Basic::seed_from_u64(304);
+        for _ in 0 .. 20 {
+            let mut image = pillared_hall();
+            for pixel in image.pixels_mut() {
+                if rng.gen_bool(0.3) {
+                    *pixel = WALL;
+                }
+            }
+            let (start, goal) = (V2::new(1, 1), V2::new(16, 11));
+            image.put_pixel(1, 1, CLEAR);
+            image.put_pixel(16, 11, CLEAR);
+            check_jps(&image, start, goal);
+            check_jps(&image, goal, start);
+        }
+    }
+
+    #[test]
+    fn pixel_paths_trade_turns_and_clearance_for_length() {
+        let mut image = pillared_hall();
+        let grid = PixelGrid::new(&image).unwrap();
+        let (start, goal) = (V2::new(1, 1), V2::new(16, 11));
+
+        // along the clear row and down the clear column, one turn and no longer than the shortest
+        let path = fewest_turns(&grid, start, goal).unwrap();
+        assert_eq!(turns(&path.pixels), 1);
+        assert_eq!(path.length, pixel_bfs(&grid, start, goal).unwrap().length);
+        assert_eq!((path.pixels[0], path.pixels[path.pixels.len() - 1]), (start, goal));
+        assert!(path.pixels.windows(2).all(|pair| (pair[1] - pair[0]).manhattan() == 1));
+        assert!(path.pixels.iter().all(|p| grid.is_clear(*p)));
+
+        // a wall across leaves no way through
+        for y in 0 .. 13 {
+            image.put_pixel(8, y, WALL);
+        }
+        assert_eq!(fewest_turns(&PixelGrid::new(&image).unwrap(), start, goal).err(),
+            Some(Error::Unreachable(goal)));
+
+        // in an open room, paying for crowding draws a path along the edge out away from it
+        let room = PixelGrid::new(&im::GrayImage::from_pixel(9, 5, CLEAR)).unwrap();
+        let clearance = clearance(&room);
+        assert_eq!((clearance[0], clearance[9 + 1], clearance[2 * 9 + 4]), (1, 2, 3));
+        let (start, goal) = (V2::new(0, 0), V2::new(8, 0));
+        let costs = PathCosts { step: 1000, turn: 0, crowding: 10_000 };
+        let hugging = cheapest_path(&room, start, goal, PathCosts::FEWEST_TURNS).unwrap();
+        let centred = cheapest_path(&room, start, goal, costs).unwrap();
+        assert!(hugging.pixels.iter().all(|p| p.y == 0));
+        assert!(centred.pixels.iter().any(|p| p.y > 0));
+        assert!(mean_clearance(&room, &clearance, &centred.pixels)
+            > mean_clearance(&room, &clearance, &hugging.pixels));
+    }
+
+    #[test]
+    fn hands_on_the_wall_circle_islands() {
+        // a room with a block standing in the middle of it, clear of the outer walls
+        let mut image = im::GrayImage::from_pixel(9, 9, CLEAR);
+        for y in 3 .. 6 {
+            for x in 3 .. 6 {
+                image.put_pixel(x, y, WALL);
+            }
+        }
+        let grid = PixelGrid::new(&image).unwrap();
+        let start = V2::new(0, 0);
+
+        // either hand keeps to the outer walls, round to a goal in the far corner
+        for hand in [Hand::Left, Hand::Right] {
+            let goal = V2::new(8, 8);
+            let route = wall_follower(&grid, start, goal, hand).unwrap();
+            assert_eq!((route.pixels[0], route.pixels[route.pixels.len() - 1]), (start, goal));
+            assert!(route.pixels.windows(2).all(|pair| (pair[1] - pair[0]).manhattan() == 1));
+            assert!(route.pixels.iter().all(|p| p.x == 0 || p.y == 0 || p.x == 8 || p.y == 8));
+        }
+
+        // but never comes near the block
+        let goal = V2::new(4, 2);
+        assert_eq!(wall_follower(&grid, start, goal, Hand::Right).err(), Some(Error::WallLoop(goal)));
+    }
+
+    #[test]
+    fn dead_end_filling_leaves_the_corridor_through() {
+        // a corridor three wide from side to side, with a dead end off it downwards
+        let mut image = im::GrayImage::from_pixel(11, 9, WALL);
+        for (x, y) in (0 .. 11).flat_map(|x| (1 .. 4).map(move |y| (x, y))) {
+            image.put_pixel(x, y, CLEAR);
+        }
+        for (x, y) in (4 .. 7).flat_map(|x| (4 .. 8).map(move |y| (x, y))) {
+            image.put_pixel(x, y, CLEAR);
+        }
+        let (start, goal) = (V2::new(0, 2), V2::new(10, 2));
+        let filled = fill_dead_ends(&PixelGrid::new(&image).unwrap(), start, goal).unwrap();
+
+        assert_eq!(filled.corridor.len(), 33);
+        assert!(filled.corridor.iter().all(|p| (1 .. 4).contains(&p.y)));
+        assert_eq!(filled.dead_ends.len(), 12);
+        assert_eq!(filled.path.pixels, (0 .. 11).map(|x| V2::new(x, 2)).collect::<Vec<_>>());
+    }
+}
@@ -2,23 +2,158 @@
 use {
     crate::{
         math::*,
-        graph::{Edge, EdgeSetGraph, NodeID},
+        error::Error,
+        graph::{
+            next_id, offset_id, AdjacencyGraph, Edge, EdgeSetGraph, Graph, HashMap, HashSet, NodeID, RawNodeID,
+            FIRST_ID,
+        },
+        spatial::Spatial,
+        tiles::{TileError, TileSource},
     },
     std::{
-        collections::{HashMap, HashSet, VecDeque,},
+        collections::VecDeque,
+        convert::TryFrom,
     },
     image as im,
+    log::{debug, trace},
 };
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
 const WHITE: im::Luma<u8> = im::Luma([255; 1]);
 
+// what a space is for, where something has told. extraction leaves every space plain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    #[default]
+    Space,
+    // building plan spaces, from floorplan::classify()
+    Room,
+    Corridor,
+}
+
+// what the pipeline knows of a space: its bounds, the pixels it covers within them, and its
+// kind. every rect is clear throughout for now, but area and centroid say what's covered either
+// way. anything more to know about a space goes here rather than in a map beside the graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeData {
+    pub rect:     Rect,
+    // covered pixels
+    pub area:     i64,
+    // the covered pixel at or just up and left of the covered pixels' mean
+    pub centroid: V2,
+    pub kind:     NodeKind,
+}
+
+impl NodeData {
+    // a wholly covered rect
+    pub fn from_rect(rect: Rect) -> NodeData {
+        let centroid = V2::new(rect.mins.x + (rect.width() - 1) / 2, rect.mins.y + (rect.height() - 1) / 2);
+        NodeData { rect, area: rect.area(), centroid, kind: NodeKind::Space }
+    }
+}
+
+// shorthand for the space data of any graph of spaces
+pub trait SpaceGraph: Graph<NodeData> {
+    fn rect(&self, id: NodeID) -> Rect { self.get_node(id).rect }
+    fn area(&self, id: NodeID) -> i64 { self.get_node(id).area }
+    fn centroid(&self, id: NodeID) -> V2 { self.get_node(id).centroid }
+    fn kind(&self, id: NodeID) -> NodeKind { self.get_node(id).kind }
+
+    fn total_area(&self) -> i64 {
+        self.nodes().values().map(|data| data.area).sum()
+    }
+
+    fn count_kind(&self, kind: NodeKind) -> usize {
+        self.nodes().values().filter(|data| data.kind == kind).count()
+    }
+}
+
+impl<G: Graph<NodeData>> SpaceGraph for G { }
+
+impl Spatial for NodeData {
+    fn rect(&self) -> Rect { self.rect }
+}
+
+// the side two touching spaces share, as a line along pixel edges from `a` to `b`. one of
+// x and y is the same at both ends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PortalSegment {
+    pub a: V2,
+    pub b: V2,
+}
+
+impl PortalSegment {
+    // None unless the rects meet along a side, rather than at a corner or not at all
+    pub fn between(r: Rect, s: Rect) -> Option<PortalSegment> {
+        let (x0, x1) = (r.mins.x.max(s.mins.x), r.maxs.x.min(s.maxs.x));
+        let (y0, y1) = (r.mins.y.max(s.mins.y), r.maxs.y.min(s.maxs.y));
+        if x0 == x1 && y0 < y1 {
+            Some(PortalSegment { a: V2::new(x0, y0), b: V2::new(x0, y1) })
+        }
+        else if y0 == y1 && x0 < x1 {
+            Some(PortalSegment { a: V2::new(x0, y0), b: V2::new(x1, y0) })
+        }
+        else {
+            None
+        }
+    }
+
+    // how many pixels wide the way through is
+    pub fn width(&self) -> Coord {
+        (self.b.x - self.a.x) + (self.b.y - self.a.y)
+    }
+
+    // whether it runs up and down, between spaces side by side
+    pub fn is_vertical(&self) -> bool {
+        self.a.x == self.b.x
+    }
+}
+
+impl EdgeSetGraph<NodeData> {
+    // into_adjacency_graph(), with each adjacency costing what `weight` says rather than 1:
+    // given the two spaces, the lower id's first, and the side they share. searches and
+    // contraction go by these weights from then on. a weight of 0 counts as 1, as every step
+    // has to cost something for astar's bound to hold.
+    pub fn into_weighted_adjacency_graph(
+        self,
+        weight: impl Fn(&NodeData, &NodeData, &PortalSegment) -> u32)
+        -> AdjacencyGraph<NodeData>
+    {
+        let weights: HashMap<Edge, i32> = self.edges.iter()
+            .filter_map(|edge| {
+                let (u, v) = (self.get_node(edge.min), self.get_node(edge.max));
+                let portal = PortalSegment::between(u.rect, v.rect)?;
+                let cost = weight(u, v, &portal).clamp(1, i32::MAX as u32) as i32;
+                Some((*edge, cost))
+            })
+            .filter(|(_, cost)| *cost != 1)
+            .collect();
+
+        let mut graph = self.into_adjacency_graph();
+        graph.weights = weights;
+        graph
+    }
+}
+
+fn into_graph(nodes: HashMap<NodeID, Rect>, start: NodeID, goal: NodeID, edges: HashSet<Edge>)
+    -> EdgeSetGraph<NodeData>
+{
+    debug!("extracted {} spaces with {} openings between them", nodes.len(), edges.len());
+    let nodes = nodes.into_iter()
+        .map(|(id, rect)| (id, NodeData::from_rect(rect)))
+        .collect();
+    EdgeSetGraph::new(nodes, start, goal, edges)
+}
+
 fn grow_rect(grid: &Grid, seed: V2) -> Rect {
     let mut mins = V2::new(seed.x, seed.y);
     let mut maxs = V2::new(seed.x + 1, seed.y + 1);
 
     loop {
         let pos = V2::new(mins.x - 1, seed.y);
-        if !grid.in_bounds(pos) || grid.get(pos) != GridSquare::Clear {
+        if !grid.in_bounds(pos) || !grid.is_clear(pos) {
             break;
         }
         mins.x -= 1;
@@ -26,7 +161,7 @@ fn grow_rect(grid: &Grid, seed: V2) -> Rect {
 
     loop {
         let pos = V2::new(maxs.x, seed.y);
-        if !grid.in_bounds(pos) || grid.get(pos) != GridSquare::Clear {
+        if !grid.in_bounds(pos) || !grid.is_clear(pos) {
             break
         }
         maxs.x += 1;
@@ -39,7 +174,7 @@ fn grow_rect(grid: &Grid, seed: V2) -> Rect {
         }
 
         for x in mins.x .. maxs.x {
-            if grid.get(V2::new(x, y)) != GridSquare::Clear {
+            if !grid.is_clear(V2::new(x, y)) {
                 break 'ymin_loop;
             }
         }
@@ -52,7 +187,7 @@ fn grow_rect(grid: &Grid, seed: V2) -> Rect {
         }
 
         for x in mins.x .. maxs.x {
-            if grid.get(V2::new(x, maxs.y)) != GridSquare::Clear {
+            if !grid.is_clear(V2::new(x, maxs.y)) {
                 break 'ymax_loop;
             }
         }
@@ -65,48 +200,61 @@ fn grow_rect(grid: &Grid, seed: V2) -> Rect {
 
 type SeedQueue = VecDeque<V2>;
 
+// the rects touching `id` along one edge are linked to it where the opening between walls is at
+// least `min_width` across, so that a pinhole left by anti-aliasing doesn't join two corridors.
+// clear squares are queued as seeds however narrow, so that the flood still covers them.
 fn scan_edge(
     grid:  &Grid,
     queue: &mut SeedQueue,
     edges: &mut HashSet<Edge>,
     id:    NodeID,
-    start: V2,
-    step:  V2,
-    count: i32)
+    side:  Segment,
+    min_width: Coord)
 {
+    let Segment { start, step, len: count } = side;
     let mut pos = start;
     let mut prev_square = GridSquare::Wall;
+    let mut opening = 0;
+    let mut touching: Vec<NodeID> = Vec::new();
+
+    let mut end_square = |square: GridSquare, pos: V2, touching: &mut Vec<NodeID>| match square {
+        GridSquare::Covered(prev_id) => touching.push(prev_id),
+        GridSquare::Clear            => queue.push_back(pos),
+        GridSquare::Wall             => { }
+    };
+    let end_opening = |opening: Coord, touching: &mut Vec<NodeID>, edges: &mut HashSet<Edge>| {
+        if opening >= min_width {
+            edges.extend(touching.iter().map(|other| Edge::new(id, *other)));
+        }
+        touching.clear();
+    };
 
-    for _ in 0 .. count {
+    let mut scanned = 0;
+    while scanned < count {
         if !grid.in_bounds(pos) { break; }
 
-        let square = grid.get(pos);
+        // a covered square stays the same to the end of its rect
+        let (square, run) = grid.get_run(pos, step);
         if square != prev_square {
-            match prev_square {
-                GridSquare::Covered(prev_id) => {
-                    edges.insert(Edge::new(id, prev_id));
-                }
-                GridSquare::Clear => {
-                    queue.push_back(pos - step);
-                }
-                GridSquare::Wall => { }
+            end_square(prev_square, pos - step, &mut touching);
+            if square == GridSquare::Wall {
+                end_opening(opening, &mut touching, edges);
+                opening = 0;
             }
 
             prev_square = square;
         }
 
-        pos = pos + step;
-    }
-
-    match prev_square {
-        GridSquare::Covered(prev_id) => {
-            edges.insert(Edge::new(id, prev_id));
+        let run = run.min(count - scanned);
+        if square != GridSquare::Wall {
+            opening += run;
         }
-        GridSquare::Clear => {
-            queue.push_back(pos - step);
-        }
-        GridSquare::Wall => { }
+        scanned += run;
+        pos = pos + step * run;
     }
+
+    end_square(prev_square, pos - step, &mut touching);
+    end_opening(opening, &mut touching, edges);
 }
 
 fn scan_rect_boundary(
@@ -114,16 +262,12 @@ fn scan_rect_boundary(
     queue: &mut SeedQueue,
     edges: &mut HashSet<Edge>,
     id:    NodeID,
-    rect:  Rect)
+    rect:  Rect,
+    min_width: Coord)
 {
-    scan_edge(grid, queue, edges, id,
-        V2::new(rect.mins.x, rect.mins.y-1), V2::new(1, 0), rect.width());
-    scan_edge(grid, queue, edges, id,
-        V2::new(rect.mins.x, rect.maxs.y), V2::new(1, 0), rect.width());
-    scan_edge(grid, queue, edges, id,
-        V2::new(rect.mins.x-1, rect.mins.y), V2::new(0, 1), rect.height());
-    scan_edge(grid, queue, edges, id,
-        V2::new(rect.maxs.x, rect.mins.y), V2::new(0, 1), rect.height());
+    for side in rect.outer_sides().iter() {
+        scan_edge(grid, queue, edges, id, *side, min_width);
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -133,93 +277,815 @@ enum GridSquare {
     Covered(NodeID)
 }
 
+// the state of each pixel takes two bits, and only the rects' top-left corners are kept with
+// their nodes, so the grid is a quarter of a byte a pixel rather than eight, plus an entry per
+// rect. a covered pixel's node is found by walking left to its rect's left edge, then up that
+// edge to the top of any rects stacked on it, then down the stack to the rect it's in.
+const CLEAR:     u8 = 0;
+const WALL:      u8 = 1;
+const COVERED:   u8 = 2;
+// covered, and in the leftmost column of its rect
+const LEFT_EDGE: u8 = 3;
+
 struct Grid {
-    squares: Vec<GridSquare>,
-    width:   usize,
-    height:  usize,
+    states: Vec<u8>,
+    // by the top-left corner of each claimed rect: its far corner, and its node
+    claims: HashMap<V2, (V2, NodeID)>,
+    width:  usize,
+    height: usize,
 }
 
 impl Grid {
-    fn new_from_image(image: &im::GrayImage) -> Grid {
-        let width = image.width() as usize;
-        let height = image.height() as usize;
-        let squares: Vec<GridSquare> = image.pixels()
-            .map(|pixel|
-                if *pixel == WHITE { GridSquare::Clear }
-                else               { GridSquare::Wall }
-            )
-            .collect();
+    fn new_from_image(image: &im::GrayImage) -> Result<Grid, Error> {
+        // the image's size checked against the coordinate type once, here, to index by after
+        let width = coord(image.width())? as usize;
+        let height = coord(image.height())? as usize;
+        let mut states = vec![0; (width * height).div_ceil(4)];
+        for (index, pixel) in image.pixels().enumerate() {
+            if *pixel != WHITE {
+                states[index / 4] |= WALL << (index % 4 * 2);
+            }
+        }
 
-        Grid { squares, width, height }
+        Ok(Grid { states, claims: HashMap::default(), width, height })
     }
 
-    fn get_mut(&mut self, pos: V2) -> &mut GridSquare {
+    fn state(&self, pos: V2) -> u8 {
         let index = pos.y as usize * self.width + pos.x as usize;
-        &mut self.squares[index]
+        (self.states[index / 4] >> (index % 4 * 2)) & 3
     }
 
-    fn get(&self, pos: V2) -> GridSquare {
+    fn set_state(&mut self, pos: V2, state: u8) {
         let index = pos.y as usize * self.width + pos.x as usize;
-        self.squares[index]
+        let byte = &mut self.states[index / 4];
+        *byte = (*byte & !(3 << (index % 4 * 2))) | state << (index % 4 * 2);
+    }
+
+    fn is_clear(&self, pos: V2) -> bool {
+        self.state(pos) == CLEAR
+    }
+
+    // marks every pixel of `rect` as covered by `id`
+    fn claim(&mut self, rect: Rect, id: NodeID) {
+        for y in rect.mins.y .. rect.maxs.y {
+            self.set_state(V2::new(rect.mins.x, y), LEFT_EDGE);
+            for x in rect.mins.x + 1 .. rect.maxs.x {
+                self.set_state(V2::new(x, y), COVERED);
+            }
+        }
+        self.claims.insert(rect.mins, (rect.maxs, id));
+    }
+
+    fn get(&self, pos: V2) -> GridSquare {
+        self.get_run(pos, V2::new(1, 0)).0
+    }
+
+    // the square at `pos`, and how many squares from it along `step`, an axis, are the same
+    // for certain
+    fn get_run(&self, pos: V2, step: V2) -> (GridSquare, Coord) {
+        match self.state(pos) {
+            CLEAR => (GridSquare::Clear, 1),
+            WALL  => (GridSquare::Wall, 1),
+            _     => {
+                let mut corner = pos;
+                while self.state(corner) != LEFT_EDGE {
+                    corner.x -= 1;
+                }
+                while corner.y > 0 && self.state(corner - V2::new(0, 1)) == LEFT_EDGE {
+                    corner.y -= 1;
+                }
+                let (maxs, id) = loop {
+                    let (maxs, id) = self.claims[&corner];
+                    if pos.y < maxs.y {
+                        break (maxs, id);
+                    }
+                    corner.y = maxs.y;
+                };
+                let run = if step.x != 0 { maxs.x - pos.x } else { maxs.y - pos.y };
+                (GridSquare::Covered(id), run)
+            }
+        }
     }
 
     fn in_bounds(&self, pos: V2) -> bool {
-        Rect::new_unchecked(V2::new(0, 0), V2::new(self.width as i32, self.height as i32))
+        Rect::new_unchecked(V2::new(0, 0), V2::new(self.width as Coord, self.height as Coord))
             .contains(pos)
     }
 }
 
-pub fn extract_graph(image: &im::GrayImage, start: V2, goal: V2) -> Option<EdgeSetGraph<Rect>> {
-    let mut grid = Grid::new_from_image(image);
+// the border pixels in clockwise order from the top-left corner
+fn border_ring(w: Coord, h: Coord) -> Vec<V2> {
+    let mut ring: Vec<V2> = Vec::with_capacity(2 * (w + h).max(0) as usize);
+    if w == 0 || h == 0 {
+        return ring;
+    }
 
-    let mut nodes: HashMap<NodeID, Rect> = HashMap::new();
-    let mut edges: HashSet<Edge> = HashSet::new();
+    ring.extend((0 .. w).map(|x| V2::new(x, 0)));
+    ring.extend((1 .. h).map(|y| V2::new(w - 1, y)));
+    if h > 1 { ring.extend((0 .. w - 1).rev().map(|x| V2::new(x, h - 1))); }
+    if w > 1 { ring.extend((1 .. h - 1).rev().map(|y| V2::new(0, y))); }
+    ring
+}
 
-    let mut queue: SeedQueue = SeedQueue::new();
-    queue.push_back(start);
+// contiguous runs of clear pixels along the image border, wrapping around the corners
+pub fn border_runs(image: &im::GrayImage) -> Vec<Vec<V2>> {
+    let mut ring = border_ring(coord_clamped(image.width()), coord_clamped(image.height()));
+    let clear = |p: &V2| *image.get_pixel(p.x as u32, p.y as u32) == WHITE;
 
-    let mut id = NodeID::new(1).unwrap();
+    // start the walk on a wall so that no run is split across the ends of the ring
+    match ring.iter().position(|p| !clear(p)) {
+        Some(first_wall) => ring.rotate_left(first_wall),
+        None if ring.is_empty() => return Vec::new(),
+        None => return vec![ring],
+    }
+
+    let mut runs: Vec<Vec<V2>> = Vec::new();
+    let mut in_run = false;
+    for pos in ring.iter() {
+        if clear(pos) {
+            if !in_run {
+                runs.push(Vec::new());
+            }
+            runs.last_mut().unwrap().push(*pos);
+        }
+        in_run = clear(pos);
+    }
 
-    let mut start_id = None;
-    let mut goal_id = None;
+    runs
+}
+
+// one representative pixel per border opening
+pub fn border_openings(image: &im::GrayImage) -> Vec<V2> {
+    border_runs(image).iter()
+        .map(|run| run[0])
+        .collect()
+}
 
-    while let Some(seed) = queue.pop_front() {
-        if grid.get(seed) != GridSquare::Clear {
+// the clear pixel nearest `pos` and at most `radius` px from it, `pos` itself if it's clear.
+// ties go to the first in reading order.
+#[allow(clippy::unnecessary_cast)]
+pub fn nearest_clear(image: &im::GrayImage, pos: V2, radius: Coord) -> Option<V2> {
+    let (w, h) = (coord_clamped(image.width()), coord_clamped(image.height()));
+    let mut best: Option<(i64, V2)> = None;
+    for y in (pos.y - radius).max(0) ..= (pos.y + radius).min(h - 1) {
+        for x in (pos.x - radius).max(0) ..= (pos.x + radius).min(w - 1) {
+            let (dx, dy) = ((x - pos.x) as i64, (y - pos.y) as i64);
+            let dist = dx * dx + dy * dy;
+            let nearer = best.is_none_or(|(best_dist, _)| dist < best_dist);
+            if nearer && dist <= radius as i64 * radius as i64 && *image.get_pixel(x as u32, y as u32) == WHITE {
+                best = Some((dist, V2::new(x, y)));
+            }
+        }
+    }
+    best.map(|(_, p)| p)
+}
+
+// walls over every border opening that doesn't contain one of `keep`, so the solver can't
+// sneak out of a gap in a badly scanned outer wall and around the outside
+pub fn seal_border(image: &mut im::GrayImage, keep: &[V2]) -> usize {
+    let mut sealed = 0;
+    for run in border_runs(image) {
+        if keep.iter().any(|pos| run.contains(pos)) {
+            continue;
+        }
+
+        for pos in run.iter() {
+            image.put_pixel(pos.x as u32, pos.y as u32, im::Luma([0]));
+        }
+        sealed += 1;
+    }
+    sealed
+}
+
+fn flood_rects(grid: &mut Grid, seeds: &[V2], min_width: Coord)
+    -> Result<(HashMap<NodeID, Rect>, HashSet<Edge>), Error>
+{
+    let mut nodes: HashMap<NodeID, Rect> = HashMap::default();
+    let mut edges: HashSet<Edge> = HashSet::default();
+
+    let mut queue: SeedQueue = seeds.iter().copied().collect();
+    flood_from(grid, &mut queue, &mut nodes, &mut edges, FIRST_ID, min_width)?;
+
+    Ok((nodes, edges))
+}
+
+// grows rects from the queued seeds until the reachable space is covered, numbering them up
+// from `id`. returns the next unused id.
+fn flood_from(
+    grid:  &mut Grid,
+    queue: &mut SeedQueue,
+    nodes: &mut HashMap<NodeID, Rect>,
+    edges: &mut HashSet<Edge>,
+    id:    NodeID,
+    min_width: Coord)
+    -> Result<NodeID, Error>
+{
+    flood_some(grid, queue, nodes, edges, id, min_width, usize::MAX)
+}
+
+// as flood_from, but stopping after `budget` seeds, grown or already covered. the flood is done
+// once the queue is empty.
+fn flood_some(
+    grid:  &mut Grid,
+    queue: &mut SeedQueue,
+    nodes: &mut HashMap<NodeID, Rect>,
+    edges: &mut HashSet<Edge>,
+    mut id: NodeID,
+    min_width: Coord,
+    budget: usize)
+    -> Result<NodeID, Error>
+{
+    for _ in 0 .. budget {
+        let seed = match queue.pop_front() {
+            Some(seed) => seed,
+            None       => break,
+        };
+        if !grid.in_bounds(seed) || !grid.is_clear(seed) {
             continue;
         }
 
         // grow rect into the space around the seed
-        let rect = grow_rect(&grid, seed);
+        let rect = grow_rect(grid, seed);
 
         // claim the covered squares
-        for y in rect.mins.y .. rect.maxs.y {
-            for x in rect.mins.x .. rect.maxs.x {
-                *grid.get_mut(V2::new(x, y)) = GridSquare::Covered(id);
-            }
-        }
+        grid.claim(rect, id);
 
         // scan the edge of the rect for adjacent spaces
-        scan_rect_boundary(&grid, &mut queue, &mut edges, id, rect);
+        let known = edges.len();
+        scan_rect_boundary(grid, queue, edges, id, rect, min_width);
+        trace!("grew space {} over {:?} from {:?}, opening onto {} more", id, rect, seed, edges.len() - known);
 
         nodes.insert(id, rect);
 
-        if rect.contains(start) {
-            debug_assert!(start_id.is_none());
-            start_id = Some(id);
+        id = next_id(id)?;
+    }
+
+    Ok(id)
+}
+
+// every row's clear runs are strips, and a strip is merged into the one above it when their
+// x-extents match exactly. unlike grow_rect this doesn't depend on seed order, and long vertical
+// corridors come out as single rects. covers the whole grid, reachable or not.
+fn all_strips(grid: &mut Grid, min_width: Coord) -> Result<(HashMap<NodeID, Rect>, HashSet<Edge>), Error> {
+    let mut rects: Vec<Rect> = Vec::new();
+    let mut open: HashMap<(Coord, Coord), usize> = HashMap::default();
+    let mut next_open: HashMap<(Coord, Coord), usize> = HashMap::default();
+
+    for y in 0 .. grid.height as Coord {
+        let mut x = 0;
+        while x < grid.width as Coord {
+            if !grid.is_clear(V2::new(x, y)) {
+                x += 1;
+                continue;
+            }
+
+            let x0 = x;
+            while x < grid.width as Coord && grid.is_clear(V2::new(x, y)) {
+                x += 1;
+            }
+
+            let index = match open.get(&(x0, x)) {
+                Some(index) => {
+                    rects[*index].maxs.y = y + 1;
+                    *index
+                }
+                None => {
+                    rects.push(Rect::new(V2::new(x0, y), V2::new(x, y + 1)));
+                    rects.len() - 1
+                }
+            };
+            next_open.insert((x0, x), index);
         }
 
-        if rect.contains(goal) {
-            debug_assert!(goal_id.is_none());
-            goal_id = Some(id);
+        std::mem::swap(&mut open, &mut next_open);
+        next_open.clear();
+    }
+
+    let mut all_nodes: HashMap<NodeID, Rect> = HashMap::with_capacity_and_hasher(rects.len(), Default::default());
+    for (index, rect) in rects.iter().enumerate() {
+        let id = RawNodeID::try_from(index + 1).ok()
+            .and_then(NodeID::new)
+            .ok_or(Error::TooManyNodes)?;
+        grid.claim(*rect, id);
+        all_nodes.insert(id, *rect);
+    }
+
+    // nothing is left clear, so the seed queue stays empty
+    let mut queue = SeedQueue::new();
+    let mut all_edges: HashSet<Edge> = HashSet::default();
+    for (id, rect) in all_nodes.iter() {
+        scan_rect_boundary(grid, &mut queue, &mut all_edges, *id, *rect, min_width);
+    }
+
+    Ok((all_nodes, all_edges))
+}
+
+// the strips connected to a seed
+fn strip_rects(grid: &mut Grid, seeds: &[V2], min_width: Coord)
+    -> Result<(HashMap<NodeID, Rect>, HashSet<Edge>), Error>
+{
+    let (all_nodes, all_edges) = all_strips(grid, min_width)?;
+    let seed_ids: Vec<NodeID> = seeds.iter()
+        .filter_map(|seed| covering_node(grid, *seed))
+        .collect();
+    Ok(connected(all_nodes, all_edges, &seed_ids))
+}
+
+// the nodes reachable from any of `from`
+fn reach(edges: &HashSet<Edge>, from: &[NodeID]) -> HashSet<NodeID> {
+    let mut adjs: HashMap<NodeID, Vec<NodeID>> = HashMap::default();
+    for Edge { min, max } in edges.iter().copied() {
+        adjs.entry(min).or_default().push(max);
+        adjs.entry(max).or_default().push(min);
+    }
+
+    let mut reached: HashSet<NodeID> = from.iter().copied().collect();
+    let mut frontier: Vec<NodeID> = reached.iter().copied().collect();
+    while let Some(id) = frontier.pop() {
+        for neighbor in adjs.get(&id).into_iter().flatten() {
+            if reached.insert(*neighbor) {
+                frontier.push(*neighbor);
+            }
         }
+    }
+    reached
+}
+
+// the nodes and edges reachable from any of `from`
+fn connected(all_nodes: HashMap<NodeID, Rect>, all_edges: HashSet<Edge>, from: &[NodeID])
+    -> (HashMap<NodeID, Rect>, HashSet<Edge>)
+{
+    let reached = reach(&all_edges, from);
+    let nodes = all_nodes.into_iter()
+        .filter(|(id, _)| reached.contains(id))
+        .collect();
+    let edges = all_edges.into_iter()
+        .filter(|edge| reached.contains(&edge.min))
+        .collect();
+
+    (nodes, edges)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decomposition {
+    // grow_rect flood from the seeds
+    Greedy,
+    // vertically merged row strips
+    Strips,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ExtractOptions {
+    pub decomposition: Decomposition,
+    // openings between rects narrower than this, in px, aren't edges. a gap one rect spans
+    // whole, as a strip spans a thin gap across its row, is no opening and still joins.
+    pub min_passage:   Coord,
+}
 
-        id = NodeID::new(id.get() + 1).unwrap();
+impl Default for ExtractOptions {
+    fn default() -> ExtractOptions {
+        ExtractOptions {
+            decomposition: Decomposition::Greedy,
+            min_passage:   1,
+        }
+    }
+}
+
+fn decompose(grid: &mut Grid, seeds: &[V2], opts: &ExtractOptions)
+    -> Result<(HashMap<NodeID, Rect>, HashSet<Edge>), Error>
+{
+    match opts.decomposition {
+        Decomposition::Greedy => flood_rects(grid, seeds, opts.min_passage),
+        Decomposition::Strips => strip_rects(grid, seeds, opts.min_passage),
+    }
+}
+
+fn covering_node(grid: &Grid, pos: V2) -> Option<NodeID> {
+    if !grid.in_bounds(pos) {
+        return None;
+    }
+
+    match grid.get(pos) {
+        GridSquare::Covered(id) => Some(id),
+        _                       => None,
     }
+}
+
+// the node a point ended up in, or why it isn't in one. strips cover the grid even where the
+// seeds can't reach, so a covering node also has to have been kept.
+fn locate(grid: &Grid, nodes: &HashMap<NodeID, Rect>, pos: V2) -> Result<NodeID, Error> {
+    if !grid.in_bounds(pos) {
+        return Err(Error::OutOfBounds(pos));
+    }
+
+    match covering_node(grid, pos) {
+        Some(id) if nodes.contains_key(&id) => Ok(id),
+        _ if grid.get(pos) == GridSquare::Wall => Err(Error::Blocked(pos)),
+        _ => Err(Error::Unreachable(pos)),
+    }
+}
+
+pub fn extract_graph(image: &im::GrayImage, start: V2, goal: V2, opts: &ExtractOptions)
+    -> Result<EdgeSetGraph<NodeData>, Error>
+{
+    Extraction::new(image, start, goal, opts)?.finish()
+}
+
+// extract_graph() a bounded number of seeds at a time, for callers that can't block until the
+// whole maze is covered. strips have no flood to pause, so they're decomposed whole in the first
+// step.
+pub struct Extraction {
+    grid:  Grid,
+    queue: SeedQueue,
+    // the goal, seeded once the flood from the start runs dry, so that a goal it never reached
+    // is still covered and found apart from the start, not just missing
+    later: Option<V2>,
+    nodes: HashMap<NodeID, Rect>,
+    edges: HashSet<Edge>,
+    next:  NodeID,
+    start: V2,
+    goal:  V2,
+    opts:  ExtractOptions,
+}
+
+impl Extraction {
+    pub fn new(image: &im::GrayImage, start: V2, goal: V2, opts: &ExtractOptions) -> Result<Extraction, Error> {
+        Ok(Extraction {
+            grid:  Grid::new_from_image(image)?,
+            queue: std::iter::once(start).collect(),
+            later: Some(goal),
+            nodes: HashMap::default(),
+            edges: HashSet::default(),
+            next:  FIRST_ID,
+            start, goal,
+            opts:  *opts,
+        })
+    }
+
+    // pops up to `budget` seeds, returning whether the flood is done
+    pub fn step(&mut self, budget: usize) -> Result<bool, Error> {
+        if self.opts.decomposition == Decomposition::Strips {
+            if !self.queue.is_empty() {
+                self.queue.clear();
+                self.later = None;
+                (self.nodes, self.edges) = strip_rects(&mut self.grid, &[self.start, self.goal], self.opts.min_passage)?;
+            }
+            return Ok(true);
+        }
+
+        self.next = flood_some(&mut self.grid, &mut self.queue, &mut self.nodes, &mut self.edges,
+            self.next, self.opts.min_passage, budget)?;
+        if self.queue.is_empty() {
+            self.queue.extend(self.later.take());
+        }
+        Ok(self.queue.is_empty())
+    }
+
+    // rects grown so far
+    pub fn nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // the flood run to the end, and the graph it makes. the start and goal both have to be clear
+    // and joined, wherever in the image they are.
+    pub fn finish(mut self) -> Result<EdgeSetGraph<NodeData>, Error> {
+        while !self.step(usize::MAX)? { }
+
+        let start = locate(&self.grid, &self.nodes, self.start)?;
+        let goal  = locate(&self.grid, &self.nodes, self.goal)?;
+        if start != goal && !reach(&self.edges, &[start]).contains(&goal) {
+            return Err(Error::Disconnected(self.start, self.goal));
+        }
+        Ok(into_graph(self.nodes, start, goal, self.edges))
+    }
+}
 
-    let start = start_id.unwrap();
-    let goal  = goal_id.unwrap();
+// the bounds of every pixel that differs between two images of the same size, or None if
+// they're identical
+pub fn changed_region(before: &im::GrayImage, after: &im::GrayImage) -> Option<Rect> {
+    let mut bounds: Option<Rect> = None;
+    for ((x, y, a), b) in before.enumerate_pixels().zip(after.pixels()) {
+        if a == b {
+            continue;
+        }
+        let p = V2::new(x as Coord, y as Coord);
+        let pixel = Rect::new_unchecked(p, p + V2::new(1, 1));
+        bounds = Some(match bounds {
+            Some(r) => Rect::new_unchecked(
+                V2::new(r.mins.x.min(p.x), r.mins.y.min(p.y)),
+                V2::new(r.maxs.x.max(p.x + 1), r.maxs.y.max(p.y + 1))),
+            None => pixel,
+        });
+    }
+    bounds
+}
+
+// re-extracts `prev`, a graph extracted from an earlier version of `image`, after the pixels in
+// `changed` have changed. rects clear of the change are kept with their ids, and only the space
+// they leave uncovered is flooded again. strips depend on whole rows, so they're always
+// extracted from scratch. unlike a fresh extraction, kept rects the change has cut off from the
+// start stay in the graph, and the goal is flooded from too, so a goal the change has cut off
+// turns out unreachable only when searched.
+pub fn update_graph(
+    prev:    &EdgeSetGraph<NodeData>,
+    image:   &im::GrayImage,
+    changed: Rect,
+    start:   V2,
+    goal:    V2,
+    opts:    &ExtractOptions)
+    -> Result<EdgeSetGraph<NodeData>, Error>
+{
+    if opts.decomposition != Decomposition::Greedy {
+        return extract_graph(image, start, goal, opts);
+    }
+
+    let mut grid = Grid::new_from_image(image)?;
+    let bounds = Rect::new_unchecked(V2::new(0, 0), V2::new(grid.width as Coord, grid.height as Coord));
+
+    let mut nodes: HashMap<NodeID, Rect> = prev.com.nodes.iter()
+        .map(|(id, data)| (*id, data.rect))
+        .filter(|(_, rect)| !rect.overlaps(changed) && rect.intersect(bounds) == *rect)
+        .collect();
+    for (id, rect) in nodes.iter() {
+        grid.claim(*rect, *id);
+    }
+
+    let mut edges: HashSet<Edge> = prev.edges.iter()
+        .filter(|e| nodes.contains_key(&e.min) && nodes.contains_key(&e.max))
+        .copied()
+        .collect();
+
+    // the kept rects around the change, and around the rects dropped for overlapping it, seed
+    // the flood into the space left uncovered
+    let border = Rect::new_unchecked(changed.mins - V2::new(1, 1), changed.maxs + V2::new(1, 1));
+    let mut frontier: HashSet<NodeID> = prev.nodes_in(border)
+        .filter(|id| nodes.contains_key(id))
+        .collect();
+    for edge in prev.edges.iter() {
+        match (nodes.contains_key(&edge.min), nodes.contains_key(&edge.max)) {
+            (true, false) => { frontier.insert(edge.min); }
+            (false, true) => { frontier.insert(edge.max); }
+            _             => { }
+        }
+    }
+
+    let mut queue: SeedQueue = VecDeque::new();
+    queue.push_back(start);
+    for id in frontier.iter() {
+        scan_rect_boundary(&grid, &mut queue, &mut edges, *id, nodes[id], opts.min_passage);
+    }
+
+    let next = match nodes.keys().max() {
+        Some(last) => next_id(*last)?,
+        None       => FIRST_ID,
+    };
+    let next = flood_from(&mut grid, &mut queue, &mut nodes, &mut edges, next, opts.min_passage)?;
+    queue.push_back(goal);
+    flood_from(&mut grid, &mut queue, &mut nodes, &mut edges, next, opts.min_passage)?;
+
+    let start = locate(&grid, &nodes, start)?;
+    let goal  = locate(&grid, &nodes, goal)?;
+
+    Ok(into_graph(nodes, start, goal, edges))
+}
+
+// `rect` cut into `inner` and the bands around it: above and below the full width, beside it
+// only its height
+fn cut(rect: Rect, inner: Rect) -> Vec<Rect> {
+    let (a, b) = (rect.mins, rect.maxs);
+    let (c, d) = (inner.mins, inner.maxs);
+    vec![
+        inner,
+        Rect::new_unchecked(a, V2::new(b.x, c.y)),
+        Rect::new_unchecked(V2::new(a.x, d.y), b),
+        Rect::new_unchecked(V2::new(a.x, c.y), V2::new(c.x, d.y)),
+        Rect::new_unchecked(V2::new(d.x, c.y), V2::new(b.x, d.y)),
+    ]
+    .into_iter()
+    .filter(|piece| !piece.is_empty())
+    .collect()
+}
+
+// cuts the least rect holding the `marked` pixels of each space off from the rest of it, so that
+// whatever the marks mean starts and stops where they're drawn rather than wherever the space
+// happened to. `start` and `goal` stay with the pieces holding them. joins other than shared
+// sides, as teleporters' are, are lost with a space cut up, so want making after.
+pub fn split_marked(graph: &mut EdgeSetGraph<NodeData>, start: V2, goal: V2, marked: impl Fn(V2) -> bool)
+    -> Result<(), Error>
+{
+    let mut nodes = graph.com.nodes.clone();
+    let mut edges = std::mem::take(&mut graph.edges);
+    let (mut start_id, mut goal_id) = (graph.start(), graph.goal());
+
+    let mut neighbors: HashMap<NodeID, Vec<NodeID>> = HashMap::default();
+    for edge in edges.iter() {
+        neighbors.entry(edge.min).or_default().push(edge.max);
+        neighbors.entry(edge.max).or_default().push(edge.min);
+    }
+
+    // in id order, so that new ids come out the same every run
+    let mut ids: Vec<NodeID> = nodes.keys().copied().collect();
+    ids.sort();
+    let mut next = nodes.keys().max().map_or(Ok(FIRST_ID), |id| next_id(*id))?;
+    for id in ids {
+        let rect = nodes[&id].rect;
+        let bounds = rect.points()
+            .filter(|p| marked(*p))
+            .map(|p| Rect::new_unchecked(p, p + V2::new(1, 1)))
+            .reduce(|bounds, at| bounds.union(at));
+        let bounds = match bounds {
+            Some(bounds) if bounds != rect => bounds,
+            _                              => continue,
+        };
+
+        // the marked piece keeps the id, and the rest take new ones
+        let mut pieces = vec![id];
+        for piece in cut(rect, bounds).into_iter().skip(1) {
+            nodes.insert(next, NodeData::from_rect(piece));
+            pieces.push(next);
+            next = next_id(next)?;
+        }
+        nodes.insert(id, NodeData::from_rect(bounds));
+        let holding = |pos: V2| pieces.iter().copied().find(|piece| nodes[piece].rect.contains(pos)).unwrap_or(id);
+        if start_id == id { start_id = holding(start); }
+        if goal_id == id  { goal_id = holding(goal); }
+
+        // each piece joins whichever of the old neighbours, and of the other pieces, it still meets
+        let old = neighbors.remove(&id).unwrap_or_default();
+        for other in old.iter() {
+            edges.remove(&Edge::new(id, *other));
+            neighbors.entry(*other).or_default().retain(|n| *n != id);
+        }
+        for (i, piece) in pieces.iter().enumerate() {
+            for other in old.iter().chain(&pieces[i + 1 ..]).copied() {
+                if PortalSegment::between(nodes[piece].rect, nodes[&other].rect).is_some() {
+                    edges.insert(Edge::new(*piece, other));
+                    neighbors.entry(*piece).or_default().push(other);
+                    neighbors.entry(other).or_default().push(*piece);
+                }
+            }
+        }
+    }
 
-    Some(EdgeSetGraph::new(nodes, start, goal, edges))
+    let mut one_way = std::mem::take(&mut graph.one_way);
+    one_way.retain(|edge, _| edges.contains(edge));
+    *graph = EdgeSetGraph::new(nodes, start_id, goal_id, edges);
+    graph.one_way = one_way;
+    Ok(())
+}
+
+// floods from every seed; the graph's start and goal are the first and last seeds' nodes
+pub fn extract_seeded_graph(image: &im::GrayImage, seeds: &[V2], opts: &ExtractOptions)
+    -> Result<(EdgeSetGraph<NodeData>, Vec<NodeID>), Error>
+{
+    let mut grid = Grid::new_from_image(image)?;
+    let (nodes, edges) = decompose(&mut grid, seeds, opts)?;
+
+    let seed_ids: Vec<NodeID> = seeds.iter()
+        .map(|seed| locate(&grid, &nodes, *seed))
+        .collect::<Result<_, _>>()?;
+
+    let start = *seed_ids.first().ok_or(Error::NoSeeds)?;
+    let goal  = *seed_ids.last().ok_or(Error::NoSeeds)?;
+
+    Ok((into_graph(nodes, start, goal, edges), seed_ids))
+}
+
+// decomposes the whole grid, reachable or not
+fn decompose_all(grid: &mut Grid, opts: &ExtractOptions)
+    -> Result<(HashMap<NodeID, Rect>, HashSet<Edge>), Error>
+{
+    match opts.decomposition {
+        Decomposition::Greedy => {
+            let mut nodes: HashMap<NodeID, Rect> = HashMap::default();
+            let mut edges: HashSet<Edge> = HashSet::default();
+            let mut id = FIRST_ID;
+            for y in 0 .. grid.height as Coord {
+                for x in 0 .. grid.width as Coord {
+                    let pos = V2::new(x, y);
+                    if grid.is_clear(pos) {
+                        let mut queue: SeedQueue = std::iter::once(pos).collect();
+                        id = flood_from(grid, &mut queue, &mut nodes, &mut edges, id, opts.min_passage)?;
+                    }
+                }
+            }
+            Ok((nodes, edges))
+        }
+        Decomposition::Strips => all_strips(grid, opts.min_passage),
+    }
+}
+
+// extracts the graph a tile at a time, so that only a row of tiles is ever held, along with the
+// seam of the row above. every tile is decomposed whole and linked to its neighbours to the left
+// and above, then the rects the start can't reach are dropped once all the tiles are in. rects
+// stop at the seams, so there are more of them than extract_graph() would give. a seam cuts
+// short the openings it crosses, even within a tile, so min_passage must be 1.
+pub fn extract_tiled_graph(
+    source:    &mut dyn TileSource,
+    tile_size: u32,
+    start:     V2,
+    goal:      V2,
+    opts:      &ExtractOptions)
+    -> Result<EdgeSetGraph<NodeData>, TileError>
+{
+    if tile_size == 0 {
+        return Err(TileError::Options("tiles must be at least a pixel across"));
+    }
+    if opts.min_passage > 1 {
+        return Err(TileError::Options("seams would cut openings short of min_passage"));
+    }
+    let size = coord_clamped(tile_size);
+    let (width, height) = source.dimensions();
+    let (width, height) = (coord(width)?, coord(height)?);
+    let bounds = Rect::new_unchecked(V2::new(0, 0), V2::new(width, height));
+    for pos in [start, goal].iter() {
+        if !bounds.contains(*pos) {
+            return Err(Error::OutOfBounds(*pos).into());
+        }
+    }
+
+    let mut nodes: HashMap<NodeID, Rect> = HashMap::default();
+    let mut edges: HashSet<Edge> = HashSet::default();
+    let mut located: HashMap<V2, NodeID> = HashMap::default();
+    let mut offset = 0;
+
+    // the nodes along the bottom of the last row of tiles, and down the right of the last tile
+    let mut above: Vec<Option<NodeID>> = vec![None; width as usize];
+    let mut left: Vec<Option<NodeID>> = Vec::new();
+    // the nodes facing each other across a seam
+    let link = |pairs: &mut dyn Iterator<Item = (Option<NodeID>, Option<NodeID>)>, edges: &mut HashSet<Edge>| {
+        edges.extend(pairs.filter_map(|pair| match pair {
+            (Some(a), Some(b)) => Some(Edge::new(a, b)),
+            _                  => None,
+        }));
+    };
+
+    for y0 in (0 .. height).step_by(size as usize) {
+        // a row of tiles is read in turn, then decomposed at once across threads, then stitched
+        // in order so that the ids come out the same however many threads there are
+        let mut row = Vec::new();
+        for x0 in (0 .. width).step_by(size as usize) {
+            let tile_rect = Rect::new_unchecked(V2::new(x0, y0),
+                V2::new(x0.saturating_add(size).min(width), y0.saturating_add(size).min(height)));
+            row.push((tile_rect, source.tile(tile_rect)?));
+        }
+
+        let decompose_tile = |(tile_rect, tile): (Rect, im::GrayImage)| {
+            let mut grid = Grid::new_from_image(&tile)?;
+            decompose_all(&mut grid, opts).map(|(nodes, edges)| (tile_rect, grid, nodes, edges))
+        };
+        #[cfg(feature = "parallel")]
+        let row: Vec<_> = row.into_par_iter().map(decompose_tile).collect();
+        #[cfg(not(feature = "parallel"))]
+        let row: Vec<_> = row.into_iter().map(decompose_tile).collect();
+
+        for decomposed in row {
+            let (tile_rect, grid, tile_nodes, tile_edges) = decomposed?;
+            let x0 = tile_rect.mins.x;
+
+            // checked once for the tile's last id, so that renumbering the rest can't fail
+            let top = tile_nodes.keys().max().copied();
+            if let Some(top) = top {
+                offset_id(top, offset)?;
+            }
+            let renumber = |id: NodeID| offset_id(id, offset).expect("checked against the last id");
+            let at = |pos: V2| covering_node(&grid, pos).map(renumber);
+
+            for (id, rect) in tile_nodes.iter() {
+                let rect = Rect::new_unchecked(rect.mins + tile_rect.mins, rect.maxs + tile_rect.mins);
+                nodes.insert(renumber(*id), rect);
+            }
+            edges.extend(tile_edges.iter().map(|e| Edge::new(renumber(e.min), renumber(e.max))));
+
+            // everything clear is covered, so an uncovered endpoint is on a wall
+            for pos in [start, goal].iter().filter(|pos| tile_rect.contains(**pos)) {
+                let id = at(*pos - tile_rect.mins).ok_or(Error::Blocked(*pos))?;
+                located.insert(*pos, id);
+            }
+
+            let (w, h) = (tile_rect.width(), tile_rect.height());
+            if x0 > 0 {
+                link(&mut (0 .. h).map(|y| (left[y as usize], at(V2::new(0, y)))), &mut edges);
+            }
+            left = (0 .. h).map(|y| at(V2::new(w - 1, y))).collect();
+            let seam = &mut above[x0 as usize .. (x0 + w) as usize];
+            link(&mut (0 .. w).map(|x| (seam[x as usize], at(V2::new(x, 0)))), &mut edges);
+            for (x, below) in seam.iter_mut().enumerate() {
+                *below = at(V2::new(x as Coord, h - 1));
+            }
+
+            offset += top.map_or(0, NodeID::get);
+        }
+    }
+
+    let start_id = located[&start];
+    let goal_id = located[&goal];
+    let (nodes, edges) = connected(nodes, edges, &[start_id]);
+    if !nodes.contains_key(&goal_id) {
+        return Err(Error::Disconnected(start, goal).into());
+    }
+
+    Ok(into_graph(nodes, start_id, goal_id, edges))
 }
 
 //fn traverse(image: &im::GrayImage, start: V2) -> HashMap<V2, HashSet<V2>> {
@@ -263,3 +1129,125 @@ pub fn extract_graph(image: &im::GrayImage, start: V2, goal: V2) -> Option<EdgeS
 //    adjs
 //}
 
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            pathfind::Solution,
+            preprocess::{CLEAR, WALL},
+            session::{SolveSession, Status},
+            tiles::ImageTiles,
+        },
+    };
+
+    // two rooms, one above the other, and the wall between them with maybe a pinhole through it
+    // at a corner of 6-px tiles, and maybe a 2-px doorway across another seam
+    fn rooms(pinhole: bool, doorway: bool) -> im::GrayImage {
+        let mut image = im::GrayImage::from_fn(18, 12, |_, y| if y == 5 || y == 6 { WALL } else { CLEAR });
+        for y in 5 .. 7 {
+            if pinhole {
+                image.put_pixel(6, y, CLEAR);
+            }
+            if doorway {
+                image.put_pixel(11, y, CLEAR);
+                image.put_pixel(12, y, CLEAR);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn tiles_join_as_the_whole_image_does() {
+        let (start, goal) = (V2::new(1, 1), V2::new(16, 10));
+        let opts = ExtractOptions::default();
+        for (pinhole, doorway) in [(false, false), (true, false), (false, true)].iter().copied() {
+            let image = rooms(pinhole, doorway);
+            let plain = extract_graph(&image, start, goal, &opts).is_ok();
+            assert_eq!(plain, pinhole || doorway);
+            for tile_size in [3, 4, 5, 6, 7, 18].iter().copied() {
+                let tiled = extract_tiled_graph(&mut ImageTiles(&image), tile_size, start, goal, &opts);
+                assert_eq!(tiled.is_ok(), plain, "tiles of {}", tile_size);
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_refuse_what_they_cant_extract() {
+        let image = rooms(false, true);
+        let (start, goal) = (V2::new(1, 1), V2::new(16, 10));
+        let narrow = ExtractOptions { min_passage: 2, ..ExtractOptions::default() };
+        for (tile_size, opts) in [(0, ExtractOptions::default()), (4, narrow)].iter() {
+            let tiled = extract_tiled_graph(&mut ImageTiles(&image), *tile_size, start, goal, opts);
+            assert!(matches!(tiled, Err(TileError::Options(_))), "tiles of {}", tile_size);
+        }
+    }
+
+    #[test]
+    fn weights_steer_away_from_narrow_passages() {
+        // a thick wall across the hall with a one-pixel gap close by and a wide one a long way
+        // round
+        let mut image = im::GrayImage::from_pixel(30, 20, CLEAR);
+        for y in 0 .. 20 {
+            for x in 14 .. 17 {
+                if y != 3 && !(12 .. 18).contains(&y) {
+                    image.put_pixel(x, y, WALL);
+                }
+            }
+        }
+        let (start, goal) = (V2::new(2, 8), V2::new(28, 8));
+        let extracted = extract_graph(&image, start, goal, &ExtractOptions::default()).unwrap();
+        let thin = |data: &NodeData| data.rect.width().min(data.rect.height()) < 2;
+        let narrow = |u: &NodeData, v: &NodeData, portal: &PortalSegment| {
+            if thin(u) || thin(v) || portal.width() < 2 { 100 } else { 1 }
+        };
+        let through = |solution: &Solution, p: V2| solution.rects.iter().any(|rect| rect.contains(p));
+
+        let plain = extracted.clone().into_adjacency_graph().into_dijkstra().solution().unwrap();
+        assert!(through(&plain, V2::new(15, 3)));
+
+        let weighted = extracted.clone().into_weighted_adjacency_graph(narrow);
+        let solution = weighted.into_dijkstra().solution().unwrap();
+        assert!(through(&solution, V2::new(15, 14)));
+        assert!(!through(&solution, V2::new(15, 3)));
+
+        // the same once pruned and contracted, with the corridors' weights carried into chains
+        let contracted = extracted.prune().into_weighted_adjacency_graph(narrow).contract();
+        let dijkstra = contracted.into_dijkstra();
+        assert_eq!(dijkstra.solution().unwrap().cost, solution.cost);
+        let rects: Vec<Rect> = dijkstra.path_to(dijkstra.goal()).iter().map(|id| dijkstra.get_node(*id).rect).collect();
+        assert!(rects.iter().any(|rect| rect.contains(V2::new(15, 14))));
+    }
+
+    #[test]
+    fn interior_points_solve_or_report_apart() {
+        // a closed box with a wall down the middle and a door in it, nowhere near the border
+        let mut image = im::GrayImage::from_pixel(20, 12, WALL);
+        for y in 2 .. 10 {
+            for x in 2 .. 18 {
+                if x != 10 || y == 5 {
+                    image.put_pixel(x, y, CLEAR);
+                }
+            }
+        }
+        let (start, goal) = (V2::new(4, 8), V2::new(15, 3));
+        let mut shut = image.clone();
+        shut.put_pixel(10, 5, WALL);
+
+        for decomposition in [Decomposition::Greedy, Decomposition::Strips].iter().copied() {
+            let opts = ExtractOptions { decomposition, ..ExtractOptions::default() };
+            let graph = extract_graph(&image, start, goal, &opts).unwrap();
+            let solved = graph.prune().into_adjacency_graph().contract().into_dijkstra();
+            assert!(solved.solution().is_some());
+
+            let apart = extract_graph(&shut, start, goal, &opts);
+            assert_eq!(apart.err(), Some(Error::Disconnected(start, goal)));
+        }
+
+        let mut session = SolveSession::new(&shut, start, goal, &ExtractOptions::default());
+        while session.step(usize::MAX) == Status::Extracting { }
+        assert_eq!(session.status(), Status::Failed);
+        assert_eq!(session.error(), Some(&Error::Disconnected(start, goal)));
+    }
+}
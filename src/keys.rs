@@ -0,0 +1,212 @@
+// keys and doors: marks of one strong colour, a door being one that spans a passage wall to
+// wall and a key one standing clear of them. a door can only be gone through once a key of its
+// colour has been stepped on, so the search runs over where it is and which keys it holds. a
+// door whose colour has no key stays shut, as wall.
+
+use {
+    crate::{
+        error::Error,
+        math::*,
+        graph::{AdjacencyGraph, EdgeSetGraph, Graph, HashMap, HashSet, NodeID, SearchStats},
+        image_graph::{split_marked, NodeData},
+        preprocess::{chroma, color_blobs, color_distance, WALL},
+        teleporters,
+    },
+    std::{cmp::Reverse, collections::BinaryHeap},
+    image as im,
+};
+
+// as for teleporters' marks
+const MIN_CHROMA: u8 = 96;
+const TOLERANCE: f32 = 64.0;
+
+// the keys held, a bit for each lock
+pub type KeySet = u32;
+pub const MAX_LOCKS: usize = KeySet::BITS as usize;
+
+pub struct Lock {
+    pub color: im::Rgb<u8>,
+    // every pixel of every key
+    pub keys:  Vec<V2>,
+    // every pixel of every door
+    pub doors: Vec<V2>,
+}
+
+pub struct Found {
+    pub locks:    Vec<Lock>,
+    // colours with doors but no keys, shut for good, and keys but no doors
+    pub keyless:  Vec<im::Rgb<u8>>,
+    pub doorless: Vec<im::Rgb<u8>>,
+}
+
+// whether `blob` meets wall on opposite sides, as a mark drawn across a passage does
+fn spans_passage(color: &im::RgbImage, binary: &im::GrayImage, blob: &[V2]) -> bool {
+    let pixels: HashSet<V2> = blob.iter().copied().collect();
+    let (w, h) = (coord_clamped(binary.width()), coord_clamped(binary.height()));
+    let walled = |step: V2| blob.iter().any(|p| {
+        let q = *p + step;
+        let beyond = q.x < 0 || q.y < 0 || q.x >= w || q.y >= h;
+        beyond || (!pixels.contains(&q)
+            && binary.get_pixel(q.x as u32, q.y as u32).0[0] < 128
+            && chroma(*color.get_pixel(q.x as u32, q.y as u32)) < MIN_CHROMA)
+    });
+    (walled(V2::new(1, 0)) && walled(V2::new(-1, 0))) || (walled(V2::new(0, 1)) && walled(V2::new(0, -1)))
+}
+
+// picks the keys and doors out of `color` and clears them in `binary`, the same image already
+// classified into clear and wall, or walls up the doors that no key opens
+pub fn find(color: &im::RgbImage, binary: &mut im::GrayImage) -> Found {
+    let mut locks: Vec<Lock> = Vec::new();
+    for (pixel, blob) in color_blobs(color, MIN_CHROMA, TOLERANCE) {
+        let door = spans_passage(color, binary, &blob);
+        let index = match locks.iter().position(|lock| color_distance(lock.color, pixel) <= TOLERANCE) {
+            Some(index) => index,
+            None        => {
+                locks.push(Lock { color: pixel, keys: Vec::new(), doors: Vec::new() });
+                locks.len() - 1
+            }
+        };
+        if door { locks[index].doors.extend(blob); }
+        else    { locks[index].keys.extend(blob); }
+    }
+
+    let mut found = Found { locks: Vec::new(), keyless: Vec::new(), doorless: Vec::new() };
+    for lock in locks {
+        if lock.keys.is_empty() {
+            for p in lock.doors.iter() {
+                binary.put_pixel(p.x as u32, p.y as u32, WALL);
+            }
+            found.keyless.push(lock.color);
+            continue;
+        }
+        teleporters::clear(binary, &lock.keys);
+        teleporters::clear(binary, &lock.doors);
+        if lock.doors.is_empty() {
+            found.doorless.push(lock.color);
+        }
+        else {
+            found.locks.push(lock);
+        }
+    }
+    found
+}
+
+// splits the keys and doors off the spaces they're in, so that stepping into one means stepping
+// onto it. see split_marked() for `start` and `goal`
+pub fn split(graph: &mut EdgeSetGraph<NodeData>, locks: &[Lock], start: V2, goal: V2) -> Result<(), Error> {
+    let marks: HashSet<V2> = locks.iter().flat_map(|lock| lock.keys.iter().chain(&lock.doors)).copied().collect();
+    split_marked(graph, start, goal, |p| marks.contains(&p))
+}
+
+pub struct Route {
+    pub cost:  i32,
+    pub nodes: Vec<NodeID>,
+    // which locks' keys were picked up, in order
+    pub keys:  Vec<usize>,
+    pub stats: SearchStats,
+}
+
+// the cheapest route from start to goal, by dijkstra's over each space with each set of keys
+// that reaches it, so up to 2^locks times the spaces
+pub fn solve(graph: &AdjacencyGraph<NodeData>, locks: &[Lock]) -> Result<Option<Route>, Error> {
+    if locks.len() > MAX_LOCKS {
+        return Err(Error::TooManyLocks(locks.len()));
+    }
+
+    // the lock each key and door space belongs to
+    let mut keys: HashMap<NodeID, KeySet> = HashMap::default();
+    let mut doors: HashMap<NodeID, usize> = HashMap::default();
+    for (index, lock) in locks.iter().enumerate() {
+        let at = |p: &V2| graph.nodes_in(Rect::new_unchecked(*p, *p + V2::new(1, 1))).next();
+        for id in lock.keys.iter().filter_map(at) {
+            *keys.entry(id).or_default() |= 1 << index;
+        }
+        for id in lock.doors.iter().filter_map(at) {
+            doors.insert(id, index);
+        }
+    }
+    let pick_up = |id: NodeID, held: KeySet| held | keys.get(&id).copied().unwrap_or(0);
+
+    type State = (NodeID, KeySet);
+    let (start, goal) = (graph.start(), graph.goal());
+    let first: State = (start, pick_up(start, 0));
+    let mut dists: HashMap<State, i32> = HashMap::default();
+    let mut preds: HashMap<State, State> = HashMap::default();
+    let mut queue = BinaryHeap::new();
+    let mut stats = SearchStats::default();
+    dists.insert(first, 0);
+    queue.push(Reverse((0, first)));
+
+    while let Some(Reverse((dist, state))) = queue.pop() {
+        if dist > dists[&state] {
+            continue;
+        }
+        stats.expansions += 1;
+        let (u, held) = state;
+        if u == goal {
+            let mut path = vec![state];
+            while let Some(pred) = preds.get(path.last().unwrap()) {
+                path.push(*pred);
+            }
+            path.reverse();
+            let picked = std::iter::once(first.1)
+                .chain(path.windows(2).map(|pair| pair[1].1 & !pair[0].1))
+                .flat_map(|new| (0 .. locks.len()).filter(move |index| new & 1 << index != 0))
+                .collect();
+            let nodes = path.into_iter().map(|(id, _)| id).collect();
+            return Ok(Some(Route { cost: dist, nodes, keys: picked, stats }));
+        }
+
+        for v in graph.neighbors(u).filter(|v| graph.nodes().contains_key(v)) {
+            if doors.get(&v).is_some_and(|lock| held & 1 << lock == 0) {
+                continue;
+            }
+            let next = (v, pick_up(v, held));
+            let new_dist = dist.saturating_add(graph.weight(u, v));
+            if dists.get(&next).is_none_or(|old| new_dist < *old) {
+                dists.insert(next, new_dist);
+                preds.insert(next, state);
+                queue.push(Reverse((new_dist, next)));
+                stats.relaxations += 1;
+            }
+        }
+        stats.saw_frontier(queue.len());
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{image_graph::{extract_graph, ExtractOptions}, preprocess::threshold},
+    };
+
+    #[test]
+    fn doors_open_once_their_key_is_held() {
+        // a corridor shut by a red door, with the red key down a side passage off it
+        let mut color = im::RgbImage::from_pixel(41, 21, im::Rgb([0, 0, 0]));
+        let mut fill = |xs: std::ops::Range<u32>, ys: std::ops::Range<u32>, pixel: [u8; 3]| {
+            for (x, y) in xs.flat_map(|x| ys.clone().map(move |y| (x, y))) {
+                color.put_pixel(x, y, im::Rgb(pixel));
+            }
+        };
+        fill(2 .. 39, 2 .. 6, [255, 255, 255]);
+        fill(8 .. 12, 6 .. 18, [255, 255, 255]);
+        fill(24 .. 26, 2 .. 6, [220, 30, 30]);
+        fill(9 .. 11, 15 .. 17, [230, 40, 40]);
+        let mut binary = im::DynamicImage::ImageRgb8(color.clone()).to_luma();
+
+        let found = find(&color, &mut binary);
+        assert_eq!((found.locks.len(), found.keyless.len(), found.doorless.len()), (1, 0, 0));
+        let (start, goal) = (V2::new(3, 3), V2::new(37, 3));
+        let mut graph = extract_graph(&threshold(&binary, 255), start, goal, &ExtractOptions::default()).unwrap();
+        split(&mut graph, &found.locks, start, goal).unwrap();
+        let graph = graph.into_adjacency_graph();
+
+        // down for the key and back, rather than straight along
+        let route = solve(&graph, &found.locks).unwrap().unwrap();
+        assert_eq!(route.keys, vec![0]);
+        assert!(route.nodes.iter().any(|id| graph.get_node(*id).rect.contains(V2::new(10, 16))));
+    }
+}
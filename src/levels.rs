@@ -0,0 +1,145 @@
+// mazes over several floors, one image per level. a staircase joins two levels wherever a pixel
+// marked going up on one level lies under a pixel marked going down on the level above.
+
+use {
+    crate::{
+        math::*,
+        error::Error,
+        graph::{offset_id, Edge, EdgeSetGraph, HashMap, HashSet, NodeID},
+        image_graph::{extract_seeded_graph, ExtractOptions},
+        preprocess::{color_distance, CLEAR},
+        spatial::Spatial,
+    },
+    image as im,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct StairColors {
+    pub up:        im::Rgb<u8>,
+    pub down:      im::Rgb<u8>,
+    // euclidean distance in RGB space
+    pub tolerance: f32,
+}
+
+impl Default for StairColors {
+    fn default() -> StairColors {
+        StairColors {
+            up:        im::Rgb([0xff, 0x00, 0xff]),
+            down:      im::Rgb([0x00, 0xff, 0xff]),
+            tolerance: 64.0,
+        }
+    }
+}
+
+pub struct Level {
+    // clear/wall, with the stairs cleared
+    pub image: im::GrayImage,
+    pub up:    HashSet<V2>,
+    pub down:  HashSet<V2>,
+}
+
+// a space and the level it's on, as the nodes of a multi-level graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LevelRect {
+    pub level: usize,
+    pub rect:  Rect,
+}
+
+impl Spatial for LevelRect {
+    fn rect(&self) -> Rect { self.rect }
+}
+
+// picks the stair markers out of a level's colour image and clears them in `binary`, the same
+// image already classified into clear and wall
+pub fn find_stairs(color: &im::RgbImage, mut binary: im::GrayImage, stairs: &StairColors) -> Level {
+    let mut up = HashSet::default();
+    let mut down = HashSet::default();
+
+    for (x, y, pixel) in color.enumerate_pixels() {
+        let pos = V2::new(x as Coord, y as Coord);
+        let found = if color_distance(*pixel, stairs.up) <= stairs.tolerance { &mut up }
+                    else if color_distance(*pixel, stairs.down) <= stairs.tolerance { &mut down }
+                    else { continue };
+        found.insert(pos);
+        if x < binary.width() && y < binary.height() {
+            binary.put_pixel(x, y, CLEAR);
+        }
+    }
+
+    Level { image: binary, up, down }
+}
+
+// extracts every level from its stairs and from the start and goal, renumbering the nodes so
+// that they're unique across levels, then links the two sides of each staircase
+pub fn extract_levels(
+    levels: &[Level],
+    start:  (usize, V2),
+    goal:   (usize, V2),
+    opts:   &ExtractOptions)
+    -> Result<EdgeSetGraph<LevelRect>, Error>
+{
+    for (level, _) in [start, goal].iter() {
+        if *level >= levels.len() {
+            return Err(Error::NoLevel(*level));
+        }
+    }
+
+    let mut nodes: HashMap<NodeID, LevelRect> = HashMap::default();
+    let mut edges: HashSet<Edge> = HashSet::default();
+    let mut located: HashMap<(usize, V2), NodeID> = HashMap::default();
+    let mut offset = 0;
+
+    // stairs that lead nowhere don't need to be in the graph
+    let stairs_at = |index: usize| -> Vec<V2> {
+        let level = &levels[index];
+        let up = level.up.iter()
+            .filter(|p| levels.get(index + 1).is_some_and(|above| above.down.contains(p)));
+        let down = level.down.iter()
+            .filter(|p| index.checked_sub(1).is_some_and(|below| levels[below].up.contains(p)));
+        let mut stairs: Vec<V2> = up.chain(down).copied().collect();
+        stairs.sort_by_key(|p| (p.y, p.x));
+        stairs
+    };
+
+    for (index, level) in levels.iter().enumerate() {
+        let mut seeds = Vec::new();
+        for (level, pos) in [start, goal].iter() {
+            if *level == index {
+                seeds.push(*pos);
+            }
+        }
+        seeds.extend(stairs_at(index));
+        if seeds.is_empty() {
+            continue;
+        }
+
+        let (graph, ids) = extract_seeded_graph(&level.image, &seeds, opts)?;
+        // checked once for the level's last id, so that renumbering the rest can't fail
+        let top = graph.com.nodes.keys().max().copied();
+        if let Some(top) = top {
+            offset_id(top, offset)?;
+        }
+        let renumber = |id: NodeID| offset_id(id, offset).expect("checked against the last id");
+
+        for (id, data) in graph.com.nodes.iter() {
+            nodes.insert(renumber(*id), LevelRect { level: index, rect: data.rect });
+        }
+        edges.extend(graph.edges.iter().map(|e| Edge::new(renumber(e.min), renumber(e.max))));
+        for (pos, id) in seeds.iter().zip(ids) {
+            located.insert((index, *pos), renumber(id));
+        }
+        offset += top.map_or(0, NodeID::get);
+    }
+
+    for ((index, pos), id) in located.iter() {
+        if levels[*index].up.contains(pos) {
+            if let Some(above) = located.get(&(index + 1, *pos)) {
+                edges.insert(Edge::new(*id, *above));
+            }
+        }
+    }
+
+    let start = located[&start];
+    let goal  = located[&goal];
+    Ok(EdgeSetGraph::new(nodes, start, goal, edges))
+}
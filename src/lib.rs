@@ -0,0 +1,33 @@
+pub mod cache;
+pub mod error;
+#[cfg(feature = "json")]
+pub mod export;
+pub mod floorplan;
+pub mod formats;
+pub mod graph;
+pub mod grid_search;
+pub mod image_graph;
+pub mod keys;
+pub mod levels;
+pub mod math;
+pub mod one_way;
+pub mod pathfind;
+pub mod preprocess;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod refine;
+pub mod render;
+#[cfg(feature = "json")]
+pub mod schema;
+pub mod session;
+pub mod sink;
+pub mod solve;
+pub mod spatial;
+pub mod teleporters;
+#[cfg(feature = "formats")]
+pub mod text_maze;
+pub mod tiles;
+pub mod tremaux;
+pub mod validate;
+
+pub use error::Error;
@@ -1,82 +1,1839 @@
+// the run manifest is one json! literal with an entry per option, past the default macro depth
+#![recursion_limit = "256"]
 
-mod graph;
-mod image_graph;
-mod math;
+mod batch;
+mod cli;
+mod config;
+mod diff;
+#[cfg(feature = "json")]
+mod manifest;
+mod report;
+mod selftest;
+mod stats;
 
 use {
     crate::{
+        cli::{Mode, Objective, Options, Search, Solver, StatsFormat},
+        stats::Stats,
+    },
+    mazesolve_rk::{
+        Error,
         math::*,
-        image_graph::extract_graph,
-        graph::Graph,
+        cache::{self, GraphView},
+        floorplan, formats,
+        image_graph::{self, border_openings, seal_border, extract_graph, extract_seeded_graph, Decomposition, ExtractOptions, NodeData, NodeKind, SpaceGraph},
+        graph::{AdjacencyGraph, DijkstraGraph, DijkstraSearch, Edge, EdgeSetGraph, Graph, HashMap, HashSet, NodeID, SearchStats},
+        grid_search::{self, Hand, PixelGrid},
+        pathfind::{self, PathFinder, Solution},
+        levels::{self, Level},
+        preprocess, refine,
+        render::{self, RenderStyle},
+        sink::{FileSink, OutputSink, PasteSink, StdoutSink},
+        keys::{self, Lock},
+        one_way,
+        teleporters::{self, Teleporter},
+        tiles::{self, ImageTiles, PgmTiles, TileSource},
+        tremaux::{self, Tremaux},
+        validate::MazeCheck,
     },
-    image::{self as im, ConvertBuffer, Pixel},
-    //rand::{prelude::*},
-    //pcg_rand,
+    image as im,
+    log::{error, info, warn, LevelFilter},
+    std::path::{Path, PathBuf},
 };
 
+#[cfg(feature = "json")]
+use mazesolve_rk::export::{GraphExport, PathExport, TraceExport};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
-fn fill_rect<P: Pixel> (image: &mut impl im::GenericImage<Pixel = P>, rect: Rect, with: P) {
-    let bounds = Rect::new_unchecked(
-        V2::new(0, 0),
-        V2::new(image.width() as i32, image.height() as i32)
-    );
-    let rect = rect.intersect(bounds);
+// reports a problem with the input or the options and exits, rather than panicking
+fn fail(msg: impl std::fmt::Display) -> ! {
+    error!("{}", msg);
+    std::process::exit(1);
+}
 
-    for y in rect.mins.y .. rect.maxs.y {
-        for x in rect.mins.x .. rect.maxs.x {
-            image.put_pixel(x as u32, y as u32, with);
-        }
+// progress on stderr as plain lines, problems prefixed as such, and -v's detail named by the
+// module it came from. RUST_LOG, when set, filters in place of -v and -q
+fn init_logging(verbosity: i32) {
+    use {log::Level as Severity, std::io::Write};
+
+    // errors are how a run says why it failed, so however quiet, those still show
+    let level = match verbosity {
+        i32::MIN ..= -2 => LevelFilter::Error,
+        -1              => LevelFilter::Warn,
+        0               => LevelFilter::Info,
+        1               => LevelFilter::Debug,
+        _               => LevelFilter::Trace,
+    };
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(level).format(|buf, record| match record.level() {
+        Severity::Error => writeln!(buf, "error: {}", record.args()),
+        Severity::Warn  => writeln!(buf, "warning: {}", record.args()),
+        Severity::Info  => writeln!(buf, "{}", record.args()),
+        _            => writeln!(buf, "{}: {}", record.target(), record.args()),
+    });
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
     }
+    builder.init();
 }
 
-fn main() {
-    let in_image = im::open("maze2.png")
-        .expect("loading image")
-        .to_luma();
+// an output of "-" sends the image to stdout
+fn to_stdout(opts: &Options) -> bool {
+    opts.output == "-"
+}
 
-    let width  = in_image.width() as usize;
-    let height = in_image.height() as usize;
+// results go to stdout, unless the image is going there
+fn report(opts: &Options, line: impl std::fmt::Display) {
+    if to_stdout(opts) { eprintln!("{}", line); }
+    else               { println!("{}", line); }
+}
+
+fn save(sink: &mut dyn OutputSink, name: &str, image: &im::RgbImage) -> Result<(), String> {
+    sink.write(name, image).map_err(|err| format!("saving {}: {}", name, err))
+}
+
+// writes one of the stages --debug-dir shows, drawn only if it was given
+fn save_debug(opts: &Options, name: &str, draw: impl FnOnce() -> im::RgbImage) -> Result<(), String> {
+    let dir = match &opts.debug_dir {
+        Some(dir) => Path::new(dir),
+        None      => return Ok(()),
+    };
+    let mut sink = FileSink::in_dir(dir).map_err(|err| format!("creating {}: {}", dir.display(), err))?;
+    sink.write(name, &draw()).map_err(|err| format!("saving {}: {}", dir.join(name).display(), err))
+}
+
+fn endpoints(opts: &Options, image: &im::GrayImage) -> (V2, V2) {
+    endpoints_in(opts, image.dimensions())
+}
+
+fn endpoints_in(opts: &Options, (width, height): (u32, u32)) -> (V2, V2) {
+    let width  = coord_clamped(width);
+    let height = coord_clamped(height);
 
     // TODO: compute
-    let start_pos = V2::new(2, 0);
-    let goal_pos  = V2::new((width - 1) as i32, (height - 3) as i32);
+    let start_pos = opts.start.unwrap_or(V2::new(2, 0));
+    let goal_pos  = opts.goal.unwrap_or(V2::new(width - 1, height - 3));
+    (start_pos, goal_pos)
+}
+
+// the points the current mode routes between or from
+fn anchor_points(opts: &Options, image: &im::GrayImage) -> Vec<V2> {
+    match opts.mode {
+        Mode::Solve | Mode::Levels | Mode::Sequence | Mode::Compare => {
+            let (start, goal) = endpoints(opts, image);
+            vec![start, goal]
+        }
+        Mode::Partition => opts.seeds.clone(),
+        Mode::Escape    => opts.exits.clone(),
+        Mode::Check | Mode::Diff | Mode::SelfTest => Vec::new(),
+    }
+}
+
+// cleans up the input into the clear/wall image extraction works from
+fn preprocess_input(opts: &Options, in_image: &im::GrayImage, stats: &mut Stats) -> im::GrayImage {
+    let mut image = in_image.clone();
+
+    if opts.invert {
+        image = preprocess::invert(&image);
+    }
+    if let Some(radius) = opts.flatten {
+        info!("Flattening illumination...");
+        image = stats.time("flatten", || preprocess::flatten_illumination(&image, radius));
+    }
+
+    if let Some(method) = opts.adaptive {
+        if opts.threshold.is_some() {
+            stats.warn("--threshold is ignored with --adaptive".into());
+        }
+        info!("Thresholding adaptively...");
+        image = stats.time("adaptive threshold", || preprocess::adaptive_threshold(&image, method));
+    }
+    else if let Some(level) = opts.threshold {
+        image = preprocess::threshold(&image, level);
+    }
+    else if opts.flatten.is_some() {
+        let level = preprocess::otsu_level(&image);
+        info!("Thresholding at {}", level);
+        image = preprocess::threshold(&image, level);
+    }
+
+    if let Some(fp_opts) = &opts.floorplan {
+        info!("Preprocessing floorplan...");
+        image = stats.time("floorplan", || floorplan::preprocess(&image, fp_opts));
+    }
+
+    if !opts.morph.is_empty() {
+        info!("Applying morphology...");
+        image = stats.time("morphology", || preprocess::apply_morph(&image, &opts.morph));
+    }
+
+    // before text masking, which would otherwise eat the dashes
+    if let Some(gap) = opts.join_walls {
+        info!("Joining dashed walls...");
+        image = stats.time("join walls", || preprocess::join_walls(&image, gap));
+    }
+
+    if let Some(max_size) = opts.mask_text {
+        info!("Masking text...");
+        let report = stats.time("mask text", || preprocess::mask_text(&mut image, max_size));
+        if report.blobs > 0 {
+            stats.warn(format!("masked {} text-like blobs ({} px) smaller than {} px",
+                report.blobs, report.pixels, max_size));
+        }
+    }
+
+    image
+}
+
+// walls up the border openings for --seal-border, once the image is the size it'll be solved at
+fn seal_input(opts: &Options, image: &mut im::GrayImage, stats: &mut Stats) {
+    if !opts.seal_border {
+        return;
+    }
+    let keep = anchor_points(opts, image);
+    if keep.is_empty() {
+        stats.warn("--seal-border needs explicit exits in escape mode; not sealing".into());
+    }
+    else {
+        let sealed = seal_border(image, &keep);
+        info!("Sealed {} border openings", sealed);
+    }
+}
+
+// the input and its clear/wall image cut down to the maze for --crop, with the box kept. none
+// without --crop, or if there's no wall to find the maze by
+fn crop_input(opts: &Options, in_image: &im::GrayImage, grid_image: &im::GrayImage, stats: &mut Stats)
+    -> Option<(im::GrayImage, im::GrayImage, Rect)>
+{
+    use im::GenericImageView;
+
+    let margin = opts.crop?;
+    let kept = match preprocess::content_bounds(grid_image, margin) {
+        Some(kept) => kept,
+        None       => {
+            stats.warn("--crop found no walls to crop to; solving the whole image".into());
+            return None;
+        }
+    };
+    info!("Cropping to {}x{} at {},{}", kept.width(), kept.height(), kept.mins.x, kept.mins.y);
+    let cut = |image: &im::GrayImage| image
+        .view(kept.mins.x as u32, kept.mins.y as u32, kept.width() as u32, kept.height() as u32)
+        .to_image();
+    Some((cut(in_image), cut(grid_image), kept))
+}
+
+// the options with their points moved into the box --crop kept of an input `size` big
+fn crop_options(opts: &Options, kept: Rect, size: (u32, u32)) -> Options {
+    let mut opts = opts.clone();
+    let shift = |p: V2| p - kept.mins;
+    opts.start = opts.start.map(shift);
+    opts.goal = opts.goal.map(shift);
+    opts.seeds = opts.seeds.iter().copied().map(shift).collect();
+    opts.exits = opts.exits.iter().copied().map(shift).collect();
+    opts.cropped = Some((kept, size));
+    opts
+}
+
+// the move that puts an export made from the cropped image, `height` high, back where it was in
+// the input, in the export's own frame; none unless cropped
+#[cfg(feature = "json")]
+fn uncrop_offset(opts: &Options, height: Coord) -> Result<V2, String> {
+    match opts.cropped {
+        Some((kept, (_, full_height))) => {
+            let full_height = coord(full_height).map_err(|err| err.to_string())?;
+            Ok(opts.origin.point(kept.mins, full_height) - opts.origin.point(V2::new(0, 0), height))
+        }
+        None => Ok(V2::new(0, 0)),
+    }
+}
+
+// the options with the start and goal moved off any wall onto the nearest clear pixel within
+// --snap px, warning of each move
+fn snap_endpoints(opts: &Options, image: &im::GrayImage, stats: &mut Stats) -> Options {
+    let mut opts = opts.clone();
+    if opts.snap == 0 || !matches!(opts.mode, Mode::Solve | Mode::Sequence | Mode::Compare) {
+        return opts;
+    }
+
+    let (start, goal) = endpoints(&opts, image);
+    let mut snap = |name: &str, pos: V2| match image_graph::nearest_clear(image, pos, opts.snap) {
+        Some(snapped) if snapped != pos => {
+            let moved = ((snapped.x - pos.x) as f64).hypot((snapped.y - pos.y) as f64);
+            stats.warn(format!("snapped the {} from {},{} to the nearest clear pixel, {},{} ({:.1} px away)",
+                name, pos.x, pos.y, snapped.x, snapped.y, moved));
+            snapped
+        }
+        _ => pos,
+    };
+    let start = snap("start", start);
+    let goal  = snap("goal", goal);
+    opts.start = Some(start);
+    opts.goal  = Some(goal);
+    opts
+}
+
+fn choose_extract_options(opts: &Options, image: &im::GrayImage, seeds: &[V2])
+    -> ExtractOptions
+{
+    if !opts.best_decomposition {
+        return opts.extract;
+    }
+
+    let count = |decomposition| {
+        let mut extract = opts.extract;
+        extract.decomposition = decomposition;
+        let nodes = extract_seeded_graph(image, seeds, &extract)
+            .map_or(0, |(graph, _)| graph.nodes().len());
+        (nodes, extract)
+    };
+
+    let (greedy_nodes, greedy) = count(Decomposition::Greedy);
+    let (strips_nodes, strips) = count(Decomposition::Strips);
+    let (name, chosen) = if strips_nodes < greedy_nodes { ("strips", strips) }
+                         else                           { ("greedy", greedy) };
+    info!("greedy: {} nodes, strips: {} nodes; using {}", greedy_nodes, strips_nodes, name);
+    chosen
+}
+
+// how many border pixels the path rects cover away from the start and goal openings.
+// a route that runs along the border has usually found its way around the outside of the maze.
+fn border_escape_len(rects: &[Rect], image: &im::GrayImage, start: V2, goal: V2) -> usize {
+    let (w, h) = (coord_clamped(image.width()), coord_clamped(image.height()));
+
+    // anything within a couple of corridor widths of an endpoint is just its opening
+    let end_width = |pos: V2| rects.iter()
+        .find(|r| r.contains(pos))
+        .map_or(0, |r| r.width().min(r.height()));
+    let radius = 2 * end_width(start).max(end_width(goal));
+    let near = |p: V2, q: V2| (p - q).chebyshev() <= radius;
+
+    let mut count = 0;
+    for rect in rects.iter() {
+        let on_border = |p: V2| p.x == 0 || p.y == 0 || p.x == w - 1 || p.y == h - 1;
+        let sides = rect.sides();
+        let edge_pixels = sides.iter().flat_map(|side| side.points());
+
+        let mut seen = HashSet::default();
+        count += edge_pixels
+            .filter(|p| on_border(*p) && !near(*p, start) && !near(*p, goal))
+            .filter(|p| seen.insert(*p))
+            .count();
+    }
+    count
+}
+
+fn solve(
+    opts:        &Options,
+    in_image:    &im::GrayImage,
+    grid_image:  &im::GrayImage,
+    teleporters: &[Teleporter],
+    sink:        &mut dyn OutputSink,
+    stats:       &mut Stats)
+    -> Result<(), String>
+{
+    if opts.solver != Solver::Rects || opts.objective != Objective::Distance {
+        return solve_grid(opts, in_image, grid_image, sink, stats);
+    }
+    let (start_pos, goal_pos) = endpoints(opts, in_image);
+
+    info!("Building graph...");
+    // the far end of a teleporter may be somewhere the start can't walk to, so every end seeds
+    // the extraction too
+    if !teleporters.is_empty() {
+        warn_ignored(stats, "--teleporters", &[
+            ("--tile", opts.tile.is_some()),
+            ("--graph-cache", opts.graph_cache.is_some()),
+        ]);
+        let seeds: Vec<V2> = std::iter::once(start_pos)
+            .chain(teleporters.iter().flat_map(|teleporter| teleporter.ends))
+            .chain(std::iter::once(goal_pos))
+            .collect();
+        let extract = choose_extract_options(opts, grid_image, &seeds);
+        let (graph, _) = stats.time("extract", || extract_seeded_graph(grid_image, &seeds, &extract))
+            .map_err(|err| err.to_string())?;
+        return solve_extracted(opts, in_image, grid_image, graph, teleporters, sink, stats);
+    }
+    let extract = choose_extract_options(opts, grid_image, &[start_pos]);
+    if let (Some(path), None) = (&opts.graph_cache, opts.tile) {
+        let graph = extract_cached(path, grid_image, start_pos, goal_pos, &extract, stats)?;
+        return solve_extracted(opts, in_image, grid_image, graph, &[], sink, stats);
+    }
+    warn_ignored(stats, "--tile", &[("--graph-cache", opts.graph_cache.is_some())]);
+    let graph = stats.time("extract", || match opts.tile {
+        Some(size) => {
+            let mut tiles = ImageTiles(grid_image);
+            image_graph::extract_tiled_graph(&mut tiles, size, start_pos, goal_pos, &extract)
+                .map_err(|err| err.to_string())
+        }
+        None => extract_graph(grid_image, start_pos, goal_pos, &extract).map_err(|err| err.to_string()),
+    })?;
+
+    solve_extracted(opts, in_image, grid_image, graph, &[], sink, stats)
+}
+
+// the graph kept at `path` if it was extracted from this image with these options, or else one
+// extracted now and kept there for next time
+fn extract_cached(path: &str, grid_image: &im::GrayImage, start_pos: V2, goal_pos: V2, extract: &ExtractOptions,
+    stats: &mut Stats)
+    -> Result<EdgeSetGraph<NodeData>, String>
+{
+    let key = cache::cache_key(grid_image, start_pos, goal_pos, extract);
+    let cached = stats.time("cache load", || -> std::io::Result<Option<EdgeSetGraph<NodeData>>> {
+        let bytes = match cache::open_cache(path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let view = GraphView::new(&bytes)?;
+        if view.key() != key {
+            info!("{} holds the graph of another image or options; extracting afresh", path);
+            return Ok(None);
+        }
+        view.to_graph().map(Some)
+    });
+    match cached {
+        Ok(Some(graph)) => {
+            info!("Loaded {} spaces from {}", graph.nodes().len(), path);
+            return Ok(graph);
+        }
+        Ok(None) => { }
+        Err(err) => stats.warn(format!("reading {}: {}; extracting afresh", path, err)),
+    }
+
+    let graph = stats.time("extract", || extract_graph(grid_image, start_pos, goal_pos, extract))
+        .map_err(|err| err.to_string())?;
+    if let Err(err) = stats.time("cache save", || cache::save_graph(&graph, key, path)) {
+        stats.warn(format!("saving {}: {}", path, err));
+    }
+    Ok(graph)
+}
+
+// everything in solve() after extraction
+fn solve_extracted(
+    opts:        &Options,
+    in_image:    &im::GrayImage,
+    grid_image:  &im::GrayImage,
+    mut graph:   EdgeSetGraph<NodeData>,
+    teleporters: &[Teleporter],
+    sink:        &mut dyn OutputSink,
+    stats:       &mut Stats)
+    -> Result<(), String>
+{
+    let (start_pos, goal_pos) = endpoints(opts, in_image);
+    if let Some(path) = &opts.one_way {
+        let map = formats::open(path).map_err(|err| format!("loading {}: {}", path, err))?.to_rgb();
+        let (width, height) = opts.cropped.map_or(in_image.dimensions(), |(_, size)| size);
+        if map.dimensions() != (width, height) {
+            return Err(format!("the one-way map {} is {}x{}, but the maze is {}x{}", path,
+                map.width(), map.height(), width, height));
+        }
+        let map = match opts.cropped {
+            Some((kept, _)) => {
+                use im::GenericImageView;
+                map.view(kept.mins.x as u32, kept.mins.y as u32, kept.width() as u32, kept.height() as u32).to_image()
+            }
+            None => map,
+        };
+        let (one_way, closed) = one_way::restrict(&mut graph, &map, start_pos, goal_pos).map_err(|err| err.to_string())?;
+        info!("{} one-way adjacencies, {} closed both ways", one_way, closed);
+    }
+    // after the one-way split, which would lose the jumps out of a space it cuts up
+    let jumps = teleporters::link(&mut graph, teleporters);
+    if !jumps.is_empty() && matches!(opts.search, Search::AStar | Search::Greedy) {
+        stats.warn(format!("--algo {} guesses by distance, which a teleporter can cut short; \
+            the path found may not be the shortest", opts.search.name()));
+    }
+    stats.nodes_extracted = graph.nodes().len();
+    stats.edges_extracted = graph.edges.len();
+    stats.record_areas(graph.nodes().values().map(|data| data.area));
+    let trivial = graph.is_trivial();
+    stats.trivial = trivial;
+    if trivial {
+        info!("Start and goal share a space");
+    }
+    save_debug(opts, "decomposition.png", || {
+        let mut image = render::to_rgb(grid_image);
+        render::render_decomposition(&mut image, graph.nodes().iter(), &RenderStyle::default());
+        image
+    })?;
 
-    eprintln!("Building graph...");
-    let mut graph = extract_graph(&in_image, start_pos, goal_pos).unwrap();
+    #[cfg(feature = "json")]
+    if let Some(path) = &opts.graph_json {
+        let height = coord(in_image.height()).map_err(|err| err.to_string())?;
+        let export = GraphExport::new(&graph, opts.origin, height).offset(uncrop_offset(opts, height)?);
+        export.save(path).map_err(|err| format!("saving {}: {}", path, err))?;
+    }
+
+    if let Some(fp_opts) = &opts.floorplan {
+        floorplan::classify(&mut graph, fp_opts);
+        info!("{} room rects, {} corridor rects",
+            graph.count_kind(NodeKind::Room), graph.count_kind(NodeKind::Corridor));
+    }
+
+    if opts.search != Search::Dijkstra {
+        if opts.frames.is_some() && opts.search != Search::Tremaux {
+            stats.warn(format!("--frames films only the path found by --algo {}", opts.search.name()));
+        }
+        warn_ignored(stats, &format!("--algo {}", opts.search.name()), &[
+            ("--full-search", opts.full_search),
+            ("--buckets", opts.buckets),
+        ]);
+    }
+    if opts.random_seed.is_some() && opts.longest.is_none() && opts.search != Search::Tremaux {
+        stats.warn("--random-seed only steers --longest and --algo tremaux".to_string());
+    }
+
+    // pruning and contraction leave the search little to do, which makes for a dull film, so
+    // with --frames the search runs over every extracted space
+    let film = opts.frames.is_some();
+    let mut dead_ends = HashMap::default();
+    if !film {
+        info!("Pruning graph...");
+        (graph, dead_ends) = stats.time("prune", || graph.prune_with_dead_ends());
+    }
+    if opts.dead_ends {
+        let area: i64 = dead_ends.values().map(|data| data.area).sum();
+        report(opts, format_args!("dead ends: {} rects, {} px", dead_ends.len(), area));
+    }
+    stats.nodes_pruned = graph.nodes().len();
+    stats.edges_pruned = graph.edges.len();
+    save_debug(opts, "pruned.png", || {
+        let mut image = render::to_rgb(grid_image);
+        render::render_graph(&mut image, &graph, &dead_ends, &RenderStyle { alpha: 0.5, ..RenderStyle::default() });
+        image
+    })?;
+
+    let mut graph = graph.into_adjacency_graph();
+    // a jump costs --teleport-cost steps, where it's still there to take after pruning
+    if opts.teleport_cost != 1 {
+        let kept: Vec<Edge> = jumps.iter().copied()
+            .filter(|jump| graph.neighbors(jump.min).any(|id| id == jump.max))
+            .collect();
+        graph.weights.extend(kept.into_iter().map(|jump| (jump, opts.teleport_cost)));
+    }
+    let routes = opts.k_paths.map(|k| {
+        info!("Finding {} shortest routes...", k);
+        let routes = stats.time("k-paths", || graph.k_shortest_paths(k));
+        for (index, (length, _)) in routes.iter().enumerate() {
+            report(opts, format_args!("route {}: length {}", index, length));
+        }
+        routes.into_iter().map(|(_, route)| route).collect::<Vec<_>>()
+    });
+    let routes = routes.or_else(|| {
+        let budget = opts.longest?;
+        info!("Finding longest route...");
+        let longest = stats.time("longest", || graph.longest_path(budget, opts.random_seed))?;
+        let how = if longest.exact { "exact" } else { "best found within budget" };
+        report(opts, format_args!("longest route: length {} ({})", longest.length, how));
+        Some(vec![longest.path])
+    });
+
+    let graph = if film { graph } else {
+        info!("Contracting corridors...");
+        stats.time("contract", || graph.contract())
+    };
+    stats.search_nodes = graph.adjs.len();
+    stats.chains = graph.chains.len();
+    if let Some(path) = &opts.skeleton {
+        let mut image = render::canvas(in_image, &opts.style);
+        render::fade(&mut image);
+        render::render_skeleton(&mut image, &graph, &opts.style);
+        image.save(path).map_err(|err| format!("saving {}: {}", path, err))?;
+        info!("Wrote {}", path);
+    }
 
-    eprintln!("Pruning graph...");
-    graph = graph.prune();
+    info!("Finding path...");
+    let (graph, solution) = search(opts, graph, goal_pos, stats)?;
+    let solution_length = solution.cost;
+    stats.solution_length = solution_length;
+    info!("Solution length: {}", solution_length);
+    save_debug(opts, "distances.png", || {
+        let mut image = render::to_rgb(grid_image);
+        render::render_distance_field(&mut image, &graph, &RenderStyle::default());
+        image
+    })?;
+    let rects = &solution.rects;
 
-    eprintln!("Finding path...");
-    let graph = graph.to_adjacency_graph();
-    let graph = graph.into_dijkstra();
+    let escape_len = border_escape_len(rects, grid_image, start_pos, goal_pos);
+    if escape_len > 0 {
+        let hint = if opts.seal_border { "" } else { " (try --seal-border)" };
+        stats.warn(format!(
+            "the solution runs {} px along the image border away from the start and goal; \
+             it probably goes around the outside of the maze{}", escape_len, hint));
+    }
 
-    let solution_length = graph.goal_distance();
-    eprintln!("Solution length: {}", solution_length);
+    // a trivial route is one space, which says little filled in, so it's always drawn as a line
+    let line = opts.refine || trivial || opts.hints.is_some();
+    let pixels = if line || opts.path_json.is_some() {
+        let pixels = stats.time("refine", || teleporters::refine_path(rects, start_pos, goal_pos, teleporters));
+        info!("Refined path: {} px", pixels.len());
+        match &opts.hints {
+            Some(path) => Some(straighten_open_fields(opts, path, grid_image, &pixels, stats)?),
+            None       => Some(pixels),
+        }
+    }
+    else {
+        None
+    };
 
     // render
-    eprintln!("Rendering...");
-    let mut image: im::RgbImage = in_image.convert();
+    if !opts.no_render {
+        info!("Rendering...");
+        let (width, height) = in_image.dimensions();
+        let mut image = if opts.path_only { render::blank_canvas(width, height, &opts.style) }
+                        else              { render::canvas(in_image, &opts.style) };
+
+        stats.time("render", || {
+            if !opts.path_only {
+                // pruning took the dead ends out of the graph, but they were classified too
+                if opts.show_rects {
+                    let spaces = graph.nodes().iter().chain(dead_ends.iter());
+                    render::render_decomposition(&mut image, spaces, &opts.style);
+                }
+                else if opts.floorplan.is_some() {
+                    let spaces = graph.nodes().values().chain(dead_ends.values());
+                    render::render_spaces(&mut image, spaces, &opts.style);
+                }
+                else {
+                    render::render_nodes(&mut image, &graph, &opts.style);
+                }
+                if opts.dead_ends {
+                    render::render_dead_ends(&mut image, &dead_ends, &opts.style);
+                }
+            }
+            match (&routes, &pixels) {
+                (Some(routes), _) => render::render_routes(&mut image, &graph, routes, &opts.style),
+                (None, Some(pixels)) if line => {
+                    render::render_pixel_path(&mut image, &solution, pixels, &opts.style)
+                }
+                _ => render::render_path(&mut image, &solution, &opts.style),
+            }
+            if trivial {
+                render::render_marker(&mut image, start_pos, &opts.style);
+                render::render_marker(&mut image, goal_pos, &opts.style);
+            }
+            save(sink, &opts.output, &image)
+        })?;
+    }
+
+    if let Some(dir) = &opts.frames {
+        info!("Writing frames...");
+        let base = render::canvas(in_image, &opts.style);
+        let pixels = pixels.as_deref().filter(|_| opts.refine);
+        let frames = stats.time("frames", || {
+            let mut frames = FileSink { indexed: opts.indexed, ..FileSink::in_dir(dir)? };
+            if opts.search == Search::Tremaux {
+                let walk = tremaux::walk(&graph.inner, graph.start(), graph.goal(), opts.random_seed);
+                render::render_walk(&base, &graph, &walk, Some(&solution), &mut frames, opts.frame_every, &opts.style)
+            }
+            else {
+                render::render_frames(&base, &graph, pixels, &mut frames, opts.frame_every, &opts.style)
+            }
+        });
+        let frames = frames.map_err(|err| format!("writing frames to {}: {}", dir, err))?;
+        info!("Wrote {} frames to {}", frames, dir);
+    }
+
+    #[cfg(feature = "json")]
+    if let Some(path) = &opts.path_json {
+        let pixels = pixels.unwrap_or_default();
+        let height = coord(in_image.height()).map_err(|err| err.to_string())?;
+        let export = PathExport::new(solution_length, rects, &pixels, opts.origin, height)
+            .offset(uncrop_offset(opts, height)?);
+        export.save(path).map_err(|err| format!("saving {}: {}", path, err))?;
+    }
+    #[cfg(feature = "json")]
+    if let Some(path) = &opts.trace_json {
+        let height = coord(in_image.height()).map_err(|err| err.to_string())?;
+        let export = TraceExport::new(&graph, opts.origin, height).offset(uncrop_offset(opts, height)?);
+        export.save(path).map_err(|err| format!("saving {}: {}", path, err))?;
+    }
+
+    Ok(())
+}
+
+// the search --algo asks for. dijkstra's keeps the distances it found, for filming; the others
+// leave just their path.
+fn search(opts: &Options, graph: AdjacencyGraph<NodeData>, goal_pos: V2, stats: &mut Stats)
+    -> Result<(DijkstraGraph<NodeData>, Solution), String>
+{
+    let unreachable = || Error::Unreachable(goal_pos).to_string();
+    let finder: &dyn PathFinder = match opts.search {
+        Search::Dijkstra => {
+            let graph = stats.time("search", || dijkstra(opts, graph));
+            stats.record_search(graph.stats);
+            let solution = graph.solution().ok_or_else(unreachable)?;
+            return Ok((graph, solution));
+        }
+        Search::AStar  => &pathfind::AStar,
+        Search::Bfs    => &pathfind::BreadthFirst,
+        Search::Greedy => &pathfind::GreedyBestFirst,
+        Search::Tremaux => {
+            let (start, goal) = (graph.start(), graph.goal());
+            let walk = stats.time("search", || tremaux::walk(&graph, start, goal, opts.random_seed));
+            let twice = walk.steps.iter().filter(|step| step.marks == 2).count();
+            report(opts, format_args!("tremaux: walked {} passages, {} of them back again",
+                walk.steps.len(), twice));
+            let solution = walk.solution(&graph).ok_or_else(unreachable)?;
+            stats.record_search(solution.stats);
+            return Ok((pathfind::into_dijkstra_graph(graph, &solution), solution));
+        }
+    };
+
+    let (start, goal) = (graph.start(), graph.goal());
+    let solution = stats.time("search", || finder.solve(&graph, start, goal)).ok_or_else(unreachable)?;
+    stats.record_search(solution.stats);
+    Ok((pathfind::into_dijkstra_graph(graph, &solution), solution))
+}
+
+// dijkstra's from the start, to the goal unless --full-search, queued as --buckets says
+fn dijkstra(opts: &Options, graph: AdjacencyGraph<NodeData>) -> DijkstraGraph<NodeData> {
+    let (start, goal) = (graph.start(), graph.goal());
+    let mut search = DijkstraSearch::new(graph, &[start]);
+    if !opts.full_search { search = search.stopping_at(goal); }
+    if opts.buckets      { search = search.bucketed(); }
+    search.finish()
+}
+
+// the refined path redone at any angle wherever the hint mask at `path` marks open field
+fn straighten_open_fields(opts: &Options, path: &str, grid_image: &im::GrayImage, pixels: &[V2], stats: &mut Stats)
+    -> Result<Vec<V2>, String>
+{
+    let hints = formats::open(path).map_err(|err| format!("loading {}: {}", path, err))?.to_luma();
+    if hints.dimensions() != grid_image.dimensions() {
+        return Err(format!("the hint mask {} is {}x{}, but the maze is {}x{}", path,
+            hints.width(), hints.height(), grid_image.width(), grid_image.height()));
+    }
 
-    //let mut rand = rand::distributions::Uniform::new_inclusive(0x80, 0xff)
-    //    .sample_iter(pcg_rand::Pcg32Basic::seed_from_u64(12345));
-    //let components: Vec<u8> = rand.take(3).collect();
-    //fill_rect(debug_image, rect, *im::Rgb::from_slice(&components));
+    info!("Straightening the path across open fields...");
+    let grid = PixelGrid::new(grid_image).map_err(|err| err.to_string())?;
+    let is_open = |p: V2| hints.get_pixel(p.x as u32, p.y as u32).0[0] >= 128;
+    let straightened = stats.time("any-angle", || grid_search::straighten_open_runs(&grid, pixels, is_open));
+    let length: f64 = straightened.windows(2)
+        .map(|pair| ((pair[1].x - pair[0].x) as f64).hypot((pair[1].y - pair[0].y) as f64))
+        .sum();
+    report(opts, format_args!("hybrid path: {:.2} px over {} pixels", length, straightened.len()));
+    Ok(straightened)
+}
+
+// solves on the pixels directly, with no graph to prune or draw, so only the path comes out
+fn solve_grid(
+    opts:       &Options,
+    in_image:   &im::GrayImage,
+    grid_image: &im::GrayImage,
+    sink:       &mut dyn OutputSink,
+    stats:      &mut Stats)
+    -> Result<(), String>
+{
+    let what = match opts.objective {
+        Objective::Distance => format!("--solver {}", opts.solver.name()),
+        _                   => format!("--objective {}", opts.objective.name()),
+    };
+    warn_ignored(stats, &what, &[
+        ("--tile", opts.tile.is_some()),
+        ("--decompose best", opts.best_decomposition),
+        ("--k-paths", opts.k_paths.is_some()),
+        ("--longest", opts.longest.is_some()),
+        ("--dead-ends", opts.dead_ends && opts.solver != Solver::DeadEndFill),
+        ("--frames", opts.frames.is_some()),
+        ("--skeleton", opts.skeleton.is_some()),
+        ("--teleporters", opts.teleporters),
+        ("--one-way", opts.one_way.is_some()),
+    ]);
+
+    let (start_pos, goal_pos) = endpoints(opts, in_image);
+    let grid = PixelGrid::new(grid_image).map_err(|err| err.to_string())?;
+
+    info!("Finding path over pixels...");
+    let mut filled = None;
+    let path = stats.time("search", || match (opts.objective, opts.solver) {
+        (Objective::Turns, _)    => grid_search::fewest_turns(&grid, start_pos, goal_pos),
+        (Objective::Weighted, _) => grid_search::cheapest_path(&grid, start_pos, goal_pos, opts.weights),
+        (_, Solver::PixelBfs)   => grid_search::pixel_bfs(&grid, start_pos, goal_pos),
+        (_, Solver::LeftHand)   => grid_search::wall_follower(&grid, start_pos, goal_pos, Hand::Left),
+        (_, Solver::RightHand)  => grid_search::wall_follower(&grid, start_pos, goal_pos, Hand::Right),
+        (_, Solver::DeadEndFill) => grid_search::fill_dead_ends(&grid, start_pos, goal_pos).map(|fill| {
+            let path = fill.path.clone();
+            filled = Some(fill);
+            path
+        }),
+        _                        => grid_search::jump_point_search(&grid, start_pos, goal_pos),
+    });
+    let path = path.map_err(|err| err.to_string())?;
+    stats.record_search(path.stats);
+    stats.solution_length = path.length.round() as i32;
+    let trivial = path.pixels.len() == 1;
+    stats.trivial = trivial;
+    report(opts, format_args!("solution length: {:.2} px over {} pixels, {} expansions",
+        path.length, path.pixels.len(), path.stats.expansions));
+    if opts.objective != Objective::Distance {
+        let clearance = grid_search::clearance(&grid);
+        report(opts, format_args!("turns: {}, mean clearance: {:.2} px",
+            grid_search::turns(&path.pixels), grid_search::mean_clearance(&grid, &clearance, &path.pixels)));
+    }
+    let walked = matches!(opts.solver, Solver::LeftHand | Solver::RightHand) && opts.objective == Objective::Distance;
+    if walked {
+        let distinct: HashSet<V2> = path.pixels.iter().copied().collect();
+        report(opts, format_args!("walked over {} distinct pixels, {} steps retraced",
+            distinct.len(), path.pixels.len() - distinct.len()));
+    }
+    if let Some(filled) = &filled {
+        report(opts, format_args!("filled {} dead-end pixels, leaving a corridor of {}",
+            filled.dead_ends.len(), filled.corridor.len()));
+    }
+
+    if !opts.no_render {
+        info!("Rendering...");
+        let (width, height) = in_image.dimensions();
+        let mut image = if opts.path_only { render::blank_canvas(width, height, &opts.style) }
+                        else              { render::canvas(in_image, &opts.style) };
+        stats.time("render", || {
+            if let Some(filled) = &filled {
+                let dead_ends = if opts.dead_ends { &filled.dead_ends[..] } else { &[] };
+                render::render_corridor(&mut image, &filled.corridor, dead_ends, &opts.style);
+            }
+            if walked { render::render_route(&mut image, &path.pixels, &opts.style); }
+            else      { render::render_line(&mut image, &path.pixels, &opts.style); }
+            if trivial {
+                render::render_marker(&mut image, start_pos, &opts.style);
+            }
+            save(sink, &opts.output, &image)
+        })?;
+    }
+
+    #[cfg(feature = "json")]
+    if let Some(json) = &opts.path_json {
+        let height = coord(in_image.height()).map_err(|err| err.to_string())?;
+        let export = PathExport::new(stats.solution_length, &[], &path.pixels, opts.origin, height)
+            .offset(uncrop_offset(opts, height)?);
+        export.save(json).map_err(|err| format!("saving {}: {}", json, err))?;
+    }
+
+    Ok(())
+}
+
+// solves a maze of keys and doors over its spaces, neither pruned, which would drop the keys off
+// in side passages, nor contracted, which would lose the doors and keys along corridors
+fn solve_locked(
+    opts:       &Options,
+    in_image:   &im::GrayImage,
+    grid_image: &im::GrayImage,
+    locks:      &[Lock],
+    sink:       &mut dyn OutputSink,
+    stats:      &mut Stats)
+    -> Result<(), String>
+{
+    warn_ignored(stats, "--keys", &[
+        ("--solver", opts.solver != Solver::Rects),
+        ("--objective", opts.objective != Objective::Distance),
+        ("--algo", opts.search != Search::Dijkstra),
+        ("--tile", opts.tile.is_some()),
+        ("--graph-cache", opts.graph_cache.is_some()),
+        ("--k-paths", opts.k_paths.is_some()),
+        ("--longest", opts.longest.is_some()),
+        ("--dead-ends", opts.dead_ends),
+        ("--frames", opts.frames.is_some()),
+        ("--skeleton", opts.skeleton.is_some()),
+        ("--one-way", opts.one_way.is_some()),
+    ]);
+    let (start_pos, goal_pos) = endpoints(opts, in_image);
+
+    info!("Building graph...");
+    let extract = choose_extract_options(opts, grid_image, &[start_pos]);
+    let mut graph = stats.time("extract", || extract_graph(grid_image, start_pos, goal_pos, &extract))
+        .map_err(|err| err.to_string())?;
+    keys::split(&mut graph, locks, start_pos, goal_pos).map_err(|err| err.to_string())?;
+    stats.nodes_extracted = graph.nodes().len();
+    stats.edges_extracted = graph.edges.len();
+    let graph = graph.into_adjacency_graph();
+
+    info!("Finding path...");
+    let route = stats.time("search", || keys::solve(&graph, locks)).map_err(|err| err.to_string())?;
+    let route = route.ok_or_else(|| Error::Unreachable(goal_pos).to_string())?;
+    stats.record_search(route.stats);
+    stats.solution_length = route.cost;
+    let held: Vec<String> = route.keys.iter()
+        .map(|index| {
+            let im::Rgb([r, g, b]) = locks[*index].color;
+            format!("{:02x}{:02x}{:02x}", r, g, b)
+        })
+        .collect();
+    report(opts, format_args!("solution length: {}, keys: {}", route.cost,
+        if held.is_empty() { "none".to_string() } else { held.join(", ") }));
+
+    let rects: Vec<Rect> = route.nodes.iter().map(|id| graph.get_node(*id).rect).collect();
+    let pixels = stats.time("refine", || refine::refine_path(&rects, start_pos, goal_pos));
+    stats.trivial = rects.len() == 1;
+    if !opts.no_render {
+        info!("Rendering...");
+        let (width, height) = in_image.dimensions();
+        let mut image = if opts.path_only { render::blank_canvas(width, height, &opts.style) }
+                        else              { render::canvas(in_image, &opts.style) };
+        stats.time("render", || {
+            render::render_line(&mut image, &pixels, &opts.style);
+            save(sink, &opts.output, &image)
+        })?;
+    }
+
+    #[cfg(feature = "json")]
+    if let Some(json) = &opts.path_json {
+        let height = coord(in_image.height()).map_err(|err| err.to_string())?;
+        let export = PathExport::new(route.cost, &rects, &pixels, opts.origin, height)
+            .offset(uncrop_offset(opts, height)?);
+        export.save(json).map_err(|err| format!("saving {}: {}", json, err))?;
+    }
+
+    Ok(())
+}
 
-    for rect in graph.nodes().values() {
-        const GREEN: im::Rgb<u8> = im::Rgb([0x00, 0xff, 0x00]);
-        fill_rect(&mut image, *rect, GREEN);
+// every search from the start to the goal, one line each: the rect searches over one pruned and
+// contracted graph, the pixel ones over the grid
+fn compare(opts: &Options, in_image: &im::GrayImage, grid_image: &im::GrayImage, stats: &mut Stats)
+    -> Result<(), String>
+{
+    warn_ignored(stats, "compare", &[
+        ("--algo", opts.search != Search::Dijkstra),
+        ("--solver", opts.solver != Solver::Rects),
+        ("--tile", opts.tile.is_some()),
+        ("--k-paths", opts.k_paths.is_some()),
+        ("--longest", opts.longest.is_some()),
+        ("--objective", opts.objective != Objective::Distance),
+        ("--skeleton", opts.skeleton.is_some()),
+    ]);
+    if opts.race.is_some() && opts.frames.is_none() {
+        stats.warn("--race has nothing to film without --frames".to_string());
     }
+    let (start_pos, goal_pos) = endpoints(opts, in_image);
+
+    info!("Building graph...");
+    let extract = choose_extract_options(opts, grid_image, &[start_pos]);
+    let graph = stats.time("extract", || extract_graph(grid_image, start_pos, goal_pos, &extract))
+        .map_err(|err| err.to_string())?;
+    stats.nodes_extracted = graph.nodes().len();
+    stats.edges_extracted = graph.edges.len();
+    let graph = stats.time("prune", || graph.prune());
+    stats.nodes_pruned = graph.nodes().len();
+    stats.edges_pruned = graph.edges.len();
+    let graph = stats.time("contract", || graph.into_adjacency_graph().contract());
+    stats.search_nodes = graph.adjs.len();
+    stats.chains = graph.chains.len();
+
+    report(opts, format_args!("{:<13} {:>12} {:>10} {:>10} {:>10} {:>9} {:>10}",
+        "algo", "length", "expanded", "relaxed", "frontier", "explored", "time"));
+    let row = |name: &str, length: String, search: SearchStats, explored: String, seconds: f64| {
+        report(opts, format_args!("{:<13} {:>12} {:>10} {:>10} {:>10} {:>9} {:>8.3} s",
+            name, length, search.expansions, search.relaxations, search.max_frontier, explored, seconds));
+    };
+    let seconds = |stats: &Stats| stats.stages.last().map_or(0.0, |stage| stage.seconds);
+    let unreachable = "unreachable".to_string();
 
-    {   let mut next_id = Some(graph.goal());
-        while let Some(id) = next_id {
-            let rect = *graph.get_node(id);
-            const RED: im::Rgb<u8> = im::Rgb([0xff, 0x00, 0x00]);
-            fill_rect(&mut image, rect, RED);
-            next_id = graph.predecessor(id);
+    let finders: [&dyn PathFinder; 4] = [&pathfind::AStar, &pathfind::BreadthFirst, &pathfind::GreedyBestFirst,
+        &Tremaux { seed: opts.random_seed }];
+    let (start, goal) = (graph.start(), graph.goal());
+    let found: Vec<_> = finders.iter()
+        .map(|finder| {
+            let solution = stats.time(finder.name(), || finder.solve(&graph, start, goal));
+            (finder.name(), solution, seconds(stats))
+        })
+        .collect();
+
+    // the search solving uses, rather than pathfind's, so its numbers are the ones --stats gives
+    let graph = stats.time("dijkstra", || dijkstra(opts, graph));
+    stats.record_search(graph.stats);
+    let solution = graph.solution();
+    stats.solution_length = solution.as_ref().map_or(0, |solution| solution.cost);
+    let searched = graph.inner.total_area().max(1);
+    let explored = |expanded: &[NodeID]| {
+        let explored = pathfind::explored(&graph.inner, expanded);
+        let area: i64 = explored.iter().map(|id| graph.area(*id)).sum();
+        (format!("{:.1}%", area as f64 * 100.0 / searched as f64), explored)
+    };
+
+    let mut maps = match &opts.explore_maps {
+        Some(dir) => {
+            let sink = FileSink::in_dir(dir).map_err(|err| format!("creating {}: {}", dir, err))?;
+            Some(FileSink { indexed: opts.indexed, ..sink })
+        }
+        None      => None,
+    };
+    let dijkstra = ("dijkstra", solution, seconds(stats));
+    let runs: Vec<_> = std::iter::once(dijkstra).chain(found).collect();
+    let expanded = |name: &str, solution: &Option<Solution>| match solution {
+        Some(solution) => (solution.stats, solution.expanded.clone()),
+        None if name == "dijkstra" => (graph.stats, graph.order.clone()),
+        None           => (SearchStats::default(), Vec::new()),
+    };
+    for (name, solution, seconds) in runs.iter() {
+        let (search, expanded) = expanded(name, solution);
+        let (share, explored) = explored(&expanded);
+        let length = solution.as_ref().map_or_else(|| unreachable.clone(), |solution| solution.cost.to_string());
+        row(name, length, search, share, *seconds);
+
+        if let Some(maps) = &mut maps {
+            let mut image = render::canvas(in_image, &opts.style);
+            render::render_explored(&mut image, &graph, &explored, &opts.style);
+            if let Some(solution) = solution {
+                render::render_path(&mut image, solution, &opts.style);
+            }
+            save(maps, &format!("{}.png", name), &image)?;
         }
     }
 
-    image.save("solved.png").expect("saving maze");
+    let grid = PixelGrid::new(grid_image).map_err(|err| err.to_string())?;
+    let pixel_solvers = [Solver::JumpPoints, Solver::PixelBfs, Solver::LeftHand, Solver::RightHand, Solver::DeadEndFill];
+    for solver in pixel_solvers {
+        let path = stats.time(solver.name(), || match solver {
+            Solver::PixelBfs    => grid_search::pixel_bfs(&grid, start_pos, goal_pos),
+            Solver::LeftHand    => grid_search::wall_follower(&grid, start_pos, goal_pos, Hand::Left),
+            Solver::RightHand   => grid_search::wall_follower(&grid, start_pos, goal_pos, Hand::Right),
+            Solver::DeadEndFill => grid_search::fill_dead_ends(&grid, start_pos, goal_pos).map(|fill| fill.path),
+            _                   => grid_search::jump_point_search(&grid, start_pos, goal_pos),
+        });
+        match path {
+            Ok(path) => row(solver.name(), format!("{:.2} px", path.length), path.stats, "-".into(), seconds(stats)),
+            Err(Error::WallLoop(_)) => row(solver.name(), "circled".into(), SearchStats::default(), "-".into(), seconds(stats)),
+            Err(_)   => row(solver.name(), unreachable.clone(), SearchStats::default(), "-".into(), seconds(stats)),
+        }
+    }
+
+    if let Some(dir) = &opts.frames {
+        let race = opts.race.unwrap_or([Search::Dijkstra, Search::AStar]);
+        let entrants: Vec<_> = race.iter()
+            .map(|search| {
+                let (name, solution, _) = runs.iter().find(|(name, _, _)| *name == search.name()).expect("every rect search runs");
+                (expanded(name, solution).1, solution.as_ref())
+            })
+            .collect();
+        let lanes: Vec<_> = entrants.iter()
+            .map(|(expanded, solution)| render::Lane { expanded, solution: *solution })
+            .collect();
+        info!("Filming {} against {}...", race[0].name(), race[1].name());
+        let base = render::canvas(in_image, &opts.style);
+        let frames = stats.time("race", || {
+            let mut frames = FileSink { indexed: opts.indexed, ..FileSink::in_dir(dir)? };
+            render::render_race(&base, &graph, &lanes, &mut frames, opts.frame_every, &opts.style)
+        });
+        let frames = frames.map_err(|err| format!("writing frames to {}: {}", dir, err))?;
+        info!("Wrote {} frames to {}", frames, dir);
+    }
+
+    Ok(())
+}
+
+// whether the maze is walled in but for its openings and all one region they lead into, a line
+// for each test; an error if any fails, so the exit status says too
+fn check(opts: &Options, grid_image: &im::GrayImage, stats: &mut Stats) -> Result<(), String> {
+    warn_ignored(stats, "check", &[
+        ("--start", opts.start.is_some()),
+        ("--goal", opts.goal.is_some()),
+        ("--algo", opts.search != Search::Dijkstra),
+        ("--solver", opts.solver != Solver::Rects),
+    ]);
+    let check = stats.time("check", || MazeCheck::new(grid_image));
+
+    let mut failed = 0;
+    let mut line = |name: &str, passed: bool, detail: String| {
+        report(opts, format_args!("{:<10} {:<4} {}", name, if passed { "ok" } else { "FAIL" }, detail));
+        failed += !passed as usize;
+    };
+
+    let at = |p: V2| format!("{},{}", p.x, p.y);
+    let openings: Vec<String> = check.openings.iter().map(|run| format!(" {}", at(run[0]))).collect();
+    line("openings", check.openings.len() == opts.openings,
+        format!("{} of {} expected{}", check.openings.len(), opts.openings, openings.concat()));
+
+    let corners: Vec<String> = check.corners.iter().copied().map(at).collect();
+    line("enclosed", check.enclosed(),
+        if check.enclosed() { "the outer walls meet at every corner".into() }
+        else                { format!("open at {}", corners.join(" and ")) });
+
+    line("connected", check.connected(), match check.reached.len() {
+        0 => "there are no openings to lead anywhere".into(),
+        1 => "every opening leads into the same region".into(),
+        n => format!("the openings lead into {} separate regions", n),
+    });
+
+    let pockets: Vec<usize> = check.pockets().collect();
+    let pocket_px: usize = pockets.iter().map(|region| check.components.sizes[*region]).sum();
+    line("pockets", pockets.is_empty(),
+        format!("{} region{} no opening leads into, {} px", pockets.len(),
+            if pockets.len() == 1 { "" } else { "s" }, pocket_px));
+
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("not a well-formed maze: {} of 4 checks failed", failed)),
+    }
 }
 
+// how the clear space splits into regions, with a warning where the points the mode joins are
+// in different ones. no extraction could join those, so the warning comes before it fails.
+fn analyze_components(opts: &Options, in_image: &im::GrayImage, grid_image: &im::GrayImage, stats: &mut Stats)
+    -> Result<(), String>
+{
+    let components = stats.time("components", || preprocess::label_components(grid_image));
+    let outside: usize = components.sizes.iter().skip(1).sum();
+    report(opts, format_args!("components: {} ({} px outside the largest)", components.sizes.len(), outside));
+
+    let anchors: Vec<(V2, usize)> = anchor_points(opts, grid_image).into_iter()
+        .filter_map(|p| components.at(p).map(|region| (p, region)))
+        .collect();
+    if let Some((first, region)) = anchors.first().copied() {
+        for (p, other) in anchors.iter().copied().filter(|(_, other)| *other != region) {
+            stats.warn(format!("{},{} is in component {} and {},{} in component {}, so nothing joins them",
+                first.x, first.y, region, p.x, p.y, other));
+        }
+    }
+
+    if let Some(path) = &opts.component_map {
+        let mut image = render::to_rgb(in_image);
+        render::render_components(&mut image, &components);
+        image.save(path).map_err(|err| format!("saving {}: {}", path, err))?;
+        info!("Wrote {}", path);
+    }
+    Ok(())
+}
+
+// the distance from the nearest of `sources` to everywhere, by delta-stepping where --delta asks
+fn distance_map(opts: &Options, graph: AdjacencyGraph<NodeData>, sources: &[NodeID]) -> DijkstraGraph<NodeData> {
+    match opts.delta {
+        Some(delta) => graph.into_delta_stepping(sources, delta),
+        None        => graph.into_multi_source_dijkstra(sources),
+    }
+}
+
+// warns about each flag given that `what` goes without
+fn warn_ignored(stats: &mut Stats, what: &str, flags: &[(&str, bool)]) {
+    for (flag, _) in flags.iter().filter(|(_, given)| *given) {
+        stats.warn(format!("{} is ignored for {}", flag, what));
+    }
+}
+
+fn partition(
+    opts:       &Options,
+    in_image:   &im::GrayImage,
+    grid_image: &im::GrayImage,
+    sink:       &mut dyn OutputSink,
+    stats:      &mut Stats)
+    -> Result<(), String>
+{
+    info!("Building graph...");
+    let extract = choose_extract_options(opts, grid_image, &opts.seeds);
+    let (graph, seeds) = stats.time("extract", || extract_seeded_graph(grid_image, &opts.seeds, &extract))
+        .map_err(|err| format!("seed {}", err))?;
+
+    info!("Partitioning...");
+    let graph = graph.into_adjacency_graph();
+    let graph = stats.time("search", || distance_map(opts, graph, &seeds));
+    stats.record_search(graph.stats);
+
+    let mut areas: HashMap<NodeID, (i64, usize)> = HashMap::default();
+    for (id, data) in graph.nodes().iter() {
+        if let Some(origin) = graph.origin(*id) {
+            let entry = areas.entry(origin).or_insert((0, 0));
+            entry.0 += data.area;
+            entry.1 += 1;
+        }
+    }
+
+    for (index, (pos, id)) in opts.seeds.iter().zip(seeds.iter()).enumerate() {
+        let (area, rects) = areas.get(id).copied().unwrap_or((0, 0));
+        report(opts, format_args!("region {} (seed {},{}): {} px in {} rects", index, pos.x, pos.y, area, rects));
+    }
+
+    if opts.no_render {
+        return Ok(());
+    }
+    info!("Rendering...");
+    let mut image = render::canvas(in_image, &opts.style);
+    render::render_partition(&mut image, &graph, &seeds, &opts.style);
+
+    save(sink, &opts.output, &image)
+}
+
+fn escape(
+    opts:       &Options,
+    in_image:   &im::GrayImage,
+    grid_image: &im::GrayImage,
+    sink:       &mut dyn OutputSink,
+    stats:      &mut Stats)
+    -> Result<(), String>
+{
+    let exits = if opts.exits.is_empty() { border_openings(grid_image) }
+                else                     { opts.exits.clone() };
+    if exits.is_empty() {
+        return Err("no exits given and none found on the border".into());
+    }
+
+    info!("Building graph...");
+    let extract = choose_extract_options(opts, grid_image, &exits);
+    let (graph, mut sources) = stats.time("extract", || extract_seeded_graph(grid_image, &exits, &extract))
+        .map_err(|err| format!("exit {}", err))?;
+    sources.sort();
+    sources.dedup();
+
+    info!("Computing escape distances from {} exits...", exits.len());
+    let graph = graph.into_adjacency_graph();
+    let graph = stats.time("search", || distance_map(opts, graph, &sources));
+    stats.record_search(graph.stats);
+
+    let worst = graph.nodes().iter()
+        .filter(|(id, _)| graph.distance(**id) != i32::MAX)
+        .max_by_key(|(id, _)| graph.distance(**id));
+    if let Some((id, data)) = worst {
+        report(opts, format_args!("worst-case escape distance: {} (from {},{})",
+            graph.distance(*id), data.rect.mins.x, data.rect.mins.y));
+    }
+
+    if opts.no_render {
+        return Ok(());
+    }
+    info!("Rendering...");
+    let mut image = render::canvas(in_image, &opts.style);
+    render::render_distance_field(&mut image, &graph, &opts.style);
+
+    save(sink, &opts.output, &image)
+}
+
+// what the defaults and estimates came to, for the manifest
+#[cfg_attr(not(feature = "json"), allow(dead_code))]
+struct Resolved {
+    anchors: Vec<V2>,
+    skew:    Option<f32>,
+}
+
+// floor N of a multi-level solve goes to solved.levelN.png beside the output
+fn level_output(output: &str, level: usize) -> String {
+    let path = Path::new(output).with_extension(format!("level{}.png", level));
+    path.to_string_lossy().into_owned()
+}
+
+fn solve_levels(opts: &Options, sink: &mut dyn OutputSink, stats: &mut Stats) -> Resolved {
+    warn_ignored(stats, "--level", &[
+        ("--rotate", opts.rotate.is_some()),
+        ("--deskew", opts.deskew),
+        ("--refine", opts.refine),
+        ("--k-paths", opts.k_paths.is_some()),
+        ("--longest", opts.longest.is_some()),
+        ("--dead-ends", opts.dead_ends),
+        ("--frames", opts.frames.is_some()),
+        ("--objective", opts.objective != Objective::Distance),
+        ("--skeleton", opts.skeleton.is_some()),
+        ("--teleporters", opts.teleporters),
+        ("--one-way", opts.one_way.is_some()),
+        ("--keys", opts.keys),
+        ("--path-json", opts.path_json.is_some()),
+        ("--graph-json", opts.graph_json.is_some()),
+        ("--trace-json", opts.trace_json.is_some()),
+        ("--algo", opts.search != Search::Dijkstra),
+        ("--crop", opts.crop.is_some()),
+    ]);
+
+    let mut colors = Vec::new();
+    let mut levels = Vec::new();
+    for (index, path) in opts.levels.iter().enumerate() {
+        let loaded = formats::open(path).unwrap_or_else(|err| fail(format!("loading {}: {}", path, err)));
+        let color = loaded.to_rgb();
+        let binary = if opts.colors.wall.is_some() || opts.colors.corridor.is_some() {
+            preprocess::classify_colors(&color, &opts.colors)
+        }
+        else {
+            loaded.to_luma()
+        };
+
+        // the stairs are picked out after cleanup, which could otherwise erode them
+        let mut binary = preprocess_input(opts, &binary, stats);
+        seal_input(opts, &mut binary, stats);
+        let level = levels::find_stairs(&color, binary, &opts.stairs);
+        info!("Level {}: {} up and {} down stair px", index, level.up.len(), level.down.len());
+        colors.push(color);
+        levels.push(level);
+    }
+
+    let goal_level = opts.goal_level.unwrap_or(levels.len() - 1);
+    let image_of = |index: usize| levels.get(index).map(|level: &Level| &level.image)
+        .unwrap_or_else(|| fail(format!("there is no level {}", index)));
+    let (start_pos, _) = endpoints(opts, image_of(opts.start_level));
+    let (_, goal_pos)  = endpoints(opts, image_of(goal_level));
+
+    info!("Building graph...");
+    let start = (opts.start_level, start_pos);
+    let goal  = (goal_level, goal_pos);
+    let graph = stats.time("extract", || levels::extract_levels(&levels, start, goal, &opts.extract))
+        .unwrap_or_else(|err| fail(err));
+    stats.nodes_extracted = graph.nodes().len();
+    stats.edges_extracted = graph.edges.len();
+    stats.record_areas(graph.nodes().values().map(|space| space.rect.area()));
+
+    info!("Pruning graph...");
+    let graph = stats.time("prune", || graph.prune());
+    stats.nodes_pruned = graph.nodes().len();
+    stats.edges_pruned = graph.edges.len();
+
+    info!("Contracting corridors...");
+    let graph = stats.time("contract", || graph.into_adjacency_graph().contract());
+    stats.search_nodes = graph.adjs.len();
+    stats.chains = graph.chains.len();
+
+    info!("Finding path...");
+    let graph = stats.time("search", || graph.into_dijkstra());
+    stats.record_search(graph.stats);
+    stats.solution_length = graph.goal_distance();
+    stats.trivial = graph.is_trivial();
+    info!("Solution length: {}", stats.solution_length);
+
+    let route = graph.path_to(graph.goal());
+    for index in 0 .. levels.len() {
+        let spaces = route.iter().filter(|id| graph.get_node(**id).level == index).count();
+        report(opts, format_args!("level {}: {} spaces of the route", index, spaces));
+    }
+
+    if opts.no_render {
+        return Resolved { anchors: vec![start_pos, goal_pos], skew: None };
+    }
+    info!("Rendering...");
+    stats.time("render", || {
+        for (index, color) in colors.iter().enumerate() {
+            let mut image = if opts.path_only { render::blank_canvas(color.width(), color.height(), &opts.style) }
+                            else              { render::rgb_canvas(color, &opts.style) };
+            if !opts.path_only {
+                render::render_level_nodes(&mut image, &graph, index, &opts.style);
+            }
+            render::render_level_path(&mut image, &graph, index, &opts.style);
+            save(sink, &level_output(&opts.output, index), &image)
+                .unwrap_or_else(|err| fail(err));
+        }
+    });
+
+    Resolved { anchors: vec![start_pos, goal_pos], skew: None }
+}
+
+// an image file, or with a path of "-" an image piped in, in whatever format it turns out to be
+fn open_input(path: &str) -> Result<formats::Input, String> {
+    if path != "-" {
+        return formats::open_input(path).map_err(|err| format!("loading {}: {}", path, err));
+    }
+    let mut bytes = Vec::new();
+    std::io::Read::read_to_end(&mut std::io::stdin().lock(), &mut bytes)
+        .map_err(|err| format!("reading stdin: {}", err))?;
+    formats::read(&bytes).map_err(|err| format!("loading stdin: {}", err))
+}
+
+fn open_image(path: &str) -> Result<im::DynamicImage, String> {
+    open_input(path).map(|input| input.image)
+}
+
+// what an input marks in itself: a start and goal, as text mazes can, and with --teleporters
+// or --keys the coloured marks
+#[derive(Default)]
+struct Marks {
+    start:       Option<V2>,
+    goal:        Option<V2>,
+    teleporters: Vec<Teleporter>,
+    locks:       Vec<Lock>,
+}
+
+// the marked start and goal, wherever --start and --goal don't say otherwise
+fn mark_endpoints(opts: &Options, marks: &Marks) -> Options {
+    let mut opts = opts.clone();
+    opts.start = opts.start.or(marks.start);
+    opts.goal  = opts.goal.or(marks.goal);
+    opts
+}
+
+// the image at `path` in clear/wall greys, straightened as asked. returns any skew corrected,
+// and whatever the input marks in itself
+fn load_input(opts: &Options, path: &str) -> Result<(im::GrayImage, Option<f32>, Marks), String> {
+    let formats::Input { image: loaded, start, goal } = open_input(path)?;
+    // marks in the image as given, so off once it's turned
+    let turned = opts.rotate.is_some() || opts.deskew;
+    if turned && (opts.teleporters || opts.keys) {
+        return Err("--teleporters and --keys can't be used with --rotate or --deskew".into());
+    }
+    if opts.crop.is_some() && (opts.teleporters || opts.keys) {
+        return Err("--teleporters and --keys can't be used with --crop".into());
+    }
+    if opts.teleporters && opts.keys {
+        return Err("--teleporters and --keys both read the coloured marks; give one".into());
+    }
+    let mut marks = if turned { Marks::default() } else { Marks { start, goal, ..Marks::default() } };
+    let in_image = if let Some(k) = opts.clusters {
+        let color = loaded.to_rgb();
+        let clusters = preprocess::cluster_colors(&color, k);
+        for (index, cluster) in clusters.iter().enumerate() {
+            let im::Rgb([r, g, b]) = cluster.color;
+            info!("Cluster {}: {:02x}{:02x}{:02x}, {} px", index, r, g, b, cluster.pixels);
+        }
+        let walls = match &opts.wall_clusters {
+            Some(walls) => walls.clone(),
+            None        => ask_wall_clusters()?,
+        };
+        if let Some(bad) = walls.iter().find(|index| **index >= clusters.len()) {
+            return Err(format!("there is no cluster {}", bad));
+        }
+        preprocess::classify_clusters(&color, &clusters, &walls)
+    }
+    else if opts.colors.wall.is_some() || opts.colors.corridor.is_some() {
+        preprocess::classify_colors(&loaded.to_rgb(), &opts.colors)
+    }
+    else {
+        loaded.to_luma()
+    };
+
+    let mut in_image = in_image;
+    if opts.teleporters {
+        let found = teleporters::find(&loaded.to_rgb(), &mut in_image);
+        for (im::Rgb([r, g, b]), count) in &found.unpaired {
+            warn!("{:02x}{:02x}{:02x} is marked in {} places rather than 2; it joins nothing", r, g, b, count);
+        }
+        info!("{} teleporters", found.teleporters.len());
+        marks.teleporters = found.teleporters;
+    }
+    if opts.keys {
+        let found = keys::find(&loaded.to_rgb(), &mut in_image);
+        let hex = |im::Rgb([r, g, b]): im::Rgb<u8>| format!("{:02x}{:02x}{:02x}", r, g, b);
+        for color in &found.keyless {
+            warn!("{} has doors but no key; they stay shut", hex(*color));
+        }
+        for color in &found.doorless {
+            warn!("{} has keys but no doors", hex(*color));
+        }
+        info!("{} colours of key and door", found.locks.len());
+        marks.locks = found.locks;
+    }
+
+    // rotated before anything else so that rendering lines up with what was solved
+    let in_image = match opts.rotate {
+        Some(degrees) => preprocess::rotate(&in_image, degrees),
+        None          => in_image,
+    };
+
+    let skew = if opts.deskew { Some(preprocess::estimate_skew(&in_image)) } else { None };
+    let in_image = match skew {
+        Some(skew) => {
+            info!("Deskewing by {:.2} degrees...", skew);
+            preprocess::rotate(&in_image, -skew)
+        }
+        None => in_image,
+    };
+
+    Ok((in_image, skew, marks))
+}
+
+// which clusters are walls, asked on the terminal; just the darkest if there's no one to ask
+fn ask_wall_clusters() -> Result<Vec<usize>, String> {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        info!("Taking cluster 0 as the walls (give --wall-clusters to choose)");
+        return Ok(vec![0]);
+    }
+
+    eprint!("Wall clusters, comma-separated [0]: ");
+    std::io::stderr().flush().map_err(|err| err.to_string())?;
+    let mut line = String::new();
+    stdin.lock().read_line(&mut line).map_err(|err| format!("reading the wall clusters: {}", err))?;
+    match line.trim() {
+        ""   => Ok(vec![0]),
+        text => cli::parse_indices(text),
+    }
+}
+
+// a tiled PGM is solved straight from the file, a tile in memory at a time
+fn streams(opts: &Options) -> bool {
+    opts.mode == Mode::Solve && opts.solver == Solver::Rects && opts.tile.is_some() && tiles::is_binary_pgm(&opts.input)
+}
+
+// solves a tiled PGM without ever loading it. there's no image to clean up or draw on, so only
+// the results and the exported path come out.
+fn solve_streamed(opts: &Options, stats: &mut Stats) -> Result<Resolved, String> {
+    warn_ignored(stats, "a tiled PGM", &[
+        ("--wall-color/--corridor-color", opts.colors.wall.is_some() || opts.colors.corridor.is_some()),
+        ("--invert", opts.invert),
+        ("--threshold", opts.threshold.is_some()),
+        ("--adaptive", opts.adaptive.is_some()),
+        ("--flatten", opts.flatten.is_some()),
+        ("--rotate", opts.rotate.is_some()),
+        ("--deskew", opts.deskew),
+        ("--floorplan", opts.floorplan.is_some()),
+        ("--morph", !opts.morph.is_empty()),
+        ("--join-walls", opts.join_walls.is_some()),
+        ("--mask-text", opts.mask_text.is_some()),
+        ("--crop", opts.crop.is_some()),
+        ("--seal-border", opts.seal_border),
+        ("--decompose best", opts.best_decomposition),
+        ("--k-paths", opts.k_paths.is_some()),
+        ("--longest", opts.longest.is_some()),
+        ("--dead-ends", opts.dead_ends),
+        ("--frames", opts.frames.is_some()),
+        ("--objective", opts.objective != Objective::Distance),
+        ("--skeleton", opts.skeleton.is_some()),
+        ("--teleporters", opts.teleporters),
+        ("--one-way", opts.one_way.is_some()),
+        ("--keys", opts.keys),
+    ]);
+
+    let mut tiles = PgmTiles::open(&opts.input)
+        .map_err(|err| format!("loading {}: {}", opts.input, err))?;
+    let (start_pos, goal_pos) = endpoints_in(opts, tiles.dimensions());
+    let size = opts.tile.expect("streaming needs a tile size");
+
+    info!("Building graph from {}-px tiles...", size);
+    let graph = stats.time("extract", || {
+        image_graph::extract_tiled_graph(&mut tiles, size, start_pos, goal_pos, &opts.extract)
+    });
+    let graph = graph.map_err(|err| err.to_string())?;
+    stats.nodes_extracted = graph.nodes().len();
+    stats.edges_extracted = graph.edges.len();
+    stats.record_areas(graph.nodes().values().map(|data| data.area));
+
+    info!("Pruning graph...");
+    let graph = stats.time("prune", || graph.prune());
+    stats.nodes_pruned = graph.nodes().len();
+    stats.edges_pruned = graph.edges.len();
+
+    info!("Contracting corridors...");
+    let graph = stats.time("contract", || graph.into_adjacency_graph().contract());
+    stats.search_nodes = graph.adjs.len();
+    stats.chains = graph.chains.len();
+
+    info!("Finding path...");
+    let (graph, solution) = search(opts, graph, goal_pos, stats)?;
+    stats.solution_length = solution.cost;
+    stats.trivial = graph.is_trivial();
+    report(opts, format_args!("solution length: {}", stats.solution_length));
+
+    #[cfg(feature = "json")]
+    if let Some(path) = &opts.path_json {
+        let rects = &solution.rects;
+        let pixels = stats.time("refine", || refine::refine_path(rects, start_pos, goal_pos));
+        let height = coord(tiles.dimensions().1).map_err(|err| err.to_string())?;
+        let export = PathExport::new(stats.solution_length, rects, &pixels, opts.origin, height);
+        export.save(path).map_err(|err| format!("saving {}: {}", path, err))?;
+    }
+
+    Ok(Resolved { anchors: vec![start_pos, goal_pos], skew: None })
+}
+
+// loads, cleans up and solves the one input image
+fn run_single(opts: &Options, sink: &mut dyn OutputSink, stats: &mut Stats) -> Result<Resolved, String> {
+    if streams(opts) {
+        return solve_streamed(opts, stats);
+    }
+
+    let (in_image, skew, marks) = load_input(opts, &opts.input)?;
+    let opts = &mark_endpoints(opts, &marks);
+    if opts.mode != Mode::Solve {
+        warn_ignored(stats, "anything but solving", &[
+            ("--teleporters", opts.teleporters),
+            ("--one-way", opts.one_way.is_some()),
+            ("--keys", opts.keys),
+        ]);
+    }
+    let grid_image = preprocess_input(opts, &in_image, stats);
+
+    // cut down to the maze, with everything drawn pasted back over the whole input
+    let (in_image, mut grid_image, opts, mut pasted) = match crop_input(opts, &in_image, &grid_image, stats) {
+        Some((cut_image, cut_grid, kept)) => {
+            let (width, height) = in_image.dimensions();
+            let backdrop = if opts.path_only { render::blank_canvas(width, height, &opts.style) }
+                           else              { render::canvas(&in_image, &opts.style) };
+            let at = opts.style.canvas_point(kept.mins);
+            let paste = PasteSink { inner: sink, backdrop, at: (at.x as u32, at.y as u32) };
+            (cut_image, cut_grid, crop_options(opts, kept, (width, height)), Some(paste))
+        }
+        None => (in_image, grid_image, opts.clone(), None),
+    };
+    let sink: &mut dyn OutputSink = match pasted.as_mut() {
+        Some(paste) => paste,
+        None        => sink,
+    };
+    seal_input(&opts, &mut grid_image, stats);
+    save_debug(&opts, "binarized.png", || render::to_rgb(&grid_image))?;
+    let opts = &snap_endpoints(&opts, &grid_image, stats);
+    if opts.components {
+        analyze_components(opts, &in_image, &grid_image, stats)?;
+    }
+
+    match opts.mode {
+        Mode::Solve if !marks.locks.is_empty() => solve_locked(opts, &in_image, &grid_image, &marks.locks, sink, stats)?,
+        Mode::Solve     => solve(opts, &in_image, &grid_image, &marks.teleporters, sink, stats)?,
+        Mode::Partition => partition(opts, &in_image, &grid_image, sink, stats)?,
+        Mode::Escape    => escape(opts, &in_image, &grid_image, sink, stats)?,
+        Mode::Compare   => compare(opts, &in_image, &grid_image, stats)?,
+        Mode::Check     => check(opts, &grid_image, stats)?,
+        Mode::Levels | Mode::Sequence | Mode::Diff | Mode::SelfTest => unreachable!("{:?} runs without a single input", opts.mode),
+    }
+
+    let offset = opts.cropped.map_or(V2::new(0, 0), |(kept, _)| kept.mins);
+    let anchors = anchor_points(opts, &grid_image).into_iter().map(|p| p + offset).collect();
+    Ok(Resolved { anchors, skew })
+}
+
+// the stats and manifest that follow every run
+#[cfg_attr(not(feature = "json"), allow(unused_variables))]
+fn finish(args: &[String], opts: &Options, stats: &Stats, resolved: &Resolved) -> Result<(), String> {
+    match opts.stats {
+        Some(StatsFormat::Text) => report(opts, stats.to_text().trim_end()),
+        #[cfg(feature = "json")]
+        Some(StatsFormat::Json) => report(opts, stats.to_json()),
+        None                    => { }
+    }
+
+    #[cfg(feature = "json")]
+    if opts.manifest {
+        let manifest = manifest::Manifest {
+            args, opts, anchors: &resolved.anchors, skew: resolved.skew, stats,
+        };
+        let path = manifest.save().map_err(|err| format!("saving manifest: {}", err))?;
+        info!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+// batch and sequence runs write into a directory: the output if one was given, or else solved/
+// inside the input directory
+fn batch_output_dir(opts: &Options) -> PathBuf {
+    if opts.output == cli::DEFAULT_OUTPUT { Path::new(&opts.input).join("solved") }
+    else                                  { PathBuf::from(&opts.output) }
+}
+
+// one maze of a batch, with its sidecar's overrides, into `out_dir` under the same name
+fn solve_batch_entry(args: &[String], opts: &Options, input: &Path, out_dir: &Path, sink: &mut dyn OutputSink)
+    -> Result<Stats, String>
+{
+    let mut opts = opts.clone();
+    batch::read_overrides(input)?.apply(&mut opts);
+    opts.input = input.to_string_lossy().into_owned();
+    let output = out_dir.join(input.with_extension("png").file_name().unwrap_or_default());
+    opts.output = output.to_string_lossy().into_owned();
+
+    let mut stats = Stats::default();
+    let resolved = run_single(&opts, sink, &mut stats)?;
+    finish(args, &opts, &stats, &resolved)?;
+    Ok(stats)
+}
+
+// solves every image in the input directory, carrying on past any that fail, and sums them up
+// in report.html. returns whether they all succeeded.
+fn run_batch(args: &[String], opts: &Options) -> bool {
+    let dir = Path::new(&opts.input);
+    let out_dir = batch_output_dir(opts);
+    let inputs = batch::inputs(dir).unwrap_or_else(|err| fail(format!("reading {}: {}", dir.display(), err)));
+    std::fs::create_dir_all(&out_dir)
+        .unwrap_or_else(|err| fail(format!("creating {}: {}", out_dir.display(), err)));
+
+    let solve_one = |input: &PathBuf| {
+        let name = input.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let output = input.with_extension("png").file_name().unwrap_or_default().to_string_lossy().into_owned();
+        info!("== {}", name);
+
+        let mut sink = FileSink { indexed: opts.indexed, ..FileSink::default() };
+        let result = solve_batch_entry(args, opts, input, &out_dir, &mut sink);
+        let thumbnail = match &result {
+            Ok(stats) => {
+                report(opts, format_args!("{}: solution length {}", name, stats.solution_length));
+                let thumbnail = Path::new("thumbs").join(&output);
+                if opts.no_render {
+                    None
+                }
+                else {
+                    match report::save_thumbnail(&out_dir.join(&output), &out_dir.join(&thumbnail)) {
+                        Ok(()) => Some(thumbnail.to_string_lossy().into_owned()),
+                        Err(err) => {
+                            warn!("thumbnailing {}: {}", output, err);
+                            None
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                report(opts, format_args!("{}: error: {}", name, err));
+                None
+            }
+        };
+        report::Entry { name, output, thumbnail, result }
+    };
+
+    // the mazes share the thread pool with any tiles they split into, so that a few big mazes
+    // among many small ones keep every thread busy. their progress lines interleave.
+    #[cfg(feature = "parallel")]
+    let entries: Vec<report::Entry> = inputs.par_iter().map(solve_one).collect();
+    #[cfg(not(feature = "parallel"))]
+    let entries: Vec<report::Entry> = inputs.iter().map(solve_one).collect();
+
+    let failed = entries.iter().filter(|entry| entry.result.is_err()).count();
+    let report_path = out_dir.join("report.html");
+    let html = report::to_html(&format!("mazesolve batch: {}", dir.display()), &entries);
+    let reported = match std::fs::write(&report_path, html) {
+        Ok(())   => { info!("Wrote {}", report_path.display()); true }
+        Err(err) => { error!("writing {}: {}", report_path.display(), err); false }
+    };
+
+    info!("Solved {} of {} mazes into {}", inputs.len() - failed, inputs.len(), out_dir.display());
+    failed == 0 && reported
+}
+
+// the last frame's cleaned-up image and its graph as extracted, before pruning
+type PrevFrame = (im::GrayImage, EdgeSetGraph<NodeData>);
+
+// one frame of a sequence. the graph comes from the previous frame's where there is one: as it
+// was if nothing changed, or updated around whatever did. returns which of those it was.
+fn solve_frame(
+    args:   &[String],
+    opts:   &Options,
+    input:  &Path,
+    output: &Path,
+    prev:   &mut Option<PrevFrame>,
+    sink:   &mut dyn OutputSink)
+    -> Result<(Stats, &'static str), String>
+{
+    let mut opts = opts.clone();
+    opts.input = input.to_string_lossy().into_owned();
+    opts.output = output.to_string_lossy().into_owned();
+
+    let mut stats = Stats::default();
+    let (in_image, skew, marks) = load_input(&opts, &opts.input)?;
+    let opts = mark_endpoints(&opts, &marks);
+    warn_ignored(&mut stats, "a sequence", &[
+        ("--teleporters", opts.teleporters),
+        ("--keys", opts.keys),
+        ("--crop", opts.crop.is_some()),
+    ]);
+    let mut grid_image = preprocess_input(&opts, &in_image, &mut stats);
+    seal_input(&opts, &mut grid_image, &mut stats);
+    let opts = snap_endpoints(&opts, &grid_image, &mut stats);
+    if opts.components {
+        analyze_components(&opts, &in_image, &grid_image, &mut stats)?;
+    }
+    let (start_pos, goal_pos) = endpoints(&opts, &in_image);
+
+    let (graph, how) = match prev.take() {
+        Some((prev_image, prev_graph)) if prev_image.dimensions() == grid_image.dimensions() => {
+            match image_graph::changed_region(&prev_image, &grid_image) {
+                None => (prev_graph, "unchanged"),
+                Some(changed) => {
+                    let graph = stats.time("extract", || {
+                        image_graph::update_graph(&prev_graph, &grid_image, changed, start_pos, goal_pos, &opts.extract)
+                    });
+                    (graph.map_err(|err| err.to_string())?, "updated")
+                }
+            }
+        }
+        _ => {
+            let graph = stats.time("extract", || extract_graph(&grid_image, start_pos, goal_pos, &opts.extract));
+            (graph.map_err(|err| err.to_string())?, "extracted")
+        }
+    };
+    *prev = Some((grid_image.clone(), graph.clone()));
+
+    solve_extracted(&opts, &in_image, &grid_image, graph, &[], sink, &mut stats)?;
+    finish(args, &opts, &stats, &Resolved { anchors: vec![start_pos, goal_pos], skew })?;
+    Ok((stats, how))
+}
+
+// solves every frame of an image sequence, one maze changing over time, into the output
+// directory. returns whether every frame solved.
+fn run_sequence(args: &[String], opts: &Options) -> bool {
+    let dir = Path::new(&opts.input);
+    let out_dir = batch_output_dir(opts);
+    let frames = batch::inputs(dir).unwrap_or_else(|err| fail(format!("reading {}: {}", dir.display(), err)));
+    std::fs::create_dir_all(&out_dir)
+        .unwrap_or_else(|err| fail(format!("creating {}: {}", out_dir.display(), err)));
+
+    let mut sink = FileSink { indexed: opts.indexed, ..FileSink::default() };
+    let mut prev = None;
+    let mut failed = 0;
+    for frame in frames.iter() {
+        let name = frame.file_name().unwrap_or_default().to_string_lossy();
+        let output = out_dir.join(frame.with_extension("png").file_name().unwrap_or_default());
+        match solve_frame(args, opts, frame, &output, &mut prev, &mut sink) {
+            Ok((stats, how)) => {
+                report(opts, format_args!("{}: solution length {} ({})", name, stats.solution_length, how));
+            }
+            Err(err) => {
+                failed += 1;
+                report(opts, format_args!("{}: error: {}", name, err));
+            }
+        }
+    }
+
+    info!("Solved {} of {} frames into {}", frames.len() - failed, frames.len(), out_dir.display());
+    failed == 0
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let opts = match cli::parse_args(args.iter().cloned()) {
+        Ok(opts) => opts,
+        Err(msg) => {
+            eprintln!("error: {}\n\n{}", msg, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    init_logging(opts.verbosity);
+
+    #[cfg(feature = "parallel")]
+    if let Some(jobs) = opts.jobs {
+        rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global()
+            .unwrap_or_else(|err| fail(format!("starting {} threads: {}", jobs, err)));
+    }
+    #[cfg(not(feature = "parallel"))]
+    if opts.jobs.is_some() {
+        warn!("--jobs needs the parallel feature; running on one thread");
+    }
+
+    #[cfg(feature = "json")]
+    if let Some(schema) = opts.schema {
+        println!("{}", serde_json::to_string_pretty(&schema.document()).expect("serializing a schema"));
+        return;
+    }
+
+    if opts.mode == Mode::SelfTest {
+        let passed = selftest::run();
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    let batch = !matches!(opts.mode, Mode::Levels | Mode::Sequence | Mode::Compare | Mode::Check | Mode::Diff) && Path::new(&opts.input).is_dir();
+    if (batch || opts.mode == Mode::Levels || opts.mode == Mode::Sequence) && to_stdout(&opts) {
+        fail("batch, sequence and --level runs write several images, so they can't write to stdout");
+    }
+    #[cfg(feature = "json")]
+    if opts.manifest && to_stdout(&opts) {
+        fail("--manifest is written beside the output, so it needs an output file");
+    }
+
+    if batch::is_video(Path::new(&opts.input)) {
+        fail(format!("{} is a video, which isn't read directly: split it into frames first, e.g. \
+            ffmpeg -i {} frames/%05d.png, and give --sequence the directory", opts.input, opts.input));
+    }
+    if (batch || opts.mode == Mode::Sequence) && batch::is_video(Path::new(&opts.output)) {
+        fail(format!("batches and sequences write a directory of frames, not {}: join them after, e.g. \
+            ffmpeg -i solved/%05d.png {}", opts.output, opts.output));
+    }
+    if batch || opts.mode == Mode::Sequence {
+        let passed = if batch { run_batch(&args, &opts) } else { run_sequence(&args, &opts) };
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    let mut sink: Box<dyn OutputSink> = if to_stdout(&opts) {
+        Box::new(StdoutSink { indexed: opts.indexed })
+    }
+    else {
+        Box::new(FileSink { indexed: opts.indexed, ..FileSink::default() })
+    };
+
+    if opts.mode == Mode::Diff {
+        let same = diff::run(&opts, sink.as_mut()).unwrap_or_else(|err| fail(err));
+        std::process::exit(if same { 0 } else { 1 });
+    }
+
+    let mut stats = Stats::default();
+    let resolved = match opts.mode {
+        Mode::Levels => solve_levels(&opts, sink.as_mut(), &mut stats),
+        _            => run_single(&opts, sink.as_mut(), &mut stats).unwrap_or_else(|err| fail(err)),
+    };
+    finish(&args, &opts, &stats, &resolved).unwrap_or_else(|err| fail(err));
+}
@@ -0,0 +1,204 @@
+use {
+    crate::{
+        cli::{Mode, Options},
+        stats::Stats,
+    },
+    mazesolve_rk::{math::*, preprocess::Adaptive},
+    image as im,
+    serde_json::{json, Value},
+    sha2::{Digest, Sha256},
+    std::{fs, io, path::{Path, PathBuf}},
+};
+
+// solved.png -> solved.manifest.json, in the same directory
+pub fn manifest_path(output: &str) -> PathBuf {
+    Path::new(output).with_extension("manifest.json")
+}
+
+fn hex_color(color: Option<im::Rgb<u8>>) -> Value {
+    match color {
+        Some(im::Rgb([r, g, b])) => json!(format!("{:02x}{:02x}{:02x}", r, g, b)),
+        None                     => Value::Null,
+    }
+}
+
+// hashed as it's read, since a tiled input may not fit in memory
+// piped in, the input's gone by the time the manifest's written
+fn file_record(path: &str) -> io::Result<Value> {
+    if path == "-" {
+        return Ok(json!({ "path": path }));
+    }
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let bytes = io::copy(&mut file, &mut hasher)?;
+    let sha256: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(json!({
+        "path":   path,
+        "bytes":  bytes,
+        "sha256": sha256,
+    }))
+}
+
+fn points(points: &[V2]) -> Value {
+    points.iter().map(|p| json!([p.x, p.y])).collect()
+}
+
+fn options(opts: &Options) -> Value {
+    let mode = match opts.mode {
+        Mode::Solve     => "solve",
+        Mode::Partition => "partition",
+        Mode::Escape    => "escape",
+        Mode::Levels    => "levels",
+        Mode::Sequence  => "sequence",
+        Mode::Compare   => "compare",
+        Mode::Check     => "check",
+        Mode::Diff      => "diff",
+        Mode::SelfTest  => "self-test",
+    };
+    let weights = json!({
+        "length":    opts.weights.step as f64 / 1000.0,
+        "turns":     opts.weights.turn as f64 / 1000.0,
+        "clearance": opts.weights.crowding as f64 / 1000.0,
+    });
+    let floorplan = opts.floorplan.map(|fp| json!({
+        "threshold":  fp.threshold,
+        "despeckle":  fp.despeckle,
+        "thin_walls": fp.thin_walls,
+        "room_width": fp.room_width,
+    }));
+    let adaptive = opts.adaptive.map(|method| match method {
+        Adaptive::Mean { window, k }    => json!({ "method": "mean", "window": window, "k": k }),
+        Adaptive::Sauvola { window, k } => json!({ "method": "sauvola", "window": window, "k": k }),
+        Adaptive::Niblack { window, k } => json!({ "method": "niblack", "window": window, "k": k }),
+    });
+    let colors = json!({
+        "wall":          hex_color(opts.colors.wall),
+        "corridor":      hex_color(opts.colors.corridor),
+        "tolerance":     opts.colors.tolerance,
+        "clusters":      opts.clusters,
+        "wall_clusters": opts.wall_clusters,
+    });
+    let morph: Vec<Value> = opts.morph.iter()
+        .map(|step| json!({
+            "op":         format!("{:?}", step.op).to_lowercase(),
+            "radius":     step.radius,
+            "iterations": step.iterations,
+        }))
+        .collect();
+
+    json!({
+        "mode":   mode,
+        "config": opts.config,
+        "output": opts.output,
+        "start":  opts.start.map(|p| [p.x, p.y]),
+        "goal":   opts.goal.map(|p| [p.x, p.y]),
+        "snap":   opts.snap,
+        "seeds":  points(&opts.seeds),
+        "exits":  points(&opts.exits),
+        "delta":  opts.delta,
+        "start_level": opts.start_level,
+        "goal_level":  opts.goal_level,
+        "teleporters": opts.teleporters,
+        "teleport_cost": opts.teleport_cost,
+        "one_way":     opts.one_way,
+        "keys":        opts.keys,
+        "stairs": {
+            "up":        hex_color(Some(opts.stairs.up)),
+            "down":      hex_color(Some(opts.stairs.down)),
+            "tolerance": opts.stairs.tolerance,
+        },
+        "colors": colors,
+        "invert":      opts.invert,
+        "threshold":   opts.threshold,
+        "adaptive":    adaptive,
+        "flatten":     opts.flatten,
+        "rotate":      opts.rotate,
+        "deskew":      opts.deskew,
+        "floorplan":   floorplan,
+        "morph":       morph,
+        "join_walls":  opts.join_walls,
+        "mask_text":   opts.mask_text,
+        "crop":        opts.crop,
+        "seal_border": opts.seal_border,
+        "decompose":   if opts.best_decomposition { "best".to_string() }
+                       else { format!("{:?}", opts.extract.decomposition).to_lowercase() },
+        "min_passage": opts.extract.min_passage,
+        "tile":        opts.tile,
+        "graph_cache": opts.graph_cache,
+        "solver":      opts.solver.name(),
+        "algo":        opts.search.name(),
+        "objective":   opts.objective.name(),
+        "weights":     weights,
+        "full_search": opts.full_search,
+        "buckets":     opts.buckets,
+        "jobs":        opts.jobs,
+        "refine":      opts.refine,
+        "hints":       opts.hints,
+        "k_paths":     opts.k_paths,
+        "dead_ends":   opts.dead_ends,
+        "show_rects":  opts.show_rects,
+        "components":  opts.components,
+        "component_map": opts.component_map,
+        "skeleton":    opts.skeleton,
+        "openings":    opts.openings,
+        "path_only":   opts.path_only,
+        "indexed":     opts.indexed,
+        "no_render":   opts.no_render,
+        "longest":     opts.longest,
+        "random_seed": opts.random_seed,
+        "frames":      opts.frames,
+        "explore_maps": opts.explore_maps,
+        "race":        opts.race.map(|race| [race[0].name(), race[1].name()]),
+        "frame_every": opts.frame_every,
+        "theme":       opts.theme.name(),
+        "style": {
+            "path_color":    hex_color(Some(opts.style.path_color)),
+            "explore_color": hex_color(Some(opts.style.explore_color)),
+            "thickness":     opts.style.thickness,
+            "alpha":         opts.style.alpha,
+            "downscale":     opts.style.downscale,
+            "upscale":       opts.style.upscale,
+        },
+        "path_json":   opts.path_json,
+        "graph_json":  opts.graph_json,
+        "trace_json":  opts.trace_json,
+        "origin":      opts.origin.name(),
+    })
+}
+
+// everything needed to rerun and check a result: what went in, how it was configured, including
+// the values the defaults and estimates resolved to, and what came out
+pub struct Manifest<'a> {
+    pub args:    &'a [String],
+    pub opts:    &'a Options,
+    pub anchors: &'a [V2],
+    pub skew:    Option<f32>,
+    pub stats:   &'a Stats,
+}
+
+impl Manifest<'_> {
+    pub fn save(&self) -> io::Result<PathBuf> {
+        // a multi-level solve reads its levels instead of the input
+        let input = if self.opts.mode == Mode::Levels { Value::Null }
+                    else                              { file_record(&self.opts.input)? };
+        let levels: Vec<Value> = self.opts.levels.iter()
+            .map(|path| file_record(path))
+            .collect::<io::Result<_>>()?;
+
+        let manifest = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "args":    self.args,
+            "input":   input,
+            "levels":  levels,
+            "options": options(self.opts),
+            "anchors": points(self.anchors),
+            "skew":    self.skew,
+            "stats":   self.stats,
+        });
+
+        let path = manifest_path(&self.opts.output);
+        let text = serde_json::to_string_pretty(&manifest).map_err(io::Error::other)?;
+        fs::write(&path, text)?;
+        Ok(path)
+    }
+}
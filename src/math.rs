@@ -1,15 +1,60 @@
+use std::convert::TryFrom;
 
+use crate::error::Error;
+
+// 32 bits unless the wide-coords feature asks for 64, for stitched scans whose sums of
+// coordinates run past two billion
+#[cfg(not(feature = "wide-coords"))]
+pub type Coord = i32;
+#[cfg(feature = "wide-coords")]
+pub type Coord = i64;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct V2 {
-    pub x: i32,
-    pub y: i32
+    pub x: Coord,
+    pub y: Coord,
 }
 
 impl V2 {
-    pub const fn new(x: i32, y: i32) -> V2 {
+    pub const fn new(x: Coord, y: Coord) -> V2 {
         V2 { x, y }
     }
+
+    // widened, so that products of far-apart coordinates don't overflow
+    #[allow(clippy::unnecessary_cast)]
+    pub fn dot(self, rhs: V2) -> i64 {
+        self.x as i64 * rhs.x as i64 + self.y as i64 * rhs.y as i64
+    }
+
+    // steps to get here from the origin moving 4-connected
+    pub fn manhattan(self) -> Coord {
+        self.x.abs() + self.y.abs()
+    }
+
+    // steps to get here from the origin moving 8-connected
+    pub fn chebyshev(self) -> Coord {
+        self.x.abs().max(self.y.abs())
+    }
+
+    // the squared euclidean length, for comparing distances without a square root
+    pub fn length2(self) -> i64 {
+        self.dot(self)
+    }
+}
+
+// a width, height or pixel position from the image side as a coordinate. u32s past two billion
+// don't fit 32-bit coordinates, and rather than wrap round to negative positions that's an
+// error. infallible with wide-coords
+#[allow(clippy::unnecessary_fallible_conversions)]
+pub fn coord(n: u32) -> Result<Coord, Error> {
+    Coord::try_from(n).map_err(|_| Error::TooLarge(n))
+}
+
+// as coord(), for bounds only ever compared against coordinates, which can be taken as the
+// largest coordinate where they're past it
+#[allow(clippy::unnecessary_fallible_conversions)]
+pub fn coord_clamped(n: u32) -> Coord {
+    Coord::try_from(n).unwrap_or(Coord::MAX)
 }
 
 impl std::ops::Add for V2 {
@@ -26,6 +71,20 @@ impl std::ops::Sub for V2 {
     }
 }
 
+impl std::ops::Mul<Coord> for V2 {
+    type Output = V2;
+    fn mul(self, rhs: Coord) -> V2 {
+        V2 { x: self.x * rhs, y: self.y * rhs }
+    }
+}
+
+impl std::ops::Neg for V2 {
+    type Output = V2;
+    fn neg(self) -> V2 {
+        V2 { x: -self.x, y: -self.y }
+    }
+}
+
 // XXX rects are half-open
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Rect {
@@ -51,21 +110,63 @@ impl Rect {
         r
     }
 
-    pub fn width(&self) -> i32 {
+    pub fn width(&self) -> Coord {
         let w = self.maxs.x - self.mins.x;
         debug_assert!(w >= 0);
         w
     }
 
-    pub fn height(&self) -> i32 {
+    pub fn height(&self) -> Coord {
         let h =self.maxs.y - self.mins.y;
         debug_assert!(h >= 0);
         h
     }
 
-  //fn dims(&self) -> V2 {
-  //    V2::new(self.width(), self.height())
-  //}
+    // a no-op cast with wide-coords
+    #[allow(clippy::unnecessary_cast)]
+    pub fn area(&self) -> i64 {
+        self.width() as i64 * self.height() as i64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.maxs.x <= self.mins.x || self.maxs.y <= self.mins.y
+    }
+
+    pub fn dims(&self) -> V2 {
+        V2::new(self.width(), self.height())
+    }
+
+    // the middle pixel, or the one up and left of the middle where there's no single one
+    pub fn center(&self) -> V2 {
+        // rounded down rather than towards zero, so that it's up and left of the middle below zero too
+        V2::new((self.mins.x + self.maxs.x - 1).div_euclid(2), (self.mins.y + self.maxs.y - 1).div_euclid(2))
+    }
+
+    // grown by `margin` px on every side, or shrunk if it's negative. shrunk past nothing, it's
+    // left empty in the middle rather than turned inside out
+    pub fn expand(&self, margin: Coord) -> Rect {
+        let span = |lo: Coord, hi: Coord| {
+            if hi - lo + 2 * margin >= 0 { (lo - margin, hi + margin) }
+            else                         { let mid = lo + (hi - lo) / 2; (mid, mid) }
+        };
+        let (xmin, xmax) = span(self.mins.x, self.maxs.x);
+        let (ymin, ymax) = span(self.mins.y, self.maxs.y);
+        Rect { mins: V2::new(xmin, ymin), maxs: V2::new(xmax, ymax) }
+    }
+
+    pub fn shrink(&self, margin: Coord) -> Rect {
+        self.expand(-margin)
+    }
+
+    // the smallest rect holding both. an empty one holds nothing, so adds nothing
+    pub fn union(&self, other: Rect) -> Rect {
+        if self.is_empty()  { return other; }
+        if other.is_empty() { return *self; }
+        Rect {
+            mins: V2::new(self.mins.x.min(other.mins.x), self.mins.y.min(other.mins.y)),
+            maxs: V2::new(self.maxs.x.max(other.maxs.x), self.maxs.y.max(other.maxs.y)),
+        }
+    }
 
     pub fn intersect(&self, other: Rect) -> Rect {
         let xmin = self.mins.x.max(other.mins.x);
@@ -77,11 +178,176 @@ impl Rect {
         Rect { mins, maxs }
     }
 
+    // whether the two share any pixels
+    pub fn overlaps(&self, other: Rect) -> bool {
+        self.mins.x.max(other.mins.x) < self.maxs.x.min(other.maxs.x) &&
+        self.mins.y.max(other.mins.y) < self.maxs.y.min(other.maxs.y)
+    }
+
     pub fn contains(&self, p: V2) -> bool {
         p.x >= self.mins.x &&
         p.y >= self.mins.y &&
         p.x <  self.maxs.x &&
         p.y <  self.maxs.y
     }
+
+    // whether every pixel of `other` is in this one
+    pub fn contains_rect(&self, other: Rect) -> bool {
+        other.is_empty() || (
+            other.mins.x >= self.mins.x &&
+            other.mins.y >= self.mins.y &&
+            other.maxs.x <= self.maxs.x &&
+            other.maxs.y <= self.maxs.y)
+    }
+
+    // the top, bottom, left and right rows of pixels inside the rect. on a rect a pixel thin
+    // they're the same pixels more than once
+    pub fn sides(&self) -> [Segment; 4] {
+        [
+            Segment { start: self.mins, step: V2::new(1, 0), len: self.width() },
+            Segment { start: V2::new(self.mins.x, self.maxs.y - 1), step: V2::new(1, 0), len: self.width() },
+            Segment { start: self.mins, step: V2::new(0, 1), len: self.height() },
+            Segment { start: V2::new(self.maxs.x - 1, self.mins.y), step: V2::new(0, 1), len: self.height() },
+        ]
+    }
+
+    // the rows of pixels just outside the top, bottom, left and right, without the corners:
+    // the pixels a neighbour sharing a side would have to touch
+    pub fn outer_sides(&self) -> [Segment; 4] {
+        [
+            Segment { start: V2::new(self.mins.x, self.mins.y - 1), step: V2::new(1, 0), len: self.width() },
+            Segment { start: V2::new(self.mins.x, self.maxs.y), step: V2::new(1, 0), len: self.width() },
+            Segment { start: V2::new(self.mins.x - 1, self.mins.y), step: V2::new(0, 1), len: self.height() },
+            Segment { start: V2::new(self.maxs.x, self.mins.y), step: V2::new(0, 1), len: self.height() },
+        ]
+    }
+
+    // every pixel, row by row
+    pub fn points(&self) -> impl Iterator<Item = V2> {
+        let (xs, ys) = (self.mins.x .. self.maxs.x, self.mins.y .. self.maxs.y);
+        ys.flat_map(move |y| xs.clone().map(move |x| V2::new(x, y)))
+    }
+}
+
+// a straight run of `len` pixels from `start`, each `step` on from the last
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub start: V2,
+    pub step:  V2,
+    pub len:   Coord,
+}
+
+impl Segment {
+    pub fn points(self) -> impl Iterator<Item = V2> {
+        let Segment { start, step, len } = self;
+        (0 .. len).map(move |i| start + step * i)
+    }
+}
+
+
+// the corner coordinates count from. images count y down from the top; robotics frames, ROS
+// maps for one, count it up from the bottom
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Origin {
+    #[default]
+    TopLeft,
+    BottomLeft,
+}
+
+impl Origin {
+    // as --origin spells it
+    pub fn name(self) -> &'static str {
+        match self {
+            Origin::TopLeft    => "top-left",
+            Origin::BottomLeft => "bottom-left",
+        }
+    }
+
+    // a pixel of an image `height` high, from image coordinates into this frame
+    pub fn point(self, p: V2, height: Coord) -> V2 {
+        match self {
+            Origin::TopLeft    => p,
+            Origin::BottomLeft => V2::new(p.x, height - 1 - p.y),
+        }
+    }
+
+    // a rect of pixels, still half-open, so its bounds swap ends as well as flipping
+    pub fn rect(self, r: Rect, height: Coord) -> Rect {
+        match self {
+            Origin::TopLeft    => r,
+            Origin::BottomLeft => Rect::new_unchecked(
+                V2::new(r.mins.x, height - r.maxs.y),
+                V2::new(r.maxs.x, height - r.mins.y)),
+        }
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x0: Coord, y0: Coord, x1: Coord, y1: Coord) -> Rect {
+        Rect::new_unchecked(V2::new(x0, y0), V2::new(x1, y1))
+    }
+
+    #[test]
+    fn rects_touching_at_an_edge_share_no_pixels() {
+        let (left, right) = (rect(0, 0, 4, 3), rect(4, 0, 7, 3));
+        assert!(!left.overlaps(right) && !right.overlaps(left));
+        assert!(left.intersect(right).is_empty());
+        assert_eq!(left.union(right), rect(0, 0, 7, 3));
+        assert!(!left.contains(V2::new(4, 1)) && right.contains(V2::new(4, 1)));
+        assert!(!left.contains_rect(right));
+        // corner to corner, likewise
+        assert!(!left.overlaps(rect(4, 3, 6, 5)));
+        assert!(left.overlaps(rect(3, 2, 6, 5)));
+
+        // the rows just outside one are the other's own side
+        let [.., outer_right] = left.outer_sides();
+        let [.., inner_left, _] = right.sides();
+        assert_eq!(outer_right.points().collect::<Vec<_>>(), inner_left.points().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn empty_rects_hold_and_add_nothing() {
+        let (flat, hall) = (rect(2, 5, 9, 5), rect(0, 0, 4, 3));
+        assert!(flat.is_empty() && flat.area() == 0);
+        assert_eq!(flat.points().count(), 0);
+        assert_eq!(hall.union(flat), hall);
+        assert_eq!(flat.union(hall), hall);
+        assert!(hall.contains_rect(flat));
+        assert!(!flat.overlaps(hall) && !hall.overlaps(flat));
+        assert!(!flat.contains(V2::new(2, 5)));
+
+        // shrunk past nothing, left empty in the middle
+        let shrunk = hall.shrink(3);
+        assert!(shrunk.is_empty());
+        assert_eq!(shrunk, rect(2, 1, 2, 1));
+        assert_eq!(hall.shrink(1), rect(1, 1, 3, 2));
+    }
+
+    #[test]
+    fn negative_coordinates_behave_as_positive_ones() {
+        let r = rect(-4, -3, 0, 0);
+        assert_eq!((r.width(), r.height(), r.area()), (4, 3, 12));
+        assert_eq!(r.center(), V2::new(-3, -2));
+        assert_eq!(rect(2, 3, 6, 6).center(), V2::new(3, 4));
+        assert!(r.contains(V2::new(-4, -3)) && !r.contains(V2::new(0, -1)));
+        assert_eq!(r.points().next(), Some(V2::new(-4, -3)));
+        assert_eq!(r.points().last(), Some(V2::new(-1, -1)));
+
+        assert_eq!(r.expand(2), rect(-6, -5, 2, 2));
+        assert_eq!(r.union(rect(1, 1, 2, 2)), rect(-4, -3, 2, 2));
+        assert_eq!(r.intersect(rect(-2, -5, 5, -1)), rect(-2, -3, 0, -1));
+        assert!(r.overlaps(rect(-1, -1, 3, 3)));
+        assert_eq!(Rect::new(V2::new(0, 0), V2::new(-4, -3)), r);
+    }
+
+    #[cfg(not(feature = "wide-coords"))]
+    #[test]
+    fn sizes_past_the_coordinates_are_refused_or_clamped() {
+        assert_eq!(coord(i32::MAX as u32), Ok(i32::MAX));
+        assert_eq!(coord(i32::MAX as u32 + 1), Err(Error::TooLarge(i32::MAX as u32 + 1)));
+        assert_eq!(coord_clamped(u32::MAX), i32::MAX);
+    }
+}
@@ -0,0 +1,112 @@
+// one-way passages, marked on a map the size of the maze in a colour for each way: red for
+// right, green for down, blue for left and yellow for up, anything else meaning nothing. the
+// marked part of each space is split off into a space of its own, which can't be crossed into or
+// out of against its arrow; so a corridor marked across its width flows one way from there,
+// while stepping across it sideways is left alone.
+
+use {
+    crate::{
+        error::Error,
+        math::*,
+        graph::{Edge, EdgeSetGraph, Graph, HashMap, HashSet, NodeID},
+        image_graph::{split_marked, NodeData, PortalSegment},
+        preprocess::color_distance,
+    },
+    image as im,
+};
+
+// euclidean distance in RGB space within which a pixel is an arrow's colour
+const TOLERANCE: f32 = 64.0;
+
+pub const ARROWS: [(im::Rgb<u8>, V2); 4] = [
+    (im::Rgb([255, 0, 0]),   V2::new(1, 0)),
+    (im::Rgb([0, 255, 0]),   V2::new(0, 1)),
+    (im::Rgb([0, 0, 255]),   V2::new(-1, 0)),
+    (im::Rgb([255, 255, 0]), V2::new(0, -1)),
+];
+
+// the ways marked at `p`, as bits in ARROWS' order
+fn arrows_at(map: &im::RgbImage, p: V2) -> u8 {
+    let pixel = *map.get_pixel(p.x as u32, p.y as u32);
+    ARROWS.iter().enumerate()
+        .filter(|(_, (color, _))| color_distance(pixel, *color) <= TOLERANCE)
+        .fold(0, |arrows, (bit, _)| arrows | 1 << bit)
+}
+
+fn against(arrows: u8, step: V2) -> bool {
+    ARROWS.iter().enumerate().any(|(bit, (_, arrow))| arrows & 1 << bit != 0 && *arrow == -step)
+}
+
+// splits off the marked part of every space with marks in it, then makes one-way every edge the
+// marks turn back one way and drops those they turn back both. see split_marked() for `start`
+// and `goal`. returns how many edges are one-way, and how many were closed.
+pub fn restrict(graph: &mut EdgeSetGraph<NodeData>, map: &im::RgbImage, start: V2, goal: V2)
+    -> Result<(usize, usize), Error>
+{
+    split_marked(graph, start, goal, |p| arrows_at(map, p) != 0)?;
+    let marked: HashMap<NodeID, u8> = graph.nodes().iter()
+        .map(|(id, data)| (*id, data.rect.points().fold(0, |arrows, p| arrows | arrows_at(map, p))))
+        .filter(|(_, arrows)| *arrows != 0)
+        .collect();
+
+    let mut closed: HashSet<Edge> = HashSet::default();
+    for edge in graph.edges.iter().copied() {
+        let arrows = marked.get(&edge.min).copied().unwrap_or(0) | marked.get(&edge.max).copied().unwrap_or(0);
+        if arrows == 0 {
+            continue;
+        }
+        let (r, s) = (graph.get_node(edge.min).rect, graph.get_node(edge.max).rect);
+        let Some(portal) = PortalSegment::between(r, s) else { continue };
+
+        // the step from min's space into max's
+        let step = match (portal.is_vertical(), r.maxs.x == portal.a.x, r.maxs.y == portal.a.y) {
+            (true, true, _)   => V2::new(1, 0),
+            (true, false, _)  => V2::new(-1, 0),
+            (false, _, true)  => V2::new(0, 1),
+            (false, _, false) => V2::new(0, -1),
+        };
+        match (against(arrows, step), against(arrows, -step)) {
+            (false, false) => { }
+            (false, true)  => { graph.one_way.insert(edge, edge.max); }
+            (true, false)  => { graph.one_way.insert(edge, edge.min); }
+            (true, true)   => { closed.insert(edge); }
+        }
+    }
+    graph.edges.retain(|edge| !closed.contains(edge));
+    graph.one_way.retain(|edge, _| !closed.contains(edge));
+    Ok((graph.one_way.len(), closed.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{image_graph::{extract_graph, ExtractOptions}, preprocess::{CLEAR, WALL}, refine},
+    };
+
+    #[test]
+    fn one_way_passages_are_only_taken_their_way() {
+        // a ring of corridors, the top one marked to flow left
+        let mut image = im::GrayImage::from_pixel(31, 21, WALL);
+        let mut map = im::RgbImage::from_pixel(31, 21, im::Rgb([255, 255, 255]));
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            if (2 .. 29).contains(&x) && (2 .. 19).contains(&y) && !((5 .. 26).contains(&x) && (5 .. 16).contains(&y)) {
+                *pixel = CLEAR;
+            }
+        }
+        for (x, y) in (12 .. 18).flat_map(|x| (2 .. 5).map(move |y| (x, y))) {
+            map.put_pixel(x, y, im::Rgb([0, 0, 255]));
+        }
+
+        // from the bottom left into the marks, the long way round; and back out the short way
+        let solve = |start: V2, goal: V2| {
+            let mut graph = extract_graph(&image, start, goal, &ExtractOptions::default()).unwrap();
+            assert_eq!(restrict(&mut graph, &map, start, goal).unwrap(), (2, 0));
+            let solution = graph.into_adjacency_graph().contract().into_dijkstra().solution().unwrap();
+            refine::refine_path(&solution.rects, start, goal)
+        };
+        let (corner, top) = (V2::new(3, 17), V2::new(14, 3));
+        assert!(solve(corner, top).iter().any(|p| p.x > 25));
+        assert!(solve(top, corner).iter().all(|p| p.x < 25));
+    }
+}
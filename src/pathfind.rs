@@ -0,0 +1,327 @@
+// searches from one space to another behind a common trait, so callers can swap one for another.
+// all of them run over the adjacency graph as given, contracted or not, and hand back the path
+// with any contracted corridors filled back in.
+
+use {
+    crate::{
+        math::*,
+        graph::{AdjacencyGraph, DijkstraGraph, Edge, Graph, HashMap, HashSet, NodeID, SearchStats},
+        image_graph::NodeData,
+    },
+    std::{cmp::Reverse, collections::VecDeque},
+    priority_queue::PriorityQueue,
+};
+
+// a route from start to goal, as every search here returns it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+    // every space from start to goal, inclusive
+    pub nodes:      Vec<NodeID>,
+    // each of those spaces' rect, in the same order
+    pub rects:      Vec<Rect>,
+    // in the graph's weights, which count spaces stepped into unless it was built weighted
+    pub cost:       i32,
+    pub stats:      SearchStats,
+    // the search nodes taken off the open list, in order
+    pub expanded:   Vec<NodeID>,
+}
+
+pub type Steps<'a> = std::iter::Zip<
+    std::iter::Copied<std::slice::Iter<'a, NodeID>>,
+    std::iter::Copied<std::slice::Iter<'a, Rect>>>;
+
+impl Solution {
+    // the route's spaces along with their rects, start first
+    pub fn iter(&self) -> Steps<'_> {
+        self.nodes.iter().copied().zip(self.rects.iter().copied())
+    }
+
+    pub fn start(&self) -> NodeID { self.nodes[0] }
+    pub fn goal(&self) -> NodeID { self.nodes[self.nodes.len() - 1] }
+}
+
+impl<'a> IntoIterator for &'a Solution {
+    type Item = (NodeID, Rect);
+    type IntoIter = Steps<'a>;
+    fn into_iter(self) -> Steps<'a> {
+        self.iter()
+    }
+}
+
+impl DijkstraGraph<NodeData> {
+    // the route to the goal, or None where it was never reached
+    pub fn solution(&self) -> Option<Solution> {
+        let cost = self.goal_distance();
+        if cost == i32::MAX {
+            return None;
+        }
+
+        let nodes = self.path_to(self.goal());
+        let rects = nodes.iter().map(|id| self.get_node(*id).rect).collect();
+        Some(Solution { nodes, rects, cost, stats: self.stats, expanded: self.order.clone() })
+    }
+}
+
+pub trait PathFinder {
+    fn name(&self) -> &'static str;
+
+    // None if the goal can't be reached from the start
+    fn solve(&self, graph: &AdjacencyGraph<NodeData>, start: NodeID, goal: NodeID) -> Option<Solution>;
+}
+
+// shortest by weight
+pub struct Dijkstra;
+
+// shortest by weight, steered by a bound on the steps left that never overestimates
+pub struct AStar;
+
+// fewest adjacencies, ignoring weights. on an uncontracted graph every weight is one, so this is
+// as short as dijkstra's.
+pub struct BreadthFirst;
+
+// straight for whatever looks closest to the goal. quick, but there's no telling how short
+pub struct GreedyBestFirst;
+
+impl PathFinder for Dijkstra {
+    fn name(&self) -> &'static str { "dijkstra" }
+
+    fn solve(&self, graph: &AdjacencyGraph<NodeData>, start: NodeID, goal: NodeID) -> Option<Solution> {
+        best_first(graph, start, goal, |dist, _| dist)
+    }
+}
+
+impl PathFinder for AStar {
+    fn name(&self) -> &'static str { "astar" }
+
+    fn solve(&self, graph: &AdjacencyGraph<NodeData>, start: NodeID, goal: NodeID) -> Option<Solution> {
+        // every space stepped through covers at most the largest extent of any, so the gap to the
+        // goal needs at least that many of them to cross
+        let extent = graph.nodes().values()
+            .map(|data| data.rect.width().max(data.rect.height()))
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let target = graph.com.nodes.get(&goal)?.rect;
+        let steps = |id: NodeID| {
+            let gap = rect_gap(graph.get_node(id).rect, target);
+            cost((gap + extent - 1) / extent)
+        };
+        best_first(graph, start, goal, |dist, id| dist.saturating_add(steps(id)))
+    }
+}
+
+impl PathFinder for BreadthFirst {
+    fn name(&self) -> &'static str { "bfs" }
+
+    fn solve(&self, graph: &AdjacencyGraph<NodeData>, start: NodeID, goal: NodeID) -> Option<Solution> {
+        if !graph.com.nodes.contains_key(&start) || !graph.com.nodes.contains_key(&goal) {
+            return None;
+        }
+
+        let mut preds: HashMap<NodeID, NodeID> = HashMap::default();
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        preds.insert(start, start);
+
+        let mut stats = SearchStats { max_frontier: 1, ..SearchStats::default() };
+        let mut expanded = Vec::new();
+        while let Some(u) = queue.pop_front() {
+            stats.expansions += 1;
+            expanded.push(u);
+            if u == goal {
+                return Some(walk_back(graph, &preds, start, goal, stats, expanded));
+            }
+
+            for v in graph.neighbors(u) {
+                if graph.com.nodes.contains_key(&v) && !preds.contains_key(&v) {
+                    preds.insert(v, u);
+                    queue.push_back(v);
+                    stats.relaxations += 1;
+                }
+            }
+            stats.saw_frontier(queue.len());
+        }
+        None
+    }
+}
+
+impl PathFinder for GreedyBestFirst {
+    fn name(&self) -> &'static str { "greedy" }
+
+    fn solve(&self, graph: &AdjacencyGraph<NodeData>, start: NodeID, goal: NodeID) -> Option<Solution> {
+        let target = graph.com.nodes.get(&goal)?.centroid;
+        let remaining = |id: NodeID| {
+            let d = graph.get_node(id).centroid - target;
+            cost(d.manhattan())
+        };
+        best_first(graph, start, goal, |_, id| remaining(id))
+    }
+}
+
+// a distance in px as a path cost, which is 32 bits even with wide-coords
+#[allow(clippy::unnecessary_cast)]
+fn cost(px: Coord) -> i32 {
+    px.min(i32::MAX as Coord) as i32
+}
+
+// the pixels between two rects along whichever axis they're further apart on; 0 if they touch
+fn rect_gap(a: Rect, b: Rect) -> Coord {
+    let x = (b.mins.x - a.maxs.x).max(a.mins.x - b.maxs.x).max(0);
+    let y = (b.mins.y - a.maxs.y).max(a.mins.y - b.maxs.y).max(0);
+    x.max(y)
+}
+
+// expands nodes lowest `priority` first, given each one's distance so far. with a priority that
+// never overestimates what's left, the first time the goal comes off the queue it's by a
+// shortest path.
+fn best_first(
+    graph:    &AdjacencyGraph<NodeData>,
+    start:    NodeID,
+    goal:     NodeID,
+    priority: impl Fn(i32, NodeID) -> i32)
+    -> Option<Solution>
+{
+    if !graph.com.nodes.contains_key(&start) || !graph.com.nodes.contains_key(&goal) {
+        return None;
+    }
+
+    let mut dists: HashMap<NodeID, i32> = HashMap::default();
+    let mut preds: HashMap<NodeID, NodeID> = HashMap::default();
+    let mut queue: PriorityQueue<NodeID, Reverse<i32>> = PriorityQueue::new();
+    dists.insert(start, 0);
+    queue.push(start, Reverse(priority(0, start)));
+
+    let mut stats = SearchStats { max_frontier: 1, ..SearchStats::default() };
+    let mut expanded = Vec::new();
+    while let Some((u, _)) = queue.pop() {
+        stats.expansions += 1;
+        expanded.push(u);
+        if u == goal {
+            return Some(walk_back(graph, &preds, start, goal, stats, expanded));
+        }
+
+        let u_dist = dists[&u];
+        for v in graph.neighbors(u) {
+            if !graph.com.nodes.contains_key(&v) {
+                continue;
+            }
+
+            let new_dist = u_dist.saturating_add(graph.weight(u, v));
+            if dists.get(&v).is_none_or(|dist| new_dist < *dist) {
+                dists.insert(v, new_dist);
+                preds.insert(v, u);
+                queue.push(v, Reverse(priority(new_dist, v)));
+                stats.relaxations += 1;
+            }
+        }
+        stats.saw_frontier(queue.len());
+    }
+    None
+}
+
+// the path to `goal` by `preds`, with each contracted adjacency along it opened back up into
+// the corridor it stands for
+pub(crate) fn walk_back(
+    graph:      &AdjacencyGraph<NodeData>,
+    preds:      &HashMap<NodeID, NodeID>,
+    start:      NodeID,
+    goal:       NodeID,
+    stats:      SearchStats,
+    expanded:   Vec<NodeID>)
+    -> Solution
+{
+    let mut junctions = vec![goal];
+    let mut at = goal;
+    while at != start {
+        at = preds[&at];
+        junctions.push(at);
+    }
+    junctions.reverse();
+
+    let mut nodes = vec![start];
+    let mut cost = 0;
+    for pair in junctions.windows(2) {
+        let (u, v) = (pair[0], pair[1]);
+        cost += graph.weight(u, v);
+        if let Some(index) = graph.links.get(&Edge::new(u, v)) {
+            let chain = &graph.chains[*index];
+            if chain.from == u { nodes.extend(chain.via.iter().copied()); }
+            else               { nodes.extend(chain.via.iter().rev().copied()); }
+        }
+        nodes.push(v);
+    }
+
+    let rects = nodes.iter().map(|id| graph.get_node(*id).rect).collect();
+    Solution { nodes, rects, cost, stats, expanded }
+}
+
+// the spaces a search looked at, given the nodes it expanded: those, and along every corridor
+// contraction folded out of them, which relaxing the link walks the length of
+pub fn explored<Data>(graph: &AdjacencyGraph<Data>, expanded: &[NodeID]) -> HashSet<NodeID> {
+    let mut explored: HashSet<NodeID> = expanded.iter().copied().collect();
+    for chain in graph.chains.iter() {
+        if explored.contains(&chain.from) || explored.contains(&chain.to) {
+            explored.extend(chain.via.iter().copied());
+        }
+    }
+    explored
+}
+
+// a found path in the shape dijkstra leaves its results, for everything that draws or reports
+// from those. only the nodes on the path get a distance; the expansion order is the path itself.
+pub fn into_dijkstra_graph<Data: Copy>(graph: AdjacencyGraph<Data>, path: &Solution) -> DijkstraGraph<Data> {
+    // every step along an expanded path is between adjacent spaces, never over a chain
+    let mut dist = 0;
+    let mut dists = HashMap::default();
+    for (i, id) in path.nodes.iter().enumerate() {
+        if i > 0 {
+            dist = graph.weight(path.nodes[i - 1], *id).saturating_add(dist);
+        }
+        dists.insert(*id, dist);
+    }
+    let paths = path.nodes.windows(2).map(|pair| (pair[1], pair[0])).collect();
+    let origins = path.nodes.first().map(|start| (*start, *start)).into_iter().collect();
+    DijkstraGraph { inner: graph, dists, paths, origins, order: path.nodes.clone(), stats: path.stats }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{graph::tests::pillared_hall, image_graph::{extract_graph, ExtractOptions}, tremaux::Tremaux},
+    };
+
+    #[test]
+    fn path_finders_agree_on_a_pillared_hall() {
+        let image = pillared_hall();
+        let (start, goal) = (V2::new(1, 1), V2::new(16, 11));
+        let extracted = extract_graph(&image, start, goal, &ExtractOptions::default()).unwrap();
+        let full = extracted.clone().into_adjacency_graph();
+        let contracted = || extracted.clone().prune().into_adjacency_graph().contract();
+        let shortest = contracted().into_dijkstra().solution().unwrap().cost;
+        let graph = contracted();
+
+        let finders: [&dyn PathFinder; 5] = [&Dijkstra, &AStar, &BreadthFirst, &GreedyBestFirst,
+            &Tremaux::default()];
+        for finder in finders.iter() {
+            let path = finder.solve(&graph, graph.start(), graph.goal()).unwrap();
+            assert_eq!(path.nodes.first(), Some(&graph.start()), "{}", finder.name());
+            assert_eq!(path.nodes.last(), Some(&graph.goal()), "{}", finder.name());
+            assert!(path.nodes.windows(2).all(|pair| full.neighbors(pair[0]).any(|v| v == pair[1])),
+                "{} steps between spaces that don't touch", finder.name());
+            assert_eq!(path.cost as usize, path.nodes.len() - 1, "{}", finder.name());
+            assert!(path.iter().all(|(id, rect)| graph.get_node(id).rect == rect), "{}", finder.name());
+
+            // each expansion recorded once, ending on the goal, and everything on the path explored
+            assert_eq!(path.expanded.len(), path.stats.expansions, "{}", finder.name());
+            assert_eq!(path.expanded.last(), Some(&graph.goal()), "{}", finder.name());
+            let explored = explored(&graph, &path.expanded);
+            assert!(path.nodes.iter().all(|id| explored.contains(id)), "{}", finder.name());
+
+            match finder.name() {
+                "dijkstra" | "astar" => assert_eq!(path.cost, shortest, "{}", finder.name()),
+                _                    => assert!(path.cost >= shortest, "{}", finder.name()),
+            }
+        }
+    }
+}
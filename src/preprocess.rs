@@ -0,0 +1,848 @@
+use {
+    crate::math::*,
+    image as im,
+    std::collections::VecDeque,
+};
+
+pub const CLEAR: im::Luma<u8> = im::Luma([255; 1]);
+pub const WALL:  im::Luma<u8> = im::Luma([0; 1]);
+
+// everything at or above `level` becomes clear, everything else wall
+pub fn threshold(image: &im::GrayImage, level: u8) -> im::GrayImage {
+    let mut out = image.clone();
+    for pixel in out.pixels_mut() {
+        *pixel = if pixel.0[0] >= level { CLEAR } else { WALL };
+    }
+    out
+}
+
+// how a threshold is picked from the mean and spread of the window round each pixel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Adaptive {
+    // the mean, less a fraction k of it. cheapest, and steady on line drawings of even weight
+    Mean { window: u32, k: f32 },
+    // mean plus k standard deviations. picks up faint walls, and noise in blank paper with them
+    Niblack { window: u32, k: f32 },
+    // the mean, lowered where the window barely varies. keeps blank paper clear
+    Sauvola { window: u32, k: f32 },
+}
+
+impl Adaptive {
+    pub fn mean(window: u32) -> Adaptive { Adaptive::Mean { window, k: 0.15 } }
+    pub fn niblack(window: u32) -> Adaptive { Adaptive::Niblack { window, k: -0.2 } }
+    pub fn sauvola(window: u32) -> Adaptive { Adaptive::Sauvola { window, k: 0.34 } }
+
+    pub fn window(&self) -> u32 {
+        match *self {
+            Adaptive::Mean { window, .. } | Adaptive::Niblack { window, .. } | Adaptive::Sauvola { window, .. } => window,
+        }
+    }
+}
+
+// sums of values and squared values over every rect from the origin, a row and column of zeros
+// ahead, so any window's can be had from four corners
+struct Integral {
+    sums:    Vec<f64>,
+    squares: Vec<f64>,
+    width:   usize,
+}
+
+impl Integral {
+    fn new(image: &im::GrayImage) -> Integral {
+        let (w, h) = (image.width() as usize, image.height() as usize);
+        let width = w + 1;
+        let mut sums = vec![0.0; width * (h + 1)];
+        let mut squares = vec![0.0; width * (h + 1)];
+        for y in 0 .. h {
+            let (mut row, mut row_squares) = (0.0, 0.0);
+            for x in 0 .. w {
+                let v = image.get_pixel(x as u32, y as u32).0[0] as f64;
+                row += v;
+                row_squares += v * v;
+                let at = (y + 1) * width + x + 1;
+                sums[at] = sums[at - width] + row;
+                squares[at] = squares[at - width] + row_squares;
+            }
+        }
+        Integral { sums, squares, width }
+    }
+
+    // the mean and standard deviation over [x0, x1) by [y0, y1)
+    fn stats(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> (f64, f64) {
+        let total = |table: &[f64]| table[y1 * self.width + x1] - table[y0 * self.width + x1]
+            - table[y1 * self.width + x0] + table[y0 * self.width + x0];
+        let n = ((x1 - x0) * (y1 - y0)) as f64;
+        let mean = total(&self.sums) / n;
+        let variance = (total(&self.squares) / n - mean * mean).max(0.0);
+        (mean, variance.sqrt())
+    }
+}
+
+// binarizes against a threshold worked out afresh round every pixel, for photographed pages
+// whose lighting falls away toward the spine. walls bled grey in a shadow are still darker than
+// the paper near them, even where they're lighter than paper elsewhere.
+pub fn adaptive_threshold(image: &im::GrayImage, method: Adaptive) -> im::GrayImage {
+    let (w, h) = (image.width() as usize, image.height() as usize);
+    let integral = Integral::new(image);
+    let half = (method.window() / 2) as usize;
+
+    let mut out = im::GrayImage::new(image.width(), image.height());
+    for y in 0 .. h {
+        let (y0, y1) = (y.saturating_sub(half), (y + half + 1).min(h));
+        for x in 0 .. w {
+            let (x0, x1) = (x.saturating_sub(half), (x + half + 1).min(w));
+            let (mean, deviation) = integral.stats(x0, y0, x1, y1);
+            let level = match method {
+                Adaptive::Mean { k, .. }    => mean * (1.0 - k as f64),
+                Adaptive::Niblack { k, .. } => mean + k as f64 * deviation,
+                Adaptive::Sauvola { k, .. } => mean * (1.0 + k as f64 * (deviation / 128.0 - 1.0)),
+            };
+            let v = image.get_pixel(x as u32, y as u32).0[0] as f64;
+            // a window all one value says nothing locally, so it goes by how light it is
+            let clear = v > level || (v == level && v >= 128.0);
+            out.put_pixel(x as u32, y as u32, if clear { CLEAR } else { WALL });
+        }
+    }
+    out
+}
+
+// the brightest, or darkest, value within `radius` of each pixel, along one axis at a time
+fn extreme_filter(image: &im::GrayImage, radius: u32, brightest: bool) -> im::GrayImage {
+    let (w, h) = (image.width() as usize, image.height() as usize);
+    let mut values: Vec<u8> = image.pixels().map(|pixel| pixel.0[0]).collect();
+    let beats = |a: u8, b: u8| if brightest { a >= b } else { a <= b };
+
+    // one line of `len` values `stride` apart from `first`, kept as a queue of indices whose
+    // values only get less extreme, so the front is always the window's most
+    let sweep = |values: &mut [u8], first: usize, stride: usize, len: usize| {
+        let line: Vec<u8> = (0 .. len).map(|i| values[first + i * stride]).collect();
+        let radius = radius as usize;
+        let mut window: VecDeque<usize> = VecDeque::new();
+        let mut next = 0;
+        for i in 0 .. len {
+            while next < len && next <= i + radius {
+                while window.back().is_some_and(|j| beats(line[next], line[*j])) {
+                    window.pop_back();
+                }
+                window.push_back(next);
+                next += 1;
+            }
+            while window.front().is_some_and(|j| j + radius < i) {
+                window.pop_front();
+            }
+            values[first + i * stride] = line[window[0]];
+        }
+    };
+
+    for y in 0 .. h {
+        sweep(&mut values, y * w, 1, w);
+    }
+    for x in 0 .. w {
+        sweep(&mut values, x, w, h);
+    }
+    im::GrayImage::from_raw(w as u32, h as u32, values).unwrap()
+}
+
+// divides out the lighting. the paper's brightness is estimated by a closing: spreading the
+// lightest values `radius` px round swallows every wall narrower than that, and spreading the
+// darkest back as far puts the shadows' edges back where they were. what's left is as if the
+// page were lit evenly.
+pub fn flatten_illumination(image: &im::GrayImage, radius: u32) -> im::GrayImage {
+    let paper = extreme_filter(&extreme_filter(image, radius, true), radius, false);
+
+    let mut out = image.clone();
+    for (pixel, paper) in out.pixels_mut().zip(paper.pixels()) {
+        let (v, paper) = (pixel.0[0] as f64, paper.0[0] as f64);
+        let flat = if paper > 0.0 { (v * 255.0 / paper).min(255.0) } else { 0.0 };
+        *pixel = im::Luma([flat.round() as u8]);
+    }
+    out
+}
+
+// the level best splitting the image's values in two, by otsu's method: the one leaving the
+// least spread within each side
+pub fn otsu_level(image: &im::GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in image.pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    let sum: f64 = histogram.iter().enumerate().map(|(v, n)| v as f64 * *n as f64).sum();
+
+    let (mut below, mut below_sum) = (0u64, 0.0);
+    let (mut best, mut best_between) = (0, -1.0);
+    for (v, n) in histogram.iter().enumerate() {
+        below += n;
+        below_sum += v as f64 * *n as f64;
+        let above = total - below;
+        if below == 0 || above == 0 {
+            continue;
+        }
+
+        let mean_below = below_sum / below as f64;
+        let mean_above = (sum - below_sum) / above as f64;
+        let between = below as f64 * above as f64 * (mean_below - mean_above).powi(2);
+        if between > best_between {
+            best = v;
+            best_between = between;
+        }
+    }
+    // the split is after `best`, and threshold() keeps values at or above its level
+    (best + 1).min(255) as u8
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ColorClasses {
+    pub wall:      Option<im::Rgb<u8>>,
+    pub corridor:  Option<im::Rgb<u8>>,
+    // euclidean distance in RGB space
+    pub tolerance: f32,
+}
+
+impl Default for ColorClasses {
+    fn default() -> ColorClasses {
+        ColorClasses { wall: None, corridor: None, tolerance: 64.0 }
+    }
+}
+
+pub(crate) fn color_distance(a: im::Rgb<u8>, b: im::Rgb<u8>) -> f32 {
+    a.0.iter().zip(b.0.iter())
+        .map(|(a, b)| (*a as f32 - *b as f32).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
+
+// how far apart a pixel's channels are: 0 for greys, black and white
+pub fn chroma(pixel: im::Rgb<u8>) -> u8 {
+    let im::Rgb([r, g, b]) = pixel;
+    r.max(g).max(b) - r.min(g).min(b)
+}
+
+// the 4-connected blobs of pixels at least `min_chroma` coloured, each of pixels within
+// `tolerance` of its first one's colour, with that colour
+pub fn color_blobs(image: &im::RgbImage, min_chroma: u8, tolerance: f32) -> Vec<(im::Rgb<u8>, Vec<V2>)> {
+    let (width, height) = image.dimensions();
+    let mut seen = vec![false; width as usize * height as usize];
+    let index = |p: V2| p.y as usize * width as usize + p.x as usize;
+    let bounds = Rect::new_unchecked(V2::new(0, 0), V2::new(coord_clamped(width), coord_clamped(height)));
+
+    let mut blobs = Vec::new();
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let first = V2::new(x as Coord, y as Coord);
+        if seen[index(first)] || chroma(*pixel) < min_chroma {
+            continue;
+        }
+
+        let mut blob = vec![first];
+        let mut stack = vec![first];
+        seen[index(first)] = true;
+        while let Some(p) = stack.pop() {
+            for d in [V2::new(1, 0), V2::new(-1, 0), V2::new(0, 1), V2::new(0, -1)] {
+                let next = p + d;
+                if !bounds.contains(next) || seen[index(next)] {
+                    continue;
+                }
+                let near = *image.get_pixel(next.x as u32, next.y as u32);
+                if chroma(near) >= min_chroma && color_distance(near, *pixel) <= tolerance {
+                    seen[index(next)] = true;
+                    blob.push(next);
+                    stack.push(next);
+                }
+            }
+        }
+        blobs.push((*pixel, blob));
+    }
+    blobs
+}
+
+// light for dark, for mazes drawn as light corridors on a dark ground
+pub fn invert(image: &im::GrayImage) -> im::GrayImage {
+    let mut out = image.clone();
+    for pixel in out.pixels_mut() {
+        pixel.0[0] = 255 - pixel.0[0];
+    }
+    out
+}
+
+// binarizes a colour image by what the walls and/or corridors look like. with only a wall colour,
+// anything near it is wall; with only a corridor colour, anything near it is clear; with both,
+// a pixel is clear when it's near the corridor colour and nearer to it than to the wall colour.
+pub fn classify_colors(image: &im::RgbImage, classes: &ColorClasses) -> im::GrayImage {
+    let tol = classes.tolerance;
+    let is_clear = |pixel: im::Rgb<u8>| match (classes.wall, classes.corridor) {
+        (Some(wall), None)     => color_distance(pixel, wall) > tol,
+        (None, Some(corridor)) => color_distance(pixel, corridor) <= tol,
+        (Some(wall), Some(corridor)) => {
+            let to_corridor = color_distance(pixel, corridor);
+            to_corridor <= tol && to_corridor <= color_distance(pixel, wall)
+        }
+        (None, None) => pixel == im::Rgb([255, 255, 255]),
+    };
+
+    let mut out = im::GrayImage::new(image.width(), image.height());
+    for (dst, src) in out.pixels_mut().zip(image.pixels()) {
+        *dst = if is_clear(*src) { CLEAR } else { WALL };
+    }
+    out
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorCluster {
+    pub color:  im::Rgb<u8>,
+    // how many of the image's pixels are nearest it
+    pub pixels: usize,
+}
+
+// groups the image's colours into `k` by k-means, for mazes whose walls come in several colours.
+// the first cluster starts at the commonest brightness and each one after at the colour furthest
+// from all of those so far, so that a colour few pixels are drawn in still gets a cluster of its
+// own, and the same image always clusters the same way. they come back darkest first. a large
+// image is clustered from a sample of its pixels, then every pixel is counted into its nearest.
+pub fn cluster_colors(image: &im::RgbImage, k: usize) -> Vec<ColorCluster> {
+    const MAX_SAMPLES: usize = 1 << 16;
+    const ITERATIONS: usize = 20;
+
+    let stride = (image.pixels().len() / MAX_SAMPLES).max(1);
+    let mut samples: Vec<[f32; 3]> = image.pixels()
+        .step_by(stride)
+        .map(|pixel| [pixel.0[0] as f32, pixel.0[1] as f32, pixel.0[2] as f32])
+        .collect();
+    if samples.is_empty() || k == 0 {
+        return Vec::new();
+    }
+    let luma = |c: &[f32; 3]| 0.299 * c[0] + 0.587 * c[1] + 0.114 * c[2];
+    samples.sort_by(|a, b| luma(a).total_cmp(&luma(b)));
+
+    let dist = |a: &[f32; 3], b: &[f32; 3]| (0 .. 3).map(|i| (a[i] - b[i]).powi(2)).sum::<f32>();
+    let nearest = |centers: &[[f32; 3]], c: &[f32; 3]| -> usize {
+        (0 .. centers.len())
+            .min_by(|a, b| dist(&centers[*a], c).total_cmp(&dist(&centers[*b], c)))
+            .unwrap()
+    };
+
+    let k = k.min(samples.len());
+    let mut centers = vec![samples[samples.len() / 2]];
+    let mut gaps: Vec<f32> = samples.iter().map(|c| dist(c, &centers[0])).collect();
+    while centers.len() < k {
+        let furthest = (0 .. samples.len()).max_by(|a, b| gaps[*a].total_cmp(&gaps[*b])).unwrap();
+        let center = samples[furthest];
+        for (gap, c) in gaps.iter_mut().zip(samples.iter()) {
+            *gap = gap.min(dist(c, &center));
+        }
+        centers.push(center);
+    }
+
+    for _ in 0 .. ITERATIONS {
+        let mut sums = vec![([0.0f64; 3], 0usize); k];
+        for sample in samples.iter() {
+            let (sum, count) = &mut sums[nearest(&centers, sample)];
+            for i in 0 .. 3 {
+                sum[i] += sample[i] as f64;
+            }
+            *count += 1;
+        }
+
+        // a cluster left with nothing keeps its centre
+        let moved: Vec<[f32; 3]> = sums.iter().zip(centers.iter())
+            .map(|((sum, count), old)| {
+                if *count == 0 { *old }
+                else { [0, 1, 2].map(|i| (sum[i] / *count as f64) as f32) }
+            })
+            .collect();
+        if moved == centers {
+            break;
+        }
+        centers = moved;
+    }
+    centers.sort_by(|a, b| luma(a).total_cmp(&luma(b)));
+
+    let mut counts = vec![0; k];
+    for pixel in image.pixels() {
+        counts[nearest(&centers, &[pixel.0[0] as f32, pixel.0[1] as f32, pixel.0[2] as f32])] += 1;
+    }
+    centers.iter().zip(counts)
+        .map(|(c, pixels)| ColorCluster { color: im::Rgb(c.map(|v| v.round() as u8)), pixels })
+        .collect()
+}
+
+// binarizes by cluster: a pixel is wall if the cluster nearest it is one of `walls`
+pub fn classify_clusters(image: &im::RgbImage, clusters: &[ColorCluster], walls: &[usize]) -> im::GrayImage {
+    let mut out = im::GrayImage::new(image.width(), image.height());
+    for (dst, src) in out.pixels_mut().zip(image.pixels()) {
+        let nearest = (0 .. clusters.len())
+            .min_by(|a, b| color_distance(*src, clusters[*a].color)
+                .total_cmp(&color_distance(*src, clusters[*b].color)));
+        let wall = nearest.is_some_and(|index| walls.contains(&index));
+        *dst = if wall { WALL } else { CLEAR };
+    }
+    out
+}
+
+// anything not fully clear becomes wall
+#[cfg(feature = "imageproc")]
+fn binarize(image: &im::GrayImage) -> im::GrayImage {
+    threshold(image, 255)
+}
+
+fn is_wall(image: &im::GrayImage, x: u32, y: u32) -> bool {
+    *image.get_pixel(x, y) != CLEAR
+}
+
+// calls `f` with every 4-connected blob of wall pixels
+fn wall_blobs(image: &im::GrayImage, mut f: impl FnMut(&[(u32, u32)])) {
+    let (w, h) = image.dimensions();
+    let mut seen = vec![false; w as usize * h as usize];
+    let mut blob = Vec::new();
+    let mut stack = Vec::new();
+
+    for y0 in 0 .. h {
+        for x0 in 0 .. w {
+            let index = (y0 * w + x0) as usize;
+            if seen[index] || !is_wall(image, x0, y0) {
+                continue;
+            }
+
+            blob.clear();
+            stack.push((x0, y0));
+            seen[index] = true;
+
+            while let Some((x, y)) = stack.pop() {
+                blob.push((x, y));
+
+                let neighbors = [
+                    (x.wrapping_sub(1), y), (x + 1, y),
+                    (x, y.wrapping_sub(1)), (x, y + 1),
+                ];
+                for (nx, ny) in neighbors.iter().copied() {
+                    if nx >= w || ny >= h { continue; }
+                    let n_index = (ny * w + nx) as usize;
+                    if !seen[n_index] && is_wall(image, nx, ny) {
+                        seen[n_index] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+
+            f(&blob);
+        }
+    }
+}
+
+// as wall_blobs(), clearing the blobs `f` returns true for
+fn clear_wall_blobs(image: &mut im::GrayImage, mut f: impl FnMut(&[(u32, u32)]) -> bool) {
+    let mut cleared = Vec::new();
+    wall_blobs(image, |blob| if f(blob) { cleared.extend_from_slice(blob); });
+    for (x, y) in cleared {
+        image.put_pixel(x, y, CLEAR);
+    }
+}
+
+// the box around the biggest blob of wall and every other whose box overlaps it, widened by
+// `margin` px within the image: the maze itself, even with its outline split by the entrances,
+// on a scan with margins and lettering round it. none if there's no wall at all.
+pub fn content_bounds(image: &im::GrayImage, margin: Coord) -> Option<Rect> {
+    let mut boxes: Vec<(usize, Rect)> = Vec::new();
+    wall_blobs(image, |blob| {
+        let (mut mins, mut maxs) = (V2::new(Coord::MAX, Coord::MAX), V2::new(0, 0));
+        for (x, y) in blob.iter().copied() {
+            let p = V2::new(coord_clamped(x), coord_clamped(y));
+            mins = V2::new(mins.x.min(p.x), mins.y.min(p.y));
+            maxs = V2::new(maxs.x.max(p.x + 1), maxs.y.max(p.y + 1));
+        }
+        boxes.push((blob.len(), Rect::new_unchecked(mins, maxs)));
+    });
+
+    let (_, mut bounds) = boxes.iter().copied().max_by_key(|(size, _)| *size)?;
+    loop {
+        let grown = boxes.iter().filter(|(_, r)| r.overlaps(bounds)).fold(bounds, |bounds, (_, r)| bounds.union(*r));
+        if grown == bounds {
+            break;
+        }
+        bounds = grown;
+    }
+    let image_rect = Rect::new_unchecked(V2::new(0, 0), V2::new(coord_clamped(image.width()), coord_clamped(image.height())));
+    Some(bounds.expand(margin).intersect(image_rect))
+}
+
+// clears wall blobs smaller than `min_area` pixels (specks)
+pub fn despeckle(image: &mut im::GrayImage, min_area: usize) {
+    clear_wall_blobs(image, |blob| blob.len() < min_area);
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MaskReport {
+    pub blobs:  usize,
+    pub pixels: usize,
+}
+
+// clears isolated wall blobs whose bounding box fits within `max_size` pixels either way.
+// the walls of a real maze are one (or a few) huge connected pieces, so what's left small and
+// detached is nearly always lettering: titles, page numbers, instructions.
+pub fn mask_text(image: &mut im::GrayImage, max_size: u32) -> MaskReport {
+    let mut report = MaskReport::default();
+    clear_wall_blobs(image, |blob| {
+        let (mut x0, mut y0, mut x1, mut y1) = (u32::MAX, u32::MAX, 0, 0);
+        for (x, y) in blob.iter().copied() {
+            x0 = x0.min(x); y0 = y0.min(y);
+            x1 = x1.max(x); y1 = y1.max(y);
+        }
+
+        let is_text = x1 - x0 < max_size && y1 - y0 < max_size;
+        if is_text {
+            report.blobs  += 1;
+            report.pixels += blob.len();
+        }
+        is_text
+    });
+    report
+}
+
+// the clear pixels split into 4-connected regions, labelled largest first
+#[derive(Clone, Debug)]
+pub struct Components {
+    pub width:  u32,
+    // by pixel, row by row: the region's index plus one, or 0 on a wall
+    pub labels: Vec<u32>,
+    // each region's pixel count
+    pub sizes:  Vec<usize>,
+}
+
+impl Components {
+    // the region at `p`, if it's clear and in the image
+    pub fn at(&self, p: V2) -> Option<usize> {
+        if p.x < 0 || p.y < 0 || p.x as u32 >= self.width {
+            return None;
+        }
+        let index = p.y as usize * self.width as usize + p.x as usize;
+        match self.labels.get(index).copied() {
+            None | Some(0) => None,
+            Some(label)    => Some(label as usize - 1),
+        }
+    }
+}
+
+// labels every region of clear space no wall divides. extraction can only split these further,
+// across passages narrower than its minimum, so points in different regions are never joined.
+pub fn label_components(image: &im::GrayImage) -> Components {
+    let (w, h) = image.dimensions();
+    let mut labels = vec![0u32; w as usize * h as usize];
+    let mut sizes = Vec::new();
+    let mut stack = Vec::new();
+
+    for y0 in 0 .. h {
+        for x0 in 0 .. w {
+            let index = (y0 * w + x0) as usize;
+            if labels[index] != 0 || is_wall(image, x0, y0) {
+                continue;
+            }
+
+            let label = sizes.len() as u32 + 1;
+            let mut size = 0;
+            labels[index] = label;
+            stack.push((x0, y0));
+            while let Some((x, y)) = stack.pop() {
+                size += 1;
+                let neighbors = [
+                    (x.wrapping_sub(1), y), (x + 1, y),
+                    (x, y.wrapping_sub(1)), (x, y + 1),
+                ];
+                for (nx, ny) in neighbors.iter().copied() {
+                    if nx >= w || ny >= h { continue; }
+                    let n_index = (ny * w + nx) as usize;
+                    if labels[n_index] == 0 && !is_wall(image, nx, ny) {
+                        labels[n_index] = label;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+    }
+
+    // renumbered biggest first, so that region 0 is the maze proper
+    let mut by_size: Vec<usize> = (0 .. sizes.len()).collect();
+    by_size.sort_by_key(|region| std::cmp::Reverse(sizes[*region]));
+    let mut renumber = vec![0u32; sizes.len() + 1];
+    for (rank, region) in by_size.iter().enumerate() {
+        renumber[region + 1] = rank as u32 + 1;
+    }
+    for label in labels.iter_mut() {
+        *label = renumber[*label as usize];
+    }
+    let sizes = by_size.iter().map(|region| sizes[*region]).collect();
+
+    Components { width: w, labels, sizes }
+}
+
+// one pass of a line-shaped structuring element of length 2*radius+1.
+// `grow_walls` picks whether walls spread (dilate) or recede (erode).
+fn line_pass(src: &im::GrayImage, radius: u32, horizontal: bool, grow_walls: bool) -> im::GrayImage {
+    let (w, h) = src.dimensions();
+    let r = radius as i64;
+
+    let mut dst = src.clone();
+    for y in 0 .. h {
+        for x in 0 .. w {
+            let mut any_wall  = false;
+            let mut all_walls = true;
+            for d in -r ..= r {
+                let (sx, sy) = if horizontal { (x as i64 + d, y as i64) }
+                               else          { (x as i64, y as i64 + d) };
+                // outside the image counts as clear
+                let wall = sx >= 0 && sy >= 0 && sx < w as i64 && sy < h as i64
+                    && is_wall(src, sx as u32, sy as u32);
+                any_wall  |= wall;
+                all_walls &= wall;
+            }
+            let wall = if grow_walls { any_wall } else { all_walls };
+            dst.put_pixel(x, y, if wall { WALL } else { CLEAR });
+        }
+    }
+    dst
+}
+
+// square structuring element of side 2*radius+1, done as two separable passes
+#[cfg(not(feature = "imageproc"))]
+fn morph(image: &im::GrayImage, radius: u32, grow_walls: bool) -> im::GrayImage {
+    let horizontal = line_pass(image, radius, true, grow_walls);
+    line_pass(&horizontal, radius, false, grow_walls)
+}
+
+#[cfg(not(feature = "imageproc"))]
+pub fn dilate_walls(image: &im::GrayImage, radius: u32) -> im::GrayImage {
+    morph(image, radius, true)
+}
+
+#[cfg(not(feature = "imageproc"))]
+pub fn erode_walls(image: &im::GrayImage, radius: u32) -> im::GrayImage {
+    morph(image, radius, false)
+}
+
+// imageproc's morphology works on white foreground, which for us is the clear space, so growing
+// walls is eroding the foreground and vice versa
+#[cfg(feature = "imageproc")]
+pub fn dilate_walls(image: &im::GrayImage, radius: u32) -> im::GrayImage {
+    use imageproc::distance_transform::Norm;
+    imageproc::morphology::erode(&binarize(image), Norm::LInf, radius.min(255) as u8)
+}
+
+#[cfg(feature = "imageproc")]
+pub fn erode_walls(image: &im::GrayImage, radius: u32) -> im::GrayImage {
+    use imageproc::distance_transform::Norm;
+    imageproc::morphology::dilate(&binarize(image), Norm::LInf, radius.min(255) as u8)
+}
+
+// rotates clockwise about the centre, filling the uncovered corners with clear space.
+// nearest-neighbour sampling keeps the image binary.
+#[cfg(not(feature = "imageproc"))]
+pub fn rotate(image: &im::GrayImage, degrees: f32) -> im::GrayImage {
+    let (w, h) = image.dimensions();
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (cx, cy) = (w as f32 / 2.0, h as f32 / 2.0);
+
+    let mut out = im::GrayImage::from_pixel(w, h, CLEAR);
+    for y in 0 .. h {
+        for x in 0 .. w {
+            // inverse map each output pixel centre back into the source
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            let sx = ( dx * cos + dy * sin + cx).floor();
+            let sy = (-dx * sin + dy * cos + cy).floor();
+            if sx >= 0.0 && sy >= 0.0 && sx < w as f32 && sy < h as f32 {
+                out.put_pixel(x, y, *image.get_pixel(sx as u32, sy as u32));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(feature = "imageproc")]
+pub fn rotate(image: &im::GrayImage, degrees: f32) -> im::GrayImage {
+    use imageproc::geometric_transformations::{rotate, Interpolation};
+    // imageproc samples at pixel indices rather than pixel centres, so its centre is half a
+    // pixel up and left of ours
+    let (w, h) = image.dimensions();
+    let centre = ((w as f32 - 1.0) / 2.0, (h as f32 - 1.0) / 2.0);
+    rotate(image, centre, degrees.to_radians(), Interpolation::Nearest, CLEAR)
+}
+
+// estimates how far the walls are turned clockwise off the axes, in degrees within [-45, 45).
+// this is a hough transform folded into a quarter turn: at each candidate angle the wall pixels
+// vote for the lines along and across that angle they lie on, and the angle whose votes pile up
+// into the fewest, fullest lines wins. scored by the sum of squared votes, a coarse sweep finds
+// the peak and a fine sweep around it settles it.
+pub fn estimate_skew(image: &im::GrayImage) -> f32 {
+    const MAX_SAMPLES: usize = 50_000;
+
+    let (w, h) = image.dimensions();
+    let mut walls: Vec<(f32, f32)> = image.enumerate_pixels()
+        .filter(|(_, _, pixel)| pixel.0[0] < 128)
+        .map(|(x, y, _)| (x as f32 - w as f32 / 2.0, y as f32 - h as f32 / 2.0))
+        .collect();
+    if walls.is_empty() {
+        return 0.0;
+    }
+    // the score only needs the shape of the votes, which an even subsample keeps
+    let stride = walls.len().div_ceil(MAX_SAMPLES);
+    walls = walls.into_iter().step_by(stride).collect();
+
+    let diagonal = (w as f32).hypot(h as f32) as usize + 3;
+    let offset = diagonal as f32 / 2.0;
+    let mut votes = vec![0.0f32; 2 * diagonal];
+    // each pixel's vote is split between the two nearest lines, or else at angles where the
+    // pixel grid lines up with the bins (45 degrees especially) the votes alias into spikes
+    let mut score = |degrees: f32| -> f64 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        votes.iter_mut().for_each(|v| *v = 0.0);
+        let (rows, cols) = votes.split_at_mut(diagonal);
+        let vote = |lines: &mut [f32], rho: f32| {
+            let rho = rho + offset;
+            let (i, t) = (rho.floor() as usize, rho.fract());
+            lines[i]     += 1.0 - t;
+            lines[i + 1] += t;
+        };
+        for (x, y) in walls.iter().copied() {
+            vote(rows, -x * sin + y * cos);
+            vote(cols,  x * cos + y * sin);
+        }
+        votes.iter().map(|&v| v as f64 * v as f64).sum()
+    };
+
+    let mut sweep = |from: f32, to: f32, step: f32| {
+        let n = ((to - from) / step).round() as i32;
+        (0 ..= n)
+            .map(|i| from + i as f32 * step)
+            .map(|degrees| (degrees, score(degrees)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(from, |(degrees, _)| degrees)
+    };
+    let coarse = sweep(-45.0, 44.75, 0.25);
+    let fine = sweep(coarse - 0.25, coarse + 0.25, 0.025);
+    (fine + 45.0).rem_euclid(90.0) - 45.0
+}
+
+// seals breaks in walls up to 2*radius+1 pixels wide
+pub fn close_walls(image: &im::GrayImage, radius: u32) -> im::GrayImage {
+    erode_walls(&dilate_walls(image, radius), radius)
+}
+
+// removes wall strokes thinner than 2*radius+1 (door swings, dimension lines)
+// while leaving thick walls, and so the gaps between them, intact
+pub fn open_walls(image: &im::GrayImage, radius: u32) -> im::GrayImage {
+    dilate_walls(&erode_walls(image, radius), radius)
+}
+
+// consolidates dashed and double-line walls into solid ones. closing along each axis separately
+// bridges gaps of up to `gap` pixels between dashes running that way, and between the strands
+// of a double line running the other way, without smearing walls diagonally the way a square
+// closing would.
+pub fn join_walls(image: &im::GrayImage, gap: u32) -> im::GrayImage {
+    let radius = gap.div_ceil(2);
+
+    let close = |horizontal: bool| {
+        let grown = line_pass(image, radius, horizontal, true);
+        line_pass(&grown, radius, horizontal, false)
+    };
+    let along_x = close(true);
+    let along_y = close(false);
+
+    let mut out = along_x;
+    for (pixel, other) in out.pixels_mut().zip(along_y.pixels()) {
+        if *other == WALL {
+            *pixel = WALL;
+        }
+    }
+    out
+}
+
+// morphology on the walls: dilating thickens them, eroding thins them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MorphOp {
+    Dilate,
+    Erode,
+    Open,
+    Close,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MorphStep {
+    pub op:         MorphOp,
+    pub radius:     u32,
+    pub iterations: u32,
+}
+
+pub fn apply_morph(image: &im::GrayImage, steps: &[MorphStep]) -> im::GrayImage {
+    let mut image = image.clone();
+    for step in steps.iter() {
+        for _ in 0 .. step.iterations {
+            image = match step.op {
+                MorphOp::Dilate => dilate_walls(&image, step.radius),
+                MorphOp::Erode  => erode_walls(&image, step.radius),
+                MorphOp::Open   => open_walls(&image, step.radius),
+                MorphOp::Close  => close_walls(&image, step.radius),
+            };
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components_split_where_walls_divide() {
+        // a wall down column 5 and a sealed-off pocket in the corner of the right side
+        let mut image = im::GrayImage::from_pixel(12, 8, CLEAR);
+        for y in 0 .. 8 {
+            image.put_pixel(5, y, WALL);
+        }
+        for (x, y) in [(9, 6), (10, 6), (11, 6), (9, 7)].iter().copied() {
+            image.put_pixel(x, y, WALL);
+        }
+        let components = label_components(&image);
+
+        // biggest first
+        assert_eq!(components.sizes, vec![42, 40, 2]);
+        assert_eq!(components.at(V2::new(6, 0)), Some(0));
+        assert_eq!(components.at(V2::new(0, 0)), Some(1));
+        assert_eq!(components.at(V2::new(11, 7)), Some(2));
+        assert_eq!(components.at(V2::new(5, 3)), None);
+        assert_eq!(components.at(V2::new(12, 0)), None);
+    }
+
+    #[test]
+    fn crop_finds_the_maze_past_its_entrances_and_margin() {
+        // a box outlined from 10,8 to 30,20 with a gap in its top and bottom, so its outline
+        // is two pieces, and a label off to the side
+        let mut image = im::GrayImage::from_pixel(40, 30, CLEAR);
+        for x in 10 .. 30 {
+            if x != 12 { image.put_pixel(x, 8, WALL); }
+            if x != 27 { image.put_pixel(x, 19, WALL); }
+        }
+        for y in 8 .. 20 {
+            image.put_pixel(10, y, WALL);
+            image.put_pixel(29, y, WALL);
+        }
+        for x in 1 .. 5 {
+            image.put_pixel(x, 1, WALL);
+        }
+
+        assert_eq!(content_bounds(&image, 0), Some(Rect::new_unchecked(V2::new(10, 8), V2::new(30, 20))));
+        assert_eq!(content_bounds(&image, 9), Some(Rect::new_unchecked(V2::new(1, 0), V2::new(39, 29))));
+        assert_eq!(content_bounds(&im::GrayImage::from_pixel(4, 4, CLEAR), 0), None);
+    }
+
+    #[test]
+    fn adaptive_thresholds_follow_the_light() {
+        // paper fading from 240 at the left to 90 at the right, with a wall down every tenth
+        // column at half the paper's brightness there, so the lit walls outshine the shaded paper
+        let paper = |x: u32| 240 - x * 5;
+        let image = im::GrayImage::from_fn(31, 8, |x, _| im::Luma([if x % 10 == 5 { paper(x) / 2 } else { paper(x) } as u8]));
+        for method in [Adaptive::mean(7), Adaptive::sauvola(7)] {
+            let binary = adaptive_threshold(&image, method);
+            for (x, _, pixel) in binary.enumerate_pixels() {
+                assert_eq!(*pixel == WALL, x % 10 == 5, "{:?} at column {}", method, x);
+            }
+        }
+    }
+}
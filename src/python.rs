@@ -0,0 +1,51 @@
+// the python module, built by maturin with --features python:
+//
+//     import mazesolve_rk, numpy
+//     path = mazesolve_rk.solve(numpy.asarray(image), (2, 0), (401, 398), threshold=128)
+//
+// the array is a greyscale image, a byte a pixel, indexed [y, x] as numpy loads images; points
+// go in and the path comes out as (x, y) tuples.
+
+// pyo3's macros convert a function's error into PyErr, even when it already is one
+#![allow(clippy::useless_conversion)]
+
+use {
+    crate::{math::*, solve::solve_buffer},
+    numpy::PyReadonlyArray2,
+    pyo3::{exceptions::PyValueError, prelude::*},
+    std::convert::TryFrom,
+};
+
+// the shortest path from `start` to `goal`, through the pixels at least `threshold` bright
+#[pyfunction]
+#[pyo3(signature = (pixels, start, goal, threshold = 128))]
+fn solve(
+    py:        Python<'_>,
+    pixels:    PyReadonlyArray2<'_, u8>,
+    start:     (Coord, Coord),
+    goal:      (Coord, Coord),
+    threshold: u8)
+    -> PyResult<Vec<(Coord, Coord)>>
+{
+    let (height, width) = pixels.as_array().dim();
+    let (width, height) = match (u32::try_from(width), u32::try_from(height)) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => return Err(PyValueError::new_err(format!("a {}x{} image is too big", width, height))),
+    };
+    // a slice or transpose of another array isn't laid out row by row, so is copied first
+    let copied: Vec<u8>;
+    let buffer = match pixels.as_slice() {
+        Ok(buffer) => buffer,
+        Err(_)     => { copied = pixels.as_array().iter().copied().collect(); &copied }
+    };
+
+    let (start, goal) = (V2::new(start.0, start.1), V2::new(goal.0, goal.1));
+    let path = py.allow_threads(|| solve_buffer(buffer, width, height, start, goal, threshold))
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+    Ok(path.into_iter().map(|p| (p.x, p.y)).collect())
+}
+
+#[pymodule]
+fn mazesolve_rk(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(solve, module)?)
+}
@@ -0,0 +1,52 @@
+use {
+    crate::math::*,
+};
+
+// appends the 8-connected line from `from` to `to`, excluding `from` itself
+fn push_line(path: &mut Vec<V2>, from: V2, to: V2) {
+    let d = to - from;
+    let steps = d.chebyshev();
+    for i in 1 ..= steps {
+        let t = i as f64 / steps as f64;
+        let x = from.x + (d.x as f64 * t).round() as Coord;
+        let y = from.y + (d.y as f64 * t).round() as Coord;
+        path.push(V2::new(x, y));
+    }
+}
+
+fn clamp(v: Coord, lo: Coord, hi: Coord) -> Coord {
+    v.clamp(lo, hi.max(lo))
+}
+
+// where a walk currently at `pos` inside `a` should leave `a` for the neighbouring `b`:
+// the last pixel in `a` and the first in `b`, chosen as close to `pos` as the shared side allows
+fn crossing(a: Rect, b: Rect, pos: V2) -> (V2, V2) {
+    if a.maxs.x == b.mins.x || b.maxs.x == a.mins.x {
+        let y = clamp(pos.y, a.mins.y.max(b.mins.y), a.maxs.y.min(b.maxs.y) - 1);
+        if a.maxs.x == b.mins.x { (V2::new(a.maxs.x - 1, y), V2::new(b.mins.x, y)) }
+        else                    { (V2::new(a.mins.x, y),     V2::new(b.maxs.x - 1, y)) }
+    }
+    else {
+        let x = clamp(pos.x, a.mins.x.max(b.mins.x), a.maxs.x.min(b.maxs.x) - 1);
+        if a.maxs.y == b.mins.y { (V2::new(x, a.maxs.y - 1), V2::new(x, b.mins.y)) }
+        else                    { (V2::new(x, a.mins.y),     V2::new(x, b.maxs.y - 1)) }
+    }
+}
+
+// turns a chain of adjacent rects into a pixel path from `start` to `goal`. each rect is
+// crossed in a straight line, which can't leave it since rects are convex, and each portal
+// is crossed as near the current position as the shared side allows, pulling the path taut.
+pub fn refine_path(rects: &[Rect], start: V2, goal: V2) -> Vec<V2> {
+    let mut path = vec![start];
+    let mut pos = start;
+
+    for pair in rects.windows(2) {
+        let (exit, entry) = crossing(pair[0], pair[1], pos);
+        push_line(&mut path, pos, exit);
+        path.push(entry);
+        pos = entry;
+    }
+
+    push_line(&mut path, pos, goal);
+    path
+}
@@ -0,0 +1,973 @@
+use {
+    crate::{
+        math::*,
+        graph::{Graph, AdjacencyGraph, DijkstraGraph, Edge, EdgeSetGraph, HashMap, HashSet, NodeID},
+        image_graph::{NodeData, NodeKind, SpaceGraph},
+        levels::LevelRect,
+        pathfind::Solution,
+        preprocess::Components,
+        refine,
+        sink::OutputSink,
+        tremaux::Walk,
+    },
+    std::io,
+    image as im,
+    pcg_rand::Pcg32Basic,
+    rand::{distributions::{Distribution, Uniform}, SeedableRng},
+};
+
+pub const GREEN: im::Rgb<u8> = im::Rgb([0x00, 0xff, 0x00]);
+pub const RED:   im::Rgb<u8> = im::Rgb([0xff, 0x00, 0x00]);
+
+// the strip between a race's lanes in px
+const RACE_GAP: u32 = 4;
+
+const REGION_COLORS: [im::Rgb<u8>; 8] = [
+    im::Rgb([0xe6, 0x19, 0x4b]),
+    im::Rgb([0x3c, 0xb4, 0x4b]),
+    im::Rgb([0x43, 0x63, 0xd8]),
+    im::Rgb([0xf5, 0x82, 0x31]),
+    im::Rgb([0x91, 0x1e, 0xb4]),
+    im::Rgb([0x42, 0xd4, 0xf4]),
+    im::Rgb([0xf0, 0x32, 0xe6]),
+    im::Rgb([0xbf, 0xef, 0x45]),
+];
+
+// okabe and ito's, with grey for their black, which the walls already are
+const COLORBLIND_REGIONS: [im::Rgb<u8>; 8] = [
+    im::Rgb([0xe6, 0x9f, 0x00]),
+    im::Rgb([0x56, 0xb4, 0xe9]),
+    im::Rgb([0x00, 0x9e, 0x73]),
+    im::Rgb([0xf0, 0xe4, 0x42]),
+    im::Rgb([0x00, 0x72, 0xb2]),
+    im::Rgb([0xd5, 0x5e, 0x00]),
+    im::Rgb([0xcc, 0x79, 0xa7]),
+    im::Rgb([0x99, 0x99, 0x99]),
+];
+
+// greys far enough apart that neighbours still tell apart on paper
+const PRINT_REGIONS: [im::Rgb<u8>; 8] = [
+    im::Rgb([0x40, 0x40, 0x40]),
+    im::Rgb([0xa0, 0xa0, 0xa0]),
+    im::Rgb([0x70, 0x70, 0x70]),
+    im::Rgb([0xd0, 0xd0, 0xd0]),
+    im::Rgb([0x58, 0x58, 0x58]),
+    im::Rgb([0xb8, 0xb8, 0xb8]),
+    im::Rgb([0x88, 0x88, 0x88]),
+    im::Rgb([0xe0, 0xe0, 0xe0]),
+];
+
+pub const WHITE: im::Rgb<u8> = im::Rgb([0xff, 0xff, 0xff]);
+
+// every colour drawn with besides the path and explore colours, which are the style's own so
+// they can be set one at a time
+#[derive(Clone, Copy, Debug)]
+pub struct Palette {
+    // the maze's walls and floors, the canvas mapping black and white to these
+    pub wall:          im::Rgb<u8>,
+    pub floor:         im::Rgb<u8>,
+    pub dead_end:      im::Rgb<u8>,
+    // spaces a search could have reached but didn't
+    pub unexplored:    im::Rgb<u8>,
+    pub skeleton_edge: im::Rgb<u8>,
+    pub skeleton_node: im::Rgb<u8>,
+    pub room:          im::Rgb<u8>,
+    pub corridor:      im::Rgb<u8>,
+    // between a race's lanes
+    pub gap:           im::Rgb<u8>,
+    // near, halfway and far, for distances and routes coloured as they go
+    pub heat:          [im::Rgb<u8>; 3],
+    // for partitions and the runners-up of --k-paths
+    pub regions:       &'static [im::Rgb<u8>],
+}
+
+impl Palette {
+    pub const CLASSIC: Palette = Palette {
+        wall:          im::Rgb([0x00, 0x00, 0x00]),
+        floor:         WHITE,
+        dead_end:      im::Rgb([0xc0, 0xc0, 0xc0]),
+        unexplored:    im::Rgb([0xc6, 0xdb, 0xef]),
+        skeleton_edge: im::Rgb([0x43, 0x63, 0xd8]),
+        skeleton_node: im::Rgb([0xe6, 0x19, 0x4b]),
+        room:          im::Rgb([0xa6, 0xce, 0xe3]),
+        corridor:      im::Rgb([0xfd, 0xbf, 0x6f]),
+        gap:           im::Rgb([0x80, 0x80, 0x80]),
+        heat:          [GREEN, im::Rgb([0xff, 0xff, 0x00]), RED],
+        regions:       &REGION_COLORS,
+    };
+
+    // whether the canvas is the maze's own black and white
+    fn is_plain(&self) -> bool {
+        self.wall == im::Rgb([0x00, 0x00, 0x00]) && self.floor == WHITE
+    }
+
+    pub fn region(&self, index: usize) -> im::Rgb<u8> {
+        self.regions[index % self.regions.len()]
+    }
+
+    // along the heat colours, 0 being the near one and 1 the far
+    pub fn heat(&self, t: f32) -> im::Rgb<u8> {
+        let t = t.clamp(0.0, 1.0) * 2.0;
+        if t <= 1.0 { mix(self.heat[0], self.heat[1], t) }
+        else        { mix(self.heat[1], self.heat[2], t - 1.0) }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    // red on green over black and white, as it always was
+    Classic,
+    // light walls on dark floors
+    Dark,
+    // blues, oranges and yellows, which the common colour blindnesses still tell apart
+    Colorblind,
+    // greys only, for printing in black and white
+    Print,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [Theme::Classic, Theme::Dark, Theme::Colorblind, Theme::Print];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Classic    => "classic",
+            Theme::Dark       => "dark",
+            Theme::Colorblind => "colorblind",
+            Theme::Print      => "print",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        Theme::ALL.iter().copied().find(|theme| theme.name() == name)
+    }
+
+    // the path and explore colours, then the rest
+    fn colors(self) -> (im::Rgb<u8>, im::Rgb<u8>, Palette) {
+        match self {
+            Theme::Classic => (RED, GREEN, Palette::CLASSIC),
+            Theme::Dark => (im::Rgb([0xff, 0x5c, 0x5c]), im::Rgb([0x2e, 0x7d, 0x32]), Palette {
+                wall:          im::Rgb([0xc8, 0xc8, 0xc8]),
+                floor:         im::Rgb([0x1e, 0x1e, 0x1e]),
+                dead_end:      im::Rgb([0x3a, 0x3a, 0x3a]),
+                unexplored:    im::Rgb([0x1f, 0x3a, 0x5f]),
+                skeleton_edge: im::Rgb([0x6f, 0x8c, 0xff]),
+                skeleton_node: im::Rgb([0xff, 0x6b, 0x8b]),
+                room:          im::Rgb([0x1f, 0x4e, 0x6b]),
+                corridor:      im::Rgb([0x7a, 0x5a, 0x1e]),
+                gap:           im::Rgb([0x50, 0x50, 0x50]),
+                ..Palette::CLASSIC
+            }),
+            // viridis' ends and middle for the heat
+            Theme::Colorblind => (im::Rgb([0xd5, 0x5e, 0x00]), im::Rgb([0x56, 0xb4, 0xe9]), Palette {
+                unexplored:    im::Rgb([0xf0, 0xe4, 0x42]),
+                skeleton_edge: im::Rgb([0x00, 0x72, 0xb2]),
+                skeleton_node: im::Rgb([0xd5, 0x5e, 0x00]),
+                room:          im::Rgb([0x56, 0xb4, 0xe9]),
+                corridor:      im::Rgb([0xe6, 0x9f, 0x00]),
+                heat:          [im::Rgb([0x44, 0x01, 0x54]), im::Rgb([0x21, 0x91, 0x8c]), im::Rgb([0xfd, 0xe7, 0x25])],
+                regions:       &COLORBLIND_REGIONS,
+                ..Palette::CLASSIC
+            }),
+            Theme::Print => (im::Rgb([0x30, 0x30, 0x30]), im::Rgb([0xb0, 0xb0, 0xb0]), Palette {
+                dead_end:      im::Rgb([0xe0, 0xe0, 0xe0]),
+                unexplored:    im::Rgb([0xf0, 0xf0, 0xf0]),
+                skeleton_edge: im::Rgb([0x60, 0x60, 0x60]),
+                skeleton_node: im::Rgb([0x00, 0x00, 0x00]),
+                room:          im::Rgb([0xc0, 0xc0, 0xc0]),
+                corridor:      im::Rgb([0x80, 0x80, 0x80]),
+                gap:           WHITE,
+                heat:          [im::Rgb([0xe0, 0xe0, 0xe0]), im::Rgb([0x90, 0x90, 0x90]), im::Rgb([0x20, 0x20, 0x20])],
+                regions:       &PRINT_REGIONS,
+                ..Palette::CLASSIC
+            }),
+        }
+    }
+
+    // `style` recoloured, its sizes left as they were
+    pub fn apply(self, style: RenderStyle) -> RenderStyle {
+        let (path_color, explore_color, palette) = self.colors();
+        RenderStyle { path_color, explore_color, palette, ..style }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RenderStyle {
+    pub path_color:    im::Rgb<u8>,
+    pub explore_color: im::Rgb<u8>,
+    pub palette:       Palette,
+    // width of the refined path line in px
+    pub thickness:     u32,
+    // opacity of the fills over the maze, 0 to 1
+    pub alpha:         f32,
+    // the canvas is the image shrunk by this, each of its pixels a square this many px across
+    pub downscale:     u32,
+    // or enlarged by this, each pixel of the image a square this many px across
+    pub upscale:       u32,
+}
+
+impl Default for RenderStyle {
+    fn default() -> RenderStyle {
+        RenderStyle {
+            path_color:    RED,
+            explore_color: GREEN,
+            palette:       Palette::CLASSIC,
+            thickness:     1,
+            alpha:         1.0,
+            downscale:     1,
+            upscale:       1,
+        }
+    }
+}
+
+impl RenderStyle {
+    // under a refined path the solution rects are only lightly tinted with the path colour
+    fn path_tint(&self) -> im::Rgb<u8> {
+        mix(self.palette.floor, self.path_color, 79.0 / 255.0)
+    }
+
+    // the canvas size for a `width` by `height` image
+    pub fn canvas_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (up, down) = (self.upscale.max(1), self.downscale.max(1));
+        ((width * up).div_ceil(down), (height * up).div_ceil(down))
+    }
+
+    // the canvas pixel over image pixel `p`, the top-left one when the image is enlarged
+    pub fn canvas_point(&self, p: V2) -> V2 {
+        let (up, down) = (self.upscale.max(1) as Coord, self.downscale.max(1) as Coord);
+        V2::new((p.x * up).div_euclid(down), (p.y * up).div_euclid(down))
+    }
+
+    // the canvas pixels over any of `rect`, so that a space thinner than a canvas pixel still
+    // shows
+    fn canvas_rect(&self, rect: Rect) -> Rect {
+        if (self.downscale <= 1 && self.upscale <= 1) || rect.area() == 0 {
+            return rect;
+        }
+        if self.upscale > 1 {
+            let up = self.upscale as Coord;
+            let scale = |p: V2| p * up;
+            return Rect::new_unchecked(scale(rect.mins), scale(rect.maxs));
+        }
+        let maxs = self.canvas_point(rect.maxs - V2::new(1, 1)) + V2::new(1, 1);
+        Rect::new_unchecked(self.canvas_point(rect.mins), maxs)
+    }
+
+    // a pixel path on the canvas, still contiguous, with the steps within a canvas pixel dropped
+    fn canvas_line(&self, pixels: &[V2]) -> Vec<V2> {
+        let mut line: Vec<V2> = pixels.iter().map(|p| self.canvas_point(*p)).collect();
+        line.dedup();
+        line
+    }
+
+    fn fill(&self, image: &mut im::RgbImage, rect: Rect, color: im::Rgb<u8>) {
+        let rect = self.canvas_rect(rect);
+        if self.alpha >= 1.0 {
+            fill_rect(image, rect, color);
+        }
+        else {
+            blend_rect(image, rect, color, self.alpha);
+        }
+    }
+}
+
+fn mix(from: im::Rgb<u8>, to: im::Rgb<u8>, t: f32) -> im::Rgb<u8> {
+    let mut out = from;
+    for (o, (a, b)) in out.0.iter_mut().zip(from.0.iter().zip(to.0.iter())) {
+        *o = (*a as f32 + (*b as f32 - *a as f32) * t).round() as u8;
+    }
+    out
+}
+
+pub fn region_color(index: usize) -> im::Rgb<u8> {
+    REGION_COLORS[index % REGION_COLORS.len()]
+}
+
+// the pixels of each row of `rect` inside the image, as slices of the image buffer. going
+// through the buffer rather than put_pixel is what keeps rendering big mazes from outlasting
+// solving.
+fn rows_mut(image: &mut im::RgbImage, rect: Rect) -> impl Iterator<Item = &mut [u8]> {
+    let (width, height) = (coord_clamped(image.width()), coord_clamped(image.height()));
+    let row_len = image.width().max(1) as usize * 3;
+    let span = |lo: Coord, hi: Coord, max: Coord| {
+        let lo = lo.clamp(0, max);
+        (lo as usize, hi.clamp(lo, max) as usize)
+    };
+    let (x0, x1) = span(rect.mins.x, rect.maxs.x, width);
+    let (y0, y1) = span(rect.mins.y, rect.maxs.y, height);
+
+    let buf: &mut [u8] = image;
+    buf.chunks_exact_mut(row_len)
+        .skip(y0)
+        .take(y1 - y0)
+        .map(move |row| &mut row[x0 * 3 .. x1 * 3])
+}
+
+pub fn fill_rect(image: &mut im::RgbImage, rect: Rect, with: im::Rgb<u8>) {
+    let mut rows = rows_mut(image, rect);
+    let first = match rows.next() {
+        Some(row) => row,
+        None      => return,
+    };
+    for pixel in first.chunks_exact_mut(3) {
+        pixel.copy_from_slice(&with.0);
+    }
+    for row in rows {
+        row.copy_from_slice(first);
+    }
+}
+
+// mixes `color` into the rect by `alpha`, leaving the maze showing through
+pub fn blend_rect(image: &mut im::RgbImage, rect: Rect, color: im::Rgb<u8>, alpha: f32) {
+    for row in rows_mut(image, rect) {
+        for pixel in row.chunks_exact_mut(3) {
+            let mixed = mix(im::Rgb([pixel[0], pixel[1], pixel[2]]), color, alpha);
+            pixel.copy_from_slice(&mixed.0);
+        }
+    }
+}
+
+// the maze as the canvas to render over. much quicker than image's generic convert().
+pub fn to_rgb(image: &im::GrayImage) -> im::RgbImage {
+    let mut buf = vec![0; image.len() * 3];
+    for (pixel, v) in buf.chunks_exact_mut(3).zip(image.iter()) {
+        pixel.copy_from_slice(&[*v, *v, *v]);
+    }
+    im::RgbImage::from_raw(image.width(), image.height(), buf).expect("rgb buffer size")
+}
+
+// greys the canvas out towards white, leaving the maze as a backdrop for what's drawn over it
+pub fn fade(image: &mut im::RgbImage) {
+    for pixel in image.pixels_mut() {
+        let fade = 0x80 + im::Pixel::to_luma(pixel).0[0] / 2;
+        *pixel = im::Rgb([fade, fade, fade]);
+    }
+}
+
+// a white canvas, for rendering the path alone
+pub fn blank(width: u32, height: u32) -> im::RgbImage {
+    let buf = vec![0xff; width as usize * height as usize * 3];
+    im::RgbImage::from_raw(width, height, buf).expect("rgb buffer size")
+}
+
+// each `scale`-px square of a `width`-px-wide buffer of `channels`-byte pixels as one pixel of
+// their mean, the squares along the right and bottom edges being what's left of the image
+fn shrink(buf: &[u8], width: u32, height: u32, channels: usize, scale: u32) -> Vec<u8> {
+    let (w, h, s) = (width as usize, height as usize, scale as usize);
+    let (out_w, out_h) = (w.div_ceil(s), h.div_ceil(s));
+    let mut sums = vec![0u64; out_w * channels];
+    let mut out = Vec::with_capacity(out_w * out_h * channels);
+    for (block, rows) in buf.chunks(w * channels * s).enumerate() {
+        sums.iter_mut().for_each(|sum| *sum = 0);
+        for row in rows.chunks_exact(w * channels) {
+            for (x, pixel) in row.chunks_exact(channels).enumerate() {
+                for (c, v) in pixel.iter().enumerate() {
+                    sums[x / s * channels + c] += u64::from(*v);
+                }
+            }
+        }
+        let rows = (h - block * s).min(s) as u64;
+        for (index, sum) in sums.iter().enumerate() {
+            let cols = (w - index / channels * s).min(s) as u64;
+            out.push((sum / (rows * cols)) as u8);
+        }
+    }
+    out
+}
+
+// each pixel of a `width`-px-wide buffer of `channels`-byte pixels as a `scale`-px square
+fn enlarge(buf: &[u8], width: u32, channels: usize, scale: u32) -> Vec<u8> {
+    let (w, s) = (width as usize, scale as usize);
+    let mut out = Vec::with_capacity(buf.len() * s * s);
+    for row in buf.chunks_exact(w.max(1) * channels) {
+        let start = out.len();
+        for pixel in row.chunks_exact(channels) {
+            for _ in 0 .. s {
+                out.extend_from_slice(pixel);
+            }
+        }
+        for _ in 1 .. s {
+            out.extend_from_within(start .. start + w * s * channels);
+        }
+    }
+    out
+}
+
+// the pixels of a `channels`-byte-per-pixel image scaled to the style's canvas
+fn rescale(buf: &[u8], width: u32, height: u32, channels: usize, style: &RenderStyle) -> Vec<u8> {
+    if style.upscale > 1 {
+        enlarge(buf, width, channels, style.upscale)
+    }
+    else {
+        shrink(buf, width, height, channels, style.downscale)
+    }
+}
+
+// the maze, scaled to the style's canvas, as the canvas to render over
+pub fn canvas(image: &im::GrayImage, style: &RenderStyle) -> im::RgbImage {
+    let mut canvas = if style.downscale <= 1 && style.upscale <= 1 { to_rgb(image) } else {
+        let (width, height) = style.canvas_size(image.width(), image.height());
+        let buf = rescale(image, image.width(), image.height(), 1, style);
+        to_rgb(&im::GrayImage::from_raw(width, height, buf).expect("gray buffer size"))
+    };
+    if !style.palette.is_plain() {
+        recolor(&mut canvas, &style.palette);
+    }
+    canvas
+}
+
+// the black to white of a maze's canvas as the palette's wall to floor
+fn recolor(image: &mut im::RgbImage, palette: &Palette) {
+    let ramp: Vec<im::Rgb<u8>> = (0 ..= 255).map(|v| mix(palette.wall, palette.floor, v as f32 / 255.0)).collect();
+    let buf: &mut [u8] = image;
+    for pixel in buf.chunks_exact_mut(3) {
+        pixel.copy_from_slice(&ramp[pixel[0] as usize].0);
+    }
+}
+
+// a colour image as a canvas, like canvas()
+pub fn rgb_canvas(image: &im::RgbImage, style: &RenderStyle) -> im::RgbImage {
+    if style.downscale <= 1 && style.upscale <= 1 {
+        return image.clone();
+    }
+    let (width, height) = style.canvas_size(image.width(), image.height());
+    let buf = rescale(image, image.width(), image.height(), 3, style);
+    im::RgbImage::from_raw(width, height, buf).expect("rgb buffer size")
+}
+
+// a canvas of bare floor for a `width` by `height` image, like blank()
+pub fn blank_canvas(width: u32, height: u32, style: &RenderStyle) -> im::RgbImage {
+    let (width, height) = style.canvas_size(width, height);
+    im::RgbImage::from_pixel(width, height, style.palette.floor)
+}
+
+pub fn render_nodes(image: &mut im::RgbImage, graph: &DijkstraGraph<NodeData>, style: &RenderStyle) {
+    for data in graph.nodes().values() {
+        style.fill(image, data.rect, style.explore_color);
+    }
+}
+
+// what one search looked at against what it left alone: the `explored` spaces in the explore
+// colour and the rest it could have reached tinted, so searches can be told apart at a glance
+pub fn render_explored(
+    image:    &mut im::RgbImage,
+    graph:    &DijkstraGraph<NodeData>,
+    explored: &HashSet<NodeID>,
+    style:    &RenderStyle)
+{
+    for (id, data) in graph.nodes().iter() {
+        let color = if explored.contains(id) { style.explore_color } else { style.palette.unexplored };
+        style.fill(image, data.rect, color);
+    }
+}
+
+pub fn render_path(image: &mut im::RgbImage, solution: &Solution, style: &RenderStyle) {
+    for rect in solution.rects.iter() {
+        style.fill(image, *rect, style.path_color);
+    }
+}
+
+// the explored spaces on one floor of a multi-level solve
+pub fn render_level_nodes(image: &mut im::RgbImage, graph: &DijkstraGraph<LevelRect>, level: usize, style: &RenderStyle) {
+    for space in graph.nodes().values().filter(|space| space.level == level) {
+        style.fill(image, space.rect, style.explore_color);
+    }
+}
+
+// the stretches of a multi-level path on one floor
+pub fn render_level_path(image: &mut im::RgbImage, graph: &DijkstraGraph<LevelRect>, level: usize, style: &RenderStyle) {
+    for id in graph.path_to(graph.goal()) {
+        let space = graph.get_node(id);
+        if space.level == level {
+            style.fill(image, space.rect, style.path_color);
+        }
+    }
+}
+
+// dims every space that prune() proved can't be on any route
+pub fn render_dead_ends(image: &mut im::RgbImage, dead_ends: &HashMap<NodeID, NodeData>, style: &RenderStyle) {
+    for data in dead_ends.values() {
+        style.fill(image, data.rect, style.palette.dead_end);
+    }
+}
+
+// fills several routes, the best in the path colour and the rest from the region palette.
+// worse routes go underneath, so that where they share corridors the better one shows.
+pub fn render_routes(image: &mut im::RgbImage, graph: &DijkstraGraph<NodeData>, routes: &[Vec<NodeID>], style: &RenderStyle) {
+    for (index, route) in routes.iter().enumerate().rev() {
+        let color = if index == 0 { style.path_color } else { style.palette.region(index) };
+        for id in route.iter() {
+            style.fill(image, graph.rect(*id), color);
+        }
+    }
+}
+
+// tints the solution rects and draws the refined pixel path through them
+pub fn render_pixel_path(
+    image:    &mut im::RgbImage,
+    solution: &Solution,
+    pixels:   &[V2],
+    style:    &RenderStyle)
+{
+    for rect in solution.rects.iter() {
+        style.fill(image, *rect, style.path_tint());
+    }
+    render_line(image, pixels, style);
+}
+
+// a square `thickness` px across over `pos`
+fn brush(pos: V2, thickness: u32) -> Rect {
+    let lo = (thickness as Coord - 1) / 2;
+    let hi = thickness as Coord - lo;
+    Rect::new_unchecked(pos - V2::new(lo, lo), pos + V2::new(hi, hi))
+}
+
+// a contiguous run of pixels, drawn in the path color at the style's thickness
+pub fn render_line(image: &mut im::RgbImage, pixels: &[V2], style: &RenderStyle) {
+    if style.upscale > 1 {
+        // a brush sized in px of the image is enlarged with it, widening the line to match
+        for pos in pixels.iter() {
+            fill_rect(image, style.canvas_rect(brush(*pos, style.thickness)), style.path_color);
+        }
+        return;
+    }
+
+    let shrunk;
+    let pixels = if style.downscale <= 1 { pixels }
+                 else                    { shrunk = style.canvas_line(pixels); &shrunk[..] };
+    if style.thickness > 1 {
+        // a square brush on every pixel of the path, which is contiguous, makes a thick line
+        for pos in pixels.iter() {
+            fill_rect(image, brush(*pos, style.thickness), style.path_color);
+        }
+    }
+    else {
+        draw_polyline(image, pixels, style.path_color);
+    }
+}
+
+// what dead-end filling leaves: the corridor lightly tinted in the path colour, and the
+// filled pixels, if given, dimmed
+pub fn render_corridor(image: &mut im::RgbImage, corridor: &[V2], dead_ends: &[V2], style: &RenderStyle) {
+    let pixel = |p: &V2| style.canvas_rect(Rect::new_unchecked(*p, *p + V2::new(1, 1)));
+    for p in dead_ends.iter() {
+        fill_rect(image, pixel(p), style.palette.dead_end);
+    }
+    for p in corridor.iter() {
+        fill_rect(image, pixel(p), style.path_tint());
+    }
+}
+
+// a route that may go back over itself, each pixel coloured by how far along it comes, green
+// at the start through to red at the goal, so the later passes show over the earlier
+pub fn render_route(image: &mut im::RgbImage, pixels: &[V2], style: &RenderStyle) {
+    let last = pixels.len().saturating_sub(1).max(1);
+    for (step, pos) in pixels.iter().enumerate() {
+        fill_rect(image, style.canvas_rect(brush(*pos, style.thickness)), style.palette.heat(step as f32 / last as f32));
+    }
+}
+
+// an open square around `pos`, for routes too short to show as a path
+pub fn render_marker(image: &mut im::RgbImage, pos: V2, style: &RenderStyle) {
+    // centred on the square an enlarged pixel becomes, and enlarged with it
+    let unit = style.upscale.max(1) as Coord;
+    let pos = style.canvas_point(pos) + V2::new(unit / 2, unit / 2);
+    let line = style.thickness as Coord * unit;
+    let radius = (2 + 2 * style.thickness as Coord) * unit;
+    let outer = Rect::new_unchecked(pos - V2::new(radius, radius), pos + V2::new(radius + 1, radius + 1));
+    let sides = [
+        Rect::new_unchecked(outer.mins, V2::new(outer.maxs.x, outer.mins.y + line)),
+        Rect::new_unchecked(V2::new(outer.mins.x, outer.maxs.y - line), outer.maxs),
+        Rect::new_unchecked(outer.mins, V2::new(outer.mins.x + line, outer.maxs.y)),
+        Rect::new_unchecked(V2::new(outer.maxs.x - line, outer.mins.y), outer.maxs),
+    ];
+    for side in sides.iter() {
+        fill_rect(image, *side, style.path_color);
+    }
+}
+
+#[cfg(not(feature = "imageproc"))]
+pub fn draw_polyline(image: &mut im::RgbImage, points: &[V2], color: im::Rgb<u8>) {
+    // refined paths are already pixel-contiguous, so plotting the points draws the line
+    for pos in points.iter() {
+        if pos.x >= 0 && pos.y >= 0 && (pos.x as u32) < image.width() && (pos.y as u32) < image.height() {
+            image.put_pixel(pos.x as u32, pos.y as u32, color);
+        }
+    }
+}
+
+#[cfg(feature = "imageproc")]
+pub fn draw_polyline(image: &mut im::RgbImage, points: &[V2], color: im::Rgb<u8>) {
+    use imageproc::drawing::draw_line_segment_mut;
+    for pair in points.windows(2) {
+        let a = (pair[0].x as f32, pair[0].y as f32);
+        let b = (pair[1].x as f32, pair[1].y as f32);
+        draw_line_segment_mut(image, a, b, color);
+    }
+}
+
+// tints every classified space by its kind
+pub fn render_spaces<'a>(
+    image:  &mut im::RgbImage,
+    spaces: impl Iterator<Item = &'a NodeData>,
+    style:  &RenderStyle)
+{
+    for data in spaces {
+        let tint = match data.kind {
+            NodeKind::Room     => style.palette.room,
+            NodeKind::Corridor => style.palette.corridor,
+            NodeKind::Space    => continue,
+        };
+        style.fill(image, data.rect, tint);
+    }
+}
+
+// a light colour of its own for each node, the same for the same id every time. the conversion
+// is a no-op with wide-ids
+#[allow(clippy::useless_conversion)]
+pub fn node_color(id: NodeID) -> im::Rgb<u8> {
+    let rng = Pcg32Basic::seed_from_u64(u64::from(id.get()));
+    let mut components = Uniform::new_inclusive(0x80, 0xff).sample_iter(rng);
+    im::Rgb([(); 3].map(|_| components.next().expect("uniform samples never run out")))
+}
+
+// fills every space in its own colour, showing how the clear space was cut into rects
+pub fn render_decomposition<'a>(
+    image:  &mut im::RgbImage,
+    spaces: impl Iterator<Item = (&'a NodeID, &'a NodeData)>,
+    style:  &RenderStyle)
+{
+    for (id, data) in spaces {
+        style.fill(image, data.rect, node_color(*id));
+    }
+}
+
+// the graph as pruned: its spaces tinted, the dead ends dimmed, and a line in the path colour
+// from the middle of each space through every opening it has to the middle of the next
+pub fn render_graph(
+    image:     &mut im::RgbImage,
+    graph:     &EdgeSetGraph<NodeData>,
+    dead_ends: &HashMap<NodeID, NodeData>,
+    style:     &RenderStyle)
+{
+    for data in graph.nodes().values() {
+        style.fill(image, data.rect, style.explore_color);
+    }
+    render_dead_ends(image, dead_ends, style);
+
+    for edge in graph.edges.iter() {
+        let (a, b) = (graph.get_node(edge.min).rect, graph.get_node(edge.max).rect);
+        render_line(image, &refine::refine_path(&[a, b], a.center(), b.center()), style);
+    }
+}
+
+// the graph the search runs over as nodes and edges rather than spaces: a dot in the middle of
+// each space it keeps, and a line to each neighbour through the openings between, or along the
+// corridor a contracted link stands for
+pub fn render_skeleton(image: &mut im::RgbImage, graph: &AdjacencyGraph<NodeData>, style: &RenderStyle) {
+    let edges = RenderStyle { path_color: style.palette.skeleton_edge, ..*style };
+    let mut draw = |mut ids: Vec<NodeID>| {
+        // a chain into a dead end ends on its last space
+        ids.dedup();
+        let rects: Vec<Rect> = ids.iter().map(|id| graph.get_node(*id).rect).collect();
+        let (from, to) = (rects[0].center(), rects[rects.len() - 1].center());
+        render_line(image, &refine::refine_path(&rects, from, to), &edges);
+    };
+    for chain in graph.chains.iter() {
+        draw(std::iter::once(chain.from).chain(chain.via.iter().copied()).chain(std::iter::once(chain.to)).collect());
+    }
+    for (u, neighbors) in graph.adjs.iter() {
+        for v in neighbors.iter().filter(|v| u < *v && !graph.links.contains_key(&Edge::new(*u, **v))) {
+            draw(vec![*u, *v]);
+        }
+    }
+
+    for id in graph.adjs.keys() {
+        let dot = brush(graph.get_node(*id).rect.center(), 1 + 2 * style.thickness);
+        fill_rect(image, style.canvas_rect(dot), style.palette.skeleton_node);
+    }
+}
+
+// colours every reached rect by the seed it is closest to
+pub fn render_partition(
+    image: &mut im::RgbImage,
+    graph: &DijkstraGraph<NodeData>,
+    seeds: &[NodeID],
+    style: &RenderStyle)
+{
+    for (id, data) in graph.nodes().iter() {
+        let origin = match graph.origin(*id) {
+            Some(origin) => origin,
+            None         => continue,
+        };
+
+        if let Some(index) = seeds.iter().position(|seed| *seed == origin) {
+            style.fill(image, data.rect, style.palette.region(index));
+        }
+    }
+}
+
+// colours every reached rect by its distance from the nearest source
+pub fn render_distance_field(image: &mut im::RgbImage, graph: &DijkstraGraph<NodeData>, style: &RenderStyle) {
+    let max_dist = graph.nodes().keys()
+        .map(|id| graph.distance(*id))
+        .filter(|dist| *dist != i32::MAX)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    for (id, data) in graph.nodes().iter() {
+        let dist = graph.distance(*id);
+        if dist != i32::MAX {
+            style.fill(image, data.rect, style.palette.heat(dist as f32 / max_dist as f32));
+        }
+    }
+}
+
+// colours every clear pixel by its region, leaving the walls as they are
+pub fn render_components(image: &mut im::RgbImage, components: &Components) {
+    let buf: &mut [u8] = image;
+    for (pixel, label) in buf.chunks_exact_mut(3).zip(components.labels.iter()) {
+        if *label != 0 {
+            pixel.copy_from_slice(&region_color(*label as usize - 1).0);
+        }
+    }
+}
+
+// writes the search to `sink` as numbered frames: one every `every` expansions, then one with
+// the traced path. the interiors of contracted chains are never expanded themselves, so each
+// frame shows everything as near the sources as the latest expansion. returns the frame count.
+pub fn render_frames(
+    base:   &im::RgbImage,
+    graph:  &DijkstraGraph<NodeData>,
+    pixels: Option<&[V2]>,
+    sink:   &mut dyn OutputSink,
+    every:  usize,
+    style:  &RenderStyle)
+    -> io::Result<usize>
+{
+    let mut reached: Vec<(i32, Rect)> = graph.nodes().iter()
+        .map(|(id, data)| (graph.distance(*id), data.rect))
+        .filter(|(dist, _)| *dist != i32::MAX)
+        .collect();
+    reached.sort_by_key(|(dist, _)| *dist);
+
+    let mut canvas = base.clone();
+    let mut frames = 0;
+    let mut save = |canvas: &im::RgbImage| {
+        frames += 1;
+        sink.write(&format!("frame_{:05}.png", frames - 1), canvas)
+    };
+
+    let mut painted = 0;
+    let mut paint_up_to = |canvas: &mut im::RgbImage, frontier: i32| {
+        while painted < reached.len() && reached[painted].0 <= frontier {
+            style.fill(canvas, reached[painted].1, style.explore_color);
+            painted += 1;
+        }
+    };
+
+    for (step, id) in graph.order.iter().enumerate() {
+        let last = step + 1 == graph.order.len();
+        if (step + 1) % every.max(1) == 0 || last {
+            paint_up_to(&mut canvas, graph.distance(*id));
+            save(&canvas)?;
+        }
+    }
+
+    paint_up_to(&mut canvas, i32::MAX);
+    if let Some(solution) = graph.solution() {
+        match pixels {
+            Some(pixels) => render_pixel_path(&mut canvas, &solution, pixels, style),
+            None         => render_path(&mut canvas, &solution, style),
+        }
+    }
+    save(&canvas)?;
+
+    Ok(frames)
+}
+
+// one search's part in a race: what it expanded, in order, and the path it found
+pub struct Lane<'a> {
+    pub expanded: &'a [NodeID],
+    pub solution: Option<&'a Solution>,
+}
+
+// searches over one graph raced side by side, a lane each and a frame every `every` expansions:
+// each lane fills in as its search explores, and draws its path once it's done, so the one
+// that finishes first sits still while the rest catch up. returns how many frames were written.
+pub fn render_race(
+    base:  &im::RgbImage,
+    graph: &DijkstraGraph<NodeData>,
+    lanes: &[Lane],
+    sink:  &mut dyn OutputSink,
+    every: usize,
+    style: &RenderStyle)
+    -> io::Result<usize>
+{
+    // each lane's spaces by the expansion that explored them, the corridors a contracted graph
+    // skips over going with whichever of their ends came first
+    let painting: Vec<Vec<(usize, Rect)>> = lanes.iter()
+        .map(|lane| {
+            let mut explored_at: HashMap<NodeID, usize> = HashMap::default();
+            for (step, id) in lane.expanded.iter().enumerate() {
+                explored_at.entry(*id).or_insert(step);
+            }
+            for chain in graph.inner.chains.iter() {
+                let at = [chain.from, chain.to].iter().filter_map(|id| explored_at.get(id).copied()).min();
+                if let Some(at) = at {
+                    for id in chain.via.iter() {
+                        explored_at.entry(*id).or_insert(at);
+                    }
+                }
+            }
+            let mut painting: Vec<(usize, Rect)> = explored_at.into_iter()
+                .filter_map(|(id, step)| graph.nodes().get(&id).map(|data| (step, data.rect)))
+                .collect();
+            painting.sort_by_key(|(step, _)| *step);
+            painting
+        })
+        .collect();
+
+    let (width, height) = base.dimensions();
+    let gap = RACE_GAP * (lanes.len().max(1) as u32 - 1);
+    let mut frame = im::RgbImage::from_pixel(width * lanes.len() as u32 + gap, height, style.palette.gap);
+    let mut canvases: Vec<im::RgbImage> = lanes.iter().map(|_| base.clone()).collect();
+    let mut painted = vec![0; lanes.len()];
+    let mut finished = vec![false; lanes.len()];
+
+    let longest = lanes.iter().map(|lane| lane.expanded.len()).max().unwrap_or(0);
+    let mut steps: Vec<usize> = (every.max(1) .. longest).step_by(every.max(1)).collect();
+    steps.push(longest.max(1));
+
+    let mut frames = 0;
+    for steps_done in steps {
+        for (index, lane) in lanes.iter().enumerate() {
+            let canvas = &mut canvases[index];
+            while painted[index] < painting[index].len() && painting[index][painted[index]].0 < steps_done {
+                style.fill(canvas, painting[index][painted[index]].1, style.explore_color);
+                painted[index] += 1;
+            }
+            if !finished[index] && steps_done >= lane.expanded.len() {
+                if let Some(solution) = lane.solution {
+                    render_path(canvas, solution, style);
+                }
+                finished[index] = true;
+            }
+            im::imageops::replace(&mut frame, canvas, index as u32 * (width + RACE_GAP), 0);
+        }
+        sink.write(&format!("frame_{:05}.png", frames), &frame)?;
+        frames += 1;
+    }
+
+    Ok(frames)
+}
+
+// a trémaux walk as numbered frames, one every `every` passages walked: each passage in the
+// explore colour once it's marked once and dimmed once it's marked twice, as is each junction
+// with no passage out of it still marked once, with the walker boxed where it's got to, ending
+// on the path it found. returns how many frames were written.
+pub fn render_walk(
+    base:     &im::RgbImage,
+    graph:    &DijkstraGraph<NodeData>,
+    walk:     &Walk,
+    solution: Option<&Solution>,
+    sink:     &mut dyn OutputSink,
+    every:    usize,
+    style:    &RenderStyle)
+    -> io::Result<usize>
+{
+    let color = |once: bool| if once { style.explore_color } else { style.palette.dead_end };
+    // each junction's passages marked just once
+    let mut trails: HashMap<NodeID, usize> = HashMap::default();
+    let mut canvas = base.clone();
+    let mut frames = 0;
+    for (index, step) in walk.steps.iter().enumerate() {
+        let once = step.marks == 1;
+        let via = graph.inner.links.get(&Edge::new(step.from, step.to))
+            .map_or(&[][..], |chain| &graph.inner.chains[*chain].via[..]);
+        for id in via.iter() {
+            style.fill(&mut canvas, graph.get_node(*id).rect, color(once));
+        }
+        for id in [step.from, step.to].iter() {
+            let trail = trails.entry(*id).or_default();
+            if once { *trail += 1; }
+            else    { *trail -= 1; }
+            style.fill(&mut canvas, graph.get_node(*id).rect, color(*trail > 0 || *id == step.to));
+        }
+
+        if (index + 1) % every.max(1) == 0 {
+            let mut frame = canvas.clone();
+            render_marker(&mut frame, graph.get_node(step.to).rect.center(), style);
+            sink.write(&format!("frame_{:05}.png", frames), &frame)?;
+            frames += 1;
+        }
+    }
+
+    if let Some(solution) = solution {
+        render_path(&mut canvas, solution, style);
+    }
+    sink.write(&format!("frame_{:05}.png", frames), &canvas)?;
+    Ok(frames + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::preprocess::{CLEAR, WALL},
+    };
+
+    #[test]
+    fn downscaled_render_averages_and_keeps_thin_lines() {
+        // a wall down the first column of a 10x6 room, shrunk to 3x2 with the last column and
+        // row of canvas pixels covering what's left over
+        let mut image = im::GrayImage::from_pixel(10, 6, CLEAR);
+        for y in 0 .. 6 {
+            image.put_pixel(0, y, WALL);
+        }
+        let style = RenderStyle { downscale: 4, ..RenderStyle::default() };
+        let mut canvas = canvas(&image, &style);
+        assert_eq!(canvas.dimensions(), (3, 2));
+        assert_eq!(canvas.get_pixel(0, 1).0[0], (u32::from(CLEAR[0]) * 3 / 4) as u8);
+        assert_eq!(canvas.get_pixel(2, 1).0[0], CLEAR[0]);
+
+        // a line one px wide along the bottom row still covers the canvas pixels it crosses
+        let line: Vec<V2> = (0 .. 10).map(|x| V2::new(x, 5)).collect();
+        render_line(&mut canvas, &line, &style);
+        assert!((0 .. 3).all(|x| *canvas.get_pixel(x, 1) == style.path_color));
+        assert!((0 .. 3).all(|x| *canvas.get_pixel(x, 0) != style.path_color));
+    }
+
+    #[test]
+    fn upscaled_render_enlarges_pixels_and_lines() {
+        let mut image = im::GrayImage::from_pixel(3, 2, CLEAR);
+        image.put_pixel(1, 0, WALL);
+        let style = RenderStyle { upscale: 3, thickness: 1, ..RenderStyle::default() };
+        let mut canvas = canvas(&image, &style);
+        assert_eq!(canvas.dimensions(), (9, 6));
+        for (x, y, pixel) in canvas.enumerate_pixels() {
+            assert_eq!(pixel.0[0], image.get_pixel(x / 3, y / 3).0[0]);
+        }
+
+        // a one-px line down the last column comes out three px wide
+        render_line(&mut canvas, &[V2::new(2, 0), V2::new(2, 1)], &style);
+        for (x, _, pixel) in canvas.enumerate_pixels() {
+            assert_eq!(*pixel == style.path_color, x >= 6);
+        }
+    }
+
+    #[test]
+    fn themes_paint_walls_and_floors_their_own() {
+        let mut image = im::GrayImage::from_pixel(2, 1, CLEAR);
+        image.put_pixel(0, 0, WALL);
+        let classic = canvas(&image, &RenderStyle::default());
+        assert_eq!(classic.get_pixel(0, 0).0, [WALL[0]; 3]);
+
+        let style = Theme::Dark.apply(RenderStyle { upscale: 2, ..RenderStyle::default() });
+        assert_eq!(style.upscale, 2);
+        let canvas = canvas(&image, &style);
+        assert_eq!(*canvas.get_pixel(0, 0), style.palette.wall);
+        assert_eq!(*canvas.get_pixel(3, 1), style.palette.floor);
+        assert_eq!(*blank_canvas(2, 1, &style).get_pixel(0, 0), style.palette.floor);
+    }
+}
@@ -0,0 +1,109 @@
+// the summary of a batch run, written into its output directory as report.html. failures come
+// first, then mazes with warnings, so that triage starts at the top.
+
+use {
+    crate::stats::Stats,
+    image as im,
+    std::{fs, io, path::Path},
+};
+
+const THUMBNAIL_SIZE: u32 = 160;
+
+pub struct Entry {
+    pub name:      String,
+    // the solved image and its thumbnail, relative to the report
+    pub output:    String,
+    pub thumbnail: Option<String>,
+    pub result:    Result<Stats, String>,
+}
+
+impl Entry {
+    fn rank(&self) -> usize {
+        match &self.result {
+            Err(_)                                  => 0,
+            Ok(stats) if !stats.warnings.is_empty() => 1,
+            Ok(_)                                   => 2,
+        }
+    }
+}
+
+// shrinks the image at `from` to fit a thumbnail, saving it at `to`
+pub fn save_thumbnail(from: &Path, to: &Path) -> io::Result<()> {
+    let image = im::open(from).map_err(io::Error::other)?;
+    if let Some(dir) = to.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).save(to)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn row(html: &mut String, entry: &Entry) {
+    let name = escape(&entry.name);
+    let output = escape(&entry.output);
+    let class = ["failed", "warned", "solved"][entry.rank()];
+    *html += &format!("<tr class=\"{}\"><td>{}</td><td>", class, name);
+
+    match &entry.thumbnail {
+        Some(thumb) => {
+            *html += &format!("<a href=\"{}\"><img src=\"{}\" alt=\"{}\"></a>", output, escape(thumb), name);
+        }
+        None if entry.result.is_ok() => { *html += &format!("<a href=\"{}\">{}</a>", output, output); }
+        None => { }
+    }
+    html.push_str("</td>");
+
+    match &entry.result {
+        Ok(stats) => {
+            let seconds: f64 = stats.stages.iter().map(|stage| stage.seconds).sum();
+            let stages: Vec<String> = stats.stages.iter()
+                .map(|stage| format!("{} {:.3} s", stage.name, stage.seconds))
+                .collect();
+            let trivial = if stats.trivial { " (start and goal share a space)" } else { "" };
+            *html += &format!("<td>{}{}</td><td title=\"{}\">{:.3} s</td><td>",
+                stats.solution_length, trivial, escape(&stages.join(", ")), seconds);
+            for warning in stats.warnings.iter() {
+                *html += &format!("<p>warning: {}</p>", escape(warning));
+            }
+            html.push_str("</td>");
+        }
+        Err(err) => {
+            *html += &format!("<td></td><td></td><td><p>error: {}</p></td>", escape(err));
+        }
+    }
+
+    html.push_str("</tr>\n");
+}
+
+pub fn to_html(title: &str, entries: &[Entry]) -> String {
+    let failed = entries.iter().filter(|e| e.rank() == 0).count();
+    let warned = entries.iter().filter(|e| e.rank() == 1).count();
+
+    let mut html = format!("<!DOCTYPE html>\n\
+        <html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+        <style>\n\
+        body {{ font-family: sans-serif; }}\n\
+        table {{ border-collapse: collapse; }}\n\
+        th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }}\n\
+        td p {{ margin: 0; }}\n\
+        tr.failed {{ background: #fdd; }}\n\
+        tr.warned {{ background: #ffd; }}\n\
+        </style>\n</head>\n<body>\n<h1>{}</h1>\n\
+        <p>{} of {} solved; {} failed, {} with warnings.</p>\n\
+        <table>\n<tr><th>maze</th><th>solution</th><th>length</th><th>time</th><th>notes</th></tr>\n",
+        escape(title), escape(title), entries.len() - failed, entries.len(), failed, warned);
+
+    let mut order: Vec<&Entry> = entries.iter().collect();
+    order.sort_by_key(|entry| entry.rank());
+    for entry in order {
+        row(&mut html, entry);
+    }
+
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
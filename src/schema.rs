@@ -0,0 +1,142 @@
+// the json every export writes is tagged with its schema, as "schema": "mazesolve-rk/path/1",
+// and the schemas themselves are printed by --schema. a version only goes up when a field is
+// removed, renamed or changes meaning; new fields are added within one, so readers should
+// ignore fields they don't know rather than reject them.
+
+use {
+    serde::Serialize,
+    serde_json::{json, Value},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Schema {
+    pub name:    &'static str,
+    pub version: u32,
+}
+
+pub const PATH:  Schema = Schema { name: "path",  version: 1 };
+pub const STATS: Schema = Schema { name: "stats", version: 1 };
+pub const GRAPH: Schema = Schema { name: "graph", version: 1 };
+pub const TRACE: Schema = Schema { name: "trace", version: 1 };
+
+pub const ALL: &[Schema] = &[PATH, STATS, GRAPH, TRACE];
+
+impl Schema {
+    pub fn by_name(name: &str) -> Option<Schema> {
+        ALL.iter().copied().find(|schema| schema.name == name)
+    }
+
+    // the tag an export carries
+    pub fn id(self) -> String {
+        format!("mazesolve-rk/{}/{}", self.name, self.version)
+    }
+
+    // `body` with its schema tag first
+    pub fn tag<T: Serialize>(self, body: &T) -> Tagged<'_, T> {
+        Tagged { schema: self.id(), body }
+    }
+
+    // the json schema describing the export
+    pub fn document(self) -> Value {
+        let (title, required, properties): (&str, &[&str], Value) = match self.name {
+            "path" => ("a solution path", &["length", "origin", "rects", "pixels"], json!({
+                "length": { "type": "integer", "description": "in graph steps, or px for the pixel solvers" },
+                "origin": origin(),
+                "rects":  { "type": "array", "items": rect(), "description": "from start to goal" },
+                "pixels": { "type": "array", "items": point(), "description": "from start to goal" },
+            })),
+            "stats" => ("solver statistics",
+                &["nodes_extracted", "edges_extracted", "solution_length", "stages", "warnings"], json!({
+                "nodes_extracted": count(),
+                "edges_extracted": count(),
+                "mean_rect_area":  { "type": "number" },
+                "nodes_pruned":    count(),
+                "edges_pruned":    count(),
+                "search_nodes":    count(),
+                "chains":          count(),
+                "expansions":      count(),
+                "relaxations":     count(),
+                "max_frontier":    count(),
+                "solution_length": { "type": "integer" },
+                "trivial":         { "type": "boolean", "description": "start and goal share a space" },
+                "stages": { "type": "array", "items": {
+                    "type": "object",
+                    "required": ["name", "seconds"],
+                    "properties": { "name": { "type": "string" }, "seconds": { "type": "number" } },
+                }},
+                "warnings": { "type": "array", "items": { "type": "string" } },
+            })),
+            "graph" => ("the extracted spaces and the openings between them",
+                &["origin", "start", "goal", "nodes", "edges"], json!({
+                "origin": origin(),
+                "start":  count(),
+                "goal":   count(),
+                "nodes":  { "type": "array", "description": "by id", "items": {
+                    "type": "object",
+                    "required": ["id", "rect", "area"],
+                    "properties": {
+                        "id":   count(),
+                        "rect": rect(),
+                        "area": { "type": "integer", "description": "covered px" },
+                    },
+                }},
+                "edges": { "type": "array", "items": {
+                    "type": "array", "items": count(), "minItems": 2, "maxItems": 2,
+                    "description": "two node ids, the lower first",
+                }},
+            })),
+            "trace" => ("the spaces a search expanded, in order",
+                &["origin", "steps"], json!({
+                "origin": origin(),
+                "steps":  { "type": "array", "items": {
+                    "type": "object",
+                    "required": ["node", "dist", "rect"],
+                    "properties": {
+                        "node": count(),
+                        "dist": { "type": "integer", "description": "from the start when expanded" },
+                        "rect": rect(),
+                    },
+                }},
+            })),
+            _ => unreachable!("{} is in ALL", self.name),
+        };
+
+        let mut properties = properties;
+        properties["schema"] = json!({ "const": self.id() });
+        let required: Vec<&str> = std::iter::once("schema").chain(required.iter().copied()).collect();
+        json!({
+            "$schema":  "https://json-schema.org/draft/2020-12/schema",
+            "$id":      self.id(),
+            "title":    title,
+            "type":     "object",
+            "required": required,
+            "properties": properties,
+            "additionalProperties": true,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct Tagged<'a, T> {
+    schema: String,
+    #[serde(flatten)]
+    body:   &'a T,
+}
+
+fn count() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+
+fn origin() -> Value {
+    json!({ "enum": ["top-left", "bottom-left"], "description": "the corner coordinates count from" })
+}
+
+fn rect() -> Value {
+    json!({ "type": "array", "items": { "type": "integer" }, "minItems": 4, "maxItems": 4,
+            "description": "[x0, y0, x1, y1], half-open" })
+}
+
+fn point() -> Value {
+    json!({ "type": "array", "items": { "type": "integer" }, "minItems": 2, "maxItems": 2,
+            "description": "[x, y]" })
+}
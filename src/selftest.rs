@@ -0,0 +1,130 @@
+use {
+    mazesolve_rk::{
+        math::*,
+        image_graph::{extract_graph, Decomposition, ExtractOptions},
+        preprocess::{CLEAR, WALL},
+        refine::refine_path,
+    },
+    image as im,
+};
+
+// '#' is wall, anything else clear; 'S' and 'G' mark the start and goal
+struct Sample {
+    name:    &'static str,
+    rows:    &'static [&'static str],
+    // expected solution lengths with the greedy and strip decompositions
+    lengths: [i32; 2],
+}
+
+const SAMPLES: &[Sample] = &[
+    Sample {
+        name: "corridors",
+        rows: &[
+            "#S#########",
+            "# #     # #",
+            "# # ### # #",
+            "#   #   # #",
+            "##### ### #",
+            "#   #     #",
+            "# # ##### #",
+            "# #   #   #",
+            "# ### # ###",
+            "#   #     #",
+            "#########G#",
+        ],
+        lengths: [12, 12],
+    },
+    Sample {
+        name: "loop",
+        rows: &[
+            "#S###########",
+            "#           #",
+            "# ######### #",
+            "# #       # #",
+            "# # ##### # #",
+            "#   #   #   #",
+            "### # # # ###",
+            "#     #     #",
+            "###########G#",
+        ],
+        lengths: [6, 6],
+    },
+    Sample {
+        name: "room",
+        rows: &[
+            "#S######",
+            "#      #",
+            "#  ##  #",
+            "#  ##  #",
+            "#      #",
+            "######G#",
+        ],
+        lengths: [2, 4],
+    },
+];
+
+impl Sample {
+    fn image(&self) -> (im::GrayImage, V2, V2) {
+        let (w, h) = (self.rows[0].len() as u32, self.rows.len() as u32);
+        let mut image = im::GrayImage::new(w, h);
+        let (mut start, mut goal) = (V2::new(0, 0), V2::new(0, 0));
+
+        for (y, row) in self.rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let pos = V2::new(x as Coord, y as Coord);
+                match c {
+                    'S' => start = pos,
+                    'G' => goal  = pos,
+                    _   => { }
+                }
+                image.put_pixel(x as u32, y as u32, if c == '#' { WALL } else { CLEAR });
+            }
+        }
+        (image, start, goal)
+    }
+
+    // the whole solve: extract, prune, contract, search and refine
+    fn solve(&self, decomposition: Decomposition) -> Result<i32, String> {
+        let (image, start, goal) = self.image();
+        let opts = ExtractOptions { decomposition, ..ExtractOptions::default() };
+        let graph = extract_graph(&image, start, goal, &opts)
+            .map_err(|err| err.to_string())?
+            .prune()
+            .into_adjacency_graph()
+            .contract()
+            .into_dijkstra();
+
+        let solution = graph.solution().ok_or("goal unreached")?;
+        let pixels = refine_path(&solution.rects, start, goal);
+        let clear = |p: &V2| image.get_pixel(p.x as u32, p.y as u32) == &CLEAR;
+        let touching = |a: V2, b: V2| (a - b).chebyshev() <= 1;
+        if !pixels.iter().all(clear) || !pixels.windows(2).all(|p| touching(p[0], p[1])) {
+            return Err("refined path leaves the corridors".into());
+        }
+
+        Ok(solution.cost)
+    }
+}
+
+// runs every sample through the pipeline, reporting as it goes; true if all pass
+pub fn run() -> bool {
+    let mut passed = true;
+    for sample in SAMPLES.iter() {
+        let decompositions = [Decomposition::Greedy, Decomposition::Strips];
+        for (decomposition, expected) in decompositions.iter().zip(sample.lengths.iter()) {
+            let label = format!("{} ({:?})", sample.name, decomposition).to_lowercase();
+            match sample.solve(*decomposition) {
+                Ok(length) if length == *expected => println!("ok   {}: length {}", label, length),
+                Ok(length) => {
+                    println!("FAIL {}: expected length {}, got {}", label, expected, length);
+                    passed = false;
+                }
+                Err(msg) => {
+                    println!("FAIL {}: {}", label, msg);
+                    passed = false;
+                }
+            }
+        }
+    }
+    passed
+}
@@ -0,0 +1,230 @@
+// a maze solved a bounded chunk at a time, for GUI event loops and async servers that can't block
+// on a whole solve or hand it to a thread. each step() does about `budget` units of work: seeds
+// popped while extracting, nodes expanded while searching. pruning and contraction are linear in
+// the graph and run whole, in the step that ends extraction. dropping a session between steps
+// cancels it, with nothing left to clean up; run() steps it until done, cancelled from another
+// thread by a CancelToken, or out of time.
+
+use {
+    crate::{
+        math::*,
+        error::Error,
+        graph::{DijkstraGraph, DijkstraSearch, Graph},
+        image_graph::{Extraction, ExtractOptions, NodeData},
+    },
+    image as im,
+    std::{
+        sync::{atomic::{AtomicBool, Ordering}, Arc},
+        time::{Duration, Instant},
+    },
+};
+
+// the units of work run() does between looks at its token and the clock
+const CHECK_EVERY: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Extracting,
+    Searching,
+    Solved,
+    Failed,
+}
+
+enum Stage {
+    Extracting(Extraction),
+    Searching(DijkstraSearch<NodeData>),
+    Solved(DijkstraGraph<NodeData>),
+    Failed(Error),
+}
+
+// shared between a solve and whoever may want it stopped, as a server's handler for a request
+// the client gave up on
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub struct SolveSession {
+    // only ever None while a step moves it on
+    stage: Option<Stage>,
+    goal:  V2,
+}
+
+impl SolveSession {
+    pub fn new(image: &im::GrayImage, start: V2, goal: V2, opts: &ExtractOptions) -> SolveSession {
+        // an image too large to extract from fails the session, as any other extraction error does
+        let stage = match Extraction::new(image, start, goal, opts) {
+            Ok(extraction) => Stage::Extracting(extraction),
+            Err(err)       => Stage::Failed(err),
+        };
+        SolveSession { stage: Some(stage), goal }
+    }
+
+    pub fn status(&self) -> Status {
+        match self.stage.as_ref().expect("stage is put back after every step") {
+            Stage::Extracting(_) => Status::Extracting,
+            Stage::Searching(_)  => Status::Searching,
+            Stage::Solved(_)     => Status::Solved,
+            Stage::Failed(_)     => Status::Failed,
+        }
+    }
+
+    // advances by up to about `budget` units of work, returning where that left the solve
+    pub fn step(&mut self, budget: usize) -> Status {
+        let stage = self.stage.take().expect("stage is put back after every step");
+        self.stage = Some(match stage {
+            Stage::Extracting(mut extraction) => match extraction.step(budget) {
+                Ok(false) => Stage::Extracting(extraction),
+                Ok(true)  => match extraction.finish() {
+                    Ok(graph) => {
+                        let graph = graph.prune().into_adjacency_graph().contract();
+                        let (start, goal) = (graph.start(), graph.goal());
+                        Stage::Searching(DijkstraSearch::new(graph, &[start]).stopping_at(goal))
+                    }
+                    Err(err) => Stage::Failed(err),
+                },
+                Err(err) => Stage::Failed(err),
+            },
+            Stage::Searching(mut search) => {
+                if search.run(budget) {
+                    let graph = search.finish();
+                    if graph.goal_distance() == i32::MAX { Stage::Failed(Error::Unreachable(self.goal)) }
+                    else                                  { Stage::Solved(graph) }
+                }
+                else {
+                    Stage::Searching(search)
+                }
+            }
+            done => done,
+        });
+        self.status()
+    }
+
+    // steps until solved or failed, or until `cancel` is cancelled or `limit` has passed, one
+    // chunk of work after. an interrupted session is left as it stood, for another run() or
+    // step() to carry on or to drop, and what it had reached so far is in progress().
+    pub fn run(&mut self, cancel: &CancelToken, limit: Option<Duration>) -> Status {
+        let deadline = limit.map(|limit| Instant::now() + limit);
+        loop {
+            let status = self.step(CHECK_EVERY);
+            if matches!(status, Status::Solved | Status::Failed) || cancel.is_cancelled()
+                || deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return status;
+            }
+        }
+    }
+
+    // how far an unfinished session has got: the spaces extracted, or once searching, the
+    // spaces expanded
+    pub fn progress(&self) -> usize {
+        match self.stage.as_ref().expect("stage is put back after every step") {
+            Stage::Extracting(extraction) => extraction.nodes(),
+            Stage::Searching(search)      => search.stats().expansions,
+            Stage::Solved(graph)          => graph.stats.expansions,
+            Stage::Failed(_)              => 0,
+        }
+    }
+
+    // the searched graph, once solved
+    pub fn solution(&self) -> Option<&DijkstraGraph<NodeData>> {
+        match &self.stage {
+            Some(Stage::Solved(graph)) => Some(graph),
+            _                          => None,
+        }
+    }
+
+    pub fn error(&self) -> Option<&Error> {
+        match &self.stage {
+            Some(Stage::Failed(err)) => Some(err),
+            _                        => None,
+        }
+    }
+}
+
+// a whole solve that gives up with Cancelled or TimedOut rather than run on past either
+pub fn solve_interruptible(
+    image:  &im::GrayImage,
+    start:  V2,
+    goal:   V2,
+    opts:   &ExtractOptions,
+    cancel: &CancelToken,
+    limit:  Option<Duration>)
+    -> Result<DijkstraGraph<NodeData>, Error>
+{
+    let mut session = SolveSession::new(image, start, goal, opts);
+    session.run(cancel, limit);
+    match session.stage.take().expect("stage is put back after every step") {
+        Stage::Solved(graph)       => Ok(graph),
+        Stage::Failed(err)         => Err(err),
+        _ if cancel.is_cancelled() => Err(Error::Cancelled),
+        _                          => Err(Error::TimedOut),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{graph::tests::{pillared_hall, single_room}, image_graph::{extract_graph, Decomposition}},
+    };
+
+    #[test]
+    fn session_in_small_steps_matches_whole_solve() {
+        // pruning and contraction leave the hall plenty of junctions for the search to work through
+        let image = pillared_hall();
+        let (start, goal) = (V2::new(1, 1), V2::new(16, 11));
+
+        for decomposition in [Decomposition::Greedy, Decomposition::Strips].iter().copied() {
+            let opts = ExtractOptions { decomposition, ..ExtractOptions::default() };
+            let whole = extract_graph(&image, start, goal, &opts).unwrap()
+                .prune().into_adjacency_graph().contract().into_dijkstra();
+
+            let mut session = SolveSession::new(&image, start, goal, &opts);
+            let mut steps = 0;
+            while session.step(1) != Status::Solved {
+                assert!(session.error().is_none());
+                steps += 1;
+            }
+            assert!(steps > 2);
+            assert_eq!(session.solution().unwrap().goal_distance(), whole.goal_distance());
+        }
+
+        let mut blocked = SolveSession::new(&image, start, V2::new(0, 0), &ExtractOptions::default());
+        while blocked.step(usize::MAX) != Status::Failed { }
+        assert_eq!(blocked.error(), Some(&Error::Blocked(V2::new(0, 0))));
+    }
+
+    #[test]
+    fn sessions_stop_when_cancelled_or_out_of_time() {
+        let image = single_room();
+        let (start, goal) = (V2::new(1, 1), V2::new(6, 4));
+        let cancel = CancelToken::new();
+        let solved = solve_interruptible(&image, start, goal, &ExtractOptions::default(), &cancel, None);
+        assert!(solved.is_ok());
+
+        // cancelled before it starts, it stops after the first chunk of work, still resumable
+        cancel.cancel();
+        let mut session = SolveSession::new(&image, start, goal, &ExtractOptions::default());
+        assert_ne!(session.run(&cancel, None), Status::Solved);
+        assert_eq!(session.run(&CancelToken::new(), None), Status::Solved);
+        let stopped = solve_interruptible(&image, start, goal, &ExtractOptions::default(), &cancel, None);
+        assert_eq!(stopped.err(), Some(Error::Cancelled));
+
+        let late = solve_interruptible(&image, start, goal, &ExtractOptions::default(), &CancelToken::new(),
+            Some(Duration::ZERO));
+        assert_eq!(late.err(), Some(Error::TimedOut));
+    }
+}
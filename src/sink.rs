@@ -0,0 +1,172 @@
+// where rendered images go. the pipeline names each image it writes, after the file it would be
+// saved as; sinks that don't write files can use the name to tell several outputs apart.
+
+use {
+    std::{collections::HashMap, fs, io::{self, Write}, path::{Path, PathBuf}},
+    image as im,
+};
+
+pub trait OutputSink {
+    fn write(&mut self, name: &str, image: &im::RgbImage) -> io::Result<()>;
+}
+
+pub fn encode_png(image: &im::RgbImage) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    im::png::PNGEncoder::new(&mut bytes)
+        .encode(image, image.width(), image.height(), im::ColorType::RGB(8))?;
+    Ok(bytes)
+}
+
+// as a PNG of palette indices, a quarter the size of RGB before compression and often far less
+// after, with a render's handful of colours packed several pixels to a byte. an image of more
+// than 256 colours keeps its 256 commonest, each of the rest drawn as the nearest of those.
+pub fn encode_indexed_png(image: &im::RgbImage) -> io::Result<Vec<u8>> {
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    for pixel in image.pixels() {
+        *counts.entry(pixel.0).or_default() += 1;
+    }
+    let mut palette: Vec<([u8; 3], usize)> = counts.iter().map(|(color, count)| (*color, *count)).collect();
+    palette.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    palette.truncate(256);
+
+    let distance = |a: [u8; 3], b: [u8; 3]| -> i32 {
+        a.iter().zip(b.iter()).map(|(a, b)| (*a as i32 - *b as i32).pow(2)).sum()
+    };
+    let index: HashMap<[u8; 3], u8> = counts.keys()
+        .map(|color| {
+            let nearest = (0 .. palette.len())
+                .min_by_key(|i| distance(palette[*i].0, *color))
+                .expect("an image with pixels has colours");
+            (*color, nearest as u8)
+        })
+        .collect();
+
+    let depth = match palette.len() {
+        0 ..= 2  => png::BitDepth::One,
+        3 ..= 4  => png::BitDepth::Two,
+        5 ..= 16 => png::BitDepth::Four,
+        _        => png::BitDepth::Eight,
+    };
+    let bits = depth as usize;
+    let row_len = (image.width() as usize * bits).div_ceil(8);
+    let mut data = vec![0u8; row_len * image.height() as usize];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let at = y as usize * row_len * 8 + x as usize * bits;
+        data[at / 8] |= index[&pixel.0] << (8 - bits - at % 8);
+    }
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, image.width(), image.height());
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(depth);
+        encoder.set_filter(png::FilterType::NoFilter);
+        let mut writer = encoder.write_header()?;
+        let colors: Vec<u8> = palette.iter().flat_map(|(color, _)| color.iter().copied()).collect();
+        writer.write_chunk(png::chunk::PLTE, &colors)?;
+        writer.write_image_data(&data)?;
+    }
+    Ok(bytes)
+}
+
+// saves each image under its name, in the format its extension names
+#[derive(Clone, Debug, Default)]
+pub struct FileSink {
+    // prefixed to every name; empty for the working directory
+    pub dir: PathBuf,
+    // PNGs are written as palette indices
+    pub indexed: bool,
+}
+
+impl FileSink {
+    // for writing a set of images into `dir`, which is created if need be
+    pub fn in_dir(dir: impl Into<PathBuf>) -> io::Result<FileSink> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(FileSink { dir, indexed: false })
+    }
+}
+
+impl OutputSink for FileSink {
+    fn write(&mut self, name: &str, image: &im::RgbImage) -> io::Result<()> {
+        let path = self.dir.join(name);
+        let png = Path::new(name).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+        if self.indexed && png {
+            return fs::write(path, encode_indexed_png(image)?);
+        }
+        image.save(path)
+    }
+}
+
+// PNG-encodes every image onto stdout, one after another
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdoutSink {
+    // as palette indices
+    pub indexed: bool,
+}
+
+impl OutputSink for StdoutSink {
+    fn write(&mut self, _name: &str, image: &im::RgbImage) -> io::Result<()> {
+        let bytes = if self.indexed { encode_indexed_png(image)? } else { encode_png(image)? };
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        stdout.write_all(&bytes)?;
+        stdout.flush()
+    }
+}
+
+// keeps every image PNG-encoded, in the order written
+#[derive(Clone, Debug, Default)]
+pub struct MemorySink {
+    pub images: Vec<(String, Vec<u8>)>,
+}
+
+impl OutputSink for MemorySink {
+    fn write(&mut self, name: &str, image: &im::RgbImage) -> io::Result<()> {
+        self.images.push((name.to_string(), encode_png(image)?));
+        Ok(())
+    }
+}
+
+// hands every image to a function, unencoded
+pub struct CallbackSink<F>(pub F);
+
+impl<F> OutputSink for CallbackSink<F>
+    where F: FnMut(&str, &im::RgbImage) -> io::Result<()>
+{
+    fn write(&mut self, name: &str, image: &im::RgbImage) -> io::Result<()> {
+        (self.0)(name, image)
+    }
+}
+
+// pastes every image into a copy of `backdrop` at `at` before handing it on, so renders of a
+// part cut out of a bigger image come out the bigger image's size
+pub struct PasteSink<'a> {
+    pub inner:    &'a mut dyn OutputSink,
+    pub backdrop: im::RgbImage,
+    pub at:       (u32, u32),
+}
+
+impl OutputSink for PasteSink<'_> {
+    fn write(&mut self, name: &str, image: &im::RgbImage) -> io::Result<()> {
+        let mut pasted = self.backdrop.clone();
+        im::imageops::replace(&mut pasted, image, self.at.0, self.at.1);
+        self.inner.write(name, &pasted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexed_png_decodes_to_the_same_pixels() {
+        // a render's four colours, which pack two bits to the pixel, over an odd width
+        let colors = [[0, 0, 0], [255, 255, 255], [200, 220, 255], [255, 0, 0]];
+        let image = im::RgbImage::from_fn(13, 7, |x, y| im::Rgb(colors[((x * 3 + y) % 4) as usize]));
+        let bytes = encode_indexed_png(&image).unwrap();
+        let read = im::load_from_memory(&bytes).unwrap().to_rgb();
+        assert_eq!(read.into_raw(), image.clone().into_raw());
+        assert!(bytes.len() < image.len());
+    }
+}
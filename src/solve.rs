@@ -0,0 +1,53 @@
+// the whole solve behind one call, from a buffer of pixels to the pixels of the path, for
+// bindings to other languages: a pyo3 module, say, can hand over a numpy array's data as it
+// lies, row-major at a byte a pixel.
+
+use {
+    crate::{
+        math::*,
+        error::Error,
+        image_graph::{extract_graph, ExtractOptions},
+        refine::refine_path,
+    },
+    image as im,
+};
+
+// the shortest path from `start` to `goal` through a `width` by `height` image whose pixels at
+// least `threshold` bright are clear, 8-connected pixel by pixel from the start
+pub fn solve_buffer(pixels: &[u8], width: u32, height: u32, start: V2, goal: V2, threshold: u8)
+    -> Result<Vec<V2>, Error>
+{
+    let expected = width as usize * height as usize;
+    if pixels.len() != expected {
+        return Err(Error::BufferSize { len: pixels.len(), expected });
+    }
+    let buffer = pixels.iter().map(|v| if *v >= threshold { 255 } else { 0 }).collect();
+    let image = im::GrayImage::from_raw(width, height, buffer).expect("buffer size was checked");
+
+    let graph = extract_graph(&image, start, goal, &ExtractOptions::default())?;
+    let solved = graph.prune().into_adjacency_graph().contract().into_dijkstra();
+    let solution = solved.solution().ok_or(Error::Unreachable(goal))?;
+    Ok(refine_path(&solution.rects, start, goal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffers_solve_to_a_pixel_path() {
+        // a walled 8x6 room, clear from 128 up, as a mask of 0s and 200s
+        let pixels: Vec<u8> = (0 .. 6).flat_map(|y| (0 .. 8).map(move |x| {
+            if (1 .. 7).contains(&x) && (1 .. 5).contains(&y) { 200 } else { 0 }
+        })).collect();
+        let path = solve_buffer(&pixels, 8, 6, V2::new(1, 1), V2::new(6, 4), 128).unwrap();
+        assert_eq!((path.first(), path.last()), (Some(&V2::new(1, 1)), Some(&V2::new(6, 4))));
+        // 8-connected, as refined paths are
+        assert!(path.windows(2).all(|pair| (pair[1] - pair[0]).chebyshev() == 1));
+
+        assert_eq!(solve_buffer(&pixels, 8, 5, V2::new(1, 1), V2::new(6, 4), 128),
+            Err(Error::BufferSize { len: 48, expected: 40 }));
+        assert_eq!(solve_buffer(&pixels, 8, 6, V2::new(1, 1), V2::new(6, 4), 255),
+            Err(Error::Blocked(V2::new(1, 1))));
+    }
+}
@@ -0,0 +1,107 @@
+// finding the spaces under a rect without looking at every space: a grid of square cells, each
+// listing the rects that overlap it.
+
+use {
+    crate::{
+        math::*,
+        graph::{HashMap, NodeID},
+    },
+};
+
+// node data that covers a rect of the image
+pub trait Spatial {
+    fn rect(&self) -> Rect;
+}
+
+impl Spatial for Rect {
+    fn rect(&self) -> Rect { *self }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RectIndex {
+    // the side of a cell in px
+    cell:  Coord,
+    // by cell, the rects overlapping it
+    cells: HashMap<V2, Vec<(NodeID, Rect)>>,
+}
+
+impl RectIndex {
+    pub fn new(rects: impl Iterator<Item = (NodeID, Rect)>) -> RectIndex {
+        let rects: Vec<_> = rects.collect();
+
+        // cells about the size of the typical rect keep both the copies of big rects and the
+        // rects per cell to a handful
+        let area: i64 = rects.iter().map(|(_, rect)| rect.area()).sum();
+        let mean = area as f64 / rects.len().max(1) as f64;
+        let cell = (mean.sqrt().ceil() as Coord).max(1);
+
+        let mut index = RectIndex { cell, cells: HashMap::default() };
+        for (id, rect) in rects {
+            if rect.area() == 0 {
+                continue;
+            }
+            for at in index.cells_under(rect) {
+                index.cells.entry(at).or_default().push((id, rect));
+            }
+        }
+        index
+    }
+
+    // the cell holding pixel `p`
+    fn cell_of(&self, p: V2) -> V2 {
+        V2::new(p.x.div_euclid(self.cell), p.y.div_euclid(self.cell))
+    }
+
+    fn cells_under(&self, rect: Rect) -> impl Iterator<Item = V2> {
+        let mins = self.cell_of(rect.mins);
+        let maxs = self.cell_of(rect.maxs - V2::new(1, 1));
+        (mins.y ..= maxs.y).flat_map(move |y| (mins.x ..= maxs.x).map(move |x| V2::new(x, y)))
+    }
+
+    // every rect sharing a pixel with `query`, each once. a rect across several cells is only
+    // reported from the one holding the top-left of its overlap with the query.
+    pub fn nodes_in(&self, query: Rect) -> impl Iterator<Item = NodeID> + '_ {
+        let cells = if query.area() == 0 { None } else { Some(self.cells_under(query)) };
+        cells.into_iter().flatten()
+            .filter_map(move |at| self.cells.get(&at).map(|rects| (at, rects)))
+            .flat_map(move |(at, rects)| {
+                rects.iter()
+                    .filter(move |(_, rect)| rect.overlaps(query))
+                    .filter(move |(_, rect)| self.cell_of(rect.intersect(query).mins) == at)
+                    .map(|(id, _)| *id)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{graph::{tests::pillared_hall, Graph}, image_graph::{extract_graph, ExtractOptions}},
+    };
+
+    #[test]
+    fn nodes_in_finds_what_a_scan_does() {
+        let image = pillared_hall();
+        let graph = extract_graph(&image, V2::new(1, 1), V2::new(16, 11), &ExtractOptions::default()).unwrap();
+
+        let queries = [
+            Rect::new_unchecked(V2::new(0, 0), V2::new(17, 13)),
+            Rect::new_unchecked(V2::new(4, 2), V2::new(9, 7)),
+            Rect::new_unchecked(V2::new(8, 8), V2::new(9, 9)),
+            Rect::new_unchecked(V2::new(-5, -5), V2::new(2, 30)),
+            Rect::new_unchecked(V2::new(20, 20), V2::new(30, 30)),
+            Rect::new_unchecked(V2::new(5, 5), V2::new(5, 9)),
+        ];
+        for query in queries.iter().copied() {
+            let mut found: Vec<NodeID> = graph.nodes_in(query).collect();
+            found.sort();
+            let mut scanned: Vec<NodeID> = graph.nodes().iter()
+                .filter(|(_, data)| data.rect.overlaps(query))
+                .map(|(id, _)| *id)
+                .collect();
+            scanned.sort();
+            assert_eq!(found, scanned, "{:?}", query);
+        }
+    }
+}
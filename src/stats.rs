@@ -0,0 +1,96 @@
+use {
+    mazesolve_rk::graph::SearchStats,
+    std::time::Instant,
+};
+
+#[cfg(feature = "json")]
+use serde::Serialize;
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Stage {
+    pub name:    &'static str,
+    pub seconds: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Stats {
+    pub nodes_extracted: usize,
+    pub edges_extracted: usize,
+    pub mean_rect_area:  f64,
+    pub nodes_pruned:    usize,
+    pub edges_pruned:    usize,
+    pub search_nodes:    usize,
+    pub chains:          usize,
+    pub expansions:      usize,
+    pub relaxations:     usize,
+    pub max_frontier:    usize,
+    pub solution_length: i32,
+    // start and goal fell in the same space
+    pub trivial:         bool,
+    pub stages:          Vec<Stage>,
+    pub warnings:        Vec<String>,
+}
+
+impl Stats {
+    pub fn time<T>(&mut self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        let begin = Instant::now();
+        let result = f();
+        let seconds = begin.elapsed().as_secs_f64();
+        self.stages.push(Stage { name, seconds });
+        result
+    }
+
+    pub fn warn(&mut self, msg: String) {
+        log::warn!("{}", msg);
+        self.warnings.push(msg);
+    }
+
+    pub fn record_search(&mut self, search: SearchStats) {
+        self.expansions = search.expansions;
+        self.relaxations = search.relaxations;
+        self.max_frontier = search.max_frontier;
+    }
+
+    // the mean of the spaces' areas
+    pub fn record_areas(&mut self, areas: impl Iterator<Item = i64>) {
+        let (count, area) = areas.fold((0, 0), |(n, a), area| (n + 1, a + area));
+        self.mean_rect_area = if count == 0 { 0.0 } else { area as f64 / count as f64 };
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let mut line = |label: &str, value: String| {
+            text += &format!("{:<20} {}\n", label, value);
+        };
+
+        line("nodes extracted:",   self.nodes_extracted.to_string());
+        line("edges extracted:",   self.edges_extracted.to_string());
+        line("mean rect area:",    format!("{:.2} px", self.mean_rect_area));
+        line("nodes after prune:", self.nodes_pruned.to_string());
+        line("edges after prune:", self.edges_pruned.to_string());
+        line("search nodes:",      self.search_nodes.to_string());
+        line("chains contracted:", self.chains.to_string());
+        line("expansions:",        self.expansions.to_string());
+        line("relaxations:",       self.relaxations.to_string());
+        line("max frontier:",      self.max_frontier.to_string());
+        let trivial = if self.trivial { " (start and goal share a space)" } else { "" };
+        line("solution length:",   format!("{}{}", self.solution_length, trivial));
+
+        for stage in self.stages.iter() {
+            line(&format!("time {}:", stage.name), format!("{:.3} s", stage.seconds));
+        }
+
+        for warning in self.warnings.iter() {
+            line("warning:", warning.clone());
+        }
+
+        text
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&mazesolve_rk::schema::STATS.tag(self)).expect("serializing stats")
+    }
+}
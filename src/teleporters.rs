@@ -0,0 +1,154 @@
+// teleporters: marks of one colour in two places, stepping onto either of which takes you to the
+// other. a mark is a blob of strongly coloured pixels, so the greys, blacks and whites of walls
+// and floors are never taken for one. blobs pair up by colour, and a colour marked in other
+// than two places joins nothing.
+
+use {
+    crate::{
+        math::*,
+        graph::{Edge, EdgeSetGraph, Graph},
+        image_graph::{NodeData, PortalSegment},
+        preprocess::{color_blobs, color_distance, CLEAR},
+        refine,
+    },
+    image as im,
+};
+
+// how far apart a pixel's channels must be for it to count as coloured rather than grey
+const MIN_CHROMA: u8 = 96;
+// euclidean distance in RGB space within which two pixels are the same colour
+const TOLERANCE: f32 = 64.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Teleporter {
+    pub color: im::Rgb<u8>,
+    // a pixel in each mark
+    pub ends:  [V2; 2],
+}
+
+pub struct Found {
+    pub teleporters: Vec<Teleporter>,
+    // colours marked in other than two places, and in how many
+    pub unpaired:    Vec<(im::Rgb<u8>, usize)>,
+}
+
+// picks the marks out of `color` and clears them in `binary`, the same image already classified
+// into clear and wall, so that each is a space to step onto
+pub fn find(color: &im::RgbImage, binary: &mut im::GrayImage) -> Found {
+    // each colour, with a pixel in each of its blobs
+    let mut colors: Vec<(im::Rgb<u8>, Vec<V2>)> = Vec::new();
+    for (pixel, blob) in color_blobs(color, MIN_CHROMA, TOLERANCE) {
+        clear(binary, &blob);
+        let middle = middle(&blob);
+        match colors.iter_mut().find(|(known, _)| color_distance(*known, pixel) <= TOLERANCE) {
+            Some((_, blobs)) => blobs.push(middle),
+            None             => colors.push((pixel, vec![middle])),
+        }
+    }
+
+    let mut found = Found { teleporters: Vec::new(), unpaired: Vec::new() };
+    for (color, blobs) in colors {
+        match blobs[..] {
+            [a, b] => found.teleporters.push(Teleporter { color, ends: [a, b] }),
+            _      => found.unpaired.push((color, blobs.len())),
+        }
+    }
+    found
+}
+
+// the blob's pixel nearest its middle, which for a ring is still on the ring
+pub(crate) fn middle(blob: &[V2]) -> V2 {
+    let sum = blob.iter().fold(V2::new(0, 0), |sum, p| sum + *p);
+    let mean = V2::new(sum.x / blob.len() as Coord, sum.y / blob.len() as Coord);
+    *blob.iter().min_by_key(|p| (**p - mean).length2()).expect("a blob has its first pixel")
+}
+
+pub(crate) fn clear(binary: &mut im::GrayImage, pixels: &[V2]) {
+    for p in pixels {
+        if (p.x as u32) < binary.width() && (p.y as u32) < binary.height() {
+            binary.put_pixel(p.x as u32, p.y as u32, CLEAR);
+        }
+    }
+}
+
+// joins the spaces at the ends of each teleporter, returning the joins made. the ends should
+// have been seeds of the extraction, or one off every passage from the start has no space
+pub fn link(graph: &mut EdgeSetGraph<NodeData>, teleporters: &[Teleporter]) -> Vec<Edge> {
+    let space_at = |p: V2| graph.nodes().iter().find(|(_, data)| data.rect.contains(p)).map(|(id, _)| *id);
+    let joins: Vec<Edge> = teleporters.iter()
+        .filter_map(|teleporter| match teleporter.ends.map(space_at) {
+            [Some(a), Some(b)] if a != b => Some(Edge::new(a, b)),
+            _                            => None,
+        })
+        .collect();
+    graph.edges.extend(joins.iter().copied());
+    joins
+}
+
+// refine::refine_path() for a route that may jump between teleporters: walked to the mark at one
+// end of each jump and on from the mark at the other. without any, just refine_path()
+pub fn refine_path(rects: &[Rect], start: V2, goal: V2, teleporters: &[Teleporter]) -> Vec<V2> {
+    let mut path = Vec::new();
+    let (mut from, mut pos) = (0, start);
+    for (i, pair) in rects.windows(2).enumerate() {
+        if PortalSegment::between(pair[0], pair[1]).is_some() {
+            continue;
+        }
+        let jump = teleporters.iter().find_map(|teleporter| {
+            let [a, b] = teleporter.ends;
+            if pair[0].contains(a) && pair[1].contains(b)      { Some((a, b)) }
+            else if pair[0].contains(b) && pair[1].contains(a) { Some((b, a)) }
+            else                                               { None }
+        });
+        if let Some((exit, entry)) = jump {
+            path.extend(refine::refine_path(&rects[from ..= i], pos, exit));
+            from = i + 1;
+            pos = entry;
+        }
+    }
+    path.extend(refine::refine_path(&rects[from ..], pos, goal));
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{image_graph::{extract_seeded_graph, ExtractOptions}, preprocess::WALL},
+    };
+
+    #[test]
+    fn teleporters_join_rooms_no_passage_does() {
+        // two rooms walled apart, a red mark in each and a lone blue one
+        let mut color = im::RgbImage::from_pixel(21, 9, im::Rgb([255, 255, 255]));
+        for (x, y, pixel) in color.enumerate_pixels_mut() {
+            if x == 0 || x == 10 || x == 20 || y == 0 || y == 8 {
+                *pixel = im::Rgb([0, 0, 0]);
+            }
+        }
+        for (x, y) in [(7, 2), (8, 2), (13, 6), (14, 6)] {
+            color.put_pixel(x, y, im::Rgb([220, 30, 30]));
+        }
+        color.put_pixel(4, 6, im::Rgb([30, 30, 220]));
+        // marks dark enough to read as wall until they're found
+        let mut binary = im::GrayImage::from_fn(21, 9, |x, y| match color.get_pixel(x, y) {
+            im::Rgb([255, 255, 255]) => CLEAR,
+            _                        => WALL,
+        });
+
+        let found = find(&color, &mut binary);
+        assert_eq!(found.teleporters.len(), 1);
+        assert_eq!(found.unpaired, vec![(im::Rgb([30, 30, 220]), 1)]);
+        assert_eq!(*binary.get_pixel(7, 2), CLEAR);
+
+        let (start, goal) = (V2::new(2, 2), V2::new(18, 6));
+        let ends = found.teleporters[0].ends;
+        let (mut graph, _) = extract_seeded_graph(&binary, &[start, ends[0], ends[1], goal], &ExtractOptions::default()).unwrap();
+        assert_eq!(link(&mut graph, &found.teleporters).len(), 1);
+        let solution = graph.into_adjacency_graph().into_dijkstra().solution().unwrap();
+        let path = refine_path(&solution.rects, start, goal, &found.teleporters);
+        assert_eq!((path[0], *path.last().unwrap()), (start, goal));
+        assert!(path.windows(2).any(|pair| (pair[1] - pair[0]).chebyshev() > 1));
+        assert!(path.iter().all(|p| *binary.get_pixel(p.x as u32, p.y as u32) == CLEAR));
+    }
+}
@@ -0,0 +1,181 @@
+// mazes written as text rather than drawn, as puzzle generators and libraries print them. each is
+// made into an image like any other input, one pixel a cell for the grid formats and a 3x3
+// block a character for box drawing, whose walls run through cells rather than filling them.
+// start and goal marks, where a format has them, are kept to solve between:
+//
+//   - mazelib's: '#' walls and spaces, 'S' at the start and 'E' at the end
+//   - CSV: one row of 0s and 1s a line, 1 for wall as mazelib's grids have it, with S and E
+//     allowed in place of a 0
+//   - box drawing: the walls drawn with ┌─┬─┐ │ ├ and the rest, light, heavy, double or
+//     rounded, or with ASCII's + - and |
+
+use {
+    crate::{math::*, preprocess::{CLEAR, WALL}},
+    image as im,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextFormat {
+    Mazelib,
+    Csv,
+    BoxDrawing,
+}
+
+pub struct TextMaze {
+    pub image: im::GrayImage,
+    pub start: Option<V2>,
+    pub goal:  Option<V2>,
+}
+
+const UP:    u8 = 1;
+const RIGHT: u8 = 2;
+const DOWN:  u8 = 4;
+const LEFT:  u8 = 8;
+
+// the sides each box-drawing character runs out to, by shape, whatever its weight
+const BOX_SHAPES: &[(&str, u8)] = &[
+    ("─━═╌╍┄┅┈┉╼╾-",      LEFT | RIGHT),
+    ("│┃║╎╏┆┇┊┋╽╿|",      UP | DOWN),
+    ("┌┍┎┏╒╓╔╭",          RIGHT | DOWN),
+    ("┐┑┒┓╕╖╗╮",          LEFT | DOWN),
+    ("└┕┖┗╘╙╚╰",          UP | RIGHT),
+    ("┘┙┚┛╛╜╝╯",          UP | LEFT),
+    ("├┝┞┟┠┡┢┣╞╟╠",       UP | DOWN | RIGHT),
+    ("┤┥┦┧┨┩┪┫╡╢╣",       UP | DOWN | LEFT),
+    ("┬┭┮┯┰┱┲┳╤╥╦",       LEFT | RIGHT | DOWN),
+    ("┴┵┶┷┸┹┺┻╧╨╩",       LEFT | RIGHT | UP),
+    ("┼┽┾┿╀╁╂╃╄╅╆╇╈╉╊╋╪╫╬", UP | RIGHT | DOWN | LEFT),
+    ("╴╸", LEFT),
+    ("╵╹", UP),
+    ("╶╺", RIGHT),
+    ("╷╻", DOWN),
+];
+
+fn is_box_drawing(c: char) -> bool {
+    ('\u{2500}' ..= '\u{257f}').contains(&c)
+}
+
+// the sides a wall character runs out to. an ASCII '+' joins whatever walls are beside it
+fn arms(c: char, beside: impl Fn(u8) -> Option<char>) -> u8 {
+    if c == '+' {
+        return [UP, RIGHT, DOWN, LEFT].iter()
+            .filter(|side| beside(**side).is_some_and(|c| c == '+' || c == '#' || box_arms(c).is_some()))
+            .fold(0, |arms, side| arms | side);
+    }
+    box_arms(c).unwrap_or(UP | RIGHT | DOWN | LEFT)
+}
+
+fn box_arms(c: char) -> Option<u8> {
+    BOX_SHAPES.iter().find(|(chars, _)| chars.contains(c)).map(|(_, arms)| *arms)
+}
+
+impl TextFormat {
+    // the format `text` is written in, if it's one of these at all
+    pub fn sniff(text: &str) -> Option<TextFormat> {
+        let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+        let only = |allowed: &str| lines.iter().all(|line| line.trim_end().chars().all(|c| allowed.contains(c)));
+        if lines.is_empty() {
+            None
+        }
+        else if text.chars().any(is_box_drawing) || (only("+-| SEG") && text.contains('+')) {
+            Some(TextFormat::BoxDrawing)
+        }
+        else if only("01SEG,; \t") && text.contains(',') {
+            Some(TextFormat::Csv)
+        }
+        else if only("# .SEG") && text.contains('#') {
+            Some(TextFormat::Mazelib)
+        }
+        else {
+            None
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<TextMaze, String> {
+    match TextFormat::sniff(text) {
+        Some(TextFormat::Mazelib)    => Ok(from_cells(text.lines().map(|line| line.chars().collect()).collect())),
+        Some(TextFormat::Csv)        => parse_csv(text),
+        Some(TextFormat::BoxDrawing) => Ok(from_box_drawing(text)),
+        None                         => Err("not a maze in any text format known".into()),
+    }
+}
+
+fn parse_csv(text: &str) -> Result<TextMaze, String> {
+    let rows = text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split([',', ';'])
+            .map(|cell| match cell.trim() {
+                "1"               => Ok('#'),
+                "0"               => Ok(' '),
+                mark @ ("S" | "E" | "G") => Ok(mark.chars().next().unwrap()),
+                other             => Err(format!("bad CSV cell '{}'", other)),
+            })
+            .collect::<Result<Vec<char>, String>>())
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(from_cells(rows))
+}
+
+// a pixel a cell, '#' wall and anything else clear. short rows are clear past their end, where
+// trailing spaces were trimmed
+fn from_cells(rows: Vec<Vec<char>>) -> TextMaze {
+    let rows: Vec<Vec<char>> = rows.into_iter().filter(|row| !row.is_empty()).collect();
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0) as u32;
+    let mut image = im::GrayImage::from_pixel(width, rows.len() as u32, CLEAR);
+    let (mut start, mut goal) = (None, None);
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.iter().enumerate() {
+            let pos = V2::new(x as Coord, y as Coord);
+            match c {
+                '#'       => image.put_pixel(x as u32, y as u32, WALL),
+                'S'       => start = Some(pos),
+                'E' | 'G' => goal = Some(pos),
+                _         => { }
+            }
+        }
+    }
+    TextMaze { image, start, goal }
+}
+
+// each character a 3x3 block, a wall through its middle pixel and out to the sides it joins.
+// the blocks' outer rim is cut off, leaving the outermost walls on the image's edge rather than
+// a pixel in with a way round outside them
+fn from_box_drawing(text: &str) -> TextMaze {
+    let rows: Vec<Vec<char>> = text.lines()
+        .map(|line| line.trim_end().chars().collect())
+        .collect();
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0) as u32;
+    let (w, h) = ((width * 3).saturating_sub(2), (rows.len() as u32 * 3).saturating_sub(2));
+    let mut image = im::GrayImage::from_pixel(w, h, CLEAR);
+    let (mut start, mut goal) = (None, None);
+
+    let at = |x: usize, y: usize| rows.get(y).and_then(|row| row.get(x)).copied();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.iter().enumerate() {
+            let middle = V2::new(x as Coord * 3, y as Coord * 3);
+            match c {
+                ' ' | '.' => continue,
+                'S'       => { start = Some(middle); continue; }
+                'E' | 'G' => { goal = Some(middle); continue; }
+                _         => { }
+            }
+
+            let beside = |side: u8| match side {
+                UP    => y.checked_sub(1).and_then(|y| at(x, y)),
+                RIGHT => at(x + 1, y),
+                DOWN  => at(x, y + 1),
+                _     => x.checked_sub(1).and_then(|x| at(x, y)),
+            };
+            let arms = arms(*c, beside);
+            let mut wall = |p: V2| if p.x >= 0 && p.y >= 0 && (p.x as u32) < w && (p.y as u32) < h {
+                image.put_pixel(p.x as u32, p.y as u32, WALL);
+            };
+            wall(middle);
+            if arms & UP != 0    { wall(middle - V2::new(0, 1)); }
+            if arms & RIGHT != 0 { wall(middle + V2::new(1, 0)); }
+            if arms & DOWN != 0  { wall(middle + V2::new(0, 1)); }
+            if arms & LEFT != 0  { wall(middle - V2::new(1, 0)); }
+        }
+    }
+    TextMaze { image, start, goal }
+}
@@ -0,0 +1,188 @@
+// images read a tile at a time, for mazes too big to hold whole. extract_tiled_graph() in
+// image_graph pulls the tiles a row at a time and stitches their graphs together at the seams.
+
+use {
+    crate::{math::*, error::Error},
+    std::{fmt, fs::File, io::{self, BufRead, BufReader, Read, Seek, SeekFrom}, path::Path},
+    image as im,
+};
+
+pub trait TileSource {
+    fn dimensions(&self) -> (u32, u32);
+    // the pixels under `rect`, which lies within the image
+    fn tile(&mut self, rect: Rect) -> io::Result<im::GrayImage>;
+}
+
+// tiles cut from an image already in memory
+pub struct ImageTiles<'a>(pub &'a im::GrayImage);
+
+impl TileSource for ImageTiles<'_> {
+    fn dimensions(&self) -> (u32, u32) {
+        self.0.dimensions()
+    }
+
+    fn tile(&mut self, rect: Rect) -> io::Result<im::GrayImage> {
+        let (x0, y0) = (rect.mins.x as u32, rect.mins.y as u32);
+        Ok(im::ImageBuffer::from_fn(rect.width() as u32, rect.height() as u32,
+            |x, y| *self.0.get_pixel(x0 + x, y0 + y)))
+    }
+}
+
+// tiles read straight from a binary PGM (P5) file, seeking to each row, so only a row of tiles
+// is ever in memory. PGM is the one common format whose rows can be found without decoding
+// everything before them; convert with e.g. vips pgmsave or ImageMagick's -depth 8.
+pub struct PgmTiles {
+    file:   BufReader<File>,
+    width:  u32,
+    height: u32,
+    maxval: u32,
+    // where the pixels start
+    offset: u64,
+}
+
+fn bad_header(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("bad PGM header: {}", msg))
+}
+
+// the next whitespace-separated header token, skipping # comments
+fn header_token(file: &mut BufReader<File>) -> io::Result<String> {
+    let mut token = String::new();
+    let mut byte = [0];
+    loop {
+        if file.read(&mut byte)? == 0 {
+            return Err(bad_header("ends early"));
+        }
+        match byte[0] {
+            b'#' => { file.read_until(b'\n', &mut Vec::new())?; }
+            b if b.is_ascii_whitespace() => if !token.is_empty() { return Ok(token); },
+            b => token.push(b as char),
+        }
+    }
+}
+
+// whether the file starts as a binary PGM does, whatever it's called
+pub fn is_binary_pgm(path: impl AsRef<Path>) -> bool {
+    let mut magic = [0; 2];
+    File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && &magic == b"P5"
+}
+
+impl PgmTiles {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<PgmTiles> {
+        let mut file = BufReader::new(File::open(path)?);
+        if header_token(&mut file)? != "P5" {
+            return Err(bad_header("not a binary PGM"));
+        }
+
+        let mut number = |what| header_token(&mut file)?
+            .parse::<u32>()
+            .map_err(|_| bad_header(what));
+        let width  = number("bad width")?;
+        let height = number("bad height")?;
+        let maxval = number("bad maxval")?;
+        // refused here rather than partway through tiling
+        coord(width).and(coord(height)).map_err(|err| bad_header(&err.to_string()))?;
+        if maxval == 0 || maxval > 255 {
+            return Err(bad_header("only 8-bit PGMs are supported"));
+        }
+
+        // the single whitespace byte after maxval was consumed with it
+        let offset = file.stream_position()?;
+        Ok(PgmTiles { file, width, height, maxval, offset })
+    }
+}
+
+impl TileSource for PgmTiles {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn tile(&mut self, rect: Rect) -> io::Result<im::GrayImage> {
+        let (w, h) = (rect.width() as u32, rect.height() as u32);
+        let mut pixels = vec![0; w as usize * h as usize];
+        for (row, y) in pixels.chunks_mut(w as usize).zip(rect.mins.y ..) {
+            let at = self.offset + y as u64 * self.width as u64 + rect.mins.x as u64;
+            self.file.seek(SeekFrom::Start(at))?;
+            self.file.read_exact(row)?;
+        }
+
+        // rescaled so that maxval is white
+        if self.maxval != 255 {
+            let maxval = self.maxval;
+            for p in pixels.iter_mut() {
+                *p = ((*p as u32).min(maxval) * 255 / maxval) as u8;
+            }
+        }
+
+        Ok(im::ImageBuffer::from_raw(w, h, pixels).expect("tile buffer is the tile's size"))
+    }
+}
+
+// a tiled extraction fails reading a tile, in the extraction itself, or on options it can't
+// tile with
+#[derive(Debug)]
+pub enum TileError {
+    Read(io::Error),
+    Extract(Error),
+    Options(&'static str),
+}
+
+impl From<io::Error> for TileError {
+    fn from(err: io::Error) -> TileError { TileError::Read(err) }
+}
+
+impl From<Error> for TileError {
+    fn from(err: Error) -> TileError { TileError::Extract(err) }
+}
+
+impl fmt::Display for TileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TileError::Read(err)    => write!(f, "reading tile: {}", err),
+            TileError::Extract(err) => write!(f, "{}", err),
+            TileError::Options(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TileError { }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::io::Write};
+
+    #[test]
+    fn pgm_tiles_read_back_as_the_image_cuts() {
+        // a 7-bit PGM with a comment in its header, so its greys come back rescaled
+        let image = im::GrayImage::from_fn(13, 9, |x, y| im::Luma([((x * 7 + y * 11) % 128) as u8]));
+        let path = std::env::temp_dir().join(format!("mazesolve-tiles-{}.pgm", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        write!(file, "P5\n# made for a test\n13 9\n127\n").unwrap();
+        file.write_all(&image).unwrap();
+        drop(file);
+
+        assert!(is_binary_pgm(&path));
+        let mut pgm = PgmTiles::open(&path).unwrap();
+        let mut scaled = image.clone();
+        for p in scaled.pixels_mut() {
+            p.0[0] = (p.0[0] as u32 * 255 / 127) as u8;
+        }
+        assert_eq!(pgm.dimensions(), (13, 9));
+        // the whole image, tiles at its corners and in its middle, and one cut short by its edge
+        for (x0, y0, x1, y1) in [(0, 0, 13, 9), (0, 0, 4, 4), (4, 4, 8, 8), (12, 8, 13, 9), (8, 0, 13, 9)].iter().copied() {
+            let rect = Rect::new_unchecked(V2::new(x0, y0), V2::new(x1, y1));
+            let (read, cut) = (pgm.tile(rect).unwrap(), ImageTiles(&scaled).tile(rect).unwrap());
+            assert_eq!((read.dimensions(), read.into_raw()), (cut.dimensions(), cut.into_raw()));
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(not(feature = "wide-coords"))]
+    #[test]
+    fn pgm_tiles_refuse_sizes_past_the_coordinates() {
+        let path = std::env::temp_dir().join(format!("mazesolve-tiles-huge-{}.pgm", std::process::id()));
+        std::fs::write(&path, "P5\n3000000000 2\n255\n").unwrap();
+        let err = PgmTiles::open(&path).err().expect("a width past i32::MAX is refused");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+// trémaux's algorithm, the way someone in the maze with no map and a piece of chalk could solve
+// it: mark each passage at its end every time you go along it. on reaching a junction you've
+// been to before by a passage you hadn't, go back the way you came; otherwise take a passage
+// with no marks if there is one, else one with one mark, and never one with two. a dead end
+// turns you back too. every passage gets walked at most twice, once each way, and those marked
+// just once when you reach the goal are the way back to the start.
+
+use {
+    crate::{
+        graph::{AdjacencyGraph, Edge, Graph, HashMap, HashSet, NodeID, SearchStats},
+        image_graph::NodeData,
+        pathfind::{walk_back, PathFinder, Solution},
+    },
+    log::debug,
+    pcg_rand::Pcg32Basic,
+    rand::{seq::SliceRandom, SeedableRng},
+};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tremaux {
+    // picks between passages as marked as each other at random, seeded by this, rather than in
+    // id order
+    pub seed: Option<u64>,
+}
+
+// one walk along a passage, from junction to junction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub from:  NodeID,
+    pub to:    NodeID,
+    // the passage's marks once walked, 1 or 2
+    pub marks: u8,
+}
+
+pub struct Walk {
+    pub start: NodeID,
+    pub steps: Vec<Step>,
+    // the junctions marked once from start to goal, or None if the walk came back to the start
+    // with every way out of it marked twice
+    pub path:  Option<Vec<NodeID>>,
+    pub stats: SearchStats,
+}
+
+impl Walk {
+    // each junction come to, in order and as often as it was, starting from the start
+    pub fn arrivals(&self) -> Vec<NodeID> {
+        std::iter::once(self.start)
+            .chain(self.steps.iter().map(|step| step.to))
+            .collect()
+    }
+
+    // the path found, with the corridors along it filled back in
+    pub fn solution(&self, graph: &AdjacencyGraph<NodeData>) -> Option<Solution> {
+        let path = self.path.as_ref()?;
+        let preds = path.windows(2).map(|pair| (pair[1], pair[0])).collect();
+        Some(walk_back(graph, &preds, path[0], path[path.len() - 1], self.stats, self.arrivals()))
+    }
+}
+
+// walks from the start until at the goal, or stuck with every way on marked twice. where there's a
+// choice of passage, it takes them in id order, or in an order shuffled by `seed`, so the same
+// maze always walks the same way
+pub fn walk(graph: &AdjacencyGraph<NodeData>, start: NodeID, goal: NodeID, seed: Option<u64>) -> Walk {
+    let mut rng = seed.map(Pcg32Basic::seed_from_u64);
+    let mut marks: HashMap<Edge, u8> = HashMap::default();
+    let mut visited: HashSet<NodeID> = HashSet::default();
+    let mut stats = SearchStats { max_frontier: 1, ..SearchStats::default() };
+    let mut steps = Vec::new();
+    // the passages marked once, as the junctions at their ends
+    let mut trail = vec![start];
+    visited.insert(start);
+
+    let (mut at, mut came, mut been_here) = (start, None, false);
+    loop {
+        stats.expansions += 1;
+        if at == goal {
+            break;
+        }
+        let marked = |v: NodeID| marks.get(&Edge::new(at, v)).copied().unwrap_or(0);
+        let mut ways: Vec<NodeID> = graph.neighbors(at)
+            .filter(|v| graph.nodes().contains_key(v) && marked(*v) < 2)
+            .collect();
+        ways.sort_unstable();
+        if let Some(rng) = &mut rng {
+            ways.shuffle(rng);
+        }
+
+        let back = came.filter(|from| been_here && marked(*from) == 1 && ways.contains(from));
+        let next = back
+            .or_else(|| ways.iter().copied().find(|v| marked(*v) == 0))
+            .or_else(|| ways.iter().copied().find(|v| marked(*v) == 1));
+        let Some(next) = next else {
+            return Walk { start, steps, path: None, stats };
+        };
+
+        let count = marks.entry(Edge::new(at, next)).or_default();
+        *count += 1;
+        if *count == 1 { trail.push(next); }
+        else           { trail.pop(); }
+        debug!("tremaux: {} to {}, marked {}", at, next, *count);
+        steps.push(Step { from: at, to: next, marks: *count });
+        stats.relaxations += 1;
+
+        been_here = !visited.insert(next);
+        (at, came) = (next, Some(at));
+    }
+    Walk { start, steps, path: Some(trail), stats }
+}
+
+impl PathFinder for Tremaux {
+    fn name(&self) -> &'static str { "tremaux" }
+
+    fn solve(&self, graph: &AdjacencyGraph<NodeData>, start: NodeID, goal: NodeID) -> Option<Solution> {
+        if !graph.com.nodes.contains_key(&start) || !graph.com.nodes.contains_key(&goal) {
+            return None;
+        }
+        walk(graph, start, goal, self.seed).solution(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{math::*, graph::tests::pillared_hall, image_graph::{extract_graph, ExtractOptions}},
+    };
+
+    #[test]
+    fn seeded_choices_repeat() {
+        let image = pillared_hall();
+        let (start, goal) = (V2::new(1, 1), V2::new(16, 11));
+        let graph = extract_graph(&image, start, goal, &ExtractOptions::default()).unwrap().into_adjacency_graph();
+
+        let walk = |seed| Tremaux { seed: Some(seed) }.solve(&graph, graph.start(), graph.goal()).unwrap();
+        assert_eq!(walk(7), walk(7));
+        assert_eq!(walk(7).nodes.last(), Some(&graph.goal()));
+        let longest = |seed| graph.longest_path(50, Some(seed)).unwrap().path;
+        assert_eq!(longest(7), longest(7));
+    }
+}
@@ -0,0 +1,112 @@
+// whether an image is a well-formed maze: walled all round but for a given number of openings,
+// with all its clear space one region that every opening leads into.
+
+use {
+    crate::{
+        math::*,
+        image_graph::border_runs,
+        preprocess::{label_components, Components},
+    },
+    image as im,
+};
+
+pub struct MazeCheck {
+    // the clear runs along the border
+    pub openings:   Vec<Vec<V2>>,
+    // the corners of the image that are clear, where the outer walls don't meet
+    pub corners:    Vec<V2>,
+    pub components: Components,
+    // the regions the openings lead into, each once, in order of first opening
+    pub reached:    Vec<usize>,
+}
+
+impl MazeCheck {
+    pub fn new(image: &im::GrayImage) -> MazeCheck {
+        let (w, h) = (coord_clamped(image.width()), coord_clamped(image.height()));
+        let openings = border_runs(image);
+        let components = label_components(image);
+
+        let mut corners = Vec::new();
+        for p in [V2::new(0, 0), V2::new(w - 1, 0), V2::new(w - 1, h - 1), V2::new(0, h - 1)].iter().copied() {
+            if components.at(p).is_some() && !corners.contains(&p) {
+                corners.push(p);
+            }
+        }
+
+        let mut reached = Vec::new();
+        for region in openings.iter().filter_map(|run| components.at(run[0])) {
+            if !reached.contains(&region) {
+                reached.push(region);
+            }
+        }
+
+        MazeCheck { openings, corners, components, reached }
+    }
+
+    // the regions no opening leads into
+    pub fn pockets(&self) -> impl Iterator<Item = usize> + '_ {
+        (0 .. self.components.sizes.len()).filter(move |region| !self.reached.contains(region))
+    }
+
+    // the outer walls meet at every corner. an opening across a corner is a side missing rather
+    // than a way in, and it's how a border with no wall at all shows up too.
+    pub fn enclosed(&self) -> bool {
+        self.corners.is_empty()
+    }
+
+    // every opening leads into the same region
+    pub fn connected(&self) -> bool {
+        self.reached.len() <= 1
+    }
+
+    pub fn passes(&self, expected_openings: usize) -> bool {
+        self.openings.len() == expected_openings && self.enclosed() && self.connected()
+            && self.pockets().next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::preprocess::{CLEAR, WALL},
+    };
+
+    #[test]
+    fn check_finds_what_spoils_a_maze() {
+        // a walled hall, in at the top and out at the bottom
+        let mut image = im::GrayImage::from_pixel(10, 8, WALL);
+        for y in 1 .. 7 {
+            for x in 1 .. 9 {
+                image.put_pixel(x, y, CLEAR);
+            }
+        }
+        image.put_pixel(2, 0, CLEAR);
+        image.put_pixel(7, 7, CLEAR);
+        assert!(MazeCheck::new(&image).passes(2));
+        assert!(!MazeCheck::new(&image).passes(3));
+
+        // walled off from the rest, the right end is a pocket and the way out leads only there
+        let mut split = image.clone();
+        for y in 1 .. 7 {
+            split.put_pixel(6, y, WALL);
+        }
+        let check = MazeCheck::new(&split);
+        assert!(check.enclosed());
+        assert!(!check.connected());
+        assert_eq!(check.pockets().count(), 0);
+
+        let mut pocket = split.clone();
+        pocket.put_pixel(7, 7, WALL);
+        let check = MazeCheck::new(&pocket);
+        assert!(check.connected());
+        assert_eq!(check.pockets().map(|region| check.components.sizes[region]).collect::<Vec<_>>(), vec![12]);
+
+        let mut corner = image.clone();
+        corner.put_pixel(9, 7, CLEAR);
+        corner.put_pixel(8, 7, CLEAR);
+        let check = MazeCheck::new(&corner);
+        assert_eq!(check.corners, vec![V2::new(9, 7)]);
+        assert!(!check.passes(2));
+    }
+}
@@ -0,0 +1,86 @@
+// what the integration tests share: mazes made to order, and the plain searches over a graph
+// that the pipeline's own are checked against. each test crate uses only some of it
+#![allow(dead_code)]
+
+use {
+    mazesolve_rk::{
+        math::*,
+        graph::{AdjacencyGraph, EdgeSetGraph, HashMap, HashSet, NodeID},
+        preprocess::{CLEAR, WALL},
+    },
+    image as im,
+    pcg_rand::Pcg32Basic,
+    rand::{seq::SliceRandom, SeedableRng},
+    std::collections::{hash_map::Entry, VecDeque},
+};
+
+// a perfect maze of `cells` by `cells`, carved by a seeded random walk that backs up when stuck,
+// with each cell and passage a pixel wide, entered at the top left and left at the bottom right
+pub fn carve(cells: Coord, seed: u64) -> im::GrayImage {
+    let side = (cells * 2 + 1) as u32;
+    let mut image = im::GrayImage::from_pixel(side, side, WALL);
+    let mut rng = Pcg32Basic::seed_from_u64(seed);
+    let open = |image: &mut im::GrayImage, p: V2| image.put_pixel(p.x as u32, p.y as u32, CLEAR);
+
+    let mut seen = vec![false; (cells * cells) as usize];
+    let mut stack = vec![V2::new(0, 0)];
+    seen[0] = true;
+    open(&mut image, V2::new(1, 1));
+    while let Some(&cell) = stack.last() {
+        let unseen: Vec<V2> = [V2::new(1, 0), V2::new(-1, 0), V2::new(0, 1), V2::new(0, -1)].iter()
+            .map(|step| cell + *step)
+            .filter(|next| next.x >= 0 && next.y >= 0 && next.x < cells && next.y < cells)
+            .filter(|next| !seen[(next.y * cells + next.x) as usize])
+            .collect();
+        let next = match unseen.choose(&mut rng) {
+            Some(next) => *next,
+            None       => { stack.pop(); continue; }
+        };
+        seen[(next.y * cells + next.x) as usize] = true;
+        open(&mut image, cell + next + V2::new(1, 1));
+        open(&mut image, next * 2 + V2::new(1, 1));
+        stack.push(next);
+    }
+    open(&mut image, V2::new(1, 0));
+    open(&mut image, V2::new(cells * 2 - 1, cells * 2));
+    image
+}
+
+// a maze from a grid of walls, row by row, `width` across
+pub fn from_walls(width: u32, walls: &[bool]) -> im::GrayImage {
+    let height = walls.len() as u32 / width;
+    im::GrayImage::from_fn(width, height, |x, y| if walls[(y * width + x) as usize] { WALL } else { CLEAR })
+}
+
+// hops from `start` to every node it reaches, breadth first over `neighbors`
+pub fn hops<I: IntoIterator<Item = NodeID>>(start: NodeID, neighbors: impl Fn(NodeID) -> I) -> HashMap<NodeID, i32> {
+    let mut hops = HashMap::default();
+    let mut queue = VecDeque::new();
+    hops.insert(start, 0);
+    queue.push_back(start);
+    while let Some(u) = queue.pop_front() {
+        let next = hops[&u] + 1;
+        for v in neighbors(u) {
+            if let Entry::Vacant(entry) = hops.entry(v) {
+                entry.insert(next);
+                queue.push_back(v);
+            }
+        }
+    }
+    hops
+}
+
+// the nodes an edge set graph's edges join `start` to, itself included
+pub fn reachable_by_edges<D>(graph: &EdgeSetGraph<D>, start: NodeID) -> HashSet<NodeID> {
+    let mut adjs: HashMap<NodeID, Vec<NodeID>> = HashMap::default();
+    for edge in graph.edges.iter() {
+        adjs.entry(edge.min).or_default().push(edge.max);
+        adjs.entry(edge.max).or_default().push(edge.min);
+    }
+    hops(start, |u| adjs.get(&u).cloned().unwrap_or_default()).into_keys().collect()
+}
+
+// the nodes an adjacency graph joins `start` to, itself included
+pub fn reachable_by_adjs<D>(graph: &AdjacencyGraph<D>, start: NodeID) -> HashSet<NodeID> {
+    hops(start, |u| graph.adjs.get(&u).into_iter().flatten().copied().collect::<Vec<_>>()).into_keys().collect()
+}
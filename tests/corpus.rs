@@ -0,0 +1,117 @@
+// the golden corpus in tests/corpus/index.txt, solved end to end and checked against the
+// recorded lengths. opt-in, being slow and perhaps needing the network:
+//
+//     cargo test --release --test corpus -- --ignored
+//
+// downloaded mazes are kept in $MAZESOLVE_CORPUS, default target/corpus, and fetched with curl
+// only when missing, so a local copy of the corpus can stand in for the network.
+
+use {
+    mazesolve_rk::{
+        math::*,
+        image_graph::{extract_graph, Decomposition, ExtractOptions},
+    },
+    std::{env, fs, path::{Path, PathBuf}, process::Command},
+};
+
+struct Entry {
+    name:    String,
+    start:   V2,
+    goal:    V2,
+    lengths: [i32; 2],
+    source:  String,
+}
+
+fn parse_point(text: &str) -> Result<V2, String> {
+    let mut parts = text.splitn(2, ',').map(str::parse::<Coord>);
+    match (parts.next(), parts.next()) {
+        (Some(Ok(x)), Some(Ok(y))) => Ok(V2::new(x, y)),
+        _                          => Err(format!("expected X,Y, got '{}'", text)),
+    }
+}
+
+fn parse_index(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let bad = |msg: String| format!("index.txt:{}: {}", number + 1, msg);
+        if fields.len() != 6 {
+            return Err(bad(format!("expected 6 fields, got {}", fields.len())));
+        }
+        let length = |text: &str| text.parse::<i32>().map_err(|_| bad(format!("bad length '{}'", text)));
+        entries.push(Entry {
+            name:    fields[0].to_string(),
+            start:   parse_point(fields[1]).map_err(bad)?,
+            goal:    parse_point(fields[2]).map_err(bad)?,
+            lengths: [length(fields[3])?, length(fields[4])?],
+            source:  fields[5].to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+// the entry's image on disk, downloading it first if need be
+fn fetch(entry: &Entry, root: &Path, cache: &Path) -> Result<PathBuf, String> {
+    if entry.source == "-" {
+        return Ok(root.join(&entry.name));
+    }
+
+    let path = cache.join(&entry.name);
+    if !path.exists() {
+        fs::create_dir_all(cache).map_err(|err| format!("creating {}: {}", cache.display(), err))?;
+        let status = Command::new("curl").arg("-fsSL").arg("-o").arg(&path).arg(&entry.source).status()
+            .map_err(|err| format!("running curl: {}", err))?;
+        if !status.success() {
+            let _ = fs::remove_file(&path);
+            return Err(format!("downloading {} failed ({})", entry.source, status));
+        }
+    }
+    Ok(path)
+}
+
+fn solve(path: &Path, entry: &Entry, decomposition: Decomposition) -> Result<i32, String> {
+    let image = image::open(path).map_err(|err| format!("loading {}: {}", path.display(), err))?.to_luma();
+    let opts = ExtractOptions { decomposition, ..ExtractOptions::default() };
+    let graph = extract_graph(&image, entry.start, entry.goal, &opts)
+        .map_err(|err| err.to_string())?
+        .prune()
+        .into_adjacency_graph()
+        .contract()
+        .into_dijkstra();
+    Ok(graph.goal_distance())
+}
+
+#[test]
+#[ignore = "slow, and may need the network; run with --ignored"]
+fn corpus() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let cache = env::var_os("MAZESOLVE_CORPUS")
+        .map_or_else(|| root.join("target/corpus"), PathBuf::from);
+    let index = fs::read_to_string(root.join("tests/corpus/index.txt")).expect("reading the index");
+    let entries = parse_index(&index).unwrap();
+
+    let mut failures = Vec::new();
+    for entry in entries.iter() {
+        let path = match fetch(entry, root, &cache) {
+            Ok(path) => path,
+            Err(err) => { failures.push(format!("{}: {}", entry.name, err)); continue; }
+        };
+
+        let decompositions = [Decomposition::Greedy, Decomposition::Strips];
+        for (decomposition, expected) in decompositions.iter().zip(entry.lengths.iter()) {
+            let label = format!("{} ({:?})", entry.name, decomposition).to_lowercase();
+            match solve(&path, entry, *decomposition) {
+                Ok(length) if length == *expected => println!("ok   {}: length {}", label, length),
+                Ok(length) => failures.push(format!("{}: expected length {}, got {}", label, expected, length)),
+                Err(err)   => failures.push(format!("{}: {}", label, err)),
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{} of the corpus failed:\n{}", failures.len(), failures.join("\n"));
+}
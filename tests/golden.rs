@@ -0,0 +1,193 @@
+// small mazes run through the whole pipeline as a library, extraction, pruning, contraction and
+// search, with the lengths each gives checked against those recorded. the bundled ones are
+// drawn in tests/golden/*.txt; the generated ones are perfect mazes carved from fixed seeds, so
+// they come out the same every run. a length that changes means the pipeline's behaviour did:
+// check the new solution by eye before recording it.
+//
+// a bundled maze is rows of '#' for wall and anything else clear, with 'S' and 'G' marking the
+// start and goal, after a line `lengths GREEDY STRIPS`, or `lengths none` for a maze with no way
+// through. lines starting ';' are comments.
+
+use {
+    mazesolve_rk::{
+        Error,
+        math::*,
+        graph::Graph,
+        grid_search::{pixel_bfs, PixelGrid},
+        image_graph::{extract_graph, Decomposition, ExtractOptions},
+        pathfind::{self, PathFinder},
+        preprocess::{CLEAR, WALL},
+        refine::refine_path,
+    },
+    image as im,
+    std::{fs, path::Path},
+};
+
+mod common;
+use common::carve;
+
+const DECOMPOSITIONS: [Decomposition; 2] = [Decomposition::Greedy, Decomposition::Strips];
+
+// cells across, seed, and the lengths with greedy and strips
+const GENERATED: &[(Coord, u64, [i32; 2])] = &[
+    (4,  1, [6, 6]),
+    (8,  2, [16, 16]),
+    (16, 3, [62, 64]),
+    (32, 4, [148, 156]),
+];
+
+struct Maze {
+    name:    String,
+    image:   im::GrayImage,
+    start:   V2,
+    goal:    V2,
+    // None where there's no way through
+    lengths: Option<[i32; 2]>,
+}
+
+fn parse_maze(name: &str, text: &str) -> Result<Maze, String> {
+    let mut lengths = None;
+    let mut rows = Vec::new();
+    for line in text.lines() {
+        if line.starts_with(';') || line.trim().is_empty() {
+            continue;
+        }
+        match line.strip_prefix("lengths ") {
+            Some("none") => lengths = Some(None),
+            Some(given)  => {
+                let given: Vec<i32> = given.split_whitespace()
+                    .map(|n| n.parse().map_err(|_| format!("bad length '{}'", n)))
+                    .collect::<Result<_, _>>()?;
+                match given[..] {
+                    [greedy, strips] => lengths = Some(Some([greedy, strips])),
+                    _                => return Err(format!("expected two lengths, got {}", given.len())),
+                }
+            }
+            None => rows.push(line),
+        }
+    }
+    let lengths = lengths.ok_or("no lengths line")?;
+    if rows.is_empty() || rows.iter().any(|row| row.len() != rows[0].len()) {
+        return Err("rows missing or ragged".into());
+    }
+
+    let mut image = im::GrayImage::new(rows[0].len() as u32, rows.len() as u32);
+    let (mut start, mut goal) = (None, None);
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let pos = V2::new(x as Coord, y as Coord);
+            match c {
+                'S' => start = Some(pos),
+                'G' => goal  = Some(pos),
+                _   => { }
+            }
+            image.put_pixel(x as u32, y as u32, if c == '#' { WALL } else { CLEAR });
+        }
+    }
+    Ok(Maze {
+        name: name.to_string(),
+        image,
+        start: start.ok_or("no start")?,
+        goal:  goal.ok_or("no goal")?,
+        lengths,
+    })
+}
+
+fn generated() -> Vec<Maze> {
+    GENERATED.iter()
+        .map(|&(cells, seed, lengths)| Maze {
+            name:    format!("carved {}x{} seed {}", cells, cells, seed),
+            image:   carve(cells, seed),
+            start:   V2::new(1, 0),
+            goal:    V2::new(cells * 2 - 1, cells * 2),
+            lengths: Some(lengths),
+        })
+        .collect()
+}
+
+fn bundled() -> Vec<Maze> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut paths: Vec<_> = fs::read_dir(&dir).expect("reading tests/golden")
+        .map(|entry| entry.expect("reading tests/golden").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    paths.sort();
+
+    paths.iter()
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let text = fs::read_to_string(path).expect("reading a golden maze");
+            parse_maze(&name, &text).unwrap_or_else(|err| panic!("{}: {}", name, err))
+        })
+        .collect()
+}
+
+// the whole solve, extract, prune, contract and search, checked along the way: each search
+// agrees with dijkstra's over the uncontracted graph, and the refined path stays in the clear.
+// the length found, or None for no way through
+fn solve(maze: &Maze, decomposition: Decomposition) -> Result<Option<i32>, String> {
+    let opts = ExtractOptions { decomposition, ..ExtractOptions::default() };
+    let graph = match extract_graph(&maze.image, maze.start, maze.goal, &opts) {
+        Ok(graph)                      => graph,
+        Err(Error::Disconnected(_, _)) => return Ok(None),
+        Err(err)                       => return Err(err.to_string()),
+    };
+    let graph = graph.prune().into_adjacency_graph();
+
+    let (start, goal) = (graph.start(), graph.goal());
+    let finders: [&dyn PathFinder; 3] = [&pathfind::Dijkstra, &pathfind::AStar, &pathfind::BreadthFirst];
+    let costs: Vec<Option<i32>> = finders.iter()
+        .map(|finder| finder.solve(&graph, start, goal).map(|solution| solution.cost))
+        .collect();
+
+    let solution = graph.contract().into_dijkstra().solution();
+    let length = solution.as_ref().map(|solution| solution.cost);
+    for (finder, cost) in finders.iter().zip(costs.iter()) {
+        if *cost != length {
+            return Err(format!("{} found {:?}, the contracted graph {:?}", finder.name(), cost, length));
+        }
+    }
+
+    if let Some(solution) = &solution {
+        let pixels = refine_path(&solution.rects, maze.start, maze.goal);
+        let clear = |p: &V2| maze.image.get_pixel(p.x as u32, p.y as u32) == &CLEAR;
+        if !pixels.iter().all(clear) || !pixels.windows(2).all(|p| (p[1] - p[0]).chebyshev() == 1) {
+            return Err("refined path leaves the corridors".into());
+        }
+    }
+    Ok(length)
+}
+
+fn check(mazes: &[Maze]) {
+    let mut failures = Vec::new();
+    for maze in mazes {
+        // the rects are only a faster way through the pixels, so they must find a way just
+        // where the pixels have one
+        let through = pixel_bfs(&PixelGrid::new(&maze.image).unwrap(), maze.start, maze.goal).is_ok();
+        if through != maze.lengths.is_some() {
+            failures.push(format!("{}: pixel search says solvable is {}", maze.name, through));
+        }
+
+        for (index, decomposition) in DECOMPOSITIONS.iter().enumerate() {
+            let label = format!("{} ({:?})", maze.name, decomposition).to_lowercase();
+            let expected = maze.lengths.map(|lengths| lengths[index]);
+            match solve(maze, *decomposition) {
+                Ok(length) if length == expected => { }
+                Ok(length) => failures.push(format!("{}: expected length {:?}, got {:?}", label, expected, length)),
+                Err(err)   => failures.push(format!("{}: {}", label, err)),
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{} golden checks failed:\n{}", failures.len(), failures.join("\n"));
+}
+
+#[test]
+fn bundled_mazes() {
+    check(&bundled());
+}
+
+#[test]
+fn generated_mazes() {
+    check(&generated());
+}
@@ -0,0 +1,156 @@
+// invariants of the graph pipeline over mazes proptest makes up: grids of scattered walls, and
+// carved perfect mazes with walls knocked out of them. a failing case is shrunk to a small maze
+// and its seed kept in tests/properties.proptest-regressions, to be rerun first from then on.
+
+use {
+    mazesolve_rk::{
+        Error,
+        math::*,
+        graph::{Graph, HashSet},
+        grid_search::{pixel_bfs, PixelGrid},
+        image_graph::{extract_graph, extract_tiled_graph, Decomposition, ExtractOptions, NodeData},
+        preprocess::{CLEAR, WALL},
+        tiles::{ImageTiles, TileError},
+    },
+    image as im,
+    proptest::prelude::*,
+};
+
+mod common;
+use common::{carve, from_walls, hops, reachable_by_adjs, reachable_by_edges};
+
+// a maze with a start and goal on clear pixels
+#[derive(Clone, Debug)]
+struct Maze {
+    image: im::GrayImage,
+    start: V2,
+    goal:  V2,
+}
+
+fn scattered() -> impl Strategy<Value = Maze> {
+    (2u32 .. 24, 2u32 .. 24)
+        .prop_flat_map(|(width, height)| (
+            Just(width),
+            prop::collection::vec(prop::bool::weighted(0.35), (width * height) as usize),
+            (0 .. width, 0 .. height),
+            (0 .. width, 0 .. height),
+        ))
+        .prop_map(|(width, walls, start, goal)| {
+            let mut image = from_walls(width, &walls);
+            image.put_pixel(start.0, start.1, CLEAR);
+            image.put_pixel(goal.0, goal.1, CLEAR);
+            let point = |(x, y): (u32, u32)| V2::new(x as Coord, y as Coord);
+            Maze { image, start: point(start), goal: point(goal) }
+        })
+}
+
+fn carved() -> impl Strategy<Value = Maze> {
+    (2 .. 12 as Coord, any::<u64>(), prop::collection::vec((any::<u32>(), any::<u32>()), 0 .. 8))
+        .prop_map(|(cells, seed, holes)| {
+            let mut image = carve(cells, seed);
+            let side = image.width();
+            for (x, y) in holes {
+                image.put_pixel(x % side, y % side, CLEAR);
+            }
+            Maze { image, start: V2::new(1, 0), goal: V2::new(cells * 2 - 1, cells * 2) }
+        })
+}
+
+fn maze() -> impl Strategy<Value = Maze> {
+    prop_oneof![scattered(), carved()]
+}
+
+fn decomposition() -> impl Strategy<Value = Decomposition> {
+    prop_oneof![Just(Decomposition::Greedy), Just(Decomposition::Strips)]
+}
+
+// every pixel the graph's rects cover
+fn covered(graph: &impl Graph<NodeData>) -> HashSet<V2> {
+    graph.nodes().values()
+        .flat_map(|data| (data.rect.mins.y .. data.rect.maxs.y)
+            .flat_map(move |y| (data.rect.mins.x .. data.rect.maxs.x).map(move |x| V2::new(x, y))))
+        .collect()
+}
+
+proptest! {
+    // the rects join the start to the goal just where the pixels do
+    #[test]
+    fn extraction_connects_what_the_pixels_do(maze in maze(), decomposition in decomposition()) {
+        let opts = ExtractOptions { decomposition, ..ExtractOptions::default() };
+        let through = pixel_bfs(&PixelGrid::new(&maze.image).unwrap(), maze.start, maze.goal).is_ok();
+        match extract_graph(&maze.image, maze.start, maze.goal, &opts) {
+            Ok(_)                          => prop_assert!(through, "rects join what the pixels don't"),
+            Err(Error::Disconnected(_, _)) => prop_assert!(!through, "pixels join what the rects don't"),
+            Err(err)                       => prop_assert!(false, "extraction failed: {}", err),
+        }
+    }
+
+    #[test]
+    fn prune_keeps_the_goal_reachable(maze in maze(), decomposition in decomposition()) {
+        let opts = ExtractOptions { decomposition, ..ExtractOptions::default() };
+        let graph = match extract_graph(&maze.image, maze.start, maze.goal, &opts) {
+            Ok(graph) => graph,
+            Err(_)    => return Ok(()),
+        };
+        prop_assert!(reachable_by_edges(&graph, graph.start()).contains(&graph.goal()));
+
+        let pruned = graph.prune();
+        prop_assert!(reachable_by_edges(&pruned, pruned.start()).contains(&pruned.goal()));
+        prop_assert!(pruned.nodes().contains_key(&pruned.start()) && pruned.nodes().contains_key(&pruned.goal()));
+    }
+
+    #[test]
+    fn adjacency_graph_reaches_what_the_edges_do(maze in maze(), decomposition in decomposition(), prune: bool) {
+        let opts = ExtractOptions { decomposition, ..ExtractOptions::default() };
+        let graph = match extract_graph(&maze.image, maze.start, maze.goal, &opts) {
+            Ok(graph) => graph,
+            Err(_)    => return Ok(()),
+        };
+        let graph = if prune { graph.prune() } else { graph };
+
+        let by_edges = reachable_by_edges(&graph, graph.start());
+        let adjacency = graph.into_adjacency_graph();
+        prop_assert_eq!(by_edges, reachable_by_adjs(&adjacency, adjacency.start()));
+    }
+
+    // every weight is one until contraction, so shortest is fewest hops
+    #[test]
+    fn dijkstra_matches_bfs_with_unit_weights(maze in maze(), decomposition in decomposition()) {
+        let opts = ExtractOptions { decomposition, ..ExtractOptions::default() };
+        let extract = || extract_graph(&maze.image, maze.start, maze.goal, &opts)
+            .map(|graph| graph.prune().into_adjacency_graph());
+        let graph = match extract() {
+            Ok(graph) => graph,
+            Err(_)    => return Ok(()),
+        };
+
+        let hops = hops(graph.start(), |u| graph.adjs.get(&u).into_iter().flatten().copied().collect::<Vec<_>>());
+        let expected = hops.get(&graph.goal()).copied();
+        prop_assert_eq!(Some(graph.into_dijkstra().goal_distance()), expected);
+        // nor does contracting corridors change how far it is
+        let contracted = extract().unwrap().contract();
+        prop_assert_eq!(Some(contracted.into_dijkstra().goal_distance()), expected);
+    }
+
+    // tiles of any size, dividing the image or not, cover just the space the whole image's rects
+    // do, so the shortest way through that space is just as short
+    #[test]
+    fn tiles_cover_what_the_whole_image_does(maze in maze(), decomposition in decomposition(), tile_size in 1u32 .. 26) {
+        let opts = ExtractOptions { decomposition, ..ExtractOptions::default() };
+        let plain = extract_graph(&maze.image, maze.start, maze.goal, &opts);
+        let tiled = extract_tiled_graph(&mut ImageTiles(&maze.image), tile_size, maze.start, maze.goal, &opts);
+        let (plain, tiled) = match (plain, tiled) {
+            (Ok(plain), Ok(tiled)) => (plain, tiled),
+            (Err(Error::Disconnected(_, _)), Err(TileError::Extract(Error::Disconnected(_, _)))) => return Ok(()),
+            (plain, tiled) => return Err(TestCaseError::fail(
+                format!("the whole image gave {:?}, its tiles {:?}", plain.err(), tiled.err()))),
+        };
+
+        let space = covered(&tiled);
+        prop_assert_eq!(&space, &covered(&plain));
+        let within = im::GrayImage::from_fn(maze.image.width(), maze.image.height(),
+            |x, y| if space.contains(&V2::new(x as Coord, y as Coord)) { CLEAR } else { WALL });
+        let length = |image: &im::GrayImage| pixel_bfs(&PixelGrid::new(image).unwrap(), maze.start, maze.goal).map(|path| path.length);
+        prop_assert_eq!(length(&within).ok(), length(&maze.image).ok());
+    }
+}